@@ -0,0 +1,131 @@
+use ggez::graphics::{FilterMode, Font, Image, Rect};
+use ggez::{filesystem, Context};
+use std::collections::HashMap;
+use std::io::Read;
+
+/// Lists every sprite by name, relative to the `resources/` dir.
+pub const MANIFEST: &str = "/sprites.txt";
+/// Covers the Polish letters; ggez's built-in font is only the fallback when it is missing.
+pub const FONT: &str = "/fonts/DejaVuSans.ttf";
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SpriteInfo {
+    pub image: String,
+    /// Where each frame sits in the image, in pixels.
+    pub frames: Vec<Rect>,
+}
+
+/// Sprite sheets from `resources/`. Anything missing is simply not there: games ask for a sprite
+/// and draw plain shapes when they do not get it.
+#[derive(Default)]
+pub struct Assets {
+    sprites: HashMap<String, SpriteInfo>,
+    images: HashMap<String, Option<Image>>,
+    font: Option<Font>,
+}
+
+impl Assets {
+    pub fn load(ctx: &mut Context) -> Self {
+        let font = match Font::new(ctx, FONT) {
+            Ok(font) => Some(font),
+            Err(error) => {
+                log::warn!("No {}, using the built-in font: {}", FONT, error);
+                None
+            }
+        };
+
+        let mut text = String::new();
+        let read = filesystem::open(ctx, MANIFEST).and_then(|mut file| Ok(file.read_to_string(&mut text)?));
+        if let Err(error) = read {
+            log::info!("No sprites, drawing shapes instead: {}", error);
+            return Assets { font, ..Assets::default() };
+        }
+        match Assets::parse(&text) {
+            Ok(sprites) => Assets { sprites, images: HashMap::new(), font },
+            Err(error) => {
+                log::error!("Could not read {}: {}", MANIFEST, error);
+                Assets { font, ..Assets::default() }
+            }
+        }
+    }
+
+    pub fn font(&self) -> Font {
+        self.font.unwrap_or_default()
+    }
+
+    /// `name image x y w h [frames]` per line; frames sit side by side starting at (x, y).
+    pub fn parse(text: &str) -> Result<HashMap<String, SpriteInfo>, String> {
+        let mut sprites = HashMap::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let fail = |message: &str| format!("line {}: {}", number + 1, message);
+            if fields.len() != 6 && fields.len() != 7 {
+                return Err(fail("expected name, image, x, y, width, height and an optional frame count"));
+            }
+            let numbers = fields[2..]
+                .iter()
+                .map(|field| field.parse::<u32>().map_err(|_| fail(&format!("'{}' is not a number", field))))
+                .collect::<Result<Vec<u32>, String>>()?;
+            let (x, y, w, h) = (numbers[0] as f32, numbers[1] as f32, numbers[2] as f32, numbers[3] as f32);
+            let count = numbers.get(4).copied().unwrap_or(1).max(1);
+            let frames = (0..count).map(|frame| Rect::new(x + frame as f32 * w, y, w, h)).collect();
+            sprites.insert(fields[0].to_string(), SpriteInfo { image: fields[1].to_string(), frames });
+        }
+        Ok(sprites)
+    }
+
+    /// The image and the part of it to draw, as a fraction of its size the way ggez wants it.
+    /// Frames past the last one loop back to the first.
+    pub fn frame(&mut self, ctx: &mut Context, name: &str, frame: usize) -> Option<(&Image, Rect)> {
+        let info = self.sprites.get(name)?;
+        let area = info.frames[frame % info.frames.len()];
+        let image = self.images.entry(info.image.clone()).or_insert_with(|| match Image::new(ctx, &info.image) {
+            Ok(mut image) => {
+                image.set_filter(FilterMode::Nearest);
+                Some(image)
+            }
+            Err(error) => {
+                log::error!("Could not load {}: {}", info.image, error);
+                None
+            }
+        });
+        let image = image.as_ref()?;
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        Some((image, Rect::new(area.x / width, area.y / height, area.w / width, area.h / height)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_sit_side_by_side() {
+        let sprites = Assets::parse("# comment\n\nbird /sheet.png 0 10 34 24 3\npipe /sheet.png 0 40 52 32\n").unwrap();
+        assert_eq!(sprites["bird"].frames, vec![
+            Rect::new(0.0, 10.0, 34.0, 24.0),
+            Rect::new(34.0, 10.0, 34.0, 24.0),
+            Rect::new(68.0, 10.0, 34.0, 24.0),
+        ]);
+        assert_eq!(sprites["pipe"].frames.len(), 1);
+        assert_eq!(sprites["pipe"].image, "/sheet.png");
+    }
+
+    #[test]
+    fn shipped_manifest_names_every_sprite_the_games_use() {
+        let sprites = Assets::parse(include_str!("../resources/sprites.txt")).unwrap();
+        for name in ["bird", "pipe", "pipe_cap", "snake_head", "snake_body", "snake_corner", "snake_tail", "food"] {
+            assert!(sprites.contains_key(name), "{} is missing", name);
+        }
+    }
+
+    #[test]
+    fn bad_lines_are_reported() {
+        assert!(Assets::parse("bird /sheet.png 0 0 34").unwrap_err().starts_with("line 1"));
+        assert!(Assets::parse("bird /sheet.png 0 0 34 wide").is_err());
+    }
+}
@@ -0,0 +1,101 @@
+//! Runs games without a window for as many ticks as asked, checks their invariants after every
+//! tick and reports how fast they simulate.
+//!
+//! cargo run --release --bin bench -- --ticks 5000000 --game snake --mode wrap
+
+use clap::Parser;
+use gierki_rustowe::games::{self, Difficulty, GameInput, RunConfig};
+use gierki_rustowe::soak::{self, InputScript};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+#[derive(Parser, Debug)]
+#[command(about = "Headless soak test and benchmark for the games")]
+struct Args {
+    /// Only run this game; every registered game by default
+    #[arg(long)]
+    game: Option<String>,
+
+    /// Only run this mode of the game; every mode by default
+    #[arg(long, requires = "game")]
+    mode: Option<String>,
+
+    /// Ticks per game and mode
+    #[arg(long, default_value_t = 1_000_000)]
+    ticks: u64,
+
+    /// Seed of the first run; later runs count up from it
+    #[arg(long, default_value_t = 1)]
+    seed: u64,
+
+    #[arg(long, default_value_t = Difficulty::Normal)]
+    difficulty: Difficulty,
+
+    /// Press these inputs in a loop (e.g. up,action,left) instead of random ones
+    #[arg(long, value_delimiter = ',')]
+    script: Vec<GameInput>,
+
+    /// Ticks between scripted inputs
+    #[arg(long, default_value_t = 10)]
+    every: u32,
+
+    /// Chance of a random input on each tick
+    #[arg(long, default_value_t = 0.05)]
+    chance: f64,
+
+    /// Where to write the replay of a run that breaks an invariant
+    #[arg(long, default_value = "soak-failure.replay")]
+    failure_replay: PathBuf,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let script = if args.script.is_empty() {
+        InputScript::Random { chance: args.chance.clamp(0.0, 1.0) }
+    } else {
+        InputScript::Cycle { inputs: args.script.clone(), every: args.every }
+    };
+
+    let mut configs = Vec::new();
+    for game in games::registry() {
+        if args.game.as_deref().is_some_and(|name| games::find_game(name).map(|found| found.id) != Some(game.id)) {
+            continue;
+        }
+        for mode in game.modes {
+            if args.mode.as_deref().is_some_and(|name| name != mode.id) {
+                continue;
+            }
+            let mut config = RunConfig::new(game.id, mode.id);
+            config.seed = args.seed;
+            config.difficulty = args.difficulty;
+            configs.push(config);
+        }
+    }
+    if configs.is_empty() {
+        eprintln!("error: no game matches --game {:?} --mode {:?}", args.game.unwrap_or_default(), args.mode.unwrap_or_default());
+        return ExitCode::from(2);
+    }
+
+    for config in configs {
+        match soak::soak(&config, args.ticks, &script) {
+            Ok(report) => println!(
+                "{:<12} {:<8} {:>10} ticks {:>7} runs  best {:>6}  {:>12.0} ticks/s",
+                report.config.game,
+                report.config.mode,
+                report.ticks,
+                report.runs,
+                report.best_score,
+                report.ticks_per_second()
+            ),
+            Err(violation) => {
+                eprintln!("invariant broken: {}", violation);
+                match violation.replay.save(&args.failure_replay) {
+                    Ok(()) => eprintln!("replay of the run written to {}", args.failure_replay.display()),
+                    Err(error) => eprintln!("could not write {}: {}", args.failure_replay.display(), error),
+                }
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,60 @@
+//! Keeps a leaderboard shared by several computers. Point the game at it with
+//! `leaderboard_server = http://HOST:PORT` in settings.txt; runs are stored in an SQLite file.
+//!
+//! cargo run --release --bin leaderboard-server -- --port 7780 --db leaderboard.sqlite
+
+use clap::Parser;
+use gierki_rustowe::net::leaderboard::{Database, LeaderboardServer, DEFAULT_LEADERBOARD_PORT};
+use gierki_rustowe::{logger, storage};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::AtomicBool;
+
+#[derive(Parser, Debug)]
+#[command(about = "Shared leaderboard server")]
+struct Args {
+    /// Address to listen on; 127.0.0.1 keeps the leaderboard to this machine
+    #[arg(long, default_value = "0.0.0.0")]
+    bind: String,
+
+    #[arg(long, default_value_t = DEFAULT_LEADERBOARD_PORT)]
+    port: u16,
+
+    /// SQLite file with the runs; leaderboard.sqlite in the data dir by default
+    #[arg(long, value_name = "FILE")]
+    db: Option<PathBuf>,
+
+    /// Keep the log (and by default the database) in this directory
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let storage = storage::Storage::headless(args.data_dir);
+    logger::init(&storage.data_path(storage::LOG_FILE));
+
+    let path = args.db.unwrap_or_else(|| storage.data_path("leaderboard.sqlite"));
+    let database = match Database::open(&path) {
+        Ok(database) => database,
+        Err(error) => {
+            eprintln!("Could not open the database: {}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    let address = format!("{}:{}", args.bind, args.port);
+    let mut server = match LeaderboardServer::bind(&address, database) {
+        Ok(server) => server,
+        Err(error) => {
+            eprintln!("Could not listen on {}", error);
+            return ExitCode::FAILURE;
+        }
+    };
+    let listening = server.local_addr().map_or(address, |address| address.to_string());
+    println!("Listening on http://{}, runs in {}", listening, path.display());
+    log::info!("Leaderboard server started on {} with {}", listening, path.display());
+
+    // Runs until the process is stopped.
+    server.run(&AtomicBool::new(false));
+    ExitCode::SUCCESS
+}
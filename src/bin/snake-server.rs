@@ -0,0 +1,80 @@
+//! Hosts a Snake game for up to eight players. Players join from the window version with
+//! `--connect HOST:PORT`; the server owns the board and sends every player the state each tick.
+//!
+//! cargo run --release --bin snake-server -- --port 7777
+
+use clap::Parser;
+use gierki_rustowe::games::TICKS_PER_SECOND;
+use gierki_rustowe::net::arena::MAX_PLAYERS;
+use gierki_rustowe::net::protocol::DEFAULT_PORT;
+use gierki_rustowe::net::server::{Server, ServerConfig};
+use gierki_rustowe::{logger, storage};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::sync::atomic::AtomicBool;
+use std::time::Duration;
+
+#[derive(Parser, Debug)]
+#[command(about = "Multiplayer Snake server")]
+struct Args {
+    /// Address to listen on; 127.0.0.1 keeps the game to this machine
+    #[arg(long, default_value = "0.0.0.0")]
+    bind: String,
+
+    #[arg(long, default_value_t = DEFAULT_PORT)]
+    port: u16,
+
+    /// At most eight
+    #[arg(long, default_value_t = MAX_PLAYERS)]
+    max_players: usize,
+
+    /// Ticks per second
+    #[arg(long, default_value_t = TICKS_PER_SECOND)]
+    tick_rate: u32,
+
+    /// Seconds of silence before a player is dropped
+    #[arg(long, default_value_t = 10)]
+    timeout: u64,
+
+    /// Seed of the board; a random one by default
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Snakes leave one edge and come back in on the other
+    #[arg(long)]
+    wrap: bool,
+
+    /// Keep the log in this directory
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+    let storage = storage::Storage::headless(args.data_dir);
+    logger::init(&storage.data_path(storage::LOG_FILE));
+
+    let config = ServerConfig {
+        max_players: args.max_players,
+        tick_rate: args.tick_rate.max(1),
+        timeout: Duration::from_secs(args.timeout),
+        seed: args.seed.unwrap_or_else(rand::random),
+        wrap: args.wrap,
+    };
+    let mut server = match Server::bind((args.bind.as_str(), args.port), config) {
+        Ok(server) => server,
+        Err(error) => {
+            eprintln!("Could not listen on {}:{}: {}", args.bind, args.port, error);
+            return ExitCode::FAILURE;
+        }
+    };
+    match server.local_addr() {
+        Ok(address) => println!("Listening on {}", address),
+        Err(_) => println!("Listening on {}:{}", args.bind, args.port),
+    }
+    log::info!("Snake server started on {}:{}", args.bind, args.port);
+
+    // Runs until the process is stopped.
+    server.run(&AtomicBool::new(false));
+    ExitCode::SUCCESS
+}
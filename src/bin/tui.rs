@@ -0,0 +1,579 @@
+//! Plays the games in a terminal, for machines without a display. Same games, same keys and the
+//! same score file as the window version.
+//!
+//! cargo run --bin tui
+
+use clap::Parser;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use crossterm::terminal::{self, EnterAlternateScreen, LeaveAlternateScreen};
+use crossterm::{cursor, ExecutableCommand};
+use gierki_rustowe::effects::Effects;
+use gierki_rustowe::games::ghost::{self, Ghost};
+use gierki_rustowe::games::{course, script};
+use gierki_rustowe::games::session::{RunSummary, Session};
+use gierki_rustowe::games::{self, GameInput, RunConfig, TICKS_PER_SECOND};
+use gierki_rustowe::i18n::Strings;
+use gierki_rustowe::logger;
+use gierki_rustowe::render::terminal::{TerminalRenderer, COLUMNS, ROWS};
+use gierki_rustowe::render::{RectStyle, Renderer, Shifted};
+use gierki_rustowe::replay::Replay;
+use gierki_rustowe::save::SaveGame;
+use gierki_rustowe::scenes::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use gierki_rustowe::scenes::find_theme;
+use gierki_rustowe::scenes::game_over::{GameOverPanel, PanelAction};
+use gierki_rustowe::scores::{self, ScoreBoard};
+use gierki_rustowe::settings::{self, Settings};
+use gierki_rustowe::stats::History;
+use gierki_rustowe::theme::{self, Theme};
+use gierki_rustowe::{daily, date, storage};
+use ggez::graphics::Rect;
+use std::io::{self, Stdout, Write};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const FRAME: Duration = Duration::from_millis(33);
+const STATUS_SECONDS: u64 = 4;
+const LEADERBOARD_ROWS: usize = 20;
+
+#[derive(Parser, Debug)]
+#[command(about = "Gierki Rustowe in the terminal")]
+struct Args {
+    /// Keep scores, replays and logs in this directory
+    #[arg(long, value_name = "DIR")]
+    data_dir: Option<PathBuf>,
+}
+
+enum MenuItem {
+    Continue,
+    Play(RunConfig),
+    Leaderboard,
+}
+
+struct Playing {
+    session: Session,
+    pending: Vec<GameInput>,
+    paused: bool,
+    effects: Effects,
+    /// Set once the replay and score are saved, so a run is never saved twice.
+    finished: bool,
+    panel: Option<GameOverPanel>,
+    ghost: Option<Ghost>,
+}
+
+impl Playing {
+    fn new(config: RunConfig) -> Self {
+        Playing::with_session(Session::new(config))
+    }
+
+    fn with_session(session: Session) -> Self {
+        Playing { session, pending: Vec::new(), paused: false, effects: Effects::new(), finished: false, panel: None, ghost: None }
+    }
+
+
+    /// The same run again: a fresh seed for a game, the start of the recording for a replay.
+    fn restarted(&self) -> Self {
+        if self.session.is_playback() {
+            return Playing::with_session(self.session.restarted());
+        }
+        let mut config = self.session.config.clone();
+        config.reseed();
+        Playing::new(config)
+    }
+}
+
+enum Screen {
+    Menu { selected: usize },
+    Playing(Box<Playing>),
+    Leaderboard { tab: usize, scroll: usize },
+}
+
+struct App {
+    storage: storage::Storage,
+    scores: ScoreBoard,
+    history: History,
+    settings: Settings,
+    theme: Theme,
+    strings: Strings,
+    menu: Vec<(String, MenuItem)>,
+    screen: Screen,
+    status: Option<(String, Instant)>,
+    quit: bool,
+}
+
+impl App {
+    fn new(storage: storage::Storage) -> Self {
+        let (scores, mut notices) = ScoreBoard::load(storage.data_path(storage::SCORES_FILE));
+        let (history, history_notices) = History::load(storage.data_path(storage::HISTORY_FILE));
+        notices.extend(history_notices);
+        // Settings are changed in the window version's settings screen; the terminal only follows them.
+        let (settings, settings_notices) = Settings::load(storage.config_path(settings::SETTINGS_FILE));
+        let (themes, theme_notices) = Theme::load_all(&storage.config_path(theme::THEMES_DIR));
+        notices.extend(settings_notices.into_iter().chain(theme_notices));
+        notices.extend(games::load_scripts(&storage.config_path(script::GAMES_DIR)));
+        course::set_dir(storage.config_path(course::COURSES_DIR));
+        let (courses, course_notices) = course::all();
+        notices.extend(course_notices);
+        let theme = find_theme(&themes, &settings.theme);
+        let strings = Strings::load(&settings.language);
+
+        let mut menu = vec![(strings.get("menu.continue").to_string(), MenuItem::Continue)];
+        for game in games::registry() {
+            for mode in game.modes {
+                // One entry per course, as each one is a board of its own.
+                let modes: Vec<String> = if mode.id == course::MODE {
+                    courses.iter().map(|info| course::mode(&info.id)).collect()
+                } else {
+                    vec![mode.id.to_string()]
+                };
+                for mode in modes {
+                    let label = strings.run_title("menu.run", game.id, &mode);
+                    menu.push((label, MenuItem::Play(RunConfig::new(game.id, &mode))));
+                }
+            }
+        }
+        menu.push((strings.get("menu.leaderboard").to_string(), MenuItem::Leaderboard));
+
+        let mut app = App { storage, scores, history, settings, theme, strings, menu, screen: Screen::Menu { selected: 0 }, status: None, quit: false };
+        if let Some(notice) = notices.last() {
+            app.show_status(app.strings.notice(notice));
+        }
+        app
+    }
+
+    fn show_status(&mut self, message: impl Into<String>) {
+        self.status = Some((message.into(), Instant::now()));
+    }
+
+    /// Brings in the ghost from the settings, moving a new run onto its seed, and turns daily
+    /// challenges already played today into practice, the same way the window version does.
+    fn start(&self, mut playing: Playing) -> Screen {
+        if !playing.session.is_playback() {
+            let mut config = playing.session.config.clone();
+            playing.ghost = Ghost::load(&self.storage, self.settings.ghost, &mut config, playing.session.tick > 0);
+            if config.seed != playing.session.config.seed {
+                playing.session = Session::new(config);
+            }
+            if let Some(ghost) = &mut playing.ghost {
+                ghost.skip_to(playing.session.tick);
+            }
+            playing.session.practice = daily::is_practice(&self.history, &playing.session.config, &scores::player_name());
+        }
+        Screen::Playing(Box::new(playing))
+    }
+
+    fn open(&mut self, index: usize) {
+        match self.menu.get(index).map(|(_, item)| item) {
+            Some(MenuItem::Play(config)) => {
+                let mut config = config.clone();
+                config.reseed();
+                self.screen = self.start(Playing::new(config));
+            }
+            Some(MenuItem::Continue) => self.resume(),
+            Some(MenuItem::Leaderboard) => self.screen = Screen::Leaderboard { tab: 0, scroll: 0 },
+            None => {}
+        }
+    }
+
+    /// Picks up the window version's save slot too; it is deleted once loaded.
+    fn resume(&mut self) {
+        let path = self.storage.data_path(storage::SAVE_FILE);
+        if !path.exists() {
+            self.show_status(self.strings.get("menu.no_save").to_string());
+            return;
+        }
+        match SaveGame::load(&path).and_then(Session::resume) {
+            Ok(session) => {
+                if let Err(error) = std::fs::remove_file(&path) {
+                    log::warn!("Could not remove {}: {}", path.display(), error);
+                }
+                self.screen = self.start(Playing::with_session(session));
+            }
+            Err(error) => {
+                log::error!("Could not resume the saved game: {}", error);
+                self.show_status(self.strings.format("error.resume", &[("error", &error)]));
+            }
+        }
+    }
+
+    /// Puts the run in the save slot and goes back to the menu; if it cannot be saved it ends as usual.
+    fn save_and_quit(&mut self) {
+        if let Screen::Playing(playing) = &mut self.screen {
+            if let Some(save) = playing.session.save() {
+                let path = self.storage.data_path(storage::SAVE_FILE);
+                match save.save(&path) {
+                    Ok(()) => {
+                        playing.finished = true;
+                        self.show_status(self.strings.get("play.saved").to_string());
+                    }
+                    Err(error) => {
+                        log::error!("Could not save the game to {}: {}", path.display(), error);
+                        self.show_status(self.strings.format("error.save", &[("error", &error)]));
+                    }
+                }
+            }
+        }
+        self.leave_game();
+    }
+
+    /// Saves the run's replay and, for ranked runs, its score, the same way the window version does.
+    /// Only the first call for a run saves anything.
+    fn finish(&mut self, playing: &mut Playing) -> Option<RunSummary> {
+        if playing.finished || playing.session.tick == 0 {
+            return None;
+        }
+        playing.finished = true;
+        let session = &playing.session;
+        if !session.is_playback() {
+            let config = &session.config;
+            let path = self.storage.replay_path(&Replay::file_name(&config.game, &config.mode));
+            if let Err(error) = session.recording().save(&path) {
+                log::error!("Could not save replay to {}: {}", path.display(), error);
+                self.show_status(self.strings.format("error.replay", &[("error", &error)]));
+            }
+            if let Err(error) = self.history.add(session.run_record()) {
+                log::error!("Could not save the run history: {}", error);
+                self.show_status(self.strings.format("error.history", &[("error", &error)]));
+            }
+        }
+
+        let summary = session.record(&mut self.scores);
+        if summary.entry.is_some() {
+            if let Err(error) = self.scores.save() {
+                log::error!("Could not save scores: {}", error);
+                self.show_status(self.strings.format("error.scores", &[("error", &error)]));
+            }
+        }
+        if let Err(error) = ghost::keep(&self.storage, session, &summary) {
+            log::error!("Could not keep the run as a ghost: {}", error);
+            self.show_status(self.strings.format("error.replay", &[("error", &error)]));
+        }
+        Some(summary)
+    }
+
+    /// Runs left before the panel showed up are saved here.
+    fn leave_game(&mut self) {
+        if let Screen::Playing(mut playing) = std::mem::replace(&mut self.screen, Screen::Menu { selected: 0 }) {
+            let config = &playing.session.config;
+            let unranked = !config.is_ranked() && !playing.session.is_playback();
+            let difficulty = self.strings.get(&format!("difficulty.{}", config.difficulty)).to_string();
+            if self.finish(&mut playing).is_some() && unranked {
+                self.show_status(self.strings.format("play.unranked", &[("difficulty", &difficulty)]));
+            }
+        }
+    }
+
+    /// The run is finished before the next one starts, so a daily restart already counts as practice.
+    fn run_action(&mut self, action: PanelAction) {
+        if action == PanelAction::Menu {
+            return self.leave_game();
+        }
+        if !matches!(self.screen, Screen::Playing(_)) {
+            return;
+        }
+        if let Screen::Playing(mut finished) = std::mem::replace(&mut self.screen, Screen::Menu { selected: 0 }) {
+            self.finish(&mut finished);
+            let next = match action {
+                PanelAction::WatchReplay => Playing::with_session(Session::replay(finished.session.recording())),
+                _ => finished.restarted(),
+            };
+            self.screen = self.start(next);
+        }
+    }
+
+    fn key(&mut self, key: KeyEvent) {
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            self.leave_game();
+            self.quit = true;
+            return;
+        }
+
+        match &mut self.screen {
+            Screen::Menu { selected } => match key.code {
+                KeyCode::Up => *selected = (*selected + self.menu.len() - 1) % self.menu.len(),
+                KeyCode::Down => *selected = (*selected + 1) % self.menu.len(),
+                KeyCode::Enter => {
+                    let index = *selected;
+                    self.open(index);
+                }
+                KeyCode::Char(digit @ '1'..='9') => self.open(digit as usize - '1' as usize),
+                KeyCode::Esc | KeyCode::Char('q') => self.quit = true,
+                _ => {}
+            },
+            Screen::Playing(playing) if playing.panel.is_some() => {
+                let panel = playing.panel.as_mut().expect("checked above");
+                let action = match key.code {
+                    KeyCode::Char('r') | KeyCode::Char(' ') => Some(PanelAction::Restart),
+                    KeyCode::Char('m') | KeyCode::Esc => Some(PanelAction::Menu),
+                    KeyCode::Char('w') if panel.has(PanelAction::WatchReplay) => Some(PanelAction::WatchReplay),
+                    KeyCode::Enter => Some(panel.selected()),
+                    KeyCode::Left | KeyCode::Up => {
+                        panel.select_next(-1);
+                        None
+                    }
+                    KeyCode::Right | KeyCode::Down | KeyCode::Tab => {
+                        panel.select_next(1);
+                        None
+                    }
+                    _ => None,
+                };
+                if let Some(action) = action {
+                    self.run_action(action);
+                }
+            }
+            Screen::Playing(playing) => match key.code {
+                KeyCode::Esc => self.leave_game(),
+                // R skips the death animation too, for an instant restart.
+                KeyCode::Char('r') if playing.session.game.is_over() => self.run_action(PanelAction::Restart),
+                KeyCode::Char('p') if !playing.session.game.is_over() => playing.paused = !playing.paused,
+                KeyCode::Char('q') if playing.paused => self.leave_game(),
+                KeyCode::Char('s') if playing.paused => self.save_and_quit(),
+                code if !playing.paused => {
+                    if let Some(input) = game_input(code) {
+                        playing.pending.push(input);
+                    }
+                }
+                _ => {}
+            },
+            Screen::Leaderboard { tab, scroll } => {
+                let tabs = self.scores.tabs().len().max(1);
+                match key.code {
+                    KeyCode::Esc => self.screen = Screen::Menu { selected: self.menu.len() - 1 },
+                    KeyCode::Left => (*tab, *scroll) = ((*tab + tabs - 1) % tabs, 0),
+                    KeyCode::Right | KeyCode::Tab => (*tab, *scroll) = ((*tab + 1) % tabs, 0),
+                    KeyCode::Up => *scroll = scroll.saturating_sub(1),
+                    KeyCode::Down => *scroll += 1,
+                    KeyCode::PageUp => *scroll = scroll.saturating_sub(LEADERBOARD_ROWS),
+                    KeyCode::PageDown => *scroll += LEADERBOARD_ROWS,
+                    KeyCode::Home => *scroll = 0,
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    fn tick(&mut self) {
+        let Screen::Playing(playing) = &mut self.screen else {
+            return;
+        };
+        if playing.paused {
+            return;
+        }
+        if let Some(ghost) = playing.ghost.as_mut().filter(|_| !playing.session.game.is_over()) {
+            ghost.step();
+        }
+        playing.session.step(playing.pending.drain(..));
+        if let Some(error) = playing.session.game.error() {
+            self.screen = Screen::Menu { selected: 0 };
+            self.show_status(self.strings.format("tui.script_error", &[("error", &error)]));
+            return;
+        }
+        playing.effects.follow(&mut playing.session, &self.theme, &self.settings);
+
+        if playing.session.is_finished() && playing.panel.is_none() {
+            if let Screen::Playing(mut playing) = std::mem::replace(&mut self.screen, Screen::Menu { selected: 0 }) {
+                if let Some(summary) = self.finish(&mut playing) {
+                    let mut panel = GameOverPanel::new(summary, &playing.session, &self.strings);
+                    let config = &playing.session.config;
+                    if daily::is_daily(&config.mode) && !playing.session.is_playback() {
+                        panel = panel.with_streak(daily::streak(&self.history, &config.game, &scores::player_name(), date::now_timestamp()));
+                    }
+                    playing.panel = Some(panel);
+                }
+                self.screen = Screen::Playing(playing);
+            }
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        matches!(&self.screen, Screen::Playing(playing) if !playing.paused)
+    }
+
+    fn draw(&mut self, renderer: &mut TerminalRenderer) -> ggez::GameResult {
+        let (theme, strings) = (&self.theme, &self.strings);
+        renderer.clear(theme.background);
+        match &mut self.screen {
+            Screen::Menu { selected } => {
+                renderer.text(strings.get("menu.title"), 350.0, 80.0, 50.0, theme.text)?;
+                for (index, (label, _)) in self.menu.iter().enumerate() {
+                    let y = 160.0 + index as f32 * 50.0;
+                    let color = if index == *selected { theme.button_selected } else { theme.button };
+                    renderer.rect(Rect::new(200.0, y - 10.0, 400.0, 40.0), color, RectStyle::Filled)?;
+                    renderer.text(&format!("{}. {}", index + 1, label), 220.0, y, 30.0, theme.text)?;
+                }
+                renderer.text(strings.get("tui.menu_help"), 160.0, 520.0, 20.0, theme.warning)?;
+            }
+            Screen::Playing(playing) => {
+                renderer.rect(Rect::new(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT), theme.sky, RectStyle::Filled)?;
+                let (x, y) = playing.effects.offset();
+                let mut shifted = Shifted { inner: renderer, x, y };
+                if let Some(ghost) = &playing.ghost {
+                    ghost.draw(&mut shifted, theme)?;
+                }
+                playing.session.game.draw(&mut shifted, theme)?;
+                playing.effects.draw(&mut shifted)?;
+                if let Some(panel) = &playing.panel {
+                    panel.draw(renderer, theme, strings)?;
+                } else if playing.paused {
+                    renderer.rect(Rect::new(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT), theme.overlay, RectStyle::Filled)?;
+                    renderer.text(strings.get("pause.title"), 370.0, 240.0, 50.0, theme.overlay_text)?;
+                    renderer.text(strings.get("tui.pause_help"), 220.0, 300.0, 25.0, theme.overlay_text)?;
+                } else {
+                    let score = strings.format("play.score", &[("score", &playing.session.game.score())]);
+                    renderer.text(&score, 10.0, 580.0, 20.0, theme.game_text)?;
+                    if let Some(ghost) = &playing.ghost {
+                        ghost.draw_lead(renderer, theme, strings, playing.session.game.score())?;
+                    }
+                }
+            }
+            Screen::Leaderboard { tab, scroll } => {
+                let tabs = self.scores.tabs();
+                *tab = (*tab).min(tabs.len().saturating_sub(1));
+                renderer.text(strings.get("leaderboard.title"), 340.0, 20.0, 50.0, theme.text)?;
+                let mut x = 20.0;
+                for (index, board) in tabs.iter().enumerate() {
+                    let title = strings.run_title("leaderboard.board", &board.game, &board.mode);
+                    let width = renderer.text_width(&title, 18.0) + 20.0;
+                    let color = if index == *tab { theme.button_selected } else { theme.tab };
+                    renderer.rect(Rect::new(x, 60.0, width, 20.0), color, RectStyle::Filled)?;
+                    renderer.text(&title, x + 10.0, 60.0, 18.0, theme.text)?;
+                    x += width + 10.0;
+                }
+
+                let entries = tabs.get(*tab).map(|board| self.scores.board(&board.game, &board.mode)).unwrap_or_default();
+                *scroll = (*scroll).min(entries.len().saturating_sub(LEADERBOARD_ROWS));
+                let mode = tabs.get(*tab).map_or("", |board| board.mode.as_str());
+                let score_column = if scores::is_timed(mode) { "finish" } else { "score" };
+                for (column, x) in [("rank", 40.0), ("name", 100.0), (score_column, 380.0), ("date", 500.0), ("time", 660.0)] {
+                    renderer.text(strings.get(&format!("leaderboard.{}", column)), x, 100.0, 22.0, theme.text)?;
+                }
+                if entries.is_empty() {
+                    renderer.text(strings.get("leaderboard.empty"), 330.0, 160.0, 25.0, theme.text)?;
+                }
+                for (row, (rank, entry)) in entries.iter().enumerate().skip(*scroll).take(LEADERBOARD_ROWS).enumerate() {
+                    let y = 120.0 + row as f32 * 20.0;
+                    let cells = [
+                        (rank + 1).to_string(),
+                        entry.name.clone(),
+                        scores::format_score(mode, entry.score),
+                        date::format_date(entry.timestamp),
+                        date::format_duration(entry.duration),
+                    ];
+                    for (cell, x) in cells.iter().zip([40.0, 100.0, 380.0, 500.0, 660.0]) {
+                        renderer.text(cell, x, y, 22.0, theme.text)?;
+                    }
+                }
+                renderer.text(strings.get("tui.leaderboard_help"), 60.0, 560.0, 16.0, theme.text)?;
+            }
+        }
+
+        if let Some((message, shown)) = &self.status {
+            if shown.elapsed() < Duration::from_secs(STATUS_SECONDS) {
+                let width = renderer.text_width(message, 18.0);
+                renderer.rect(Rect::new(SCREEN_WIDTH - width - 20.0, 580.0, width + 20.0, 20.0), theme.toast, RectStyle::Filled)?;
+                renderer.text(message, SCREEN_WIDTH - width - 10.0, 580.0, 18.0, theme.toast_text)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The keys the window version maps in `GameInput::from_keycode`.
+fn game_input(code: KeyCode) -> Option<GameInput> {
+    match code {
+        KeyCode::Up | KeyCode::Char('w') => Some(GameInput::Up),
+        KeyCode::Down | KeyCode::Char('s') => Some(GameInput::Down),
+        KeyCode::Left | KeyCode::Char('a') => Some(GameInput::Left),
+        KeyCode::Right | KeyCode::Char('d') => Some(GameInput::Right),
+        KeyCode::Char(' ') => Some(GameInput::Action),
+        _ => None,
+    }
+}
+
+/// Puts the terminal back the way it was, also when the game panics or fails.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter(out: &mut Stdout) -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        out.execute(EnterAlternateScreen)?.execute(cursor::Hide)?;
+        Ok(TerminalGuard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let mut out = io::stdout();
+        let _ = out.execute(cursor::Show).and_then(|out| out.execute(LeaveAlternateScreen));
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+fn run(app: &mut App, out: &mut Stdout) -> io::Result<()> {
+    let tick = Duration::from_secs_f64(1.0 / TICKS_PER_SECOND as f64);
+    let mut renderer = TerminalRenderer::new();
+    let mut next_tick = Instant::now();
+    let mut next_frame = Instant::now();
+
+    while !app.quit {
+        let now = Instant::now();
+        let wait = next_tick.min(next_frame).saturating_duration_since(now);
+        if event::poll(wait)? {
+            match event::read()? {
+                Event::Key(key) if key.kind != KeyEventKind::Release => app.key(key),
+                Event::Resize(..) => {
+                    out.execute(terminal::Clear(terminal::ClearType::All))?;
+                    renderer.invalidate();
+                }
+                _ => {}
+            }
+        }
+
+        if app.is_running() {
+            // A slow terminal may fall behind; catch up a little but never spiral.
+            let mut caught_up = 0;
+            while Instant::now() >= next_tick && caught_up < 5 {
+                app.tick();
+                next_tick += tick;
+                caught_up += 1;
+            }
+            if caught_up == 5 {
+                next_tick = Instant::now() + tick;
+            }
+        } else {
+            next_tick = Instant::now() + tick;
+        }
+
+        if Instant::now() >= next_frame {
+            next_frame = Instant::now() + FRAME;
+            let (columns, rows) = terminal::size()?;
+            if columns < COLUMNS || rows < ROWS {
+                out.execute(cursor::MoveTo(0, 0))?;
+                let (need, have) = (format!("{}x{}", COLUMNS, ROWS), format!("{}x{}", columns, rows));
+                write!(out, "{}", app.strings.format("tui.too_small", &[("need", &need), ("have", &have)]))?;
+                out.flush()?;
+                renderer.invalidate();
+                continue;
+            }
+            if let Err(error) = app.draw(&mut renderer) {
+                return Err(io::Error::other(error.to_string()));
+            }
+            renderer.flush(out)?;
+        }
+    }
+    Ok(())
+}
+
+fn main() -> io::Result<()> {
+    let args = Args::parse();
+    let storage = storage::Storage::headless(args.data_dir);
+    logger::init(&storage.data_path(storage::LOG_FILE));
+    let mut app = App::new(storage);
+
+    let mut out = io::stdout();
+    let result = {
+        let _guard = TerminalGuard::enter(&mut out)?;
+        run(&mut app, &mut out)
+    };
+    app.leave_game();
+    result
+}
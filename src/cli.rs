@@ -0,0 +1,172 @@
+use crate::daily;
+use crate::date;
+use crate::games::{course, script};
+use crate::games::{self, Difficulty, RunConfig};
+use crate::scores::{self, ScoreBoard};
+use crate::storage::Storage;
+use clap::{CommandFactory, Parser};
+use std::path::PathBuf;
+
+/// Gierki Rustowe Fajne i Kolorowe
+#[derive(Parser, Debug)]
+#[command(version, about)]
+pub struct Cli {
+    /// Start this game right away instead of showing the menu (e.g. snake, flappy_bird)
+    #[arg(long)]
+    pub game: Option<String>,
+
+    /// Game mode, e.g. classic or wrap
+    #[arg(long, requires = "game")]
+    pub mode: Option<String>,
+
+    /// Seed for the game's random numbers; the same seed and inputs replay the same run
+    #[arg(long, requires = "game")]
+    pub seed: Option<u64>,
+
+    /// easy, normal or hard; only normal runs go on the leaderboard
+    #[arg(long, requires = "game", default_value_t = Difficulty::Normal)]
+    pub difficulty: Difficulty,
+
+    /// Window size as WIDTHxHEIGHT
+    #[arg(long, value_name = "WxH", value_parser = parse_window_size, default_value = "800x600")]
+    pub windowed: (f32, f32),
+
+    /// Keep scores, replays and logs in this directory
+    #[arg(long, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// Play back a recorded .replay file
+    #[arg(long, value_name = "FILE", conflicts_with = "game")]
+    pub replay: Option<PathBuf>,
+
+    /// Join a Snake server, e.g. 192.168.1.10:7777
+    #[arg(long, value_name = "HOST:PORT", conflicts_with_all = ["game", "replay"])]
+    pub connect: Option<String>,
+
+    /// Name shown to the other players on the server; the user name by default
+    #[arg(long, requires = "connect")]
+    pub name: Option<String>,
+
+    /// Host an online Pong game for one other player on PORT (7778 by default)
+    #[arg(long, value_name = "PORT", num_args = 0..=1, default_missing_value = "7778", conflicts_with_all = ["game", "replay", "connect"])]
+    pub host_pong: Option<u16>,
+
+    /// Join an online Pong game hosted at HOST:PORT
+    #[arg(long, value_name = "HOST:PORT", conflicts_with_all = ["game", "replay", "connect", "host_pong"])]
+    pub join_pong: Option<String>,
+
+    /// Stream the running game as JSON over a WebSocket on PORT (7781 by default), with a viewer
+    /// page at http://HOST:PORT/
+    #[arg(long, value_name = "PORT", num_args = 0..=1, default_missing_value = "7781")]
+    pub spectate: Option<u16>,
+
+    /// Let other computers on the network watch too; without it only this one can
+    #[arg(long, requires = "spectate")]
+    pub spectate_lan: bool,
+
+    /// Print the leaderboard to stdout and exit
+    #[arg(long)]
+    pub list_scores: bool,
+
+    /// Write every finished run to FILE as CSV or JSON (picked by the extension) and exit
+    #[arg(long, value_name = "FILE")]
+    pub export: Option<PathBuf>,
+}
+
+impl Cli {
+    /// Parses the arguments and checks the game and mode against the registry, exiting with a
+    /// usage error if they do not exist.
+    pub fn parse_and_validate() -> Self {
+        let cli = Cli::parse();
+        // Script games have to be in the registry before the game names are checked against it.
+        let storage = Storage::headless(cli.data_dir.clone());
+        course::set_dir(storage.config_path(course::COURSES_DIR));
+        for notice in games::load_scripts(&storage.config_path(script::GAMES_DIR)) {
+            eprintln!("{}", notice);
+        }
+        if let Err(message) = cli.run_config().transpose() {
+            Cli::command().error(clap::error::ErrorKind::InvalidValue, message).exit();
+        }
+        cli
+    }
+
+    /// The run asked for with `--game`, if any.
+    pub fn run_config(&self) -> Option<Result<RunConfig, String>> {
+        let name = self.game.as_ref()?;
+        Some(build_config(name, self.mode.as_deref(), self.difficulty, self.seed))
+    }
+}
+
+fn build_config(name: &str, mode: Option<&str>, difficulty: Difficulty, seed: Option<u64>) -> Result<RunConfig, String> {
+    let game = games::find_game(name).ok_or_else(|| {
+        let known: Vec<&str> = games::registry().iter().map(|game| game.id).collect();
+        format!("unknown game '{}' (expected one of: {})", name, known.join(", "))
+    })?;
+    // `course-elevators` picks a single course for games with the Courses mode.
+    let picked_course = mode.and_then(course::id).filter(|_| game.modes.iter().any(|known| known.id == course::MODE));
+    let mode = match (mode, picked_course) {
+        (Some(mode), Some(id)) => {
+            course::find(id).map_err(|error| format!("{}: {}", mode, error))?;
+            mode
+        }
+        (Some(mode), None) => game.modes.iter().find(|known| known.id == mode).map(|known| known.id).ok_or_else(|| {
+            let known: Vec<&str> = game.modes.iter().map(|mode| mode.id).collect();
+            format!("{} has no mode '{}' (expected one of: {})", game.title, mode, known.join(", "))
+        })?,
+        (None, _) => game.modes[0].id,
+    };
+
+    if mode == daily::MODE && seed.is_some() {
+        return Err("the daily challenge always plays the day's seed".to_string());
+    }
+
+    let mut config = RunConfig::new(game.id, mode);
+    config.difficulty = difficulty;
+    if let Some(seed) = seed {
+        config.seed = seed;
+    }
+    Ok(config)
+}
+
+fn parse_window_size(value: &str) -> Result<(f32, f32), String> {
+    let (width, height) = value
+        .split_once(['x', 'X'])
+        .ok_or_else(|| format!("expected WIDTHxHEIGHT, got '{}'", value))?;
+    let parse = |side: &str| match side.trim().parse::<u32>() {
+        Ok(size) if size > 0 => Ok(size as f32),
+        _ => Err(format!("'{}' is not a valid window size", value)),
+    };
+    Ok((parse(width)?, parse(height)?))
+}
+
+/// Writes every non-empty board as a plain text table.
+pub fn print_scores(scores: &ScoreBoard) {
+    let mut printed = false;
+    for tab in scores.tabs() {
+        let entries = scores.board(&tab.game, &tab.mode);
+        if entries.is_empty() {
+            continue;
+        }
+        if printed {
+            println!();
+        }
+        printed = true;
+
+        println!("{}", tab.title);
+        let score_column = if scores::is_timed(&tab.mode) { "Finish" } else { "Score" };
+        println!("{:>4}  {:<20} {:>8}  {:<10} {:>6}", "#", "Name", score_column, "Date", "Time");
+        for (rank, entry) in entries.iter().enumerate() {
+            println!(
+                "{:>4}  {:<20} {:>8}  {:<10} {:>6}",
+                rank + 1,
+                entry.name,
+                scores::format_score(&tab.mode, entry.score),
+                date::format_date(entry.timestamp),
+                date::format_duration(entry.duration)
+            );
+        }
+    }
+    if !printed {
+        println!("No scores yet");
+    }
+}
@@ -0,0 +1,91 @@
+//! The daily challenge: one course a day, the same for everyone, seeded from the UTC date. Each
+//! day's runs go on a board of their own, and only the first run of the day is scored.
+
+use crate::date;
+use crate::games::{Difficulty, RunConfig};
+use crate::stats::History;
+
+/// The mode games register for the challenge; a run's own mode also carries the day, see [`mode`].
+pub const MODE: &str = "daily";
+
+const PREFIX: &str = "daily-";
+
+/// `daily-2024-05-01`: the mode, and so the board, of that day's challenge.
+pub fn mode(timestamp: u64) -> String {
+    format!("{}{}", PREFIX, date::format_date(timestamp))
+}
+
+pub fn is_daily(mode: &str) -> bool {
+    mode.starts_with(PREFIX)
+}
+
+/// FNV-1a of the day's mode, so the seed is the same on every machine and every platform.
+pub fn seed(mode: &str) -> u64 {
+    mode.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+}
+
+/// The challenge of the day `timestamp` falls on.
+pub fn config(game: &str, timestamp: u64) -> RunConfig {
+    let mode = mode(timestamp);
+    RunConfig { game: game.to_string(), seed: seed(&mode), mode, difficulty: Difficulty::Normal }
+}
+
+/// Runs after `player`'s first one of the day are practice: played in full, but kept off the board.
+pub fn is_practice(history: &History, config: &RunConfig, player: &str) -> bool {
+    is_daily(&config.mode) && history.has_played(&config.game, &config.mode, player)
+}
+
+/// `player`'s days in a row with the challenge of `game`, as of `now`.
+pub fn streak(history: &History, game: &str, player: &str, now: u64) -> usize {
+    history.daily_streak(game, player, now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::stats::RunRecord;
+
+    const MAY_FIRST: u64 = 1_714_521_600;
+
+    #[test]
+    fn everyone_gets_the_same_course_for_the_day() {
+        let morning = config("snake", MAY_FIRST + 3_600);
+        let evening = config("snake", MAY_FIRST + 80_000);
+        assert_eq!(morning, evening);
+        assert_eq!(morning.mode, "daily-2024-05-01");
+        assert!(is_daily(&morning.mode));
+        assert!(!is_daily("classic"));
+
+        let next_day = config("snake", MAY_FIRST + 86_400);
+        assert_ne!(next_day.seed, morning.seed);
+        assert_eq!(config("flappy_bird", MAY_FIRST).seed, morning.seed);
+    }
+
+    #[test]
+    fn only_the_first_run_of_the_day_is_scored() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = History::load(dir.path().join("history.txt")).0;
+        let now = MAY_FIRST + 43_200;
+        let today = config("snake", now);
+        assert!(!is_practice(&history, &today, "ala"));
+
+        let run = RunRecord {
+            game: today.game.clone(),
+            mode: today.mode.clone(),
+            difficulty: Difficulty::Normal,
+            player: "ala".to_string(),
+            score: 3,
+            seed: today.seed,
+            timestamp: now,
+            duration: 1.0,
+            counters: Vec::new(),
+        };
+        history.add(run).unwrap();
+        assert!(is_practice(&history, &today, "ala"));
+        assert!(!is_practice(&history, &today, "ola"));
+        assert!(!is_practice(&history, &config("flappy_bird", now), "ala"));
+        assert!(!is_practice(&history, &RunConfig { mode: "classic".to_string(), ..today.clone() }, "ala"));
+        assert_eq!(streak(&history, "snake", "ala", now), 1);
+        assert_eq!(streak(&history, "snake", "ola", now), 0);
+    }
+}
@@ -0,0 +1,40 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub fn now_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/// Converts a unix timestamp to a (year, month, day) UTC date.
+pub fn civil_from_timestamp(timestamp: u64) -> (i64, u32, u32) {
+    // Howard Hinnant's days-to-civil algorithm.
+    let days = (timestamp / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+pub fn format_date(timestamp: u64) -> String {
+    if timestamp == 0 {
+        return "-".to_string();
+    }
+    let (year, month, day) = civil_from_timestamp(timestamp);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// `m:ss`, or `h:mm:ss` from an hour up.
+pub fn format_duration(seconds: f32) -> String {
+    let total = seconds.max(0.0) as u64;
+    if total >= 3600 {
+        return format!("{}:{:02}:{:02}", total / 3600, total / 60 % 60, total % 60);
+    }
+    format!("{}:{:02}", total / 60, total % 60)
+}
@@ -0,0 +1,213 @@
+use crate::games::rng::GameRng;
+use crate::games::session::Session;
+use crate::games::GameEvent;
+use crate::render::{RectStyle, Renderer};
+use crate::settings::Settings;
+use crate::theme::Theme;
+use ggez::graphics::{Color, Rect};
+use ggez::GameResult;
+use rand::Rng;
+use std::f32::consts::TAU;
+
+/// Furthest the screen moves at full shake, in pixels.
+const MAX_SHAKE: f32 = 12.0;
+/// Shake lost per tick, so a full shake settles in half a second.
+const SHAKE_DECAY: f32 = 1.0 / 30.0;
+
+struct Particle {
+    x: f32,
+    y: f32,
+    vx: f32,
+    vy: f32,
+    gravity: f32,
+    life: u32,
+    lifetime: u32,
+    size: f32,
+    color: Color,
+}
+
+/// How a kind of burst looks; speeds are in pixels per tick.
+struct Burst {
+    count: usize,
+    speed: (f32, f32),
+    gravity: f32,
+    lifetime: u32,
+    size: f32,
+}
+
+/// Particles and screen shake played over a game in response to its events. Purely visual: it
+/// has its own random numbers and never touches the game, so replays stay exact.
+pub struct Effects {
+    particles: Vec<Particle>,
+    shake: f32,
+    offset: (f32, f32),
+    rng: GameRng,
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Effects::new()
+    }
+}
+
+impl Effects {
+    pub fn new() -> Self {
+        Effects { particles: Vec::new(), shake: 0.0, offset: (0.0, 0.0), rng: GameRng::new(GameRng::random_seed()) }
+    }
+
+    /// Starts the effects for a tick's events, leaving out whatever the player turned off.
+    pub fn react(&mut self, events: &[GameEvent], theme: &Theme, settings: &Settings) {
+        for &event in events {
+            let (x, y, color, burst, shake) = match event {
+                GameEvent::FoodEaten { x, y } => {
+                    (x, y, theme.food, Burst { count: 16, speed: (1.0, 3.0), gravity: 0.05, lifetime: 30, size: 4.0 }, 0.0)
+                }
+                GameEvent::SnakeCrashed { x, y } => {
+                    (x, y, theme.snake, Burst { count: 24, speed: (1.0, 4.0), gravity: 0.08, lifetime: 45, size: 5.0 }, 0.6)
+                }
+                GameEvent::PipePassed { x, y } => {
+                    let dust = Color { a: 0.5, ..theme.game_text };
+                    (x, y, dust, Burst { count: 8, speed: (0.3, 1.2), gravity: -0.01, lifetime: 25, size: 3.0 }, 0.0)
+                }
+                // Feathers: slow, drifting down gently.
+                GameEvent::BirdCrashed { x, y } => {
+                    (x, y, theme.bird, Burst { count: 18, speed: (0.8, 2.5), gravity: 0.03, lifetime: 60, size: 5.0 }, 0.8)
+                }
+            };
+            if settings.particles {
+                self.burst(x, y, color, &burst);
+            }
+            if settings.screen_shake {
+                self.shake = self.shake.max(shake);
+            }
+        }
+    }
+
+    fn burst(&mut self, x: f32, y: f32, color: Color, burst: &Burst) {
+        for _ in 0..burst.count {
+            let angle = self.rng.gen_range(0.0..TAU);
+            let speed = self.rng.gen_range(burst.speed.0..burst.speed.1);
+            let lifetime = self.rng.gen_range(burst.lifetime / 2..=burst.lifetime);
+            self.particles.push(Particle {
+                x,
+                y,
+                vx: angle.cos() * speed,
+                vy: angle.sin() * speed,
+                gravity: burst.gravity,
+                life: lifetime,
+                lifetime,
+                size: burst.size,
+                color,
+            });
+        }
+    }
+
+    /// Follows a session after one of its steps: reacts to the tick's events, skips the death
+    /// animation if it is turned off and moves the effects on. The skip clears the game's events,
+    /// so the crash is seen first.
+    pub fn follow(&mut self, session: &mut Session, theme: &Theme, settings: &Settings) {
+        self.react(session.game.events(), theme, settings);
+        if !settings.death_animation {
+            session.skip_death_animation();
+        }
+        self.tick();
+    }
+
+    pub fn tick(&mut self) {
+        for particle in &mut self.particles {
+            particle.x += particle.vx;
+            particle.y += particle.vy;
+            particle.vx *= 0.96;
+            particle.vy = particle.vy * 0.96 + particle.gravity;
+            particle.life -= 1;
+        }
+        self.particles.retain(|particle| particle.life > 0);
+
+        self.shake = (self.shake - SHAKE_DECAY).max(0.0);
+        // Squared, so small shakes stay small and big ones die out quickly.
+        let reach = MAX_SHAKE * self.shake * self.shake;
+        self.offset = if reach > 0.0 {
+            (self.rng.gen_range(-reach..=reach), self.rng.gen_range(-reach..=reach))
+        } else {
+            (0.0, 0.0)
+        };
+    }
+
+    /// How far to move the game this frame.
+    pub fn offset(&self) -> (f32, f32) {
+        self.offset
+    }
+
+    pub fn is_idle(&self) -> bool {
+        self.particles.is_empty() && self.shake == 0.0
+    }
+
+    pub fn draw(&self, renderer: &mut dyn Renderer) -> GameResult {
+        for particle in &self.particles {
+            let fade = particle.life as f32 / particle.lifetime as f32;
+            let color = Color { a: particle.color.a * fade, ..particle.color };
+            let half = particle.size / 2.0;
+            renderer.rect(Rect::new(particle.x - half, particle.y - half, particle.size, particle.size), color, RectStyle::Filled)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::RunConfig;
+    use crate::render::RecordingRenderer;
+    use std::path::PathBuf;
+
+    fn settings(particles: bool, screen_shake: bool) -> Settings {
+        let mut settings = Settings::load(PathBuf::from("/nonexistent/settings.txt")).0;
+        settings.particles = particles;
+        settings.screen_shake = screen_shake;
+        settings
+    }
+
+    const CRASH: GameEvent = GameEvent::BirdCrashed { x: 100.0, y: 100.0 };
+
+    #[test]
+    fn a_crash_bursts_and_shakes_until_it_settles() {
+        let mut effects = Effects::new();
+        effects.react(&[CRASH], &Theme::light(), &settings(true, true));
+        effects.tick();
+        assert_ne!(effects.offset(), (0.0, 0.0));
+
+        let mut renderer = RecordingRenderer::new();
+        effects.draw(&mut renderer).unwrap();
+        assert_eq!(renderer.commands.len(), 18);
+
+        for _ in 0..60 {
+            effects.tick();
+        }
+        assert!(effects.is_idle());
+        assert_eq!(effects.offset(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn turned_off_effects_do_nothing() {
+        let mut effects = Effects::new();
+        effects.react(&[CRASH, GameEvent::FoodEaten { x: 0.0, y: 0.0 }], &Theme::light(), &settings(false, false));
+        effects.tick();
+        assert!(effects.is_idle());
+        assert_eq!(effects.offset(), (0.0, 0.0));
+    }
+
+    #[test]
+    fn the_crash_still_bursts_without_the_death_animation() {
+        let mut settings = settings(true, true);
+        settings.death_animation = false;
+        let mut session = Session::new(RunConfig::new("flappy_bird", "classic"));
+        let mut effects = Effects::new();
+        while !session.game.is_over() {
+            session.step([]);
+            effects.follow(&mut session, &Theme::light(), &settings);
+        }
+        assert!(session.is_finished());
+        assert!(!effects.is_idle());
+        assert_ne!(effects.offset(), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,201 @@
+//! The run history as CSV or JSON, for spreadsheets and tournament tables.
+
+use crate::date;
+use crate::stats::RunRecord;
+use crate::storage;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    /// Picks the format from the file's extension.
+    pub fn from_path(path: &Path) -> Result<Self, String> {
+        match path.extension().and_then(|extension| extension.to_str()).map(str::to_lowercase).as_deref() {
+            Some("csv") => Ok(ExportFormat::Csv),
+            Some("json") => Ok(ExportFormat::Json),
+            _ => Err(format!("{}: expected a .csv or .json file", path.display())),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+
+    /// `history-2024-05-01.csv`, dated with the day of the export.
+    pub fn file_name(self, timestamp: u64) -> String {
+        format!("history-{}.{}", date::format_date(timestamp), self.extension())
+    }
+
+    pub fn format(self, runs: &[RunRecord]) -> String {
+        match self {
+            ExportFormat::Csv => to_csv(runs),
+            ExportFormat::Json => to_json(runs),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.extension().to_uppercase())
+    }
+}
+
+/// Writes the runs to `path`, creating its directory if needed.
+pub fn export(runs: &[RunRecord], format: ExportFormat, path: &Path) -> io::Result<()> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        fs::create_dir_all(dir)?;
+    }
+    storage::write_atomic(path, &format.format(runs))
+}
+
+/// Every counter any of the runs has, so each one gets its own column.
+fn counter_names(runs: &[RunRecord]) -> Vec<&str> {
+    let mut names: Vec<&str> = runs.iter().flat_map(|run| run.counters.iter().map(|(name, _)| name.as_str())).collect();
+    names.sort_unstable();
+    names.dedup();
+    names
+}
+
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// One row per run with a header row; counters a run does not have are left empty.
+pub fn to_csv(runs: &[RunRecord]) -> String {
+    let counters = counter_names(runs);
+    let mut header = vec!["game", "mode", "difficulty", "player", "score", "duration", "seed", "timestamp", "date"];
+    header.extend(&counters);
+    let mut csv = header.join(",");
+    csv.push('\n');
+
+    for run in runs {
+        let mut row = vec![
+            csv_field(&run.game),
+            csv_field(&run.mode),
+            run.difficulty.to_string(),
+            csv_field(&run.player),
+            run.score.to_string(),
+            format!("{:.1}", run.duration),
+            run.seed.to_string(),
+            run.timestamp.to_string(),
+            date::format_date(run.timestamp),
+        ];
+        row.extend(counters.iter().map(|name| run.counter(name).map_or(String::new(), |value| value.to_string())));
+        csv.push_str(&row.join(","));
+        csv.push('\n');
+    }
+    csv
+}
+
+pub fn json_string(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for character in value.chars() {
+        match character {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            control if control.is_control() => quoted.push_str(&format!("\\u{:04x}", control as u32)),
+            other => quoted.push(other),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// An array of objects, one per run, with the counters in a nested object.
+pub fn to_json(runs: &[RunRecord]) -> String {
+    let objects: Vec<String> = runs
+        .iter()
+        .map(|run| {
+            let counters: Vec<String> = run.counters.iter().map(|(name, value)| format!("{}: {}", json_string(name), value)).collect();
+            format!(
+                "  {{\"game\": {}, \"mode\": {}, \"difficulty\": {}, \"player\": {}, \"score\": {}, \"duration\": {:.1}, \"seed\": {}, \"timestamp\": {}, \"date\": {}, \"counters\": {{{}}}}}",
+                json_string(&run.game),
+                json_string(&run.mode),
+                json_string(&run.difficulty.to_string()),
+                json_string(&run.player),
+                run.score,
+                run.duration,
+                run.seed,
+                run.timestamp,
+                json_string(&date::format_date(run.timestamp)),
+                counters.join(", ")
+            )
+        })
+        .collect();
+    if objects.is_empty() {
+        return "[]\n".to_string();
+    }
+    format!("[\n{}\n]\n", objects.join(",\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::Difficulty;
+
+    fn run(player: &str, score: u32, counters: &[(&str, u32)]) -> RunRecord {
+        RunRecord {
+            game: "snake".to_string(),
+            mode: "classic".to_string(),
+            difficulty: Difficulty::Normal,
+            player: player.to_string(),
+            score,
+            seed: 42,
+            timestamp: 1_714_521_600,
+            duration: 61.5,
+            counters: counters.iter().map(|&(name, value)| (name.to_string(), value)).collect(),
+        }
+    }
+
+    #[test]
+    fn csv_has_a_column_per_counter_and_quotes_awkward_names() {
+        let runs = [run("ala", 30, &[("length", 6)]), run("Kowalski, \"Jan\"", 3, &[("pipes", 3)])];
+        let csv = to_csv(&runs);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "game,mode,difficulty,player,score,duration,seed,timestamp,date,length,pipes");
+        assert_eq!(lines[1], "snake,classic,normal,ala,30,61.5,42,1714521600,2024-05-01,6,");
+        assert_eq!(lines[2], "snake,classic,normal,\"Kowalski, \"\"Jan\"\"\",3,61.5,42,1714521600,2024-05-01,,3");
+    }
+
+    #[test]
+    fn json_escapes_strings_and_nests_counters() {
+        let json = to_json(&[run("a\"b\\c", 30, &[("length", 6)])]);
+        assert!(json.starts_with("[\n  {\"game\": \"snake\""));
+        assert!(json.contains("\"player\": \"a\\\"b\\\\c\""));
+        assert!(json.contains("\"counters\": {\"length\": 6}"));
+        assert_eq!(to_json(&[]), "[]\n");
+    }
+
+    #[test]
+    fn format_follows_the_extension() {
+        assert_eq!(ExportFormat::from_path(Path::new("wyniki.CSV")), Ok(ExportFormat::Csv));
+        assert_eq!(ExportFormat::from_path(Path::new("out/history.json")), Ok(ExportFormat::Json));
+        assert!(ExportFormat::from_path(Path::new("history.txt")).is_err());
+        assert_eq!(ExportFormat::Json.file_name(1_714_521_600), "history-2024-05-01.json");
+    }
+
+    #[test]
+    fn export_creates_the_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("exports").join("history.csv");
+        export(&[run("ala", 30, &[])], ExportFormat::Csv, &path).unwrap();
+        assert_eq!(fs::read_to_string(path).unwrap().lines().count(), 2);
+    }
+}
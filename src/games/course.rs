@@ -0,0 +1,294 @@
+//! Hand-made Flappy Bird courses, played in the Courses mode instead of random pipes: a fixed row
+//! of pipes, a finish line after the last one and the time it took to get there. Each course is a
+//! mode of its own, `course-<id>`, so it gets a leaderboard of its own.
+//!
+//! A course is a file of `pipe = spacing gap_top gap_size` lines, one per pipe in order. The
+//! spacing is the distance from the pipe before, or from the right edge of the screen for the
+//! first one. Two more numbers, `range speed`, make the gap slide down by up to `range` pixels
+//! and back, `speed` pixels a tick.
+
+use crate::games::script::{is_id, title_case};
+use crate::i18n::Notice;
+use crate::storage;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The mode games register for courses; a run's own mode also names the course, see [`mode`].
+pub const MODE: &str = "course";
+const PREFIX: &str = "course-";
+pub const COURSES_DIR: &str = "courses";
+const COURSE_EXTENSION: &str = "course";
+
+const SCREEN_WIDTH: f32 = 800.0;
+pub const FIELD_HEIGHT: f32 = 600.0;
+pub const PIPE_WIDTH: f32 = 50.0;
+/// From the end of the last pipe to the finish line.
+const FINISH_GAP: f32 = 200.0;
+pub const MIN_SPACING: f32 = 120.0;
+pub const MAX_SPACING: f32 = 1000.0;
+pub const MIN_GAP: f32 = 80.0;
+pub const MAX_DRIFT: f32 = 5.0;
+pub const MAX_PIPES: usize = 500;
+
+/// Shipped with the game, so the mode has something to play from the start.
+const BUILT_IN: [(&str, &str); 2] = [
+    ("warm_up", include_str!("../../resources/courses/warm_up.course")),
+    ("elevators", include_str!("../../resources/courses/elevators.course")),
+];
+
+static DIR: OnceLock<PathBuf> = OnceLock::new();
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct CoursePipe {
+    pub spacing: f32,
+    pub gap_top: f32,
+    pub gap_size: f32,
+    /// How far the gap slides down from `gap_top`; 0 for a pipe that stays put.
+    pub range: f32,
+    pub speed: f32,
+}
+
+impl CoursePipe {
+    pub fn new(spacing: f32, gap_top: f32, gap_size: f32) -> Self {
+        CoursePipe { spacing, gap_top, gap_size, range: 0.0, speed: 0.0 }
+    }
+
+    pub fn is_moving(&self) -> bool {
+        self.range > 0.0 && self.speed > 0.0
+    }
+
+    /// What is wrong with the pipe, if anything.
+    pub fn check(&self) -> Result<(), String> {
+        if !(MIN_SPACING..=MAX_SPACING).contains(&self.spacing) {
+            return Err(format!("the spacing has to be from {} to {}", MIN_SPACING, MAX_SPACING));
+        }
+        if self.gap_size < MIN_GAP {
+            return Err(format!("the gap has to be at least {} high", MIN_GAP));
+        }
+        if self.gap_top < 0.0 || self.range < 0.0 || self.gap_top + self.range + self.gap_size > FIELD_HEIGHT {
+            return Err(format!("the gap has to stay within the {} pixels of the screen", FIELD_HEIGHT));
+        }
+        if !(0.0..=MAX_DRIFT).contains(&self.speed) {
+            return Err(format!("the speed has to be from 0 to {}", MAX_DRIFT));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Course {
+    pub pipes: Vec<CoursePipe>,
+}
+
+impl Course {
+    /// A few plain pipes to start a new course from.
+    pub fn new() -> Self {
+        Course { pipes: vec![CoursePipe::new(300.0, 200.0, 200.0); 3] }
+    }
+
+    /// Where pipe `index` starts, counted from the left edge of the screen the run starts on.
+    pub fn pipe_x(&self, index: usize) -> f32 {
+        SCREEN_WIDTH + self.pipes.iter().take(index + 1).map(|pipe| pipe.spacing).sum::<f32>()
+    }
+
+    pub fn finish_x(&self) -> f32 {
+        self.pipe_x(self.pipes.len().saturating_sub(1)) + PIPE_WIDTH + FINISH_GAP
+    }
+
+    pub fn to_text(&self) -> String {
+        self.pipes
+            .iter()
+            .map(|pipe| {
+                if pipe.range > 0.0 || pipe.speed > 0.0 {
+                    format!("pipe = {} {} {} {} {}\n", pipe.spacing, pipe.gap_top, pipe.gap_size, pipe.range, pipe.speed)
+                } else {
+                    format!("pipe = {} {} {}\n", pipe.spacing, pipe.gap_top, pipe.gap_size)
+                }
+            })
+            .collect()
+    }
+
+    /// `key = value` lines; blank lines and `#` comments are skipped.
+    pub fn parse(text: &str) -> Result<Course, String> {
+        let mut pipes = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fail = |message: String| format!("line {}: {}", number + 1, message);
+            let (key, value) = line.split_once('=').ok_or_else(|| fail("expected key = value".to_string()))?;
+            if key.trim() != "pipe" {
+                return Err(fail(format!("unknown key '{}'", key.trim())));
+            }
+            let numbers = value
+                .split_whitespace()
+                .map(|number| number.parse::<f32>().ok().filter(|number| number.is_finite()))
+                .collect::<Option<Vec<f32>>>()
+                .ok_or_else(|| fail(format!("'{}' are not all numbers", value.trim())))?;
+            let pipe = match numbers[..] {
+                [spacing, gap_top, gap_size] => CoursePipe::new(spacing, gap_top, gap_size),
+                [spacing, gap_top, gap_size, range, speed] => CoursePipe { spacing, gap_top, gap_size, range, speed },
+                _ => return Err(fail("expected spacing, gap top and gap size, then maybe range and speed".to_string())),
+            };
+            pipe.check().map_err(fail)?;
+            pipes.push(pipe);
+        }
+        if pipes.is_empty() {
+            return Err("the course has no pipes".to_string());
+        }
+        if pipes.len() > MAX_PIPES {
+            return Err(format!("a course has at most {} pipes", MAX_PIPES));
+        }
+        Ok(Course { pipes })
+    }
+
+    pub fn load(path: &Path) -> Result<Course, String> {
+        let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        Course::parse(&text)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|error| error.to_string())?;
+        }
+        storage::write_atomic(path, &self.to_text()).map_err(|error| error.to_string())
+    }
+}
+
+impl Default for Course {
+    fn default() -> Self {
+        Course::new()
+    }
+}
+
+/// A course as the picker lists it.
+pub struct CourseInfo {
+    pub id: String,
+    pub course: Course,
+    /// `None` for the built-in ones, which cannot be saved over.
+    pub path: Option<PathBuf>,
+}
+
+/// Where the player's own courses are looked for; set once at start-up, like the script games.
+pub fn set_dir(dir: PathBuf) {
+    if DIR.set(dir).is_err() {
+        log::warn!("The courses folder was already set");
+    }
+}
+
+pub fn dir() -> Option<&'static Path> {
+    DIR.get().map(PathBuf::as_path)
+}
+
+/// `course-warm_up`: the mode, and so the board, of a course.
+pub fn mode(id: &str) -> String {
+    format!("{}{}", PREFIX, id)
+}
+
+/// The course a mode plays, if it is a course mode.
+pub fn id(mode: &str) -> Option<&str> {
+    mode.strip_prefix(PREFIX)
+}
+
+pub fn title(id: &str) -> String {
+    title_case(id)
+}
+
+/// The course played when Courses is picked without naming one.
+pub fn first() -> &'static str {
+    BUILT_IN[0].0
+}
+
+/// The built-in courses, then the player's; a file named like a built-in course is skipped, so
+/// the built-in boards stay fair. Broken files come back as notices.
+pub fn all() -> (Vec<CourseInfo>, Vec<Notice>) {
+    let mut courses: Vec<CourseInfo> = BUILT_IN
+        .iter()
+        .map(|(id, text)| CourseInfo { id: id.to_string(), course: Course::parse(text).expect("built-in courses are valid"), path: None })
+        .collect();
+    let mut notices = Vec::new();
+    let mut paths: Vec<PathBuf> = match dir().map(fs::read_dir) {
+        Some(Ok(entries)) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect(),
+        _ => Vec::new(),
+    };
+    paths.sort();
+    for path in paths.into_iter().filter(|path| path.extension().is_some_and(|ext| ext == COURSE_EXTENSION)) {
+        let id = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+        if !is_id(&id) || BUILT_IN.iter().any(|(known, _)| *known == id) {
+            notices.push(Notice::new("notice.course_name").with("path", path.display()));
+            continue;
+        }
+        match Course::load(&path) {
+            Ok(course) => courses.push(CourseInfo { id, course, path: Some(path) }),
+            Err(error) => notices.push(Notice::new("notice.broken_course").with("path", path.display()).with("error", error)),
+        }
+    }
+    for notice in &notices {
+        log::warn!("{}", notice);
+    }
+    (courses, notices)
+}
+
+/// The course called `id`, built-in or the player's.
+pub fn find(id: &str) -> Result<Course, String> {
+    if let Some((_, text)) = BUILT_IN.iter().find(|(known, _)| *known == id) {
+        return Course::parse(text);
+    }
+    match dir() {
+        Some(dir) if is_id(id) => Course::load(&dir.join(format!("{}.{}", id, COURSE_EXTENSION))),
+        _ => Err(format!("there is no course called '{}'", id)),
+    }
+}
+
+/// The first `course_N.course` in `dir` that is not taken yet.
+pub fn free_path(dir: &Path) -> PathBuf {
+    (1..)
+        .map(|number| dir.join(format!("course_{}.{}", number, COURSE_EXTENSION)))
+        .find(|path| !path.exists())
+        .expect("some number is free")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn courses_survive_a_round_trip_through_text() {
+        let mut course = Course::new();
+        course.pipes[1] = CoursePipe { spacing: 250.0, gap_top: 100.0, gap_size: 150.0, range: 120.0, speed: 1.5 };
+        let text = course.to_text();
+        assert_eq!(text, "pipe = 300 200 200\npipe = 250 100 150 120 1.5\npipe = 300 200 200\n");
+        assert_eq!(Course::parse(&text), Ok(course));
+    }
+
+    #[test]
+    fn broken_courses_say_where() {
+        assert_eq!(Course::parse("# nothing\n"), Err("the course has no pipes".to_string()));
+        assert_eq!(Course::parse("pipe = 300 200\n"), Err("line 1: expected spacing, gap top and gap size, then maybe range and speed".to_string()));
+        assert_eq!(Course::parse("pipe = 300 200 x\n"), Err("line 1: '300 200 x' are not all numbers".to_string()));
+        assert_eq!(Course::parse("pipe = 300 500 200\n"), Err("line 1: the gap has to stay within the 600 pixels of the screen".to_string()));
+        assert_eq!(Course::parse("pipe = 300 200 200\npipe = 50 200 200\n"), Err("line 2: the spacing has to be from 120 to 1000".to_string()));
+        assert_eq!(Course::parse("wall = 1\n"), Err("line 1: unknown key 'wall'".to_string()));
+    }
+
+    #[test]
+    fn built_in_courses_load_and_have_modes_of_their_own() {
+        let (courses, notices) = all();
+        assert!(notices.is_empty());
+        assert_eq!(courses[0].id, first());
+        assert_eq!(find("elevators").unwrap(), courses[1].course);
+        assert!(courses[1].course.pipes.iter().any(CoursePipe::is_moving));
+        assert_eq!(id(&mode("elevators")), Some("elevators"));
+        assert_eq!(id("classic"), None);
+        assert!(find("nowhere").is_err());
+    }
+
+    #[test]
+    fn the_finish_is_past_the_last_pipe() {
+        let course = Course { pipes: vec![CoursePipe::new(200.0, 200.0, 200.0), CoursePipe::new(300.0, 200.0, 200.0)] };
+        assert_eq!((course.pipe_x(0), course.pipe_x(1)), (1000.0, 1300.0));
+        assert_eq!(course.finish_x(), 1300.0 + PIPE_WIDTH + FINISH_GAP);
+    }
+}
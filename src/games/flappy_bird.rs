@@ -1,7 +1,23 @@
-use ggez::graphics::{self, Color, DrawMode, DrawParam, Font, Mesh, PxScale, Text, TextFragment};
-use ggez::{Context, GameResult};
+use crate::games::course::{self, Course, CoursePipe, PIPE_WIDTH};
+use crate::games::rng::GameRng;
+use crate::games::{Difficulty, GameEvent, GameInput, Minigame, RunConfig, TICKS_PER_SECOND};
+use crate::net::spectate::Frame;
+use crate::render::{RectStyle, Renderer};
+use crate::save::{self, SavedState};
+use crate::theme::Theme;
+use ggez::graphics;
+use ggez::GameResult;
 use rand::Rng;
 
+const FIELD_HEIGHT: f32 = 600.0;
+const FIELD_WIDTH: f32 = 800.0;
+const WING_FRAME_TICKS: u32 = 6;
+const BIRD_SPRITE_SIZE: (f32, f32) = (34.0, 24.0);
+const CAP_HEIGHT: f32 = 24.0;
+const FALL_GRAVITY: f32 = 0.5;
+/// How opaque the ghost bird is; sprites cannot be faded, so it is always drawn as a box.
+const GHOST_ALPHA: f32 = 0.35;
+
 #[derive(Clone)]
 #[derive(PartialEq)]
 pub struct Pipe {
@@ -9,6 +25,49 @@ pub struct Pipe {
     pub y: f32,
     pub width: f32,
     pub height: f32,
+    /// Set once the bird is past the pipe, so it only scores one point.
+    pub passed: bool,
+    /// Pixels a tick the gap slides, down while positive; 0 for a pipe that stays put.
+    pub drift: f32,
+    /// The highest and lowest the top of the gap goes while sliding.
+    pub range: (f32, f32),
+}
+
+impl Pipe {
+    fn from_course(x: f32, planned: &CoursePipe) -> Self {
+        let drift = if planned.is_moving() { planned.speed } else { 0.0 };
+        Pipe {
+            x,
+            y: planned.gap_top + planned.gap_size,
+            width: PIPE_WIDTH,
+            height: planned.gap_top,
+            passed: false,
+            drift,
+            range: (planned.gap_top, planned.gap_top + planned.range),
+        }
+    }
+
+    /// Moves the gap a tick along, turning back at either end of its range.
+    fn slide(&mut self) {
+        if self.drift == 0.0 {
+            return;
+        }
+        let top = (self.height + self.drift).clamp(self.range.0, self.range.1);
+        if top == self.range.0 || top == self.range.1 {
+            self.drift = -self.drift;
+        }
+        self.y += top - self.height;
+        self.height = top;
+    }
+
+    pub fn top_rect(&self) -> graphics::Rect {
+        graphics::Rect::new(self.x, 0.0, self.width, self.height)
+    }
+
+    /// Runs from the bottom of the gap down to the floor.
+    pub fn bottom_rect(&self) -> graphics::Rect {
+        graphics::Rect::new(self.x, self.y, self.width, (FIELD_HEIGHT - self.y).max(0.0))
+    }
 }
 
 #[derive(PartialEq)]
@@ -20,8 +79,20 @@ pub struct FlappyBirdGame {
     pub pipes: Vec<Pipe>,
     pub pipe_speed: f32,
     pub pipe_gap: f32,
+    pub rng: GameRng,
+    pub pipes_spawned: u32,
+    pub ticks: u32,
     pub score: u32,
     pub is_dead: bool,
+    pub events: Vec<GameEvent>,
+    /// The hand-made course of the Courses mode, played instead of random pipes.
+    pub course: Option<Course>,
+    /// How far the pipes have scrolled since the start.
+    pub distance: f32,
+    /// The tick the bird crossed the course's finish line on, which course boards rank by.
+    pub finish_ticks: Option<u32>,
+    /// Why the run could not start, e.g. a course that is gone.
+    pub error: Option<String>,
 }
 
 impl FlappyBirdGame {
@@ -34,78 +105,146 @@ impl FlappyBirdGame {
             pipes: Vec::new(),
             pipe_speed: 4.0,
             pipe_gap: 250.0,
+            rng: GameRng::new(GameRng::random_seed()),
+            pipes_spawned: 0,
+            ticks: 0,
             score: 0,
             is_dead: false,
+            events: Vec::new(),
+            course: None,
+            distance: 0.0,
+            finish_ticks: None,
+            error: None,
         }
     }
 
-    pub fn draw(&self, ctx: &mut Context) -> GameResult {
-        let bird_rect = graphics::Rect::new(self.position.0, self.position.1, self.width, self.height);
-        let rect_mesh = Mesh::new_rectangle(ctx, DrawMode::stroke(1.0), bird_rect, Color::RED)?;
-        graphics::draw(ctx, &rect_mesh, DrawParam::default())?;
+    pub fn with_config(config: &RunConfig) -> Self {
+        let mut game = FlappyBirdGame::new();
+        game.rng = GameRng::new(config.seed);
+        (game.pipe_speed, game.pipe_gap) = match config.difficulty {
+            Difficulty::Easy => (3.0, 300.0),
+            Difficulty::Normal => (4.0, 250.0),
+            Difficulty::Hard => (5.0, 200.0),
+        };
+        if let Some(id) = course::id(&config.mode) {
+            match course::find(id) {
+                Ok(course) => game.course = Some(course),
+                Err(error) => game.error = Some(error),
+            }
+        }
+        game
+    }
 
-        for pipe in &self.pipes {
-            let top_pipe_rect = graphics::Rect::new(pipe.x, 0.0, pipe.width, pipe.height);
-            let top_mesh = Mesh::new_rectangle(ctx, DrawMode::fill(), top_pipe_rect, Color::GREEN)?;
-            graphics::draw(ctx, &top_mesh, DrawParam::default())?;
+    /// Plays `course` instead of random pipes.
+    pub fn with_course(course: Course) -> Self {
+        FlappyBirdGame { course: Some(course), ..FlappyBirdGame::new() }
+    }
+
+    /// Where the finish line is on the screen, on a course.
+    fn finish_line(&self) -> Option<f32> {
+        self.course.as_ref().map(|course| course.finish_x() - self.distance)
+    }
 
-            let bottom_pipe_rect = graphics::Rect::new(pipe.x, pipe.y, pipe.width, pipe.height);
-            let bottom_mesh = Mesh::new_rectangle(ctx, DrawMode::fill(), bottom_pipe_rect, Color::GREEN)?;
-            graphics::draw(ctx, &bottom_mesh, DrawParam::default())?;
+    pub fn draw(&self, renderer: &mut dyn Renderer, theme: &Theme) -> GameResult {
+        let bird = self.bird_rect();
+        let (width, height) = BIRD_SPRITE_SIZE;
+        let sprite_rect = graphics::Rect::new(bird.x + bird.w / 2.0 - width / 2.0, bird.y + bird.h / 2.0 - height / 2.0, width, height);
+        let frame = (self.ticks / WING_FRAME_TICKS) as usize;
+        if !(theme.sprites && renderer.sprite("bird", frame, sprite_rect, self.tilt())?) {
+            renderer.rect(bird, theme.bird, RectStyle::Outline(1.0))?;
         }
 
-        if self.is_dead {
-            let game_over = Text::new(
-                TextFragment::new("GAME OVER")
-                    .font(Font::default().clone())
-                    .scale(PxScale::from(50.0))
-                    .color(Color::BLACK)
-            );
-            let score_text = Text::new(
-                TextFragment::new(format!("Score: {}", self.score))
-                    .font(Font::default().clone())
-                    .scale(PxScale::from(30.0))
-                    .color(Color::BLACK)
-            );
-            let exit_text = Text::new(
-                TextFragment::new(format!("Press ESC to leave"))
-                    .font(Font::default().clone())
-                    .scale(PxScale::from(30.0))
-                    .color(Color::BLACK)
-            );
-            graphics::draw(ctx, &game_over, (ggez::mint::Point2 { x: 280.0, y: 100.0 },))?;
-            graphics::draw(ctx, &score_text, (ggez::mint::Point2 { x: 280.0, y: 150.0 },))?;
-            graphics::draw(ctx, &exit_text, (ggez::mint::Point2 { x: 280.0, y: 200.0 },))?;
+        if let Some(finish) = self.finish_line() {
+            // A chequered flag line, two squares wide.
+            for row in 0..(FIELD_HEIGHT / 10.0) as usize {
+                let x = finish + (row % 2) as f32 * 10.0;
+                renderer.rect(graphics::Rect::new(x, row as f32 * 10.0, 10.0, 10.0), theme.game_text, RectStyle::Filled)?;
+            }
+        }
+
+        for pipe in &self.pipes {
+            let (top, bottom) = (pipe.top_rect(), pipe.bottom_rect());
+            if !(theme.sprites && renderer.sprite("pipe", 0, top, 0.0)?) {
+                renderer.rect(top, theme.pipe, RectStyle::Filled)?;
+            }
+            if bottom.h > 0.0 && !(theme.sprites && renderer.sprite("pipe", 0, bottom, 0.0)?) {
+                renderer.rect(bottom, theme.pipe, RectStyle::Filled)?;
+            }
+            // Caps are decoration only, so there is nothing to draw without the sprite.
+            if !theme.sprites {
+                continue;
+            }
+            renderer.sprite("pipe_cap", 0, graphics::Rect::new(top.x, top.bottom() - CAP_HEIGHT, top.w, CAP_HEIGHT), std::f32::consts::PI)?;
+            if bottom.h > 0.0 {
+                renderer.sprite("pipe_cap", 0, graphics::Rect::new(bottom.x, bottom.y, bottom.w, CAP_HEIGHT), 0.0)?;
+            }
         }
 
+        if self.course.is_some() {
+            let seconds = self.ticks as f32 / TICKS_PER_SECOND as f32;
+            renderer.text(&format!("{:.1} s", seconds), 10.0, 10.0, 24.0, theme.game_text)?;
+        }
         Ok(())
     }
 
-    pub fn update(&mut self) -> GameResult {
-        if self.is_dead {
-            return Ok(());
+    pub fn update(&mut self) {
+        self.events.clear();
+        if self.is_dead || self.finish_ticks.is_some() {
+            return;
         }
+        self.ticks += 1;
         self.velocity += 0.1;
         self.position.1 += self.velocity;
-        if self.position.1 < 0.0 || self.position.1 + self.height > 600.0 {
+        if self.position.1 < 0.0 || self.position.1 + self.height > FIELD_HEIGHT {
             self.is_dead = true;
         }
         for pipe in &mut self.pipes {
             pipe.x -= self.pipe_speed;
+            pipe.slide();
         }
+        self.distance += self.pipe_speed;
         self.pipes.retain(|pipe| pipe.x + pipe.width > 0.0);
-        if self.pipes.is_empty() || self.pipes[self.pipes.len() - 1].x < 600.0 {
+        if self.course.is_some() {
+            self.spawn_course_pipes();
+        } else if self.pipes.is_empty() || self.pipes[self.pipes.len() - 1].x < 600.0 {
             self.generate_pipe();
         }
 
-        for pipe in &self.pipes {
-            if pipe.x + pipe.width < self.position.0 {
+        for pipe in &mut self.pipes {
+            if !pipe.passed && pipe.x + pipe.width < self.position.0 {
+                pipe.passed = true;
                 self.score += 1;
+                self.events.push(GameEvent::PipePassed { x: pipe.x + pipe.width, y: (pipe.height + pipe.y) / 2.0 });
             }
         }
 
         self.check_collisions();
-        Ok(())
+        if self.is_dead {
+            let bird = self.bird_rect();
+            self.events.push(GameEvent::BirdCrashed { x: bird.x + bird.w / 2.0, y: bird.y + bird.h / 2.0 });
+        } else if self.finish_line().is_some_and(|finish| self.position.0 >= finish) {
+            self.finish_ticks = Some(self.ticks);
+        }
+    }
+
+    /// Puts the course's pipes on the screen as they scroll into view.
+    fn spawn_course_pipes(&mut self) {
+        let Some(course) = &self.course else {
+            return;
+        };
+        while let Some(planned) = course.pipes.get(self.pipes_spawned as usize) {
+            let x = course.pipe_x(self.pipes_spawned as usize) - self.distance;
+            if x > FIELD_WIDTH {
+                break;
+            }
+            self.pipes.push(Pipe::from_course(x, planned));
+            self.pipes_spawned += 1;
+        }
+    }
+
+    /// After a crash the bird drops to the floor before the game-over text shows.
+    fn is_falling(&self) -> bool {
+        self.is_dead && self.position.1 + self.height < FIELD_HEIGHT
     }
 
     pub fn flap(&mut self) {
@@ -115,27 +254,315 @@ impl FlappyBirdGame {
     }
 
     fn generate_pipe(&mut self) {
-        let mut rng = rand::thread_rng();
-        let pipe_height = rng.gen_range(100.0..400.0);
+        let pipe_height = self.rng.gen_range(100.0..400.0);
         let pipe = Pipe {
             x: 800.0,
             y: pipe_height + self.pipe_gap,
-            width: 50.0,
+            width: PIPE_WIDTH,
             height: pipe_height,
+            passed: false,
+            drift: 0.0,
+            range: (pipe_height, pipe_height),
         };
         self.pipes.push(pipe);
+        self.pipes_spawned += 1;
+    }
+
+    /// Nose up while climbing, down while falling.
+    fn tilt(&self) -> f32 {
+        (self.velocity * 0.12).clamp(-0.5, 1.2)
+    }
+
+    fn bird_rect(&self) -> graphics::Rect {
+        graphics::Rect::new(self.position.0, self.position.1, self.width, self.height)
+    }
+
+    fn hits_pipe(&self) -> bool {
+        let bird_rect = self.bird_rect();
+        self.pipes
+            .iter()
+            .any(|pipe| bird_rect.overlaps(&pipe.top_rect()) || bird_rect.overlaps(&pipe.bottom_rect()))
     }
 
     fn check_collisions(&mut self) {
-        for pipe in &self.pipes {
-            let bird_rect = graphics::Rect::new(self.position.0, self.position.1, self.width, self.height);
-            let top_pipe_rect = graphics::Rect::new(pipe.x, 0.0, pipe.width, pipe.height);
-            let bottom_pipe_rect = graphics::Rect::new(pipe.x, pipe.y, pipe.width, pipe.height);
+        if self.hits_pipe() {
+            self.is_dead = true;
+        }
+    }
+}
 
-            if bird_rect.overlaps(&top_pipe_rect) || bird_rect.overlaps(&bottom_pipe_rect) {
-                self.is_dead = true;
-                break;
+impl Default for FlappyBirdGame {
+    fn default() -> Self {
+        FlappyBirdGame::new()
+    }
+}
+
+impl Minigame for FlappyBirdGame {
+    fn tick(&mut self) {
+        self.update();
+    }
+
+    fn input(&mut self, input: GameInput) {
+        if input == GameInput::Action {
+            self.flap();
+        }
+    }
+
+    fn draw(&self, renderer: &mut dyn Renderer, theme: &Theme) -> GameResult {
+        FlappyBirdGame::draw(self, renderer, theme)
+    }
+
+    fn draw_ghost(&self, renderer: &mut dyn Renderer, theme: &Theme) -> GameResult {
+        let color = graphics::Color { a: GHOST_ALPHA, ..theme.bird };
+        renderer.rect(self.bird_rect(), color, RectStyle::Filled)?;
+        renderer.rect(self.bird_rect(), color, RectStyle::Outline(1.0))
+    }
+
+    fn score(&self) -> u32 {
+        self.score
+    }
+
+    fn is_over(&self) -> bool {
+        self.is_dead || self.finish_ticks.is_some()
+    }
+
+    fn counters(&self) -> Vec<(&'static str, u32)> {
+        vec![("pipes", self.score)]
+    }
+
+    fn save_state(&self, state: &mut SavedState) {
+        state.set("position", format!("{},{}", self.position.0, self.position.1));
+        state.set("velocity", self.velocity);
+        let pipes = self.pipes.iter().map(|pipe| {
+            let (x, y, width, height, passed) = (pipe.x, pipe.y, pipe.width, pipe.height, u8::from(pipe.passed));
+            format!("{},{},{},{},{},{},{},{}", x, y, width, height, passed, pipe.drift, pipe.range.0, pipe.range.1)
+        });
+        state.set_list("pipes", pipes);
+        state.set("pipe_speed", self.pipe_speed);
+        state.set("pipe_gap", self.pipe_gap);
+        state.set("rng", self.rng.state);
+        state.set("pipes_spawned", self.pipes_spawned);
+        state.set("distance", self.distance);
+        state.set("ticks", self.ticks);
+        state.set("score", self.score);
+    }
+
+    fn load_state(&mut self, state: &SavedState) -> Result<(), String> {
+        let position: Vec<f32> = save::parse_group(&state.get::<String>("position")?, 2)?;
+        let pipes = state
+            .list("pipes")?
+            .into_iter()
+            .map(|text| {
+                let fields: Vec<f32> = save::parse_group(text, 8)?;
+                let (drift, range) = (fields[5], (fields[6], fields[7]));
+                Ok(Pipe { x: fields[0], y: fields[1], width: fields[2], height: fields[3], passed: fields[4] != 0.0, drift, range })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        self.position = (position[0], position[1]);
+        self.velocity = state.get("velocity")?;
+        self.pipes = pipes;
+        self.pipe_speed = state.get("pipe_speed")?;
+        self.pipe_gap = state.get("pipe_gap")?;
+        self.rng = GameRng::new(state.get("rng")?);
+        self.pipes_spawned = state.get("pipes_spawned")?;
+        self.distance = state.get("distance")?;
+        self.ticks = state.get("ticks")?;
+        self.score = state.get("score")?;
+        Ok(())
+    }
+
+    fn spectate(&self, frame: &mut Frame) {
+        frame.set("width", 800);
+        frame.set("height", FIELD_HEIGHT);
+        let mut bird = Frame::default();
+        bird.set("x", self.position.0);
+        bird.set("y", self.position.1);
+        bird.set("width", self.width);
+        bird.set("height", self.height);
+        bird.set("velocity", self.velocity);
+        frame.set_object("bird", bird);
+        let pipes = self.pipes.iter().map(|pipe| {
+            let mut gap = Frame::default();
+            gap.set("x", pipe.x);
+            gap.set("width", pipe.width);
+            gap.set("gap_top", pipe.height);
+            gap.set("gap_bottom", pipe.y);
+            gap
+        });
+        frame.set_objects("pipes", pipes);
+        if let Some(finish) = self.finish_line() {
+            frame.set("finish", finish);
+        }
+    }
+
+    fn error(&self) -> Option<String> {
+        self.error.clone()
+    }
+
+    fn finish_ticks(&self) -> Option<u32> {
+        self.finish_ticks
+    }
+
+    fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    fn animate_death(&mut self) -> bool {
+        self.events.clear();
+        if self.is_falling() {
+            self.velocity = self.velocity.max(0.0) + FALL_GRAVITY;
+            self.position.1 = (self.position.1 + self.velocity).min(FIELD_HEIGHT - self.height);
+        }
+        self.is_falling()
+    }
+
+    fn check_invariants(&self) -> Result<(), String> {
+        if self.score > self.pipes_spawned {
+            return Err(format!("score {} is higher than the {} pipes spawned", self.score, self.pipes_spawned));
+        }
+        if self.is_dead {
+            return Ok(());
+        }
+        if self.hits_pipe() {
+            return Err(format!("bird at {:?} is alive inside a pipe", self.position));
+        }
+        if self.position.1 < 0.0 || self.position.1 + self.height > FIELD_HEIGHT {
+            return Err(format!("bird at {:?} is alive off the screen", self.position));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::{DrawCommand, RecordingRenderer};
+    use ggez::graphics::Color;
+
+    fn pipe_at(x: f32) -> Pipe {
+        Pipe { x, y: 350.0, width: 50.0, height: 100.0, passed: false, drift: 0.0, range: (100.0, 100.0) }
+    }
+
+    #[test]
+    fn each_pipe_scores_once() {
+        let mut game = FlappyBirdGame::new();
+        game.pipes = vec![pipe_at(60.0)];
+        game.pipes_spawned = 1;
+        for _ in 0..10 {
+            game.velocity = 0.0;
+            game.update();
+        }
+        assert_eq!(game.score, 1);
+        assert_eq!(game.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn bottom_pipe_reaches_the_floor() {
+        let mut game = FlappyBirdGame::new();
+        game.pipes = vec![pipe_at(100.0)];
+        game.position = (100.0, 550.0);
+        game.check_collisions();
+        assert!(game.is_dead);
+        assert_eq!(pipe_at(0.0).bottom_rect().bottom(), FIELD_HEIGHT);
+    }
+
+    #[test]
+    fn a_finished_game_leaves_the_summary_to_the_panel() {
+        let mut game = FlappyBirdGame::new();
+        game.pipes = vec![pipe_at(300.0)];
+        game.score = 12;
+        game.is_dead = true;
+        while game.animate_death() {}
+        let mut renderer = RecordingRenderer::new();
+        game.draw(&mut renderer, &Theme::light()).unwrap();
+
+        assert!(!renderer.has_text("GAME OVER"));
+        assert_eq!(renderer.rects(Color::GREEN).len(), 2);
+    }
+
+    #[test]
+    fn sprites_replace_shapes_when_available() {
+        let mut game = FlappyBirdGame::new();
+        game.pipes = vec![pipe_at(300.0)];
+        game.velocity = 5.0;
+
+        let mut shapes = RecordingRenderer::new();
+        game.draw(&mut shapes, &Theme::light()).unwrap();
+        assert_eq!(shapes.rects(Color::RED).len(), 1);
+        assert!(shapes.sprite_names().is_empty());
+
+        let mut sprites = RecordingRenderer::with_sprites(&["bird", "pipe", "pipe_cap"]);
+        game.draw(&mut sprites, &Theme::light()).unwrap();
+        assert!(sprites.rects(Color::RED).is_empty());
+        assert!(sprites.rects(Color::GREEN).is_empty());
+        assert_eq!(sprites.sprite_names(), vec!["bird", "pipe", "pipe", "pipe_cap", "pipe_cap"]);
+        assert!(matches!(sprites.commands[0], DrawCommand::Sprite { rotation, .. } if rotation > 0.0));
+    }
+
+    #[test]
+    fn themes_without_sprites_draw_shapes_in_their_colours() {
+        let mut game = FlappyBirdGame::new();
+        game.pipes = vec![pipe_at(300.0)];
+        let mut theme = Theme::light();
+        theme.sprites = false;
+        theme.pipe = Color::WHITE;
+
+        let mut renderer = RecordingRenderer::with_sprites(&["bird", "pipe", "pipe_cap"]);
+        game.draw(&mut renderer, &theme).unwrap();
+        assert!(renderer.sprite_names().is_empty());
+        assert_eq!(renderer.rects(Color::WHITE).len(), 2);
+    }
+
+    #[test]
+    fn courses_lay_out_their_pipes_and_end_at_the_finish() {
+        let mut planned = vec![CoursePipe::new(200.0, 100.0, 400.0); 3];
+        planned[1] = CoursePipe { spacing: 300.0, gap_top: 100.0, gap_size: 300.0, range: 100.0, speed: 2.0 };
+        let mut game = FlappyBirdGame::with_course(Course { pipes: planned });
+        game.update();
+        assert!(game.pipes.is_empty(), "the first pipe starts off the screen");
+        for _ in 0..60 {
+            game.update();
+        }
+        assert_eq!(game.pipes.len(), 1);
+        assert_eq!(game.pipes[0].x, 1000.0 - 61.0 * 4.0);
+
+        // Always in the middle of the wide gaps.
+        while !game.is_over() {
+            game.position.1 = (game.pipes.first().map_or(300.0, |pipe| (pipe.height + pipe.y) / 2.0) - 15.0).clamp(0.0, 570.0);
+            game.velocity = 0.0;
+            let sliding = game.pipes.iter().find(|pipe| pipe.drift != 0.0).map(|pipe| pipe.height);
+            game.update();
+            if let Some(top) = sliding.filter(|_| game.pipes.iter().any(|pipe| pipe.drift != 0.0)) {
+                let pipe = game.pipes.iter().find(|pipe| pipe.drift != 0.0).unwrap();
+                assert!((pipe.height - top).abs() == 2.0 && (100.0..=200.0).contains(&pipe.height));
             }
         }
+        // The finish is 1750 pixels in and the bird 100 from the left edge, at 4 pixels a tick.
+        assert_eq!((game.finish_ticks, game.is_dead, game.score), (Some(413), false, 3));
+        assert_eq!(game.finish_ticks(), Some(413));
+        assert_eq!(game.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn a_missing_course_stops_the_run_with_an_error() {
+        let game = FlappyBirdGame::with_config(&RunConfig::new("flappy_bird", &course::mode("nowhere")));
+        assert!(game.error().is_some_and(|error| error.contains("nowhere")));
+    }
+
+    #[test]
+    fn a_crashed_bird_falls_to_the_floor_first() {
+        let mut game = FlappyBirdGame::new();
+        game.pipes = vec![pipe_at(100.0)];
+        game.position = (100.0, 50.0);
+        game.update();
+        assert!(game.is_dead);
+        assert!(matches!(game.events(), [GameEvent::BirdCrashed { .. }]));
+
+        let mut falls = 0;
+        while game.animate_death() {
+            falls += 1;
+        }
+        assert!(falls > 0);
+        assert_eq!(game.position.1 + game.height, FIELD_HEIGHT);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,227 @@
+//! Racing an earlier run: its replay plays alongside the player's run, on the same seed, so both
+//! meet the same pipes.
+
+use crate::daily;
+use crate::games::session::{RunSummary, Session};
+use crate::games::{self, RunConfig};
+use crate::i18n::Strings;
+use crate::render::Renderer;
+use crate::replay::Replay;
+use crate::settings::GhostSource;
+use crate::storage::Storage;
+use crate::theme::Theme;
+use ggez::GameResult;
+use std::io;
+
+pub struct Ghost {
+    session: Session,
+    /// Where the ghost's run ended, known up front by playing the replay through once.
+    final_score: u32,
+}
+
+impl Ghost {
+    pub fn new(replay: Replay) -> Self {
+        let mut run = Session::replay(replay.clone());
+        while !run.game.is_over() && run.tick <= replay.ticks {
+            run.step([]);
+        }
+        Ghost { final_score: run.game.score(), session: Session::replay(replay) }
+    }
+
+    /// The ghost `source` picks for a run with `config`, if there is one; the run's seed is
+    /// switched to the ghost's. With `keep_seed`, and for the daily challenge, only a ghost on the
+    /// run's own seed will do.
+    pub fn load(storage: &Storage, source: GhostSource, config: &mut RunConfig, keep_seed: bool) -> Option<Ghost> {
+        let keep_seed = keep_seed || daily::is_daily(&config.mode);
+        if !games::find_game(&config.game).is_some_and(|info| info.ghost) {
+            return None;
+        }
+        let path = storage.replay_path(&file_name(source, &config.game, &config.mode)?);
+        if !path.exists() {
+            return None;
+        }
+        let replay = match Replay::load(&path) {
+            Ok(replay) => replay,
+            Err(error) => {
+                log::warn!("Could not load the ghost: {}", error);
+                return None;
+            }
+        };
+        let ghost_config = &replay.config;
+        if (&ghost_config.game, &ghost_config.mode, ghost_config.difficulty) != (&config.game, &config.mode, config.difficulty)
+            || (keep_seed && ghost_config.seed != config.seed)
+        {
+            return None;
+        }
+        config.seed = ghost_config.seed;
+        Some(Ghost::new(replay))
+    }
+
+    /// Moves on in step with the player's run.
+    pub fn step(&mut self) {
+        self.session.step([]);
+    }
+
+    /// Catches up with a resumed run.
+    pub fn skip_to(&mut self, tick: u32) {
+        while self.session.tick < tick && !self.session.game.is_over() {
+            self.step();
+        }
+    }
+
+    /// The ghost's score at this tick, or where its run ended once it is over.
+    pub fn score(&self) -> u32 {
+        if self.session.game.is_over() {
+            self.final_score
+        } else {
+            self.session.game.score()
+        }
+    }
+
+    /// Points ahead of the ghost at the same tick; negative while behind it.
+    pub fn lead(&self, score: u32) -> i64 {
+        score as i64 - self.score() as i64
+    }
+
+    pub fn draw(&self, renderer: &mut dyn Renderer, theme: &Theme) -> GameResult {
+        if self.session.is_finished() {
+            return Ok(());
+        }
+        self.session.game.draw_ghost(renderer, theme)
+    }
+
+    /// The ghost's score and the player's lead on it, in the top right corner.
+    pub fn draw_lead(&self, renderer: &mut dyn Renderer, theme: &Theme, strings: &Strings, score: u32) -> GameResult {
+        let lead = self.lead(score);
+        let text = strings.format("play.ghost", &[("score", &self.score()), ("lead", &format!("{:+}", lead))]);
+        let color = match lead {
+            lead if lead > 0 => theme.highlight,
+            0 => theme.game_text,
+            _ => theme.warning,
+        };
+        let width = renderer.text_width(&text, 20.0);
+        renderer.text(&text, 790.0 - width, 10.0, 20.0, color)
+    }
+}
+
+/// `best-flappy_bird-classic.replay` and the like; `None` when ghosts are off. Every day's
+/// challenge shares one file, so they do not pile up.
+pub fn file_name(source: GhostSource, game: &str, mode: &str) -> Option<String> {
+    let mode = if daily::is_daily(mode) { daily::MODE } else { mode };
+    match source {
+        GhostSource::Off => None,
+        source => Some(format!("{}-{}-{}.replay", source.name(), game, mode)),
+    }
+}
+
+/// Keeps a finished run's replay as the ghost to race next time, if it beat the player's best or
+/// took the top of the leaderboard.
+pub fn keep(storage: &Storage, session: &Session, summary: &RunSummary) -> io::Result<()> {
+    let config = &session.config;
+    if session.is_playback() || !games::find_game(&config.game).is_some_and(|info| info.ghost) {
+        return Ok(());
+    }
+    let recording = session.recording();
+    for (source, beaten) in [(GhostSource::PersonalBest, summary.is_new_record()), (GhostSource::TopRun, summary.rank == Some(0))] {
+        if let Some(name) = file_name(source, &config.game, &config.mode).filter(|_| beaten) {
+            recording.save(&storage.replay_path(&name))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::flappy_bird::FlappyBirdGame;
+    use crate::games::{Difficulty, GameInput, Minigame};
+    use crate::scores::ScoreBoard;
+
+    /// A flappy run that aims for the middle of each gap until it has passed four pipes, then
+    /// stops flapping. A copy of the game on the same seed tells it where the gaps are; not every
+    /// seed lets it get that far.
+    fn played(config: RunConfig) -> Session {
+        let mut sight = FlappyBirdGame::with_config(&config);
+        let mut session = Session::new(config);
+        while !session.game.is_over() {
+            let bird = sight.position.0;
+            let gap = sight.pipes.iter().find(|pipe| pipe.x + pipe.width > bird).map_or(300.0, |pipe| (pipe.height + pipe.y) / 2.0);
+            let flap = sight.score < 4 && sight.velocity >= 0.0 && sight.position.1 + sight.height / 2.0 > gap + 20.0;
+            if flap {
+                sight.input(GameInput::Action);
+            }
+            sight.tick();
+            session.step(flap.then_some(GameInput::Action));
+        }
+        session
+    }
+
+    #[test]
+    fn the_lead_is_taken_at_the_same_tick() {
+        let mut config = RunConfig::new("flappy_bird", "classic");
+        config.seed = 2;
+        let run = played(config);
+        assert_eq!(run.game.score(), 4);
+        let mut ghost = Ghost::new(run.recording());
+        let mut player = Session::replay(run.recording());
+        let mut mid_run = false;
+        while !player.game.is_over() {
+            ghost.step();
+            player.step([]);
+            assert_eq!(ghost.lead(player.game.score()), 0, "tick {}", player.tick);
+            mid_run |= (1..run.game.score()).contains(&player.game.score());
+        }
+        assert!(mid_run);
+
+        let mut behind = Ghost::new(run.recording());
+        behind.skip_to(run.tick / 2);
+        assert_eq!(behind.lead(0), -(behind.session.game.score() as i64));
+        assert!(behind.score() < run.game.score());
+    }
+
+    #[test]
+    fn a_ghost_knows_its_final_score_and_replays_the_run() {
+        let run = played(RunConfig::new("flappy_bird", "classic"));
+        let mut ghost = Ghost::new(run.recording());
+        ghost.skip_to(run.tick);
+        assert_eq!(ghost.lead(run.game.score() + 2), 2);
+        assert!(ghost.session.game.is_over());
+        assert_eq!(ghost.session.game.score(), run.game.score());
+    }
+
+    #[test]
+    fn records_are_kept_and_raced_on_their_seed() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::in_dir(dir.path().to_path_buf());
+        let run = played(RunConfig::new("flappy_bird", "classic"));
+        let mut scores = ScoreBoard::load(storage.data_path("scores.txt")).0;
+        let summary = run.record(&mut scores);
+        keep(&storage, &run, &summary).unwrap();
+
+        assert_eq!(summary.rank, Some(0));
+
+        let mut config = RunConfig::new("flappy_bird", "classic");
+        assert!(Ghost::load(&storage, GhostSource::TopRun, &mut config, false).is_some());
+        assert_eq!(config.seed, run.config.seed);
+
+        let mut other_seed = RunConfig { seed: config.seed + 1, ..config.clone() };
+        assert!(Ghost::load(&storage, GhostSource::TopRun, &mut other_seed, true).is_none());
+        let mut hard = RunConfig { difficulty: Difficulty::Hard, ..config.clone() };
+        assert!(Ghost::load(&storage, GhostSource::TopRun, &mut hard, false).is_none());
+        assert!(Ghost::load(&storage, GhostSource::Off, &mut config, false).is_none());
+    }
+
+    #[test]
+    fn only_games_with_a_ghost_keep_one() {
+        let dir = tempfile::tempdir().unwrap();
+        let storage = Storage::in_dir(dir.path().to_path_buf());
+        let mut snake = Session::new(RunConfig::new("snake", "classic"));
+        while !snake.game.is_over() {
+            snake.step([]);
+        }
+        let summary = RunSummary { score: 10, seconds: 1.0, entry: snake.score_entry(), rank: Some(0), previous_best: None, finish_ticks: None };
+        keep(&storage, &snake, &summary).unwrap();
+        assert!(!storage.replay_path("best-snake-classic.replay").exists());
+        assert!(Ghost::load(&storage, GhostSource::PersonalBest, &mut RunConfig::new("snake", "classic"), false).is_none());
+    }
+}
@@ -2,4 +2,337 @@ pub mod flappy_bird;
 pub mod snake;
 pub mod pong;
 pub mod memory;
-pub mod game_state;
\ No newline at end of file
+pub mod game_state;
+pub mod ghost;
+pub mod rng;
+pub mod script;
+pub mod session;
+pub mod snake_map;
+pub mod course;
+
+use crate::daily;
+use crate::date;
+use crate::games::flappy_bird::FlappyBirdGame;
+use crate::games::script::{ScriptGame, ScriptInfo};
+use crate::games::snake::SnakeGame;
+use crate::i18n::Notice;
+use crate::net::spectate::Frame;
+use crate::render::Renderer;
+use crate::save::SavedState;
+use crate::theme::Theme;
+use ggez::event::KeyCode;
+use ggez::GameResult;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Games advance in fixed steps so a run plays out the same way from the same seed and inputs.
+pub const TICKS_PER_SECOND: u32 = 60;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GameInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Action,
+}
+
+impl GameInput {
+    pub fn from_keycode(keycode: KeyCode) -> Option<GameInput> {
+        match keycode {
+            KeyCode::Up | KeyCode::W => Some(GameInput::Up),
+            KeyCode::Down | KeyCode::S => Some(GameInput::Down),
+            KeyCode::Left | KeyCode::A => Some(GameInput::Left),
+            KeyCode::Right | KeyCode::D => Some(GameInput::Right),
+            KeyCode::Space => Some(GameInput::Action),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GameInput::Up => "up",
+            GameInput::Down => "down",
+            GameInput::Left => "left",
+            GameInput::Right => "right",
+            GameInput::Action => "action",
+        }
+    }
+}
+
+impl FromStr for GameInput {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        [GameInput::Up, GameInput::Down, GameInput::Left, GameInput::Right, GameInput::Action]
+            .into_iter()
+            .find(|input| input.name() == name)
+            .ok_or_else(|| format!("unknown input '{}'", name))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Difficulty {
+    Easy,
+    #[default]
+    Normal,
+    Hard,
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            Difficulty::Easy => "easy",
+            Difficulty::Normal => "normal",
+            Difficulty::Hard => "hard",
+        };
+        f.write_str(name)
+    }
+}
+
+impl FromStr for Difficulty {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "easy" => Ok(Difficulty::Easy),
+            "normal" => Ok(Difficulty::Normal),
+            "hard" => Ok(Difficulty::Hard),
+            _ => Err(format!("unknown difficulty '{}' (expected easy, normal or hard)", name)),
+        }
+    }
+}
+
+/// Everything needed to start a run, and to start the exact same run again.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RunConfig {
+    pub game: String,
+    pub mode: String,
+    pub difficulty: Difficulty,
+    pub seed: u64,
+}
+
+impl RunConfig {
+    /// The daily challenge gets today's course; every other mode a random one. Courses without a
+    /// course named start on the first one.
+    pub fn new(game: &str, mode: &str) -> Self {
+        if mode == daily::MODE {
+            return daily::config(game, date::now_timestamp());
+        }
+        if mode == course::MODE {
+            return RunConfig::new(game, &course::mode(course::first()));
+        }
+        RunConfig {
+            game: game.to_string(),
+            mode: mode.to_string(),
+            difficulty: Difficulty::Normal,
+            seed: rng::GameRng::random_seed(),
+        }
+    }
+
+    /// Only runs at the default difficulty go on the leaderboard, so scores stay comparable.
+    pub fn is_ranked(&self) -> bool {
+        self.difficulty == Difficulty::Normal
+    }
+
+    /// A new course for the next run: a random one, or today's for the daily challenge.
+    pub fn reseed(&mut self) {
+        if daily::is_daily(&self.mode) {
+            let today = daily::config(&self.game, date::now_timestamp());
+            (self.mode, self.seed) = (today.mode, today.seed);
+        } else {
+            self.seed = rng::GameRng::random_seed();
+        }
+    }
+}
+
+/// Something worth an effect that happened during a tick, at a point on the screen.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum GameEvent {
+    FoodEaten { x: f32, y: f32 },
+    SnakeCrashed { x: f32, y: f32 },
+    PipePassed { x: f32, y: f32 },
+    BirdCrashed { x: f32, y: f32 },
+}
+
+/// A running game as the play scene sees it: fixed-step updates, abstract inputs and a score.
+pub trait Minigame {
+    fn tick(&mut self);
+
+    fn input(&mut self, input: GameInput);
+
+    fn draw(&self, renderer: &mut dyn Renderer, theme: &Theme) -> GameResult;
+
+    /// Draws only the player, see-through, as the ghost of an earlier run raced alongside this one.
+    fn draw_ghost(&self, _renderer: &mut dyn Renderer, _theme: &Theme) -> GameResult {
+        Ok(())
+    }
+
+    fn score(&self) -> u32;
+
+    fn is_over(&self) -> bool;
+
+    /// Game-specific numbers kept in the run history, e.g. the snake's length.
+    fn counters(&self) -> Vec<(&'static str, u32)> {
+        Vec::new()
+    }
+
+    /// What happened during the last tick or death animation step.
+    fn events(&self) -> &[GameEvent] {
+        &[]
+    }
+
+    /// Advances the death animation by a tick once the game is over; false when it has finished,
+    /// so calling it until it returns false skips the animation.
+    fn animate_death(&mut self) -> bool {
+        false
+    }
+
+    /// Writes everything needed to pick the game up again later; games that cannot be saved
+    /// leave `state` empty.
+    fn save_state(&self, _state: &mut SavedState) {}
+
+    /// Puts back what `save_state` wrote, or says why it cannot.
+    fn load_state(&mut self, _state: &SavedState) -> Result<(), String> {
+        Err("this game cannot be saved".to_string())
+    }
+
+    /// Describes what is on screen for spectators watching over the network; games with
+    /// nothing to show leave `frame` empty.
+    fn spectate(&self, _frame: &mut Frame) {}
+
+    /// Why the game had to stop, for games that can break, like scripts; the play scene shows it
+    /// instead of the game.
+    fn error(&self) -> Option<String> {
+        None
+    }
+
+    /// The tick the run crossed the finish line on, for games raced against the clock; course
+    /// boards rank by it instead of the score.
+    fn finish_ticks(&self) -> Option<u32> {
+        None
+    }
+
+    /// Rules that must hold after every tick; the soak tests run millions of ticks against this.
+    fn check_invariants(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+pub struct ModeInfo {
+    pub id: &'static str,
+    pub title: &'static str,
+}
+
+pub struct GameInfo {
+    pub id: &'static str,
+    pub title: &'static str,
+    pub modes: &'static [ModeInfo],
+    /// Whether runs can be raced against as a ghost, see `Minigame::draw_ghost`.
+    pub ghost: bool,
+    pub creator: Creator,
+}
+
+pub enum Creator {
+    Native(fn(&RunConfig) -> Box<dyn Minigame>),
+    /// A Rhai script, compiled afresh for every run so edits show up without a restart.
+    Script(PathBuf),
+}
+
+impl GameInfo {
+    pub fn create(&self, config: &RunConfig) -> Box<dyn Minigame> {
+        match &self.creator {
+            Creator::Native(create) => create(config),
+            Creator::Script(path) => Box::new(ScriptGame::load(path, config)),
+        }
+    }
+}
+
+pub const CLASSIC: ModeInfo = ModeInfo { id: "classic", title: "Classic" };
+pub const WRAP: ModeInfo = ModeInfo { id: "wrap", title: "Wrap" };
+/// Classic rules on the day's course, see the `daily` module.
+pub const DAILY: ModeInfo = ModeInfo { id: daily::MODE, title: "Daily" };
+/// Hand-made Flappy Bird courses, see the `course` module.
+pub const COURSES: ModeInfo = ModeInfo { id: course::MODE, title: "Courses" };
+
+static REGISTRY: OnceLock<Vec<GameInfo>> = OnceLock::new();
+
+/// Every playable game: the built-in ones, then the scripts added by `load_scripts`. The menu,
+/// the leaderboard tabs and the command line all read this list, so a new game only has to be
+/// added here.
+pub fn registry() -> &'static [GameInfo] {
+    REGISTRY.get_or_init(built_in_games)
+}
+
+/// Adds the script games in `dir` to the registry; this has to happen before anything reads it.
+/// Returns the problems worth telling the player about.
+pub fn load_scripts(dir: &Path) -> Vec<Notice> {
+    let (scripts, mut notices) = script::discover(dir);
+    let mut games = built_in_games();
+    for script in scripts {
+        if games.iter().any(|game| game.id == script.id) {
+            notices.push(Notice::new("notice.duplicate_game").with("path", script.path.display()).with("game", &script.id));
+            continue;
+        }
+        games.push(script_game(script));
+    }
+    for notice in &notices {
+        log::error!("{}", notice);
+    }
+    if REGISTRY.set(games).is_err() {
+        log::warn!("The games were listed before the scripts in {} were loaded", dir.display());
+    }
+    notices
+}
+
+/// Scripts are read once per run, so leaking their names for `'static` ones costs next to nothing.
+fn script_game(script: ScriptInfo) -> GameInfo {
+    let leak = |text: String| -> &'static str { Box::leak(text.into_boxed_str()) };
+    let modes: Vec<ModeInfo> = script
+        .modes
+        .into_iter()
+        .map(|mode| match [CLASSIC, WRAP, DAILY].into_iter().find(|known| known.id == mode) {
+            Some(known) => known,
+            None => {
+                let title = script::title_case(&mode);
+                ModeInfo { id: leak(mode), title: leak(title) }
+            }
+        })
+        .collect();
+    GameInfo {
+        id: leak(script.id),
+        title: leak(script.title),
+        modes: Box::leak(modes.into_boxed_slice()),
+        ghost: false,
+        creator: Creator::Script(script.path),
+    }
+}
+
+fn built_in_games() -> Vec<GameInfo> {
+    vec![
+        GameInfo {
+            id: "flappy_bird",
+            title: "Flappy Bird",
+            modes: &[CLASSIC, DAILY, COURSES],
+            ghost: true,
+            creator: Creator::Native(|config| Box::new(FlappyBirdGame::with_config(config))),
+        },
+        GameInfo {
+            id: "snake",
+            title: "Snake",
+            modes: &[CLASSIC, WRAP, DAILY],
+            ghost: false,
+            creator: Creator::Native(|config| Box::new(SnakeGame::with_config(40, config))),
+        },
+    ]
+}
+
+/// Looks a game up by id, also accepting "flappy", "flappy-bird" and other spellings of it.
+pub fn find_game(name: &str) -> Option<&'static GameInfo> {
+    let wanted = name.to_lowercase().replace(['-', ' '], "_");
+    registry()
+        .iter()
+        .find(|game| game.id == wanted || game.id.split('_').next() == Some(wanted.as_str()))
+}
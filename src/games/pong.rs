@@ -1 +1,286 @@
-//TODO
\ No newline at end of file
+//! Pong for two players, one paddle each. Positions and speeds are whole numbers of 1/256 pixel,
+//! so the same inputs give the same game bit for bit on every machine, which the online rollback
+//! in `net::rollback` relies on.
+
+use crate::games::rng::GameRng;
+use crate::games::TICKS_PER_SECOND;
+use crate::render::{RectStyle, Renderer};
+use crate::theme::Theme;
+use ggez::graphics::Rect;
+use ggez::GameResult;
+use rand::Rng;
+
+pub const FIELD_WIDTH: i32 = 800;
+pub const FIELD_HEIGHT: i32 = 600;
+pub const WINNING_SCORE: u32 = 7;
+/// Steps in a pixel.
+const SUBPIXELS: i32 = 256;
+const PADDLE_WIDTH: i32 = 12;
+const PADDLE_HEIGHT: i32 = 90;
+/// Gap between a paddle and its edge of the field.
+const PADDLE_MARGIN: i32 = 30;
+const PADDLE_SPEED: i32 = 7 * SUBPIXELS;
+const BALL_SIZE: i32 = 12;
+const SERVE_SPEED: i32 = 5 * SUBPIXELS;
+const MAX_SPEED: i32 = 14 * SUBPIXELS;
+/// Vertical speed of a ball that hits the very end of a paddle.
+const MAX_DEFLECTION: i32 = 6 * SUBPIXELS;
+const SERVE_TICKS: u32 = TICKS_PER_SECOND;
+
+/// What a player does with their paddle during one tick.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PaddleInput {
+    #[default]
+    Still,
+    Up,
+    Down,
+}
+
+impl PaddleInput {
+    /// Up wins when both keys are held, as neither key should be able to lock the paddle.
+    pub fn from_keys(up: bool, down: bool) -> Self {
+        match (up, down) {
+            (true, _) => PaddleInput::Up,
+            (false, true) => PaddleInput::Down,
+            (false, false) => PaddleInput::Still,
+        }
+    }
+
+    /// One letter per tick, as sent over the network.
+    pub fn code(self) -> char {
+        match self {
+            PaddleInput::Still => 's',
+            PaddleInput::Up => 'u',
+            PaddleInput::Down => 'd',
+        }
+    }
+
+    pub fn from_code(code: char) -> Option<Self> {
+        match code {
+            's' => Some(PaddleInput::Still),
+            'u' => Some(PaddleInput::Up),
+            'd' => Some(PaddleInput::Down),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PongGame {
+    /// Top edge of the left and the right paddle, in subpixels.
+    pub paddles: [i32; 2],
+    /// Top left corner of the ball, in subpixels.
+    pub ball: (i32, i32),
+    pub velocity: (i32, i32),
+    /// Points of the left and the right player.
+    pub scores: [u32; 2],
+    /// Ticks left before the ball is served; it waits in the middle meanwhile.
+    pub serve_in: u32,
+    /// The side the next serve goes to: 0 is left, 1 is right.
+    pub serve_to: usize,
+    pub rng: GameRng,
+}
+
+impl PongGame {
+    pub fn new(seed: u64) -> Self {
+        let paddle = (FIELD_HEIGHT - PADDLE_HEIGHT) / 2 * SUBPIXELS;
+        let mut rng = GameRng::new(seed);
+        let serve_to = rng.gen_range(0..2);
+        PongGame {
+            paddles: [paddle; 2],
+            ball: center(),
+            velocity: (0, 0),
+            scores: [0; 2],
+            serve_in: SERVE_TICKS,
+            serve_to,
+            rng,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        self.winner().is_some()
+    }
+
+    pub fn winner(&self) -> Option<usize> {
+        self.scores.iter().position(|&score| score >= WINNING_SCORE)
+    }
+
+    /// One tick with the left and the right player's inputs.
+    pub fn step(&mut self, inputs: [PaddleInput; 2]) {
+        if self.is_over() {
+            return;
+        }
+        for (paddle, input) in self.paddles.iter_mut().zip(inputs) {
+            let step = match input {
+                PaddleInput::Still => 0,
+                PaddleInput::Up => -PADDLE_SPEED,
+                PaddleInput::Down => PADDLE_SPEED,
+            };
+            *paddle = (*paddle + step).clamp(0, (FIELD_HEIGHT - PADDLE_HEIGHT) * SUBPIXELS);
+        }
+
+        if self.serve_in > 0 {
+            self.serve_in -= 1;
+            if self.serve_in == 0 {
+                self.serve();
+            }
+            return;
+        }
+
+        let previous = self.ball;
+        self.ball.0 += self.velocity.0;
+        self.ball.1 += self.velocity.1;
+        let bottom = (FIELD_HEIGHT - BALL_SIZE) * SUBPIXELS;
+        if self.ball.1 < 0 {
+            self.ball.1 = -self.ball.1;
+            self.velocity.1 = -self.velocity.1;
+        } else if self.ball.1 > bottom {
+            self.ball.1 = 2 * bottom - self.ball.1;
+            self.velocity.1 = -self.velocity.1;
+        }
+
+        // The ball has to cross a paddle's face during the tick, so fast balls cannot slip through.
+        let left_face = (PADDLE_MARGIN + PADDLE_WIDTH) * SUBPIXELS;
+        let right_face = (FIELD_WIDTH - PADDLE_MARGIN - PADDLE_WIDTH - BALL_SIZE) * SUBPIXELS;
+        if self.velocity.0 < 0 && previous.0 >= left_face && self.ball.0 < left_face && self.touches(0) {
+            self.bounce(0, left_face);
+        } else if self.velocity.0 > 0 && previous.0 <= right_face && self.ball.0 > right_face && self.touches(1) {
+            self.bounce(1, right_face);
+        }
+
+        if self.ball.0 < -BALL_SIZE * SUBPIXELS {
+            self.point(1);
+        } else if self.ball.0 > FIELD_WIDTH * SUBPIXELS {
+            self.point(0);
+        }
+    }
+
+    fn touches(&self, side: usize) -> bool {
+        let paddle = self.paddles[side];
+        self.ball.1 + BALL_SIZE * SUBPIXELS > paddle && self.ball.1 < paddle + PADDLE_HEIGHT * SUBPIXELS
+    }
+
+    /// Sends the ball back off `side`'s paddle, a little faster, and steeper the further from
+    /// the middle of the paddle it hit.
+    fn bounce(&mut self, side: usize, face: i32) {
+        self.ball.0 = face;
+        let speed = (self.velocity.0.abs() + self.velocity.0.abs() / 16).min(MAX_SPEED);
+        self.velocity.0 = if side == 0 { speed } else { -speed };
+        let offset = (self.ball.1 + BALL_SIZE * SUBPIXELS / 2) - (self.paddles[side] + PADDLE_HEIGHT * SUBPIXELS / 2);
+        self.velocity.1 = offset * MAX_DEFLECTION / (PADDLE_HEIGHT * SUBPIXELS / 2);
+    }
+
+    /// `side` scores; the ball goes back to the middle and is served to the other side.
+    fn point(&mut self, side: usize) {
+        self.scores[side] += 1;
+        self.ball = center();
+        self.velocity = (0, 0);
+        self.serve_in = SERVE_TICKS;
+        self.serve_to = 1 - side;
+    }
+
+    fn serve(&mut self) {
+        let vertical = self.rng.gen_range(-3 * SUBPIXELS..=3 * SUBPIXELS);
+        self.velocity = (if self.serve_to == 0 { -SERVE_SPEED } else { SERVE_SPEED }, vertical);
+    }
+
+    /// FNV-1a of the whole state, so two machines can check they are still playing the same game.
+    pub fn checksum(&self) -> u64 {
+        let values = [
+            self.paddles[0] as u64,
+            self.paddles[1] as u64,
+            self.ball.0 as u64,
+            self.ball.1 as u64,
+            self.velocity.0 as u64,
+            self.velocity.1 as u64,
+            self.scores[0] as u64,
+            self.scores[1] as u64,
+            self.serve_in as u64,
+            self.serve_to as u64,
+            self.rng.state,
+        ];
+        values
+            .iter()
+            .flat_map(|value| value.to_le_bytes())
+            .fold(0xcbf2_9ce4_8422_2325, |hash, byte| (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3))
+    }
+
+    pub fn draw(&self, renderer: &mut dyn Renderer, theme: &Theme) -> GameResult {
+        let pixels = |value: i32| value as f32 / SUBPIXELS as f32;
+        for y in (0..FIELD_HEIGHT).step_by(30) {
+            renderer.rect(Rect::new(FIELD_WIDTH as f32 / 2.0 - 2.0, y as f32 + 8.0, 4.0, 14.0), theme.game_text, RectStyle::Filled)?;
+        }
+        for (side, &paddle) in self.paddles.iter().enumerate() {
+            let x = if side == 0 { PADDLE_MARGIN } else { FIELD_WIDTH - PADDLE_MARGIN - PADDLE_WIDTH };
+            renderer.rect(Rect::new(x as f32, pixels(paddle), PADDLE_WIDTH as f32, PADDLE_HEIGHT as f32), theme.pipe, RectStyle::Filled)?;
+        }
+        let ball = Rect::new(pixels(self.ball.0), pixels(self.ball.1), BALL_SIZE as f32, BALL_SIZE as f32);
+        renderer.rect(ball, theme.bird, RectStyle::Filled)?;
+
+        for (side, score) in self.scores.iter().enumerate() {
+            let text = score.to_string();
+            let width = renderer.text_width(&text, 60.0);
+            let x = if side == 0 { FIELD_WIDTH as f32 / 2.0 - 60.0 - width } else { FIELD_WIDTH as f32 / 2.0 + 60.0 };
+            renderer.text(&text, x, 30.0, 60.0, theme.game_text)?;
+        }
+        Ok(())
+    }
+}
+
+fn center() -> (i32, i32) {
+    ((FIELD_WIDTH - BALL_SIZE) / 2 * SUBPIXELS, (FIELD_HEIGHT - BALL_SIZE) / 2 * SUBPIXELS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn play(seed: u64, ticks: u32) -> PongGame {
+        let mut game = PongGame::new(seed);
+        for tick in 0..ticks {
+            let left = [PaddleInput::Up, PaddleInput::Still, PaddleInput::Down][(tick / 40) as usize % 3];
+            let right = [PaddleInput::Down, PaddleInput::Up][(tick / 25) as usize % 2];
+            game.step([left, right]);
+        }
+        game
+    }
+
+    #[test]
+    fn the_same_inputs_play_the_same_game() {
+        let game = play(5, 3_000);
+        assert_eq!(game, play(5, 3_000));
+        assert_eq!(game.checksum(), play(5, 3_000).checksum());
+        assert_ne!(game.checksum(), play(6, 3_000).checksum());
+        assert!(game.scores.iter().sum::<u32>() > 0);
+    }
+
+    #[test]
+    fn paddles_return_the_ball_and_misses_score() {
+        let mut game = PongGame::new(1);
+        game.serve_in = 0;
+        game.ball = center();
+        game.velocity = (-SERVE_SPEED, 0);
+        game.paddles[0] = game.ball.1 - (PADDLE_HEIGHT - BALL_SIZE) / 2 * SUBPIXELS;
+        while game.velocity.0 < 0 {
+            game.step([PaddleInput::Still; 2]);
+        }
+        assert!(game.velocity.0 > SERVE_SPEED);
+        assert_eq!(game.velocity.1, 0);
+        assert_eq!(game.scores, [0, 0]);
+
+        // The right paddle is moved out of the way, so the ball goes past it.
+        game.paddles[1] = 0;
+        while game.serve_in == 0 {
+            game.step([PaddleInput::Still; 2]);
+        }
+        assert_eq!(game.scores, [1, 0]);
+        assert_eq!((game.ball, game.serve_to), (center(), 1));
+
+        game.scores = [WINNING_SCORE - 1, 0];
+        game.point(0);
+        assert_eq!(game.winner(), Some(0));
+        let over = game.clone();
+        game.step([PaddleInput::Up; 2]);
+        assert_eq!(game, over);
+    }
+}
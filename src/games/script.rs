@@ -0,0 +1,408 @@
+//! Games written in Rhai and loaded at start-up from the `games` folder of the config dir, so a
+//! new minigame needs no rebuild. They get a menu button and a leaderboard like the built-in ones.
+//!
+//! A script keeps its state in `this`, a map that lives for the whole run, and may define
+//! `init()`, called once, `tick()`, called sixty times a second, `input(name)`, with "up",
+//! "down", "left", "right" or "action", and `draw()`. These can call:
+//!
+//! - `rect(x, y, width, height, colour)` and `text(content, x, y, size, colour)`, on an 800x600
+//!   field, with colours named after theme keys ("snake", "game_text", ...) or given as "#rrggbb"
+//! - `random(from, to)` for a whole number from `from` up to, not including, `to`, and
+//!   `random_float()` for one from 0 to 1; seeded from the run, so replays work
+//! - `add_score(points)`, `score()`, `game_over()` and `mode()`
+//!
+//! The first lines may name the game and its modes, e.g. `// title: Catch` and
+//! `// modes: classic, daily`. Errors in a script stop the run with an error screen.
+
+use crate::games::rng::GameRng;
+use crate::games::{GameInput, Minigame, RunConfig, CLASSIC};
+use crate::i18n::Notice;
+use crate::render::{RectStyle, Renderer};
+use crate::theme::{self, Theme};
+use ggez::graphics::Rect;
+use ggez::GameResult;
+use rand::Rng;
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, Map, Scope, AST, FLOAT, INT};
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+pub const GAMES_DIR: &str = "games";
+const SCRIPT_EXTENSION: &str = "rhai";
+/// Work a single call may do before it is taken for an endless loop.
+const MAX_OPERATIONS: u64 = 2_000_000;
+/// Draw calls a single frame may make.
+const MAX_COMMANDS: usize = 10_000;
+
+/// A script found in the games folder, before it is compiled.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ScriptInfo {
+    /// The file name without `.rhai`; it names the leaderboard, so it has to stay the same.
+    pub id: String,
+    pub title: String,
+    pub modes: Vec<String>,
+    pub path: PathBuf,
+}
+
+/// Every `.rhai` file in `dir`, by name. Files that cannot be games are skipped and reported.
+pub fn discover(dir: &Path) -> (Vec<ScriptInfo>, Vec<Notice>) {
+    let mut paths: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect(),
+        Err(_) => Vec::new(),
+    };
+    paths.sort();
+    let mut scripts = Vec::new();
+    let mut notices = Vec::new();
+    for path in paths.iter().filter(|path| path.extension().is_some_and(|ext| ext == SCRIPT_EXTENSION)) {
+        let id = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or_default().to_string();
+        if !is_id(&id) {
+            notices.push(Notice::new("notice.script_name").with("path", path.display()));
+            continue;
+        }
+        match fs::read_to_string(path) {
+            Ok(text) => scripts.push(read_header(id, &text, path.clone())),
+            Err(error) => notices.push(Notice::new("notice.unreadable_script").with("path", path.display()).with("error", error)),
+        }
+    }
+    (scripts, notices)
+}
+
+pub(crate) fn is_id(text: &str) -> bool {
+    !text.is_empty() && text.chars().all(|character| character.is_ascii_lowercase() || character.is_ascii_digit() || character == '_')
+}
+
+/// The title and modes from the `// key: value` comments at the top of the script.
+fn read_header(id: String, text: &str, path: PathBuf) -> ScriptInfo {
+    let mut info = ScriptInfo { title: title_case(&id), id, modes: vec![CLASSIC.id.to_string()], path };
+    for line in text.lines().map(str::trim).take_while(|line| line.starts_with("//")) {
+        match line.trim_start_matches('/').split_once(':').map(|(key, value)| (key.trim(), value.trim())) {
+            Some(("title", title)) if !title.is_empty() => info.title = title.to_string(),
+            Some(("modes", modes)) => {
+                let modes: Vec<String> = modes.split(',').map(str::trim).filter(|mode| is_id(mode)).map(str::to_string).collect();
+                if !modes.is_empty() {
+                    info.modes = modes;
+                }
+            }
+            _ => {}
+        }
+    }
+    info
+}
+
+/// `space_race` becomes `Space Race`.
+pub fn title_case(id: &str) -> String {
+    id.split('_')
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut characters = word.chars();
+            characters.next().map(|first| first.to_ascii_uppercase().to_string() + characters.as_str()).unwrap_or_default()
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+enum Command {
+    Rect { rect: Rect, colour: String },
+    Text { content: String, x: f32, y: f32, size: f32, colour: String },
+}
+
+/// What the script's calls into the game reach.
+struct Host {
+    commands: Vec<Command>,
+    rng: GameRng,
+    score: u32,
+    over: bool,
+    mode: String,
+}
+
+pub struct ScriptGame {
+    name: String,
+    engine: Engine,
+    ast: AST,
+    host: Rc<RefCell<Host>>,
+    /// The script's `this`; drawing needs to change it too.
+    state: RefCell<Dynamic>,
+    error: RefCell<Option<String>>,
+}
+
+impl ScriptGame {
+    /// Compiles the script and runs its `init`. A script that fails still makes a game, one that
+    /// reports the error right away.
+    pub fn load(path: &Path, config: &RunConfig) -> ScriptGame {
+        let host = Rc::new(RefCell::new(Host {
+            commands: Vec::new(),
+            rng: GameRng::new(config.seed),
+            score: 0,
+            over: false,
+            mode: config.mode.clone(),
+        }));
+        let name = path.file_name().map_or_else(|| path.display().to_string(), |name| name.to_string_lossy().into_owned());
+        let engine = engine(&host, &name);
+        let compiled = fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|text| engine.compile(text).map_err(|error| error.to_string()));
+        let mut game = ScriptGame {
+            name,
+            engine,
+            ast: AST::empty(),
+            host,
+            state: RefCell::new(Dynamic::from_map(Map::new())),
+            error: RefCell::new(None),
+        };
+        match compiled {
+            Ok(ast) => {
+                game.ast = ast;
+                if !game.has_function("tick", 0) {
+                    game.fail("the script has no tick() function".to_string());
+                }
+                game.call("init", Vec::new());
+            }
+            Err(error) => game.fail(error),
+        }
+        game
+    }
+
+    fn has_function(&self, name: &str, params: usize) -> bool {
+        self.ast.iter_functions().any(|function| function.name == name && function.params.len() == params)
+    }
+
+    /// Calls one of the script's functions, if it has it, with `this` bound to the game's state.
+    fn call(&self, function: &str, args: Vec<Dynamic>) {
+        if self.error.borrow().is_some() || !self.has_function(function, args.len()) {
+            return;
+        }
+        let mut state = self.state.borrow_mut();
+        let options = CallFnOptions::new().eval_ast(false).bind_this_ptr(&mut state);
+        let result = self.engine.call_fn_with_options::<Dynamic>(options, &mut Scope::new(), &self.ast, function, args);
+        if let Err(error) = result {
+            self.fail(format!("{}(): {}", function, error));
+        }
+    }
+
+    fn fail(&self, error: String) {
+        let error = format!("{}: {}", self.name, error);
+        log::error!("Script game stopped: {}", error);
+        self.error.borrow_mut().get_or_insert(error);
+    }
+}
+
+/// An engine with the game's functions, kept from running away with the frame.
+fn engine(host: &Rc<RefCell<Host>>, name: &str) -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.set_max_call_levels(64);
+    engine.set_max_string_size(64 * 1024);
+    engine.set_max_array_size(100_000);
+    engine.set_max_map_size(100_000);
+    let source = name.to_string();
+    engine.on_print(move |text| log::info!("{}: {}", source, text));
+
+    let commands = Rc::clone(host);
+    engine.register_fn("rect", move |x: Dynamic, y: Dynamic, width: Dynamic, height: Dynamic, colour: &str| {
+        let rect = Rect::new(number(&x)?, number(&y)?, number(&width)?, number(&height)?);
+        draw(&commands, Command::Rect { rect, colour: colour.to_string() })
+    });
+    let commands = Rc::clone(host);
+    engine.register_fn("text", move |content: Dynamic, x: Dynamic, y: Dynamic, size: Dynamic, colour: &str| {
+        let (x, y, size) = (number(&x)?, number(&y)?, number(&size)?);
+        draw(&commands, Command::Text { content: content.to_string(), x, y, size, colour: colour.to_string() })
+    });
+    let rng = Rc::clone(host);
+    engine.register_fn("random", move |from: INT, to: INT| -> Result<INT, Box<EvalAltResult>> {
+        if to <= from {
+            return Err(format!("random({}, {}) has nothing to pick from", from, to).into());
+        }
+        Ok(rng.borrow_mut().rng.gen_range(from..to))
+    });
+    let rng = Rc::clone(host);
+    engine.register_fn("random_float", move || -> FLOAT { rng.borrow_mut().rng.gen::<FLOAT>() });
+    let score = Rc::clone(host);
+    engine.register_fn("add_score", move |points: INT| {
+        let mut host = score.borrow_mut();
+        host.score = (host.score as INT).saturating_add(points).clamp(0, u32::MAX as INT) as u32;
+    });
+    let score = Rc::clone(host);
+    engine.register_fn("score", move || score.borrow().score as INT);
+    let over = Rc::clone(host);
+    engine.register_fn("game_over", move || over.borrow_mut().over = true);
+    let mode = Rc::clone(host);
+    engine.register_fn("mode", move || mode.borrow().mode.clone());
+    engine
+}
+
+fn number(value: &Dynamic) -> Result<f32, Box<EvalAltResult>> {
+    match (value.as_float(), value.as_int()) {
+        (Ok(float), _) => Ok(float as f32),
+        (_, Ok(int)) => Ok(int as f32),
+        _ => Err(format!("expected a number, got {}", value.type_name()).into()),
+    }
+}
+
+fn draw(host: &Rc<RefCell<Host>>, command: Command) -> Result<(), Box<EvalAltResult>> {
+    let mut host = host.borrow_mut();
+    if host.commands.len() >= MAX_COMMANDS {
+        return Err(format!("more than {} things drawn in one frame", MAX_COMMANDS).into());
+    }
+    host.commands.push(command);
+    Ok(())
+}
+
+impl Minigame for ScriptGame {
+    fn tick(&mut self) {
+        if !self.is_over() {
+            self.call("tick", Vec::new());
+        }
+    }
+
+    fn input(&mut self, input: GameInput) {
+        if !self.is_over() {
+            self.call("input", vec![input.name().into()]);
+        }
+    }
+
+    fn draw(&self, renderer: &mut dyn Renderer, theme: &Theme) -> GameResult {
+        self.host.borrow_mut().commands.clear();
+        self.call("draw", Vec::new());
+        let commands = std::mem::take(&mut self.host.borrow_mut().commands);
+        // Theme colours can only be looked up mutably, so one copy serves the whole frame.
+        let mut palette = theme.clone();
+        for command in commands {
+            let (Command::Rect { colour, .. } | Command::Text { colour, .. }) = &command;
+            let Some(colour) = theme::parse_color(colour).or_else(|| palette.color_mut(colour).copied()) else {
+                self.fail(format!("draw(): unknown colour '{}'", colour));
+                return Ok(());
+            };
+            match command {
+                Command::Rect { rect, .. } => renderer.rect(rect, colour, RectStyle::Filled)?,
+                Command::Text { content, x, y, size, .. } => renderer.text(&content, x, y, size, colour)?,
+            }
+        }
+        Ok(())
+    }
+
+    fn score(&self) -> u32 {
+        self.host.borrow().score
+    }
+
+    fn is_over(&self) -> bool {
+        self.host.borrow().over || self.error.borrow().is_some()
+    }
+
+    fn error(&self) -> Option<String> {
+        self.error.borrow().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::RecordingRenderer;
+
+    const CATCH: &str = r##"// title: Catch the Food
+// modes: classic, daily
+fn init() {
+    this.x = 400;
+    this.food = random(0, 800);
+}
+
+fn input(name) {
+    if name == "left" { this.x -= 20; }
+    if name == "right" { this.x += 20; }
+}
+
+fn tick() {
+    add_score(1);
+    if score() >= 3 { game_over(); }
+}
+
+fn draw() {
+    rect(this.x, 550, 40, 10, "snake");
+    rect(this.food, 0, 10.5, 10, "#ff0000");
+    text("Score: " + score(), 10, 10, 20, "game_text");
+}
+"##;
+
+    fn write(dir: &Path, name: &str, text: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, text).unwrap();
+        path
+    }
+
+    #[test]
+    fn scripts_are_found_with_their_titles_and_modes() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "catch.rhai", CATCH);
+        write(dir.path(), "space_race.rhai", "fn tick() {}");
+        write(dir.path(), "Bad Name.rhai", "fn tick() {}");
+        write(dir.path(), "notes.txt", "not a game");
+
+        let (scripts, notices) = discover(dir.path());
+        let found: Vec<_> = scripts.iter().map(|script| (script.id.as_str(), script.title.as_str(), script.modes.join(","))).collect();
+        assert_eq!(found, [("catch", "Catch the Food", "classic,daily".to_string()), ("space_race", "Space Race", "classic".to_string())]);
+        assert_eq!(notices.len(), 1, "{:?}", notices);
+    }
+
+    #[test]
+    fn a_script_plays_like_a_game() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write(dir.path(), "catch.rhai", CATCH);
+        let mut config = RunConfig::new("catch", "classic");
+        config.seed = 42;
+
+        let mut game = ScriptGame::load(&path, &config);
+        game.input(GameInput::Right);
+        game.tick();
+        let mut renderer = RecordingRenderer::new();
+        game.draw(&mut renderer, &Theme::light()).unwrap();
+        assert_eq!(renderer.rects(Theme::light().snake), [Rect::new(420.0, 550.0, 40.0, 10.0)]);
+        assert_eq!(renderer.texts(), ["Score: 1"]);
+
+        // The same seed puts the food in the same place.
+        let food = |game: &ScriptGame| game.state.borrow().clone_cast::<Map>()["food"].as_int().unwrap();
+        assert_eq!(food(&game), food(&ScriptGame::load(&path, &config)));
+
+        game.tick();
+        game.tick();
+        assert!(game.is_over());
+        assert_eq!((game.score(), game.error()), (3, None));
+    }
+
+    #[test]
+    fn the_example_script_runs_until_it_ends() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("resources").join(GAMES_DIR).join("catch.rhai");
+        let mut game = ScriptGame::load(&path, &RunConfig::new("catch", "classic"));
+        for _ in 0..20_000 {
+            if game.is_over() {
+                break;
+            }
+            game.tick();
+        }
+        game.draw(&mut RecordingRenderer::new(), &Theme::light()).unwrap();
+        assert_eq!(game.error(), None);
+        assert!(game.is_over(), "nobody moves the basket, so the food has to be missed");
+    }
+
+    #[test]
+    fn script_errors_stop_the_game_instead_of_the_program() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = RunConfig::new("broken", "classic");
+
+        let syntax = ScriptGame::load(&write(dir.path(), "syntax.rhai", "fn tick( {"), &config);
+        assert!(syntax.is_over());
+        assert!(syntax.error().unwrap().starts_with("syntax.rhai: "));
+
+        let mut runtime = ScriptGame::load(&write(dir.path(), "runtime.rhai", "fn tick() { this.missing.call(); }"), &config);
+        assert_eq!(runtime.error(), None);
+        runtime.tick();
+        assert!(runtime.error().unwrap().contains("tick()"));
+
+        let mut endless = ScriptGame::load(&write(dir.path(), "endless.rhai", "fn tick() { loop { } }"), &config);
+        endless.tick();
+        assert!(endless.is_over());
+
+        let colour = ScriptGame::load(&write(dir.path(), "colour.rhai", "fn tick() {}\nfn draw() { rect(0, 0, 1, 1, \"plaid\"); }"), &config);
+        colour.draw(&mut RecordingRenderer::new(), &Theme::light()).unwrap();
+        assert!(colour.error().unwrap().contains("plaid"));
+    }
+}
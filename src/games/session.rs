@@ -0,0 +1,327 @@
+use crate::date;
+use crate::games::{self, GameInput, Minigame, RunConfig, TICKS_PER_SECOND};
+use crate::net::spectate::Frame;
+use crate::replay::Replay;
+use crate::save::{SaveGame, SavedState};
+use crate::scores::{self, ScoreBoard, ScoreEntry};
+use crate::stats::RunRecord;
+
+/// How a finished run went, for the game-over panel.
+#[derive(Clone, PartialEq, Debug)]
+pub struct RunSummary {
+    pub score: u32,
+    pub seconds: f32,
+    /// What went on the board; `None` for unranked runs and replays.
+    pub entry: Option<ScoreEntry>,
+    /// 0-based place on the board, if the run made it.
+    pub rank: Option<usize>,
+    /// The player's best on this board before this run.
+    pub previous_best: Option<u32>,
+    /// When the run crossed the finish line, for games raced against the clock.
+    pub finish_ticks: Option<u32>,
+}
+
+impl RunSummary {
+    pub fn is_new_record(&self) -> bool {
+        match &self.entry {
+            Some(entry) if scores::is_timed(&entry.mode) => self.previous_best.map_or(true, |best| entry.score < best),
+            Some(_) => self.score > 0 && self.previous_best.map_or(true, |best| self.score > best),
+            None => false,
+        }
+    }
+}
+
+struct Playback {
+    replay: Replay,
+    next_input: usize,
+}
+
+/// One run of a game as every frontend drives it: fixed ticks, inputs recorded into a replay (or
+/// read back from one) and the score entry at the end.
+pub struct Session {
+    pub game: Box<dyn Minigame>,
+    pub config: RunConfig,
+    pub tick: u32,
+    /// Set for daily challenge runs after the day's scored one; they stay off the board.
+    pub practice: bool,
+    recording: Replay,
+    playback: Option<Playback>,
+    death_animation_done: bool,
+}
+
+impl Session {
+    /// Panics if `config.game` is not registered; callers check it with `games::find_game` first.
+    pub fn new(config: RunConfig) -> Self {
+        let game = match games::find_game(&config.game) {
+            Some(info) => info.create(&config),
+            None => panic!("no game registered as '{}'", config.game),
+        };
+        Session {
+            game,
+            recording: Replay::new(config.clone()),
+            config,
+            tick: 0,
+            practice: false,
+            playback: None,
+            death_animation_done: false,
+        }
+    }
+
+    pub fn replay(replay: Replay) -> Self {
+        let mut session = Session::new(replay.config.clone());
+        session.playback = Some(Playback { replay, next_input: 0 });
+        session
+    }
+
+    pub fn is_playback(&self) -> bool {
+        self.playback.is_some()
+    }
+
+    /// Picks a saved run up where it was left.
+    pub fn resume(save: SaveGame) -> Result<Session, String> {
+        let config = save.recording.config.clone();
+        if games::find_game(&config.game).is_none() {
+            return Err(format!("unknown game '{}'", config.game));
+        }
+        let mut session = Session::new(config);
+        session.game.load_state(&save.state)?;
+        session.tick = save.recording.ticks;
+        session.recording = save.recording;
+        Ok(session)
+    }
+
+    /// Everything needed to resume the run later, or `None` when there is nothing to save:
+    /// replays, finished runs and games that do not support saving.
+    pub fn save(&self) -> Option<SaveGame> {
+        if self.is_playback() || self.game.is_over() || self.tick == 0 {
+            return None;
+        }
+        let mut state = SavedState::default();
+        self.game.save_state(&mut state);
+        (!state.is_empty()).then(|| SaveGame { recording: self.recording(), state })
+    }
+
+    /// The same run from the start: the replay again, or a new game with the same config.
+    pub fn restarted(&self) -> Session {
+        match &self.playback {
+            Some(playback) => Session::replay(playback.replay.clone()),
+            None => Session::new(self.config.clone()),
+        }
+    }
+
+    /// Advances one tick. During playback the recorded inputs are used and `inputs` is ignored.
+    /// Once the game is over only its death animation moves on.
+    pub fn step(&mut self, inputs: impl IntoIterator<Item = GameInput>) {
+        if self.game.is_over() {
+            self.death_animation_done = !self.game.animate_death();
+            return;
+        }
+
+        match &mut self.playback {
+            Some(playback) => {
+                while let Some(&(tick, input)) = playback.replay.inputs.get(playback.next_input) {
+                    if tick > self.tick {
+                        break;
+                    }
+                    self.game.input(input);
+                    playback.next_input += 1;
+                }
+            }
+            None => {
+                for input in inputs {
+                    self.recording.record(self.tick, input);
+                    self.game.input(input);
+                }
+            }
+        }
+
+        self.game.tick();
+        self.tick += 1;
+    }
+
+    /// Jumps to the end of the death animation, for players who turned animations off.
+    pub fn skip_death_animation(&mut self) {
+        while self.game.is_over() && self.game.animate_death() {}
+        self.death_animation_done = self.game.is_over();
+    }
+
+    /// Over, and done with the death animation: time for the game-over panel.
+    pub fn is_finished(&self) -> bool {
+        self.game.is_over() && self.death_animation_done
+    }
+
+    pub fn elapsed(&self) -> f32 {
+        self.tick as f32 / TICKS_PER_SECOND as f32
+    }
+
+    /// The run as spectators see it: who plays what, the score, and the game's own state.
+    pub fn spectate(&self) -> Frame {
+        let mut state = Frame::default();
+        self.game.spectate(&mut state);
+        let mut frame = Frame::default();
+        frame.set_text("game", &self.config.game);
+        frame.set_text("mode", &self.config.mode);
+        frame.set_text("player", &scores::player_name());
+        frame.set("tick", self.tick);
+        frame.set("score", self.game.score());
+        frame.set("over", self.game.is_over());
+        frame.set("replay", self.is_playback());
+        frame.set_object("state", state);
+        frame
+    }
+
+    pub fn recording(&self) -> Replay {
+        let mut replay = self.recording.clone();
+        replay.ticks = self.tick;
+        replay
+    }
+
+    /// Puts a ranked run on the board (replays never are) and sums it up. The caller saves the board.
+    pub fn record(&self, scores: &mut ScoreBoard) -> RunSummary {
+        let ranked = self.config.is_ranked() && !self.is_playback() && !self.practice;
+        let entry = self.score_entry().filter(|_| ranked);
+        let previous_best = scores.personal_best(&self.config.game, &self.config.mode, &scores::player_name());
+        let rank = entry.as_ref().and_then(|entry| scores.add(entry.clone()));
+        RunSummary {
+            score: self.game.score(),
+            seconds: self.elapsed(),
+            entry,
+            rank,
+            previous_best,
+            finish_ticks: self.game.finish_ticks(),
+        }
+    }
+
+    /// The run's board entry. Timed boards take the finish time as the score, so a run that
+    /// never reached the finish has no entry there.
+    pub fn score_entry(&self) -> Option<ScoreEntry> {
+        let score = if scores::is_timed(&self.config.mode) { self.game.finish_ticks()? } else { self.game.score() };
+        Some(ScoreEntry {
+            game: self.config.game.clone(),
+            mode: self.config.mode.clone(),
+            name: scores::player_name(),
+            score,
+            timestamp: date::now_timestamp(),
+            duration: self.elapsed(),
+        })
+    }
+
+    /// The run as the history keeps it: every difficulty, plus the game's own counters and the
+    /// finish time of a run that reached the finish line.
+    pub fn run_record(&self) -> RunRecord {
+        let mut counters: Vec<(String, u32)> = self.game.counters().into_iter().map(|(name, value)| (name.to_string(), value)).collect();
+        if let Some(ticks) = self.game.finish_ticks() {
+            counters.push(("finish_ticks".to_string(), ticks));
+        }
+        RunRecord {
+            game: self.config.game.clone(),
+            mode: self.config.mode.clone(),
+            difficulty: self.config.difficulty,
+            player: scores::player_name(),
+            score: self.game.score(),
+            seed: self.config.seed,
+            timestamp: date::now_timestamp(),
+            duration: self.elapsed(),
+            counters,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::Difficulty;
+
+    fn finished_run(difficulty: Difficulty) -> Session {
+        let mut config = RunConfig::new("flappy_bird", "classic");
+        config.difficulty = difficulty;
+        let mut session = Session::new(config);
+        while !session.game.is_over() {
+            session.step([]);
+        }
+        session
+    }
+
+    #[test]
+    fn the_panel_waits_for_the_death_animation() {
+        let mut session = finished_run(Difficulty::Normal);
+        assert!(!session.is_finished());
+        let ticks = session.tick;
+        while !session.is_finished() {
+            session.step([]);
+        }
+        assert_eq!(session.tick, ticks);
+
+        let mut skipped = finished_run(Difficulty::Normal);
+        skipped.skip_death_animation();
+        assert!(skipped.is_finished());
+    }
+
+    #[test]
+    fn ranked_runs_go_on_the_board_and_beat_the_old_best() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut scores = ScoreBoard::load(dir.path().join("scores.txt")).0;
+        let mut session = finished_run(Difficulty::Normal);
+
+        let first = session.record(&mut scores);
+        assert_eq!((first.rank, first.previous_best), (Some(0), None));
+        assert_eq!(first.is_new_record(), first.score > 0);
+
+        let mut entry = session.score_entry().unwrap();
+        entry.score = first.score + 5;
+        scores.add(entry);
+        let second = session.record(&mut scores);
+        assert_eq!(second.previous_best, Some(first.score + 5));
+        assert!(second.rank.is_some_and(|rank| rank > 0));
+        assert!(!second.is_new_record());
+
+        session = finished_run(Difficulty::Easy);
+        let unranked = session.record(&mut scores);
+        assert_eq!((unranked.entry, unranked.rank), (None, None));
+        assert_eq!(scores.board("flappy_bird", "classic").len(), 3);
+    }
+
+    #[test]
+    fn a_resumed_run_plays_on_exactly_like_the_original() {
+        for (game, input) in [("snake", GameInput::Down), ("flappy_bird", GameInput::Action)] {
+            let mut config = RunConfig::new(game, "classic");
+            config.seed = 7;
+            let mut original = Session::new(config);
+            for tick in 0..90 {
+                original.step((tick % 30 == 0).then_some(input));
+            }
+
+            let save = SaveGame::parse(&original.save().unwrap().to_text()).unwrap();
+            let mut resumed = Session::resume(save).unwrap();
+            for tick in 90..400 {
+                let inputs = (tick % 30 == 0).then_some(input);
+                original.step(inputs);
+                resumed.step(inputs);
+                assert_eq!(resumed.save(), original.save(), "{} at tick {}", game, tick);
+                assert_eq!((resumed.game.score(), resumed.game.is_over()), (original.game.score(), original.game.is_over()));
+            }
+            assert_eq!(resumed.recording(), original.recording());
+        }
+    }
+
+    #[test]
+    fn broken_or_finished_runs_are_not_resumed() {
+        assert!(finished_run(Difficulty::Normal).save().is_none());
+        let mut session = Session::new(RunConfig::new("snake", "classic"));
+        session.step([]);
+        let mut save = session.save().unwrap();
+        save.state = SavedState::default();
+        assert!(Session::resume(save.clone()).is_err());
+        save.recording.config.game = "tetris".to_string();
+        assert!(Session::resume(save).is_err());
+    }
+
+    #[test]
+    fn the_history_keeps_unranked_runs_and_counters() {
+        let session = finished_run(Difficulty::Hard);
+        let record = session.run_record();
+        assert_eq!(record.difficulty, Difficulty::Hard);
+        assert_eq!(record.counter("pipes"), Some(session.game.score()));
+        assert_eq!(record.counter("finish_ticks"), None);
+    }
+}
@@ -1,18 +1,42 @@
-use ggez::event::KeyCode;
-use ggez::graphics::{Color, DrawMode, Font, PxScale, Rect, Text, TextFragment};
-use ggez::timer;
-use ggez::{Context, GameResult};
-use std::collections::VecDeque;
+use crate::games::rng::GameRng;
+use crate::games::snake_map::SnakeMap;
+use crate::games::{Difficulty, GameEvent, GameInput, Minigame, RunConfig, WRAP};
+use crate::net::spectate::Frame;
+use crate::render::{RectStyle, Renderer};
+use crate::save::{self, SavedState};
+use crate::theme::Theme;
+use ggez::graphics::Rect;
+use ggez::GameResult;
+use rand::Rng;
+use std::collections::{BTreeSet, VecDeque};
+use std::f32::consts::{FRAC_PI_2, PI};
+
+const CELL_SIZE: f32 = 20.0;
+const DEATH_TICKS: u32 = 60;
+const BLINK_TICKS: u32 = 8;
 
 #[derive(PartialEq)]
 pub struct SnakeGame {
     pub snake: VecDeque<(i32, i32)>,
     pub direction: (i32, i32),
+    /// The direction of the last actual step; turns are checked against this so two quick key
+    /// presses between steps cannot reverse the snake into itself.
+    pub last_step: (i32, i32),
     pub grid_size: i32,
     pub food: (i32, i32),
-    pub time_since_last_update: f32,
+    /// Cells the snake crashes into, besides the edges; only maps have any.
+    pub walls: BTreeSet<(i32, i32)>,
+    /// Cells food falls on, when a map names some; anywhere free otherwise.
+    pub food_zones: Vec<(i32, i32)>,
+    pub ticks_since_move: u32,
+    pub ticks_per_move: u32,
+    pub wrap: bool,
+    pub rng: GameRng,
     pub score: u32,
     pub game_over: bool,
+    /// Ticks of the blinking played after the crash; the game-over text waits for it.
+    pub death_ticks: u32,
+    pub events: Vec<GameEvent>,
 }
 
 impl SnakeGame {
@@ -22,78 +46,159 @@ impl SnakeGame {
         SnakeGame {
             snake,
             direction: (1, 0),
+            last_step: (1, 0),
             grid_size,
             food: (10, 10),
-            time_since_last_update: 0.0,
+            walls: BTreeSet::new(),
+            food_zones: Vec::new(),
+            ticks_since_move: 0,
+            ticks_per_move: 15,
+            wrap: false,
+            rng: GameRng::new(GameRng::random_seed()),
             score: 0,
             game_over: false,
+            death_ticks: 0,
+            events: Vec::new(),
         }
     }
 
-    pub fn update(&mut self, ctx: &mut Context) {
+    pub fn with_config(grid_size: i32, config: &RunConfig) -> Self {
+        let mut game = SnakeGame::new(grid_size);
+        game.rng = GameRng::new(config.seed);
+        game.wrap = config.mode == WRAP.id;
+        game.ticks_per_move = match config.difficulty {
+            Difficulty::Easy => 20,
+            Difficulty::Normal => 15,
+            Difficulty::Hard => 9,
+        };
+        game
+    }
+
+    /// Starts the snake where the map says and builds its walls. The map has to be the size of the board.
+    pub fn use_map(&mut self, map: &SnakeMap) {
+        self.snake = VecDeque::from([map.start]);
+        self.direction = map.direction;
+        self.last_step = map.direction;
+        self.walls = map.walls().collect();
+        self.food_zones = map.food_zones().collect();
+        self.generate_food();
+    }
+
+    /// The board is as wide as `grid_size` but ten rows shorter, to fit the 800x600 window.
+    pub fn height(&self) -> i32 {
+        self.grid_size - 10
+    }
+
+    pub fn update(&mut self) {
+        self.events.clear();
         if self.game_over {
             return;
         }
 
-        let delta_time = timer::delta(ctx).as_secs_f32();
-        self.time_since_last_update += delta_time;
+        self.ticks_since_move += 1;
 
-        if self.time_since_last_update >= 0.25 {
-            self.time_since_last_update = 0.0;
+        if self.ticks_since_move >= self.ticks_per_move {
+            self.ticks_since_move = 0;
 
-            let new_head = (self.snake.front().unwrap().0 + self.direction.0,
-                            self.snake.front().unwrap().1 + self.direction.1);
+            let head = *self.snake.front().unwrap();
+            let mut new_head = (head.0 + self.direction.0, head.1 + self.direction.1);
+            if self.wrap {
+                new_head = (new_head.0.rem_euclid(self.grid_size), new_head.1.rem_euclid(self.height()));
+            }
+            self.last_step = self.direction;
 
+            let eats = new_head == self.food;
+            if !eats {
+                self.snake.pop_back();
+            }
             self.snake.push_front(new_head);
-            self.snake.pop_back();
 
-            if self.snake.front().unwrap() == &self.food {
+            if self.is_game_over() {
+                self.game_over = true;
+                let (x, y) = cell_center(new_head);
+                self.events.push(GameEvent::SnakeCrashed { x, y });
+                return;
+            }
+            if eats {
+                let (x, y) = cell_center(new_head);
+                self.events.push(GameEvent::FoodEaten { x, y });
                 self.score += 10;
-                self.snake.push_back(*self.snake.back().unwrap());
                 self.generate_food();
             }
+        }
+    }
 
-            if self.is_game_over() {
-                self.game_over = true;
+    /// Step from one segment to the next, seeing through the wrap-around edge.
+    fn step_between(&self, from: (i32, i32), to: (i32, i32)) -> (i32, i32) {
+        let wrap = |delta: i32, size: i32| match delta {
+            d if d > 1 => d - size,
+            d if d < -1 => d + size,
+            d => d,
+        };
+        (wrap(to.0 - from.0, self.grid_size), wrap(to.1 - from.1, self.height()))
+    }
+
+    /// Which tile draws the segment at `index` and how far to turn it. The tiles face right:
+    /// the head looks right, the tail and straight body join on the right, the corner joins up and right.
+    pub fn tile(&self, index: usize) -> (&'static str, f32) {
+        let segment = self.snake[index];
+        let towards_head = index.checked_sub(1).map(|previous| self.step_between(segment, self.snake[previous]));
+        let towards_tail = self.snake.get(index + 1).map(|&next| self.step_between(segment, next));
+
+        match (towards_head, towards_tail) {
+            (None, Some(tail)) => ("snake_head", angle((-tail.0, -tail.1))),
+            (None, None) => ("snake_head", angle(self.last_step)),
+            (Some(head), None) => ("snake_tail", angle(head)),
+            (Some(a), Some(b)) if a == (-b.0, -b.1) => ("snake_body", angle(a) % PI),
+            (Some(a), Some(b)) => {
+                // The corner turned by 90 degrees clockwise joins the next pair, e.g. right and down.
+                let rotation = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+                    .iter()
+                    .position(|&first| {
+                        let second = (-first.1, first.0);
+                        (a == first && b == second) || (b == first && a == second)
+                    })
+                    .unwrap_or(0);
+                ("snake_corner", rotation as f32 * FRAC_PI_2)
             }
         }
     }
 
-    pub fn change_direction(&mut self, keycode: KeyCode) {
+    pub fn change_direction(&mut self, input: GameInput) {
         if self.game_over {
             return;
         }
 
-        match keycode {
-            KeyCode::Up => if self.direction != (0, 1) { self.direction = (0, -1) },
-            KeyCode::Down => if self.direction != (0, -1) { self.direction = (0, 1) },
-            KeyCode::Left => if self.direction != (1, 0) { self.direction = (-1, 0) },
-            KeyCode::Right => if self.direction != (-1, 0) { self.direction = (1, 0) },
+        match input {
+            GameInput::Up if self.last_step != (0, 1) => self.direction = (0, -1),
+            GameInput::Down if self.last_step != (0, -1) => self.direction = (0, 1),
+            GameInput::Left if self.last_step != (1, 0) => self.direction = (-1, 0),
+            GameInput::Right if self.last_step != (-1, 0) => self.direction = (1, 0),
             _ => {}
         }
     }
 
+    /// Puts food on a random free cell, in a food zone while one is free. A snake that fills the
+    /// whole board has nowhere left to go, so that ends the game.
     fn generate_food(&mut self) {
-        use rand::Rng;
-        let mut rng = rand::thread_rng();
-
-        loop {
-            let x = rng.gen_range(0..self.grid_size);
-            let y = rng.gen_range(0..self.grid_size - 10);
-
-            if !self.snake.contains(&(x, y)) {
-                self.food = (x, y);
-                break;
-            }
+        let is_free = |cell: &(i32, i32)| !self.snake.contains(cell) && !self.walls.contains(cell);
+        let mut free: Vec<(i32, i32)> = self.food_zones.iter().copied().filter(is_free).collect();
+        if free.is_empty() {
+            free = (0..self.height()).flat_map(|y| (0..self.grid_size).map(move |x| (x, y))).filter(is_free).collect();
+        }
+        if free.is_empty() {
+            self.game_over = true;
+            return;
         }
+        self.food = free[self.rng.gen_range(0..free.len())];
     }
 
     fn is_game_over(&self) -> bool {
         let head = self.snake.front().unwrap();
-        if head.0 < 0 || head.0 >= self.grid_size || head.1 < 0 || head.1 >= self.grid_size - 10 {
+        if head.0 < 0 || head.0 >= self.grid_size || head.1 < 0 || head.1 >= self.height() || self.walls.contains(head) {
             return true;
         }
-        for segment in self.snake.iter().skip(2) {
+        for segment in self.snake.iter().skip(1) {
             if segment == head {
                 return true;
             }
@@ -101,43 +206,298 @@ impl SnakeGame {
         false
     }
 
-    pub fn draw(&self, ctx: &mut Context) -> GameResult {
-        use ggez::graphics;
+    fn is_dying(&self) -> bool {
+        self.game_over && self.death_ticks < DEATH_TICKS
+    }
 
-        for &(x, y) in &self.snake {
-            let rect = Rect::new(x as f32 * 20.0, y as f32 * 20.0, 20.0, 20.0);
-            let mesh = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), rect, Color::GREEN)?;
-            graphics::draw(ctx, &mesh, (ggez::mint::Point2 { x: 0.0, y: 0.0 },))?;
+    pub fn draw(&self, renderer: &mut dyn Renderer, theme: &Theme) -> GameResult {
+        for &(x, y) in &self.walls {
+            renderer.rect(cell_rect(x, y), theme.wall, RectStyle::Filled)?;
+        }
+        let hidden = self.is_dying() && (self.death_ticks / BLINK_TICKS) & 1 == 0;
+        for (index, &(x, y)) in self.snake.iter().enumerate().filter(|_| !hidden) {
+            let (tile, rotation) = self.tile(index);
+            if !(theme.sprites && renderer.sprite(tile, 0, cell_rect(x, y), rotation)?) {
+                renderer.rect(cell_rect(x, y), theme.snake, RectStyle::Filled)?;
+            }
+        }
+        let food = cell_rect(self.food.0, self.food.1);
+        if !(theme.sprites && renderer.sprite("food", 0, food, 0.0)?) {
+            renderer.rect(food, theme.food, RectStyle::Filled)?;
         }
 
-        let food_rect = Rect::new(self.food.0 as f32 * 20.0, self.food.1 as f32 * 20.0, 20.0, 20.0);
-        let food_mesh = graphics::Mesh::new_rectangle(ctx, DrawMode::fill(), food_rect, Color::RED)?;
-        graphics::draw(ctx, &food_mesh, (ggez::mint::Point2 { x: 0.0, y: 0.0 },))?;
+        Ok(())
+    }
+}
 
-        if self.game_over {
-            let game_over = Text::new(
-                TextFragment::new("GAME OVER")
-                    .font(Font::default().clone())
-                    .scale(PxScale::from(50.0))
-                    .color(Color::BLACK)
-            );
-            let score_text = Text::new(
-                TextFragment::new(format!("Score: {}", self.score))
-                    .font(Font::default().clone())
-                    .scale(PxScale::from(30.0))
-                    .color(Color::BLACK)
-            );
-            let exit_text = Text::new(
-                TextFragment::new(format!("Press ESC to leave"))
-                    .font(Font::default().clone())
-                    .scale(PxScale::from(30.0))
-                    .color(Color::BLACK)
-            );
-            graphics::draw(ctx, &game_over, (ggez::mint::Point2 { x: 280.0, y: 100.0 },))?;
-            graphics::draw(ctx, &score_text, (ggez::mint::Point2 { x: 280.0, y: 150.0 },))?;
-            graphics::draw(ctx, &exit_text, (ggez::mint::Point2 { x: 280.0, y: 200.0 },))?;
+/// Clockwise turn from facing right to facing along `step`.
+fn angle(step: (i32, i32)) -> f32 {
+    match step {
+        (0, 1) => FRAC_PI_2,
+        (-1, 0) => PI,
+        (0, -1) => 3.0 * FRAC_PI_2,
+        _ => 0.0,
+    }
+}
+
+fn cell_center((x, y): (i32, i32)) -> (f32, f32) {
+    ((x as f32 + 0.5) * CELL_SIZE, (y as f32 + 0.5) * CELL_SIZE)
+}
+
+fn cell_rect(x: i32, y: i32) -> Rect {
+    Rect::new(x as f32 * CELL_SIZE, y as f32 * CELL_SIZE, CELL_SIZE, CELL_SIZE)
+}
+
+impl Minigame for SnakeGame {
+    fn tick(&mut self) {
+        self.update();
+    }
+
+    fn input(&mut self, input: GameInput) {
+        self.change_direction(input);
+    }
+
+    fn draw(&self, renderer: &mut dyn Renderer, theme: &Theme) -> GameResult {
+        SnakeGame::draw(self, renderer, theme)
+    }
+
+    fn score(&self) -> u32 {
+        self.score
+    }
+
+    fn is_over(&self) -> bool {
+        self.game_over
+    }
+
+    fn counters(&self) -> Vec<(&'static str, u32)> {
+        vec![("length", self.snake.len() as u32)]
+    }
+
+    fn save_state(&self, state: &mut SavedState) {
+        let point = |(x, y): (i32, i32)| format!("{},{}", x, y);
+        state.set("grid_size", self.grid_size);
+        state.set_list("snake", self.snake.iter().copied().map(point));
+        state.set("direction", point(self.direction));
+        state.set("last_step", point(self.last_step));
+        state.set("food", point(self.food));
+        state.set_list("walls", self.walls.iter().copied().map(point));
+        state.set_list("food_zones", self.food_zones.iter().copied().map(point));
+        state.set("ticks_since_move", self.ticks_since_move);
+        state.set("ticks_per_move", self.ticks_per_move);
+        state.set("wrap", self.wrap);
+        state.set("rng", self.rng.state);
+        state.set("score", self.score);
+    }
+
+    fn load_state(&mut self, state: &SavedState) -> Result<(), String> {
+        let point = |text: &str| save::parse_group(text, 2).map(|xy: Vec<i32>| (xy[0], xy[1]));
+        let grid_size: i32 = state.get("grid_size")?;
+        // The board is ten rows shorter than it is wide, so anything smaller has no rows at all.
+        if grid_size <= 10 {
+            return Err(format!("bad grid_size '{}'", grid_size));
         }
+        let snake = state.list("snake")?.into_iter().map(point).collect::<Result<VecDeque<_>, _>>()?;
+        if snake.is_empty() {
+            return Err("the saved snake has no segments".to_string());
+        }
+        self.grid_size = grid_size;
+        self.snake = snake;
+        self.direction = point(&state.get::<String>("direction")?)?;
+        self.last_step = point(&state.get::<String>("last_step")?)?;
+        self.food = point(&state.get::<String>("food")?)?;
+        self.walls = state.list("walls")?.into_iter().map(point).collect::<Result<_, _>>()?;
+        self.food_zones = state.list("food_zones")?.into_iter().map(point).collect::<Result<_, _>>()?;
+        self.ticks_since_move = state.get("ticks_since_move")?;
+        self.ticks_per_move = state.get("ticks_per_move")?;
+        self.wrap = state.get("wrap")?;
+        self.rng = GameRng::new(state.get("rng")?);
+        self.score = state.get("score")?;
+        Ok(())
+    }
+
+    fn spectate(&self, frame: &mut Frame) {
+        frame.set("width", self.grid_size);
+        frame.set("height", self.height());
+        frame.set_points("snake", self.snake.iter().copied());
+        frame.set_point("direction", self.direction);
+        frame.set_point("food", self.food);
+        frame.set_points("walls", self.walls.iter().copied());
+        frame.set("wrap", self.wrap);
+    }
 
+    fn events(&self) -> &[GameEvent] {
+        &self.events
+    }
+
+    fn animate_death(&mut self) -> bool {
+        self.events.clear();
+        self.death_ticks = (self.death_ticks + 1).min(DEATH_TICKS);
+        self.is_dying()
+    }
+
+    fn check_invariants(&self) -> Result<(), String> {
+        if self.game_over {
+            return Ok(());
+        }
+        for (index, segment) in self.snake.iter().enumerate() {
+            if segment.0 < 0 || segment.0 >= self.grid_size || segment.1 < 0 || segment.1 >= self.height() {
+                return Err(format!("segment {} at {:?} is off the board", index, segment));
+            }
+            if self.walls.contains(segment) {
+                return Err(format!("segment {} at {:?} is in a wall", index, segment));
+            }
+            if self.snake.iter().skip(index + 1).any(|other| other == segment) {
+                return Err(format!("snake overlaps itself at {:?}", segment));
+            }
+        }
+        if self.snake.contains(&self.food) {
+            return Err(format!("food at {:?} is on the snake", self.food));
+        }
+        if self.walls.contains(&self.food) {
+            return Err(format!("food at {:?} is in a wall", self.food));
+        }
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::snake_map::{Cell, MAP_WIDTH};
+    use crate::render::RecordingRenderer;
+    use ggez::graphics::Color;
+
+    fn step(game: &mut SnakeGame) {
+        game.ticks_since_move = game.ticks_per_move - 1;
+        game.update();
+    }
+
+    #[test]
+    fn filling_the_board_ends_the_game_instead_of_hanging() {
+        // A 12x2 board with the snake snaking through every cell but the one holding the food.
+        let mut game = SnakeGame::new(12);
+        game.snake = (1..12).map(|x| (x, 1)).chain((0..12).rev().map(|x| (x, 0))).collect();
+        game.direction = (-1, 0);
+        game.last_step = (-1, 0);
+        game.food = (0, 1);
+
+        step(&mut game);
+
+        assert_eq!(game.snake.len(), 24);
+        assert_eq!(game.score, 10);
+        assert!(game.game_over);
+    }
+
+    #[test]
+    fn food_never_lands_on_the_snake() {
+        let mut game = SnakeGame::new(12);
+        game.snake = (1..12).map(|x| (x, 1)).chain((2..12).rev().map(|x| (x, 0))).collect();
+        for seed in 0..50 {
+            game.rng = GameRng::new(seed);
+            game.generate_food();
+            assert!([(0, 0), (1, 0), (0, 1)].contains(&game.food), "food at {:?}", game.food);
+        }
+    }
+
+    #[test]
+    fn growing_does_not_stack_segments() {
+        let mut game = SnakeGame::new(40);
+        game.food = (6, 5);
+        step(&mut game);
+        assert_eq!(game.snake, VecDeque::from([(6, 5), (5, 5)]));
+        assert_eq!(game.check_invariants(), Ok(()));
+    }
+
+    #[test]
+    fn two_quick_turns_cannot_reverse_into_the_neck() {
+        let mut game = SnakeGame::new(40);
+        game.snake = VecDeque::from([(5, 5), (4, 5), (3, 5)]);
+        game.change_direction(GameInput::Up);
+        game.change_direction(GameInput::Left);
+        step(&mut game);
+        assert!(!game.game_over);
+        assert_eq!(*game.snake.front().unwrap(), (5, 4));
+    }
+
+    #[test]
+    fn every_segment_is_drawn_on_its_cell() {
+        let mut game = SnakeGame::new(40);
+        game.snake = VecDeque::from([(5, 5), (4, 5), (4, 6)]);
+        game.food = (10, 2);
+        let mut renderer = RecordingRenderer::new();
+        game.draw(&mut renderer, &Theme::light()).unwrap();
+
+        let segments = renderer.rects(Color::GREEN);
+        assert_eq!(segments, vec![
+            Rect::new(100.0, 100.0, 20.0, 20.0),
+            Rect::new(80.0, 100.0, 20.0, 20.0),
+            Rect::new(80.0, 120.0, 20.0, 20.0),
+        ]);
+        assert_eq!(renderer.rects(Color::RED), vec![Rect::new(200.0, 40.0, 20.0, 20.0)]);
+        assert!(!renderer.has_text("GAME OVER"));
+    }
+
+    #[test]
+    fn a_crash_blinks_the_snake_until_the_panel_shows() {
+        let mut game = SnakeGame::new(40);
+        game.snake = VecDeque::from([(39, 5)]);
+        step(&mut game);
+        assert!(game.game_over);
+        assert_eq!(game.events(), &[GameEvent::SnakeCrashed { x: 810.0, y: 110.0 }]);
+
+        let mut shown = Vec::new();
+        while game.animate_death() {
+            let mut renderer = RecordingRenderer::new();
+            game.draw(&mut renderer, &Theme::light()).unwrap();
+            shown.push(!renderer.rects(Color::GREEN).is_empty());
+        }
+        assert!(shown.contains(&true) && shown.contains(&false));
+        assert!(game.events().is_empty());
+
+        let mut renderer = RecordingRenderer::new();
+        game.draw(&mut renderer, &Theme::light()).unwrap();
+        assert!(!renderer.rects(Color::GREEN).is_empty());
+        assert!(!renderer.has_text("GAME OVER"));
+    }
+
+    #[test]
+    fn maps_bring_walls_and_food_zones() {
+        let mut map = SnakeMap::new();
+        map.set((7, 5), Cell::Wall);
+        map.set((20, 20), Cell::Food);
+        map.set((21, 20), Cell::Food);
+        let mut game = SnakeGame::new(MAP_WIDTH);
+        for seed in 0..20 {
+            game.rng = GameRng::new(seed);
+            game.use_map(&map);
+            assert!([(20, 20), (21, 20)].contains(&game.food), "food at {:?}", game.food);
+        }
+
+        step(&mut game);
+        assert!(!game.game_over);
+        step(&mut game);
+        assert!(game.game_over, "the snake went through the wall at (7, 5)");
+    }
+
+    #[test]
+    fn tiles_follow_the_neighbours() {
+        let mut game = SnakeGame::new(40);
+        // Head going up, turning from a rightward body, tail on the left.
+        game.snake = VecDeque::from([(5, 4), (5, 5), (4, 5), (3, 5)]);
+        assert_eq!(game.tile(0), ("snake_head", 3.0 * FRAC_PI_2));
+        assert_eq!(game.tile(1), ("snake_corner", 3.0 * FRAC_PI_2));
+        assert_eq!(game.tile(2), ("snake_body", 0.0));
+        assert_eq!(game.tile(3), ("snake_tail", 0.0));
+
+        game.snake = VecDeque::from([(5, 6), (5, 5), (6, 5)]);
+        assert_eq!(game.tile(1), ("snake_corner", FRAC_PI_2));
+    }
+
+    #[test]
+    fn tiles_see_through_the_wrap() {
+        let mut game = SnakeGame::new(40);
+        game.snake = VecDeque::from([(0, 5), (39, 5), (38, 5)]);
+        assert_eq!(game.tile(0), ("snake_head", 0.0));
+        assert_eq!(game.tile(1), ("snake_body", 0.0));
+    }
+}
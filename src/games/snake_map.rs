@@ -0,0 +1,278 @@
+//! Snake maps: walls, a start and food zones on a board the size of the normal one. They are
+//! plain text, so they can be shared and touched up by hand as well as in the editor.
+//!
+//! A map is a `direction = right` line followed by one line per row: `.` is an open cell, `#` a
+//! wall, `*` a food zone and `S` the start.
+
+use crate::storage;
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MAPS_DIR: &str = "maps";
+const MAP_EXTENSION: &str = "map";
+pub const MAP_WIDTH: i32 = 40;
+/// As tall as the snake board, which is ten rows shorter than it is wide.
+pub const MAP_HEIGHT: i32 = MAP_WIDTH - 10;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Cell {
+    Open,
+    Wall,
+    /// Open, and one of the only cells food may fall on.
+    Food,
+}
+
+/// Why a map cannot be played yet.
+#[derive(Clone, PartialEq, Debug)]
+pub enum Problem {
+    StartInWall,
+    /// Open cells the snake could never get to from the start.
+    Unreachable { count: usize },
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct SnakeMap {
+    cells: Vec<Cell>,
+    pub start: (i32, i32),
+    pub direction: (i32, i32),
+}
+
+impl SnakeMap {
+    /// An open board with the start where the normal game puts it.
+    pub fn new() -> Self {
+        SnakeMap { cells: vec![Cell::Open; (MAP_WIDTH * MAP_HEIGHT) as usize], start: (5, 5), direction: (1, 0) }
+    }
+
+    pub fn contains(&self, (x, y): (i32, i32)) -> bool {
+        (0..MAP_WIDTH).contains(&x) && (0..MAP_HEIGHT).contains(&y)
+    }
+
+    /// Anything off the board counts as a wall.
+    pub fn cell(&self, position: (i32, i32)) -> Cell {
+        if self.contains(position) {
+            self.cells[(position.1 * MAP_WIDTH + position.0) as usize]
+        } else {
+            Cell::Wall
+        }
+    }
+
+    pub fn set(&mut self, position: (i32, i32), cell: Cell) {
+        if self.contains(position) {
+            self.cells[(position.1 * MAP_WIDTH + position.0) as usize] = cell;
+        }
+    }
+
+    fn positions(&self, wanted: Cell) -> impl Iterator<Item = (i32, i32)> + '_ {
+        (0..MAP_HEIGHT)
+            .flat_map(|y| (0..MAP_WIDTH).map(move |x| (x, y)))
+            .filter(move |&position| self.cell(position) == wanted)
+    }
+
+    pub fn walls(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.positions(Cell::Wall)
+    }
+
+    pub fn food_zones(&self) -> impl Iterator<Item = (i32, i32)> + '_ {
+        self.positions(Cell::Food)
+    }
+
+    /// Everything that keeps the map from being played; empty when it is fine.
+    pub fn problems(&self) -> Vec<Problem> {
+        if self.cell(self.start) == Cell::Wall {
+            return vec![Problem::StartInWall];
+        }
+        let mut reached = vec![false; self.cells.len()];
+        let index = |(x, y): (i32, i32)| (y * MAP_WIDTH + x) as usize;
+        reached[index(self.start)] = true;
+        let mut queue = VecDeque::from([self.start]);
+        while let Some((x, y)) = queue.pop_front() {
+            for next in [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)] {
+                if self.cell(next) != Cell::Wall && !reached[index(next)] {
+                    reached[index(next)] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        let count = self.cells.iter().zip(&reached).filter(|&(&cell, &reached)| cell != Cell::Wall && !reached).count();
+        if count > 0 {
+            return vec![Problem::Unreachable { count }];
+        }
+        Vec::new()
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!("direction = {}\n", direction_name(self.direction));
+        for y in 0..MAP_HEIGHT {
+            for x in 0..MAP_WIDTH {
+                text.push(match self.cell((x, y)) {
+                    _ if (x, y) == self.start => 'S',
+                    Cell::Open => '.',
+                    Cell::Wall => '#',
+                    Cell::Food => '*',
+                });
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<SnakeMap, String> {
+        let mut map = SnakeMap::new();
+        let mut start = None;
+        let mut rows = 0;
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+            let fail = |message: String| format!("line {}: {}", number + 1, message);
+            if let Some((key, value)) = line.split_once('=') {
+                match key.trim() {
+                    "direction" => {
+                        map.direction = parse_direction(value.trim()).ok_or_else(|| fail(format!("'{}' is not a direction", value.trim())))?
+                    }
+                    key => return Err(fail(format!("unknown key '{}'", key))),
+                }
+                continue;
+            }
+            if rows == MAP_HEIGHT {
+                return Err(fail(format!("a map has {} rows", MAP_HEIGHT)));
+            }
+            if line.chars().count() != MAP_WIDTH as usize {
+                return Err(fail(format!("a row has {} cells, not {}", MAP_WIDTH, line.chars().count())));
+            }
+            for (x, symbol) in line.chars().enumerate() {
+                let position = (x as i32, rows);
+                let cell = match symbol {
+                    '.' => Cell::Open,
+                    '#' => Cell::Wall,
+                    '*' => Cell::Food,
+                    'S' if start.is_none() => {
+                        start = Some(position);
+                        Cell::Open
+                    }
+                    'S' => return Err(fail("a second start".to_string())),
+                    other => return Err(fail(format!("unknown cell '{}'", other))),
+                };
+                map.set(position, cell);
+            }
+            rows += 1;
+        }
+        if rows != MAP_HEIGHT {
+            return Err(format!("the map has {} rows, not {}", rows, MAP_HEIGHT));
+        }
+        map.start = start.ok_or("the map has no start (S)")?;
+        Ok(map)
+    }
+
+    pub fn load(path: &Path) -> Result<SnakeMap, String> {
+        let text = fs::read_to_string(path).map_err(|error| error.to_string())?;
+        SnakeMap::parse(&text)
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).map_err(|error| error.to_string())?;
+        }
+        storage::write_atomic(path, &self.to_text()).map_err(|error| error.to_string())
+    }
+}
+
+impl Default for SnakeMap {
+    fn default() -> Self {
+        SnakeMap::new()
+    }
+}
+
+/// The `.map` files in `dir`, sorted by name.
+pub fn list(dir: &Path) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = match fs::read_dir(dir) {
+        Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect(),
+        Err(_) => Vec::new(),
+    };
+    paths.retain(|path| path.extension().is_some_and(|ext| ext == MAP_EXTENSION));
+    paths.sort();
+    paths
+}
+
+/// The first `map-N.map` in `dir` that is not taken yet.
+pub fn free_path(dir: &Path) -> PathBuf {
+    (1..)
+        .map(|number| dir.join(format!("map-{}.{}", number, MAP_EXTENSION)))
+        .find(|path| !path.exists())
+        .expect("some number is free")
+}
+
+pub fn direction_name(direction: (i32, i32)) -> &'static str {
+    match direction {
+        (0, -1) => "up",
+        (0, 1) => "down",
+        (-1, 0) => "left",
+        _ => "right",
+    }
+}
+
+fn parse_direction(name: &str) -> Option<(i32, i32)> {
+    match name {
+        "up" => Some((0, -1)),
+        "down" => Some((0, 1)),
+        "left" => Some((-1, 0)),
+        "right" => Some((1, 0)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_survive_a_round_trip_through_text() {
+        let mut map = SnakeMap::new();
+        map.set((0, 0), Cell::Wall);
+        map.set((39, 29), Cell::Wall);
+        map.set((7, 3), Cell::Food);
+        map.start = (10, 12);
+        map.direction = (0, -1);
+
+        let text = map.to_text();
+        assert!(text.starts_with("direction = up\n#......."));
+        assert_eq!(SnakeMap::parse(&text), Ok(map));
+    }
+
+    #[test]
+    fn broken_maps_say_where() {
+        let good = SnakeMap::new().to_text();
+        assert_eq!(SnakeMap::parse(&good.replacen('.', "x", 1)), Err("line 2: unknown cell 'x'".to_string()));
+        assert_eq!(SnakeMap::parse(&good.replace('S', ".")), Err("the map has no start (S)".to_string()));
+        assert!(SnakeMap::parse(&good.replacen("..", ".", 1)).unwrap_err().starts_with("line 2: a row has 40 cells"));
+        assert_eq!(SnakeMap::parse("direction = sideways"), Err("line 1: 'sideways' is not a direction".to_string()));
+    }
+
+    #[test]
+    fn the_start_has_to_be_open_and_reach_every_open_cell() {
+        let mut map = SnakeMap::new();
+        assert!(map.problems().is_empty());
+
+        // A closed box in the corner cuts off the single cell inside it.
+        for position in [(1, 0), (0, 1), (1, 1)] {
+            map.set(position, Cell::Wall);
+        }
+        assert_eq!(map.problems(), vec![Problem::Unreachable { count: 1 }]);
+        map.set((0, 0), Cell::Wall);
+        assert!(map.problems().is_empty());
+
+        map.set(map.start, Cell::Wall);
+        assert_eq!(map.problems(), vec![Problem::StartInWall]);
+    }
+
+    #[test]
+    fn new_maps_take_the_first_free_name() {
+        let dir = tempfile::tempdir().unwrap();
+        SnakeMap::new().save(&free_path(dir.path())).unwrap();
+        assert_eq!(free_path(dir.path()), dir.path().join("map-2.map"));
+        fs::write(dir.path().join("notes.txt"), "not a map").unwrap();
+        assert_eq!(list(dir.path()), vec![dir.path().join("map-1.map")]);
+    }
+}
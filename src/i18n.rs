@@ -0,0 +1,192 @@
+use crate::daily;
+use crate::games::{self, course};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
+
+/// Language every other one falls back to for keys it does not have.
+pub const DEFAULT_LANGUAGE: &str = "en";
+
+/// Built-in string tables by language code, in the order the settings screen cycles through them.
+const LANGUAGES: [(&str, &str); 2] = [
+    ("en", include_str!("../resources/lang/en.lang")),
+    ("pl", include_str!("../resources/lang/pl.lang")),
+];
+
+/// Something worth telling the player, e.g. that a file was damaged. It keeps its string key and
+/// values, so it reads in whatever language the texts end up in.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Notice {
+    pub key: &'static str,
+    pub args: Vec<(&'static str, String)>,
+}
+
+impl Notice {
+    pub fn new(key: &'static str) -> Self {
+        Notice { key, args: Vec::new() }
+    }
+
+    /// Fills in `{name}`.
+    pub fn with(mut self, name: &'static str, value: impl Display) -> Self {
+        self.args.push((name, value.to_string()));
+        self
+    }
+}
+
+/// In the default language, for the log and the command line.
+impl Display for Notice {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&Strings::load(DEFAULT_LANGUAGE).notice(self))
+    }
+}
+
+/// Every text shown to the player, in the selected language.
+pub struct Strings {
+    language: String,
+    table: HashMap<String, String>,
+    fallback: HashMap<String, String>,
+}
+
+impl Strings {
+    /// Loads the given language; unknown codes get the default language.
+    pub fn load(language: &str) -> Self {
+        let code = match LANGUAGES.iter().find(|(code, _)| *code == language) {
+            Some((code, _)) => code,
+            None => {
+                log::warn!("No language '{}', using {}", language, DEFAULT_LANGUAGE);
+                DEFAULT_LANGUAGE
+            }
+        };
+        Strings {
+            language: code.to_string(),
+            table: parse(builtin(code)),
+            fallback: parse(builtin(DEFAULT_LANGUAGE)),
+        }
+    }
+
+    /// Codes of the built-in languages.
+    pub fn languages() -> Vec<&'static str> {
+        LANGUAGES.iter().map(|(code, _)| *code).collect()
+    }
+
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// The text for `key`, from the default language if this one lacks it, or the key itself.
+    pub fn get<'a>(&'a self, key: &'a str) -> &'a str {
+        match self.table.get(key).or_else(|| self.fallback.get(key)) {
+            Some(text) => text,
+            None => key,
+        }
+    }
+
+    /// `get` with every `{name}` replaced by its value.
+    pub fn format(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let mut text = self.get(key).to_string();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), &value.to_string());
+        }
+        text
+    }
+
+    pub fn notice(&self, notice: &Notice) -> String {
+        let args: Vec<(&str, &dyn Display)> = notice.args.iter().map(|(name, value)| (*name, value as &dyn Display)).collect();
+        self.format(notice.key, &args)
+    }
+
+    /// A game's title, or its registry title for games without a translation.
+    pub fn game(&self, id: &str) -> String {
+        self.named("game", id, games::find_game(id).map(|game| game.title))
+    }
+
+    pub fn mode(&self, game: &str, id: &str) -> String {
+        // Every day's challenge has a board of its own, but they all go by the same name.
+        let id = if daily::is_daily(id) { daily::MODE } else { id };
+        if let Some(course) = course::id(id) {
+            return self.format("mode.course_run", &[("course", &course::title(course))]);
+        }
+        let title = games::find_game(game).and_then(|game| game.modes.iter().find(|mode| mode.id == id)).map(|mode| mode.title);
+        self.named("mode", id, title)
+    }
+
+    /// "Snake (Wrap)" for games with several modes, just "Flappy Bird" otherwise.
+    pub fn run_title(&self, key: &str, game: &str, mode: &str) -> String {
+        match games::find_game(game) {
+            Some(info) if info.modes.len() == 1 => self.game(game),
+            _ => self.format(key, &[("game", &self.game(game)), ("mode", &self.mode(game, mode))]),
+        }
+    }
+
+    fn named(&self, prefix: &str, id: &str, title: Option<&str>) -> String {
+        let key = format!("{}.{}", prefix, id);
+        match self.table.get(&key).or_else(|| self.fallback.get(&key)) {
+            Some(text) => text.clone(),
+            None => title.unwrap_or(id).to_string(),
+        }
+    }
+}
+
+impl Default for Strings {
+    fn default() -> Self {
+        Strings::load(DEFAULT_LANGUAGE)
+    }
+}
+
+fn builtin(code: &str) -> &'static str {
+    LANGUAGES.iter().find(|(known, _)| *known == code).map_or("", |(_, text)| text)
+}
+
+/// `key = value` per line; blank lines and `#` comments are skipped.
+fn parse(text: &str) -> HashMap<String, String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .filter_map(|line| line.split_once('='))
+        .map(|(key, value)| (key.trim().to_string(), value.trim().to_string()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn placeholders(text: &str) -> Vec<&str> {
+        let mut names: Vec<&str> = text.split('{').skip(1).filter_map(|part| part.split_once('}')).map(|(name, _)| name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn every_language_has_every_key_with_the_same_placeholders() {
+        let english = parse(builtin(DEFAULT_LANGUAGE));
+        for code in Strings::languages() {
+            let table = parse(builtin(code));
+            for (key, text) in &english {
+                let translated = table.get(key).unwrap_or_else(|| panic!("{} has no '{}'", code, key));
+                assert_eq!(placeholders(translated), placeholders(text), "{} '{}'", code, key);
+            }
+        }
+    }
+
+    #[test]
+    fn missing_keys_fall_back_to_english_then_to_the_key() {
+        let mut polish = Strings::load("pl");
+        assert_eq!(polish.get("menu.title"), "MENU GIER");
+        polish.table.remove("menu.title");
+        assert_eq!(polish.get("menu.title"), "GAME MENU");
+        assert_eq!(polish.get("no.such.key"), "no.such.key");
+        assert_eq!(Strings::load("xx").language(), DEFAULT_LANGUAGE);
+    }
+
+    #[test]
+    fn placeholders_and_titles_are_filled_in() {
+        let polish = Strings::load("pl");
+        assert_eq!(polish.format("play.score", &[("score", &12)]), "Wynik: 12");
+        assert_eq!(polish.run_title("menu.run", "snake", "wrap"), "Wąż (Bez ścian)");
+        assert_eq!(polish.run_title("menu.run", "flappy_bird", "daily-2024-05-01"), "Flappy Bird (Wyzwanie dnia)");
+        assert_eq!(polish.run_title("leaderboard.board", "tetris", "classic"), "tetris - Klasyczny");
+        let notice = Notice::new("notice.score_lines").with("count", 3);
+        assert_eq!(polish.notice(&notice), "Pominięto uszkodzone linie wyników: 3");
+        assert_eq!(notice.to_string(), "Skipped 3 damaged score lines");
+    }
+}
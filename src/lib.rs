@@ -0,0 +1,22 @@
+//! The games and everything around them; `main.rs` opens the window, `src/bin` holds the tools.
+
+pub mod assets;
+pub mod cli;
+pub mod daily;
+pub mod date;
+pub mod effects;
+pub mod export;
+pub mod games;
+pub mod i18n;
+pub mod logger;
+pub mod net;
+pub mod render;
+pub mod replay;
+pub mod save;
+pub mod scenes;
+pub mod scores;
+pub mod settings;
+pub mod soak;
+pub mod stats;
+pub mod storage;
+pub mod theme;
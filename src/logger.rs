@@ -0,0 +1,48 @@
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Appends our own messages to the log file in the data dir and echoes warnings to stderr.
+struct FileLogger {
+    file: Mutex<Option<File>>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.target().starts_with(module_path!().split("::").next().unwrap_or_default())
+            || metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let line = format!("{} [{}] {}", crate::date::now_timestamp(), record.level(), record.args());
+        if record.level() <= Level::Warn {
+            eprintln!("{}", line);
+        }
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(file) = file.as_mut() {
+                let _ = writeln!(file, "{}", line);
+            }
+        }
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock() {
+            if let Some(file) = file.as_mut() {
+                let _ = file.flush();
+            }
+        }
+    }
+}
+
+pub fn init(path: &Path) {
+    let file = OpenOptions::new().create(true).append(true).open(path).ok();
+    let logger: &'static FileLogger = Box::leak(Box::new(FileLogger { file: Mutex::new(file) }));
+    if log::set_logger(logger).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
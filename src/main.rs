@@ -1,15 +1,127 @@
-use crate::games::game_state::GameState;
+use gierki_rustowe::cli::{self, Cli};
+use gierki_rustowe::export::{self, ExportFormat};
+use gierki_rustowe::games::{self, game_state::GameState};
+use gierki_rustowe::logger;
+use gierki_rustowe::replay::Replay;
+use gierki_rustowe::net::client::Client;
+use gierki_rustowe::net::pong::{Lobby, DEFAULT_PONG_PORT};
+use gierki_rustowe::net::protocol::DEFAULT_PORT;
+use gierki_rustowe::net::spectate::Spectators;
+use gierki_rustowe::scenes::online::OnlineScene;
+use gierki_rustowe::scenes::play::PlayScene;
+use gierki_rustowe::scenes::pong::PongScene;
+use gierki_rustowe::scenes::{Scene, SCREEN_HEIGHT, SCREEN_WIDTH};
+use gierki_rustowe::scores::{self, ScoreBoard};
+use gierki_rustowe::stats::History;
+use gierki_rustowe::storage::{self, Storage};
 use ggez::conf::{WindowMode, WindowSetup};
+use ggez::graphics::{self, Rect};
 use ggez::{event, ContextBuilder};
+use std::path::PathBuf;
+use std::time::Duration;
 
-mod games;
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 
 fn main() -> ggez::GameResult {
-    let (ctx, event_loop) = ContextBuilder::new("GierekPare", "Olek")
+    let cli = Cli::parse_and_validate();
+
+    if cli.list_scores {
+        let storage = Storage::headless(cli.data_dir);
+        let (scores, notices) = ScoreBoard::load(storage.data_path(storage::SCORES_FILE));
+        for notice in notices {
+            eprintln!("{}", notice);
+        }
+        cli::print_scores(&scores);
+        return Ok(());
+    }
+
+    if let Some(path) = &cli.export {
+        let format = ExportFormat::from_path(path).unwrap_or_else(|error| exit_with_error(&error));
+        let storage = Storage::headless(cli.data_dir);
+        let (history, notices) = History::load(storage.data_path(storage::HISTORY_FILE));
+        for notice in notices {
+            eprintln!("{}", notice);
+        }
+        if let Err(error) = export::export(history.runs(), format, path) {
+            exit_with_error(&format!("could not write {}: {}", path.display(), error));
+        }
+        println!("Wrote {} runs to {}", history.runs().len(), path.display());
+        return Ok(());
+    }
+
+    // Everything that can fail because of a bad argument is checked before a window opens.
+    let start: Option<Box<dyn Scene>> = match (&cli.connect, &cli.replay, cli.run_config()) {
+        _ if cli.host_pong.is_some() || cli.join_pong.is_some() => Some(Box::new(PongScene::new(pong_lobby(&cli)))),
+        (Some(address), _, _) => {
+            let name = cli.name.clone().unwrap_or_else(scores::player_name);
+            match Client::connect(&with_port(address, DEFAULT_PORT), &name, CONNECT_TIMEOUT) {
+                Ok(client) => Some(Box::new(OnlineScene::new(client))),
+                Err(error) => exit_with_error(&error),
+            }
+        }
+        (None, Some(path), _) => match Replay::load(path) {
+            Ok(replay) if games::find_game(&replay.config.game).is_some() => Some(Box::new(PlayScene::replay(replay))),
+            Ok(replay) => exit_with_error(&format!("{}: unknown game '{}'", path.display(), replay.config.game)),
+            Err(error) => exit_with_error(&error),
+        },
+        (None, None, Some(Ok(config))) => {
+            let scene = PlayScene::new(config);
+            Some(Box::new(if cli.seed.is_some() { scene.keep_seed() } else { scene }))
+        }
+        _ => None,
+    };
+
+    let spectators = cli.spectate.map(|port| match Spectators::start((if cli.spectate_lan { "0.0.0.0" } else { "127.0.0.1" }, port)) {
+        Ok(spectators) => {
+            if cli.spectate_lan {
+                println!("Spectators can watch at http://<this computer's address>:{}/", port);
+            } else {
+                println!("Spectators can watch at http://localhost:{}/", port);
+            }
+            spectators
+        }
+        Err(error) => exit_with_error(&format!("could not listen on port {}: {}", port, error)),
+    });
+
+    let (width, height) = cli.windowed;
+    let mut builder = ContextBuilder::new(storage::GAME_ID, storage::AUTHOR);
+    // ggez looks for `resources/` next to the executable; under `cargo run` it lives in the project dir.
+    if let Some(manifest_dir) = std::env::var_os("CARGO_MANIFEST_DIR") {
+        builder = builder.add_resource_path(PathBuf::from(manifest_dir).join("resources"));
+    }
+    let (mut ctx, event_loop) = builder
         .window_setup(WindowSetup::default().title("Gierki Rustowe Fajne i Kolorowe"))
-        .window_mode(WindowMode::default().dimensions(800.0, 600.0))
+        .window_mode(WindowMode::default().dimensions(width, height))
         .build()?;
+    // Scenes are laid out for 800x600; other window sizes scale that up or down.
+    graphics::set_screen_coordinates(&mut ctx, Rect::new(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT))?;
 
-    let state = GameState::new();
+    let storage = Storage::from_context(&ctx, cli.data_dir);
+    logger::init(&storage.data_path(storage::LOG_FILE));
+    let state = GameState::new(&mut ctx, storage, start, spectators);
     event::run(ctx, event_loop, state)
-}
\ No newline at end of file
+}
+
+/// The lobby for `--host-pong` or `--join-pong`; exits if the port cannot be opened.
+fn pong_lobby(cli: &Cli) -> Lobby {
+    let lobby = match (cli.host_pong, &cli.join_pong) {
+        (Some(port), _) => Lobby::host(("0.0.0.0", port)).map_err(|error| format!("could not listen on port {}: {}", port, error)),
+        (None, Some(address)) => Lobby::join(&with_port(address, DEFAULT_PONG_PORT)),
+        (None, None) => unreachable!("only called with one of the Pong options"),
+    };
+    lobby.unwrap_or_else(|error| exit_with_error(&error))
+}
+
+/// `host` alone means the game's usual port on it.
+fn with_port(address: &str, port: u16) -> String {
+    if address.contains(':') {
+        address.to_string()
+    } else {
+        format!("{}:{}", address, port)
+    }
+}
+
+fn exit_with_error(message: &str) -> ! {
+    eprintln!("error: {}", message);
+    std::process::exit(2)
+}
@@ -0,0 +1,278 @@
+//! The authoritative multiplayer game: one `SnakeGame` per player on a shared board, with one
+//! piece of food for everybody, crashes into other snakes and respawns.
+
+use crate::games::rng::GameRng;
+use crate::games::snake::SnakeGame;
+use crate::games::{GameEvent, GameInput, TICKS_PER_SECOND};
+use crate::net::protocol::{PlayerState, Snapshot};
+use rand::Rng;
+use std::collections::VecDeque;
+
+pub const MAX_PLAYERS: usize = 8;
+pub const GRID_SIZE: i32 = 40;
+/// Ticks a crashed snake waits before it comes back.
+pub const RESPAWN_TICKS: u32 = 3 * TICKS_PER_SECOND;
+/// Free cells a new snake gets ahead of its head.
+const SPAWN_ROOM: i32 = 5;
+const SPAWN_TRIES: usize = 200;
+
+pub struct Player {
+    pub id: u32,
+    pub name: String,
+    pub game: SnakeGame,
+    /// Ticks left before a crashed snake comes back.
+    pub respawn_in: u32,
+}
+
+impl Player {
+    pub fn is_alive(&self) -> bool {
+        !self.game.game_over
+    }
+}
+
+pub struct Arena {
+    players: Vec<Player>,
+    food: (i32, i32),
+    rng: GameRng,
+    wrap: bool,
+    max_players: usize,
+    next_id: u32,
+    tick: u64,
+}
+
+impl Arena {
+    pub fn new(seed: u64, wrap: bool, max_players: usize) -> Self {
+        let mut arena = Arena {
+            players: Vec::new(),
+            food: (0, 0),
+            rng: GameRng::new(seed),
+            wrap,
+            max_players: max_players.clamp(1, MAX_PLAYERS),
+            next_id: 1,
+            tick: 0,
+        };
+        arena.place_food();
+        arena
+    }
+
+    pub fn width(&self) -> i32 {
+        GRID_SIZE
+    }
+
+    /// Same shape as the single player board.
+    pub fn height(&self) -> i32 {
+        SnakeGame::new(GRID_SIZE).height()
+    }
+
+    pub fn players(&self) -> &[Player] {
+        &self.players
+    }
+
+    pub fn food(&self) -> (i32, i32) {
+        self.food
+    }
+
+    /// Adds a player and returns their id, or `None` when the arena is full. If there is no room
+    /// on the board right now they start out waiting for a respawn.
+    pub fn join(&mut self, name: &str) -> Option<u32> {
+        if self.players.len() >= self.max_players {
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id += 1;
+        let game = self.spawn().unwrap_or_else(|| self.waiting_game());
+        self.players.push(Player { id, name: name.to_string(), game, respawn_in: 0 });
+        Some(id)
+    }
+
+    pub fn leave(&mut self, id: u32) {
+        self.players.retain(|player| player.id != id);
+    }
+
+    pub fn input(&mut self, id: u32, input: GameInput) {
+        if let Some(player) = self.players.iter_mut().find(|player| player.id == id) {
+            player.game.change_direction(input);
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.tick += 1;
+        for index in 0..self.players.len() {
+            if self.players[index].is_alive() {
+                continue;
+            }
+            if self.players[index].respawn_in > 0 {
+                self.players[index].respawn_in -= 1;
+            } else if let Some(game) = self.spawn() {
+                self.players[index].game = game;
+            }
+        }
+
+        let was_alive: Vec<bool> = self.players.iter().map(Player::is_alive).collect();
+        let mut eaten = false;
+        for player in self.players.iter_mut().filter(|player| player.is_alive()) {
+            player.game.food = self.food;
+            player.game.update();
+            eaten |= player.game.events.iter().any(|event| matches!(event, GameEvent::FoodEaten { .. }));
+        }
+
+        // Crashes into other snakes are checked once everybody has moved; heads that meet both crash.
+        let crashed: Vec<usize> = (0..self.players.len())
+            .filter(|&index| {
+                let player = &self.players[index];
+                let head = player.game.snake.front().copied();
+                player.is_alive()
+                    && self.players.iter().any(|other| other.id != player.id && other.is_alive() && head.is_some_and(|head| other.game.snake.contains(&head)))
+            })
+            .collect();
+        for index in crashed {
+            self.players[index].game.game_over = true;
+        }
+        for (player, was_alive) in self.players.iter_mut().zip(was_alive) {
+            if was_alive && !player.is_alive() {
+                player.respawn_in = RESPAWN_TICKS;
+            }
+        }
+
+        if eaten {
+            self.place_food();
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let players = self
+            .players
+            .iter()
+            .map(|player| PlayerState {
+                id: player.id,
+                name: player.name.clone(),
+                score: player.game.score,
+                alive: player.is_alive(),
+                snake: if player.is_alive() { player.game.snake.iter().copied().collect() } else { Vec::new() },
+            })
+            .collect();
+        Snapshot { tick: self.tick, food: self.food, players }
+    }
+
+    fn occupied(&self, cell: (i32, i32)) -> bool {
+        self.players.iter().any(|player| player.is_alive() && player.game.snake.contains(&cell))
+    }
+
+    fn place_food(&mut self) {
+        let free: Vec<(i32, i32)> = (0..self.height())
+            .flat_map(|y| (0..self.width()).map(move |x| (x, y)))
+            .filter(|&cell| !self.occupied(cell))
+            .collect();
+        if !free.is_empty() {
+            self.food = free[self.rng.gen_range(0..free.len())];
+        }
+    }
+
+    fn new_game(&mut self) -> SnakeGame {
+        let mut game = SnakeGame::new(GRID_SIZE);
+        game.wrap = self.wrap;
+        game.rng = GameRng::new(self.rng.gen());
+        game
+    }
+
+    /// A game with nobody on the board, waiting for a free spot.
+    fn waiting_game(&mut self) -> SnakeGame {
+        let mut game = self.new_game();
+        game.snake.clear();
+        game.game_over = true;
+        game
+    }
+
+    /// A fresh one-segment snake heading right, with a clear row ahead of it; `None` if no such
+    /// spot turned up.
+    fn spawn(&mut self) -> Option<SnakeGame> {
+        for _ in 0..SPAWN_TRIES {
+            let cell = (self.rng.gen_range(1..self.width() - SPAWN_ROOM - 1), self.rng.gen_range(1..self.height() - 1));
+            if (0..=SPAWN_ROOM).any(|ahead| self.occupied((cell.0 + ahead, cell.1))) {
+                continue;
+            }
+            let mut game = self.new_game();
+            game.snake = VecDeque::from([cell]);
+            game.food = self.food;
+            return Some(game);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn place(arena: &mut Arena, id: u32, snake: &[(i32, i32)], direction: (i32, i32)) {
+        let player = arena.players.iter_mut().find(|player| player.id == id).unwrap();
+        player.game.snake = snake.iter().copied().collect();
+        player.game.direction = direction;
+        player.game.last_step = direction;
+        player.game.ticks_since_move = player.game.ticks_per_move - 1;
+    }
+
+    #[test]
+    fn up_to_the_limit_can_join_and_leave() {
+        let mut arena = Arena::new(1, false, 3);
+        let ids: Vec<u32> = (0..3).map(|_| arena.join("ala").unwrap()).collect();
+        assert_eq!(ids, vec![1, 2, 3]);
+        assert_eq!(arena.join("bob"), None);
+
+        arena.leave(2);
+        assert_eq!(arena.join("bob"), Some(4));
+        let snapshot = arena.snapshot();
+        assert_eq!(snapshot.players.iter().map(|player| player.id).collect::<Vec<_>>(), vec![1, 3, 4]);
+        assert!(snapshot.players.iter().all(|player| player.alive && player.snake.len() == 1));
+        assert_eq!(Arena::new(1, false, 20).max_players, MAX_PLAYERS);
+    }
+
+    #[test]
+    fn running_into_another_snake_crashes_and_respawns_later() {
+        let mut arena = Arena::new(2, false, 2);
+        let (ala, bob) = (arena.join("ala").unwrap(), arena.join("bob").unwrap());
+        place(&mut arena, ala, &[(10, 10)], (1, 0));
+        place(&mut arena, bob, &[(11, 11), (11, 10), (11, 9)], (0, 1));
+        arena.food = (0, 0);
+        arena.tick();
+
+        let snapshot = arena.snapshot();
+        assert!(!snapshot.player(ala).unwrap().alive);
+        assert!(snapshot.player(ala).unwrap().snake.is_empty());
+        assert!(snapshot.player(bob).unwrap().alive);
+
+        for _ in 0..RESPAWN_TICKS {
+            arena.tick();
+        }
+        assert!(!arena.snapshot().player(ala).unwrap().alive);
+        arena.tick();
+        let back = arena.snapshot();
+        assert!(back.player(ala).unwrap().alive);
+        assert_eq!(back.player(ala).unwrap().score, 0);
+    }
+
+    #[test]
+    fn heads_that_meet_both_crash() {
+        let mut arena = Arena::new(3, false, 2);
+        let (ala, bob) = (arena.join("ala").unwrap(), arena.join("bob").unwrap());
+        place(&mut arena, ala, &[(10, 10)], (1, 0));
+        place(&mut arena, bob, &[(12, 10)], (-1, 0));
+        arena.tick();
+        assert!(arena.players.iter().all(|player| !player.is_alive()));
+    }
+
+    #[test]
+    fn the_shared_food_moves_once_eaten() {
+        let mut arena = Arena::new(4, false, 2);
+        let (ala, bob) = (arena.join("ala").unwrap(), arena.join("bob").unwrap());
+        place(&mut arena, ala, &[(10, 10)], (1, 0));
+        place(&mut arena, bob, &[(20, 20)], (1, 0));
+        arena.food = (11, 10);
+        arena.tick();
+
+        let snapshot = arena.snapshot();
+        assert_eq!(snapshot.player(ala).unwrap().score, 10);
+        assert_ne!(snapshot.food, (11, 10));
+        assert!(snapshot.players.iter().all(|player| !player.snake.contains(&snapshot.food)));
+    }
+}
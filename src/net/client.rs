@@ -0,0 +1,106 @@
+//! The player's end of a connection to the snake server.
+
+use crate::net::protocol::{ClientMessage, ServerMessage, PROTOCOL_VERSION};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::{Duration, Instant};
+
+/// How often a quiet client pings, well inside the server's timeout.
+const KEEP_ALIVE: Duration = Duration::from_secs(1);
+
+pub struct Client {
+    stream: TcpStream,
+    incoming: Vec<u8>,
+    last_sent: Instant,
+    pub id: u32,
+    pub width: i32,
+    pub height: i32,
+    pub tick_rate: u32,
+}
+
+impl Client {
+    /// Connects and joins, waiting up to `timeout` for the server to take or turn down the player.
+    pub fn connect(address: &str, name: &str, timeout: Duration) -> Result<Client, String> {
+        let target = address
+            .to_socket_addrs()
+            .map_err(|error| format!("{}: {}", address, error))?
+            .next()
+            .ok_or_else(|| format!("{}: no such address", address))?;
+        let mut stream = TcpStream::connect_timeout(&target, timeout).map_err(|error| format!("{}: {}", address, error))?;
+        stream.set_nodelay(true).map_err(|error| error.to_string())?;
+        stream.set_read_timeout(Some(timeout)).map_err(|error| error.to_string())?;
+
+        let join = ClientMessage::Join { version: PROTOCOL_VERSION, name: name.to_string() };
+        writeln!(stream, "{}", join.to_line()).map_err(|error| format!("{}: {}", address, error))?;
+
+        // Snapshots only start after the welcome, so the first line is the answer to the join.
+        let mut reader = BufReader::new(stream);
+        let mut line = String::new();
+        match reader.read_line(&mut line) {
+            Ok(0) => return Err(format!("{}: the server closed the connection", address)),
+            Ok(_) => {}
+            Err(error) => return Err(format!("{}: {}", address, error)),
+        }
+        let incoming = reader.buffer().to_vec();
+        let stream = reader.into_inner();
+        match ServerMessage::parse(line.trim()) {
+            Ok(ServerMessage::Welcome { id, width, height, tick_rate }) => {
+                stream.set_read_timeout(None).map_err(|error| error.to_string())?;
+                stream.set_nonblocking(true).map_err(|error| error.to_string())?;
+                Ok(Client { stream, incoming, last_sent: Instant::now(), id, width, height, tick_rate })
+            }
+            Ok(ServerMessage::Refused(reason)) => Err(format!("{}: {}", address, reason)),
+            Ok(other) => Err(format!("{}: unexpected reply '{}'", address, other.to_line())),
+            Err(error) => Err(format!("{}: {}", address, error)),
+        }
+    }
+
+    pub fn send(&mut self, message: &ClientMessage) -> io::Result<()> {
+        let line = format!("{}\n", message.to_line());
+        // Messages are a few bytes; should the socket be full, wait for it rather than lose an input.
+        self.stream.set_nonblocking(false)?;
+        let result = self.stream.write_all(line.as_bytes());
+        self.stream.set_nonblocking(true)?;
+        self.last_sent = Instant::now();
+        result
+    }
+
+    /// Pings when nothing else has been sent for a while.
+    pub fn keep_alive(&mut self) -> io::Result<()> {
+        if self.last_sent.elapsed() >= KEEP_ALIVE {
+            self.send(&ClientMessage::Ping)?;
+        }
+        Ok(())
+    }
+
+    /// Everything the server has sent since the last call; an error once the server is gone.
+    pub fn poll(&mut self) -> io::Result<Vec<ServerMessage>> {
+        let mut buffer = [0; 8192];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => return Err(io::Error::new(io::ErrorKind::ConnectionAborted, "the server closed the connection")),
+                Ok(count) => self.incoming.extend_from_slice(&buffer[..count]),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error),
+            }
+        }
+
+        let mut messages = Vec::new();
+        while let Some(end) = self.incoming.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.incoming.drain(..=end).collect();
+            let line = String::from_utf8_lossy(&line);
+            match ServerMessage::parse(line.trim()) {
+                Ok(message) => messages.push(message),
+                Err(error) => log::debug!("Ignoring a message from the server: {}", error),
+            }
+        }
+        Ok(messages)
+    }
+}
+
+impl Drop for Client {
+    fn drop(&mut self) {
+        let _ = self.send(&ClientMessage::Leave);
+    }
+}
@@ -0,0 +1,138 @@
+//! Just enough HTTP/1.1 for the leaderboard: one request per connection, plain `http://` only.
+
+use std::io::{Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::time::Duration;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct Response {
+    pub status: u16,
+    pub body: String,
+}
+
+/// `http://host:port/prefix` split into the address to connect to and the path to put in front
+/// of every request.
+pub fn split_url(url: &str) -> Result<(String, String), String> {
+    let rest = url.trim().strip_prefix("http://").ok_or_else(|| format!("'{}' is not an http:// address", url))?;
+    let (host, path) = match rest.find('/') {
+        Some(slash) => (&rest[..slash], rest[slash..].trim_end_matches('/')),
+        None => (rest, ""),
+    };
+    if host.is_empty() {
+        return Err(format!("'{}' has no host", url));
+    }
+    let host = if host.contains(':') { host.to_string() } else { format!("{}:80", host) };
+    Ok((host, path.to_string()))
+}
+
+/// Sends one request and reads the whole answer; errors are the network's, any status counts as an answer.
+pub fn request(method: &str, url: &str, body: Option<&str>, timeout: Duration) -> Result<Response, String> {
+    let (host, path) = split_url(url)?;
+    let address = host
+        .to_socket_addrs()
+        .map_err(|error| format!("{}: {}", host, error))?
+        .next()
+        .ok_or_else(|| format!("{}: no such address", host))?;
+    let mut stream = TcpStream::connect_timeout(&address, timeout).map_err(|error| format!("{}: {}", host, error))?;
+    stream.set_read_timeout(Some(timeout)).map_err(|error| error.to_string())?;
+    stream.set_write_timeout(Some(timeout)).map_err(|error| error.to_string())?;
+
+    let body = body.unwrap_or_default();
+    let head = format!(
+        "{} {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\n\r\n",
+        method,
+        if path.is_empty() { "/" } else { &path },
+        host,
+        body.len()
+    );
+    stream
+        .write_all(head.as_bytes())
+        .and_then(|()| stream.write_all(body.as_bytes()))
+        .map_err(|error| format!("{}: {}", host, error))?;
+
+    let mut answer = Vec::new();
+    stream.read_to_end(&mut answer).map_err(|error| format!("{}: {}", host, error))?;
+    parse_response(&String::from_utf8_lossy(&answer))
+}
+
+fn parse_response(text: &str) -> Result<Response, String> {
+    let (head, body) = text.split_once("\r\n\r\n").ok_or("the server sent a cut-off answer")?;
+    let status = head
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| format!("not an HTTP answer: '{}'", head.lines().next().unwrap_or_default()))?;
+    let chunked = head.lines().any(|line| line.to_ascii_lowercase().replace(' ', "") == "transfer-encoding:chunked");
+    let body = if chunked { unchunk(body)? } else { body.to_string() };
+    Ok(Response { status, body })
+}
+
+fn unchunk(mut body: &str) -> Result<String, String> {
+    let mut text = String::new();
+    loop {
+        let (size, rest) = body.split_once("\r\n").ok_or("the server sent a cut-off answer")?;
+        let size = usize::from_str_radix(size.split(';').next().unwrap_or_default().trim(), 16).map_err(|_| format!("bad chunk size '{}'", size))?;
+        if size == 0 {
+            return Ok(text);
+        }
+        let chunk = rest.get(..size).ok_or("the server sent a cut-off answer")?;
+        text.push_str(chunk);
+        body = rest[size..].strip_prefix("\r\n").unwrap_or(&rest[size..]);
+    }
+}
+
+/// Percent-encodes everything but letters, digits and `-_.~`, for names in paths and queries.
+pub fn encode(text: &str) -> String {
+    text.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (byte as char).to_string(),
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+pub fn decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let hex = text.get(index + 1..index + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[index], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            (b'+', _) => {
+                decoded.push(b' ');
+                index += 1;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_names_and_answers_are_understood() {
+        assert_eq!(split_url("http://scores.local:8080/office/"), Ok(("scores.local:8080".to_string(), "/office".to_string())));
+        assert_eq!(split_url("http://10.0.0.2"), Ok(("10.0.0.2:80".to_string(), String::new())));
+        assert!(split_url("https://scores.local").is_err());
+
+        assert_eq!(encode("Żaneta K/1"), "%C5%BBaneta%20K%2F1");
+        assert_eq!(decode(&encode("Żaneta K/1")), "Żaneta K/1");
+        assert_eq!(decode("a+b%2"), "a b%2");
+
+        let plain = parse_response("HTTP/1.1 404 Not Found\r\nContent-Length: 4\r\n\r\nnope").unwrap();
+        assert_eq!(plain, Response { status: 404, body: "nope".to_string() });
+        let chunked = parse_response("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n").unwrap();
+        assert_eq!(chunked.body, "abcde");
+    }
+}
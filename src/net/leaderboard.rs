@@ -0,0 +1,332 @@
+//! The shared leaderboard: a small HTTP server that keeps every submitted run in SQLite, so an
+//! office can see one board. Everything is plain text, one run per line in the score file's format.
+//!
+//! `POST /api/runs` with lines `<id> | <score line>` stores them and answers `stored <n>`; runs
+//! already stored under the same id are skipped, so a client may safely send them again.
+//! `GET /api/top?game=snake&mode=classic&limit=10` is the best runs of a board.
+//! `GET /api/players/<name>?game=snake&mode=classic` is one player's runs; the filters are optional.
+
+use crate::date;
+use crate::games::course;
+use crate::net::http;
+use crate::scores::{self, ScoreEntry};
+use rusqlite::{params, Connection};
+use std::io::Read;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+pub const DEFAULT_LEADERBOARD_PORT: u16 = 7780;
+pub const DEFAULT_LIMIT: usize = 10;
+pub const MAX_LIMIT: usize = 100;
+/// Requests bigger than this are turned away; a queue of a few hundred runs fits easily.
+const MAX_BODY: usize = 256 * 1024;
+const MAX_NAME_LEN: usize = 32;
+
+/// A run on its way to the server, with an id that stays the same however often it is sent.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Submission {
+    pub id: String,
+    pub entry: ScoreEntry,
+}
+
+impl Submission {
+    pub fn new(entry: ScoreEntry) -> Self {
+        Submission { id: format!("{:016x}", rand::random::<u64>()), entry }
+    }
+
+    pub fn to_line(&self) -> String {
+        format!("{} | {}", self.id, self.entry.to_line())
+    }
+
+    pub fn parse(line: &str) -> Result<Submission, String> {
+        let (id, entry) = line.split_once(" | ").ok_or_else(|| format!("not a run: '{}'", line))?;
+        if id.is_empty() || id.len() > 32 || !id.chars().all(|character| character.is_ascii_alphanumeric() || character == '-') {
+            return Err(format!("bad run id '{}'", id));
+        }
+        let mut entry = ScoreEntry::parse(entry).ok_or_else(|| format!("not a run: '{}'", line))?;
+        let is_id = |text: &str, extra: char| {
+            !text.is_empty() && text.chars().all(|character| character.is_ascii_lowercase() || character.is_ascii_digit() || character == '_' || character == extra)
+        };
+        if !is_id(&entry.game, '_') || !is_id(&entry.mode, '-') {
+            return Err(format!("bad board '{} {}'", entry.game, entry.mode));
+        }
+        entry.name = entry.name.trim().chars().take(MAX_NAME_LEN).collect();
+        if entry.name.is_empty() {
+            return Err("a run needs a player name".to_string());
+        }
+        Ok(Submission { id: id.to_string(), entry })
+    }
+}
+
+pub struct Database {
+    connection: Connection,
+}
+
+impl Database {
+    pub fn open(path: &Path) -> Result<Database, String> {
+        let connection = Connection::open(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+        Database::with_connection(connection)
+    }
+
+    pub fn in_memory() -> Result<Database, String> {
+        Database::with_connection(Connection::open_in_memory().map_err(|error| error.to_string())?)
+    }
+
+    fn with_connection(connection: Connection) -> Result<Database, String> {
+        connection
+            .execute_batch(
+                "CREATE TABLE IF NOT EXISTS runs (
+                    id TEXT PRIMARY KEY,
+                    game TEXT NOT NULL,
+                    mode TEXT NOT NULL,
+                    player TEXT NOT NULL,
+                    score INTEGER NOT NULL,
+                    timestamp INTEGER NOT NULL,
+                    duration REAL NOT NULL,
+                    submitted INTEGER NOT NULL
+                );
+                CREATE INDEX IF NOT EXISTS runs_by_board ON runs (game, mode, score DESC, timestamp);
+                CREATE INDEX IF NOT EXISTS runs_by_player ON runs (player);",
+            )
+            .map_err(|error| error.to_string())?;
+        Ok(Database { connection })
+    }
+
+    /// Stores the runs in one go and returns how many were new.
+    pub fn submit(&mut self, runs: &[Submission]) -> Result<usize, String> {
+        let transaction = self.connection.transaction().map_err(|error| error.to_string())?;
+        let mut stored = 0;
+        {
+            let mut insert = transaction
+                .prepare("INSERT OR IGNORE INTO runs (id, game, mode, player, score, timestamp, duration, submitted) VALUES (?, ?, ?, ?, ?, ?, ?, ?)")
+                .map_err(|error| error.to_string())?;
+            for run in runs {
+                let entry = &run.entry;
+                stored += insert
+                    .execute(params![run.id, entry.game, entry.mode, entry.name, entry.score, entry.timestamp as i64, entry.duration as f64, date::now_timestamp() as i64])
+                    .map_err(|error| error.to_string())?;
+            }
+        }
+        transaction.commit().map_err(|error| error.to_string())?;
+        Ok(stored)
+    }
+
+    /// The best runs of a board, best first (the fastest on timed boards); ties go to whoever
+    /// got there first.
+    pub fn top(&self, game: &str, mode: &str, limit: usize) -> Result<Vec<ScoreEntry>, String> {
+        let sql = if scores::is_timed(mode) {
+            "SELECT game, mode, player, score, timestamp, duration FROM runs WHERE game = ?1 AND mode = ?2 ORDER BY score, timestamp LIMIT ?3"
+        } else {
+            "SELECT game, mode, player, score, timestamp, duration FROM runs WHERE game = ?1 AND mode = ?2 ORDER BY score DESC, timestamp LIMIT ?3"
+        };
+        self.query(sql, params![game, mode, limit as i64])
+    }
+
+    /// One player's runs, best first, optionally only from one game or board. Runs on timed
+    /// boards (the ones named with the course prefix) count as better the lower their score.
+    pub fn player(&self, name: &str, game: Option<&str>, mode: Option<&str>, limit: usize) -> Result<Vec<ScoreEntry>, String> {
+        self.query(
+            "SELECT game, mode, player, score, timestamp, duration FROM runs
+             WHERE player = ?1 AND (?2 IS NULL OR game = ?2) AND (?3 IS NULL OR mode = ?3)
+             ORDER BY CASE WHEN substr(mode, 1, length(?5)) = ?5 THEN score ELSE -score END, timestamp LIMIT ?4",
+            params![name, game, mode, limit as i64, course::mode("")],
+        )
+    }
+
+    fn query(&self, sql: &str, values: impl rusqlite::Params) -> Result<Vec<ScoreEntry>, String> {
+        let mut statement = self.connection.prepare(sql).map_err(|error| error.to_string())?;
+        let rows = statement
+            .query_map(values, |row| {
+                Ok(ScoreEntry {
+                    game: row.get(0)?,
+                    mode: row.get(1)?,
+                    name: row.get(2)?,
+                    score: row.get(3)?,
+                    timestamp: row.get::<_, i64>(4)? as u64,
+                    duration: row.get::<_, f64>(5)? as f32,
+                })
+            })
+            .map_err(|error| error.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|error| error.to_string())
+    }
+}
+
+/// Answers one request: the status code and the body.
+pub fn handle(database: &mut Database, method: &str, url: &str, body: &str) -> (u16, String) {
+    let (path, query) = url.split_once('?').unwrap_or((url, ""));
+    let parameters: Vec<(String, String)> = query
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| (http::decode(key), http::decode(value)))
+        .collect();
+    let parameter = |name: &str| parameters.iter().find(|(key, _)| key == name).map(|(_, value)| value.as_str());
+    let limit = match parameter("limit").map(str::parse::<usize>) {
+        None => DEFAULT_LIMIT,
+        Some(Ok(limit)) => limit.clamp(1, MAX_LIMIT),
+        Some(Err(_)) => return (400, "limit has to be a number\n".to_string()),
+    };
+    let lines = |result: Result<Vec<ScoreEntry>, String>| match result {
+        Ok(entries) => (200, entries.iter().map(|entry| entry.to_line() + "\n").collect()),
+        Err(error) => {
+            log::error!("Could not read the leaderboard: {}", error);
+            (500, "could not read the leaderboard\n".to_string())
+        }
+    };
+
+    match (method, path) {
+        ("POST", "/api/runs") => {
+            let runs: Result<Vec<Submission>, String> = body.lines().filter(|line| !line.trim().is_empty()).map(Submission::parse).collect();
+            match runs.map(|runs| database.submit(&runs)) {
+                Ok(Ok(stored)) => (200, format!("stored {}\n", stored)),
+                Ok(Err(error)) => {
+                    log::error!("Could not store runs: {}", error);
+                    (500, "could not store the runs\n".to_string())
+                }
+                Err(error) => (400, error + "\n"),
+            }
+        }
+        ("GET", "/api/top") => match (parameter("game"), parameter("mode")) {
+            (Some(game), Some(mode)) => lines(database.top(game, mode, limit)),
+            _ => (400, "top needs a game and a mode\n".to_string()),
+        },
+        ("GET", path) if path.starts_with("/api/players/") => {
+            let name = http::decode(&path["/api/players/".len()..]);
+            lines(database.player(&name, parameter("game"), parameter("mode"), limit))
+        }
+        (_, "/api/runs" | "/api/top") => (405, "method not allowed\n".to_string()),
+        _ => (404, "not found\n".to_string()),
+    }
+}
+
+pub struct LeaderboardServer {
+    server: tiny_http::Server,
+    database: Database,
+}
+
+impl LeaderboardServer {
+    pub fn bind(address: &str, database: Database) -> Result<LeaderboardServer, String> {
+        let server = tiny_http::Server::http(address).map_err(|error| format!("{}: {}", address, error))?;
+        Ok(LeaderboardServer { server, database })
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.server.server_addr().to_ip()
+    }
+
+    /// Answers requests one at a time until `stop` is set; each one is a single quick query.
+    pub fn run(&mut self, stop: &AtomicBool) {
+        let text = tiny_http::Header::from_bytes(&b"Content-Type"[..], &b"text/plain; charset=utf-8"[..]).expect("a valid header");
+        while !stop.load(Ordering::Relaxed) {
+            let mut request = match self.server.recv_timeout(Duration::from_millis(100)) {
+                Ok(Some(request)) => request,
+                Ok(None) => continue,
+                Err(error) => {
+                    log::warn!("Could not take a request: {}", error);
+                    continue;
+                }
+            };
+            let mut body = String::new();
+            let read = request.as_reader().take(MAX_BODY as u64 + 1).read_to_string(&mut body);
+            let (status, answer) = match read {
+                Ok(size) if size > MAX_BODY => (413, "too many runs at once\n".to_string()),
+                Ok(_) => handle(&mut self.database, request.method().as_str(), request.url(), &body),
+                Err(_) => (400, "the body has to be UTF-8 text\n".to_string()),
+            };
+            log::info!("{} {} {} -> {}", request.remote_addr().map_or_else(|| "?".to_string(), |address| address.to_string()), request.method(), request.url(), status);
+            let response = tiny_http::Response::from_string(answer).with_status_code(status).with_header(text.clone());
+            if let Err(error) = request.respond(response) {
+                log::debug!("Could not answer: {}", error);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn run(name: &str, game: &str, mode: &str, score: u32, timestamp: u64) -> Submission {
+        Submission::new(ScoreEntry { game: game.to_string(), mode: mode.to_string(), name: name.to_string(), score, timestamp, duration: 12.5 })
+    }
+
+    fn body(runs: &[&Submission]) -> String {
+        runs.iter().map(|run| run.to_line() + "\n").collect()
+    }
+
+    #[test]
+    fn runs_are_stored_once_and_read_back_by_board_and_player() {
+        let mut database = Database::in_memory().unwrap();
+        let ala = run("ala", "snake", "classic", 120, 10);
+        let bob = run("bob", "snake", "classic", 300, 20);
+        let tie = run("cyryl", "snake", "classic", 120, 30);
+        let flappy = run("ala", "flappy_bird", "classic", 7, 40);
+
+        assert_eq!(handle(&mut database, "POST", "/api/runs", &body(&[&ala, &bob, &tie, &flappy])), (200, "stored 4\n".to_string()));
+        // Sent again after a lost answer: nothing new.
+        assert_eq!(handle(&mut database, "POST", "/api/runs", &body(&[&ala])), (200, "stored 0\n".to_string()));
+
+        let (status, top) = handle(&mut database, "GET", "/api/top?game=snake&mode=classic&limit=2", "");
+        assert_eq!(status, 200);
+        assert_eq!(top, format!("{}\n{}\n", bob.entry.to_line(), ala.entry.to_line()));
+
+        let (_, mine) = handle(&mut database, "GET", "/api/players/ala", "");
+        assert_eq!(mine.lines().map(|line| ScoreEntry::parse(line).unwrap()).collect::<Vec<_>>(), vec![ala.entry.clone(), flappy.entry.clone()]);
+        let (_, flappy_only) = handle(&mut database, "GET", "/api/players/ala?game=flappy_bird", "");
+        assert_eq!(flappy_only, flappy.entry.to_line() + "\n");
+    }
+
+    #[test]
+    fn course_boards_list_the_fastest_runs_first() {
+        let mut database = Database::in_memory().unwrap();
+        let slow = run("ala", "flappy_bird", "course-loop", 900, 10);
+        let fast = run("ala", "flappy_bird", "course-loop", 741, 20);
+        let other = run("bob", "flappy_bird", "course-loop", 800, 30);
+        assert_eq!(handle(&mut database, "POST", "/api/runs", &body(&[&slow, &fast, &other])).0, 200);
+
+        let (_, top) = handle(&mut database, "GET", "/api/top?game=flappy_bird&mode=course-loop", "");
+        assert_eq!(top, format!("{}\n{}\n{}\n", fast.entry.to_line(), other.entry.to_line(), slow.entry.to_line()));
+        let (_, mine) = handle(&mut database, "GET", "/api/players/ala?game=flappy_bird&mode=course-loop", "");
+        assert_eq!(mine, format!("{}\n{}\n", fast.entry.to_line(), slow.entry.to_line()));
+    }
+
+    #[test]
+    fn bad_requests_are_refused_without_storing_anything() {
+        let mut database = Database::in_memory().unwrap();
+        let good = run("ala", "snake", "classic", 1, 1);
+        let bad = format!("{}nonsense\n", body(&[&good]));
+        assert_eq!(handle(&mut database, "POST", "/api/runs", &bad).0, 400);
+        assert_eq!(handle(&mut database, "POST", "/api/runs", "x!y | snake | classic | ala | 1 | 1 | 1.0").0, 400);
+        assert_eq!(handle(&mut database, "POST", "/api/runs", "abc | Snake | classic | ala | 1 | 1 | 1.0").0, 400);
+        assert_eq!(handle(&mut database, "GET", "/api/top?game=snake", "").0, 400);
+        assert_eq!(handle(&mut database, "GET", "/api/top?game=snake&mode=classic&limit=many", "").0, 400);
+        assert_eq!(handle(&mut database, "DELETE", "/api/runs", "").0, 405);
+        assert_eq!(handle(&mut database, "GET", "/", "").0, 404);
+        assert_eq!(handle(&mut database, "GET", "/api/top?game=snake&mode=classic", ""), (200, String::new()));
+    }
+
+    #[test]
+    fn the_server_answers_on_localhost() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut server = LeaderboardServer::bind("127.0.0.1:0", Database::open(&dir.path().join("board.sqlite")).unwrap()).unwrap();
+        let url = format!("http://{}", server.local_addr().unwrap());
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let handle = thread::spawn(move || server.run(&flag));
+
+        let timeout = Duration::from_secs(5);
+        let ala = run("Żaneta K", "snake", "wrap", 50, 5);
+        let stored = http::request("POST", &format!("{}/api/runs", url), Some(&body(&[&ala])), timeout).unwrap();
+        assert_eq!((stored.status, stored.body.as_str()), (200, "stored 1\n"));
+        let top = http::request("GET", &format!("{}/api/top?game=snake&mode=wrap", url), None, timeout).unwrap();
+        assert_eq!(top.body, ala.entry.to_line() + "\n");
+        let player = http::request("GET", &format!("{}/api/players/{}", url, http::encode("Żaneta K")), None, timeout).unwrap();
+        assert_eq!(player.body, ala.entry.to_line() + "\n");
+
+        stop.store(true, Ordering::Relaxed);
+        handle.join().unwrap();
+    }
+}
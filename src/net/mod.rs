@@ -0,0 +1,13 @@
+//! Everything over the network: Snake for several players on an authoritative server, Pong for
+//! two peers with rollback, the shared leaderboard with its sync client, and a stream for spectators.
+
+pub mod arena;
+pub mod client;
+pub mod http;
+pub mod leaderboard;
+pub mod pong;
+pub mod protocol;
+pub mod rollback;
+pub mod server;
+pub mod spectate;
+pub mod sync;
@@ -0,0 +1,462 @@
+//! Online Pong between two machines over UDP. One side hosts on a port and the other joins it;
+//! after that both are equal peers that send their inputs every frame and roll back when the
+//! other's arrive, see `net::rollback`.
+//!
+//! Packets are one line of text: `hello 1`, `welcome 1 <seed>`, `refused <reason>`,
+//! `inputs <frame> <ack> <first> <udss…> [<frame> <checksum>]`, `ping <t>`, `pong <t>`, `bye`.
+
+use crate::games::pong::{PaddleInput, PongGame};
+use crate::net::rollback::{Rollback, RollbackStats};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+/// Bumped when the packets or the game rules change; both sides have to play the same game.
+pub const PONG_VERSION: u32 = 1;
+pub const DEFAULT_PONG_PORT: u16 = 7778;
+/// The other side is given up on after this long without a packet.
+pub const TIMEOUT: Duration = Duration::from_secs(5);
+const HELLO_INTERVAL: Duration = Duration::from_millis(250);
+const PING_INTERVAL: Duration = Duration::from_millis(500);
+/// A packet carries at most this many inputs; the rest follow in the next ones.
+const MAX_INPUTS: usize = 120;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum Packet {
+    Hello { version: u32 },
+    Welcome { version: u32, seed: u64 },
+    Refused(String),
+    Inputs {
+        /// The sender's frame, to keep both sides level.
+        frame: u64,
+        /// How many of the receiver's inputs the sender has, so older ones are not sent again.
+        ack: u64,
+        first: u64,
+        inputs: Vec<PaddleInput>,
+        checksum: Option<(u64, u64)>,
+    },
+    Ping(u64),
+    Pong(u64),
+    Bye,
+}
+
+impl Packet {
+    pub fn to_line(&self) -> String {
+        match self {
+            Packet::Hello { version } => format!("hello {}", version),
+            Packet::Welcome { version, seed } => format!("welcome {} {}", version, seed),
+            Packet::Refused(reason) => format!("refused {}", reason),
+            Packet::Inputs { frame, ack, first, inputs, checksum } => {
+                let codes: String = inputs.iter().map(|input| input.code()).collect();
+                let codes = if codes.is_empty() { "-".to_string() } else { codes };
+                let mut line = format!("inputs {} {} {} {}", frame, ack, first, codes);
+                if let Some((frame, checksum)) = checksum {
+                    line.push_str(&format!(" {} {}", frame, checksum));
+                }
+                line
+            }
+            Packet::Ping(time) => format!("ping {}", time),
+            Packet::Pong(time) => format!("pong {}", time),
+            Packet::Bye => "bye".to_string(),
+        }
+    }
+
+    pub fn parse(line: &str) -> Result<Packet, String> {
+        let mut words = line.split_whitespace();
+        let kind = words.next();
+        let mut number = |what: &str| -> Result<u64, String> {
+            let word = words.next().ok_or_else(|| format!("missing {}", what))?;
+            word.parse().map_err(|_| format!("bad {} '{}'", what, word))
+        };
+        match kind {
+            Some("hello") => Ok(Packet::Hello { version: number("version")? as u32 }),
+            Some("welcome") => Ok(Packet::Welcome { version: number("version")? as u32, seed: number("seed")? }),
+            Some("refused") => Ok(Packet::Refused(line.trim_start()["refused".len()..].trim().to_string())),
+            Some("inputs") => parse_inputs(line),
+            Some("ping") => Ok(Packet::Ping(number("time")?)),
+            Some("pong") => Ok(Packet::Pong(number("time")?)),
+            Some("bye") => Ok(Packet::Bye),
+            _ => Err(format!("unknown packet '{}'", line)),
+        }
+    }
+}
+
+fn parse_inputs(line: &str) -> Result<Packet, String> {
+    let words: Vec<&str> = line.split_whitespace().skip(1).collect();
+    let number = |index: usize, what: &str| -> Result<u64, String> {
+        let word = words.get(index).ok_or_else(|| format!("missing {}", what))?;
+        word.parse().map_err(|_| format!("bad {} '{}'", what, word))
+    };
+    let codes = words.get(3).ok_or("missing inputs")?;
+    let inputs = match *codes {
+        "-" => Vec::new(),
+        codes => codes
+            .chars()
+            .map(|code| PaddleInput::from_code(code).ok_or_else(|| format!("bad input '{}'", code)))
+            .collect::<Result<_, _>>()?,
+    };
+    let checksum = match words.len() {
+        4 => None,
+        6 => Some((number(4, "checksum frame")?, number(5, "checksum")?)),
+        _ => return Err(format!("bad inputs packet '{}'", line)),
+    };
+    Ok(Packet::Inputs { frame: number(0, "frame")?, ack: number(1, "ack")?, first: number(2, "first frame")?, inputs, checksum })
+}
+
+fn send(socket: &UdpSocket, to: SocketAddr, packet: &Packet) {
+    // A lost packet is no worse than a dropped one, and everything that matters is sent again.
+    if let Err(error) = socket.send_to(packet.to_line().as_bytes(), to) {
+        log::debug!("Could not send to {}: {}", to, error);
+    }
+}
+
+/// Every packet waiting on the socket, with its sender; junk is skipped.
+fn receive(socket: &UdpSocket) -> Vec<(SocketAddr, Packet)> {
+    let mut packets = Vec::new();
+    let mut buffer = [0; 2048];
+    loop {
+        match socket.recv_from(&mut buffer) {
+            Ok((count, from)) => match Packet::parse(&String::from_utf8_lossy(&buffer[..count])) {
+                Ok(packet) => packets.push((from, packet)),
+                Err(error) => log::debug!("Ignoring a packet from {}: {}", from, error),
+            },
+            Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+            // Windows reports an earlier send to a closed port here; there may be more to read.
+            Err(error) if error.kind() == io::ErrorKind::ConnectionReset => continue,
+            Err(error) => {
+                log::warn!("Could not read from the network: {}", error);
+                break;
+            }
+        }
+    }
+    packets
+}
+
+enum Role {
+    Host { seed: u64 },
+    Join { host: SocketAddr, last_hello: Option<Instant> },
+}
+
+/// Waiting for the other player: the host for someone to join, the joining side for the host
+/// to answer.
+pub struct Lobby {
+    socket: UdpSocket,
+    role: Role,
+}
+
+impl Lobby {
+    pub fn host(address: impl ToSocketAddrs) -> io::Result<Lobby> {
+        let socket = UdpSocket::bind(address)?;
+        socket.set_nonblocking(true)?;
+        Ok(Lobby { socket, role: Role::Host { seed: rand::random() } })
+    }
+
+    pub fn join(address: &str) -> Result<Lobby, String> {
+        let host = address
+            .to_socket_addrs()
+            .map_err(|error| format!("{}: {}", address, error))?
+            .next()
+            .ok_or_else(|| format!("{}: no such address", address))?;
+        let any: SocketAddr = if host.is_ipv4() { ([0, 0, 0, 0], 0).into() } else { ([0u16; 8], 0).into() };
+        let socket = UdpSocket::bind(any).map_err(|error| error.to_string())?;
+        socket.set_nonblocking(true).map_err(|error| error.to_string())?;
+        Ok(Lobby { socket, role: Role::Join { host, last_hello: None } })
+    }
+
+    pub fn is_host(&self) -> bool {
+        matches!(self.role, Role::Host { .. })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.socket.local_addr()
+    }
+
+    /// The host's address when joining.
+    pub fn host_addr(&self) -> Option<SocketAddr> {
+        match self.role {
+            Role::Host { .. } => None,
+            Role::Join { host, .. } => Some(host),
+        }
+    }
+
+    /// Answers and sends handshakes; the match once the other player is there.
+    pub fn poll(&mut self) -> Result<Option<PongMatch>, String> {
+        if let Role::Join { host, last_hello } = &mut self.role {
+            if last_hello.filter(|sent| sent.elapsed() < HELLO_INTERVAL).is_none() {
+                send(&self.socket, *host, &Packet::Hello { version: PONG_VERSION });
+                *last_hello = Some(Instant::now());
+            }
+        }
+        for (from, packet) in receive(&self.socket) {
+            match (&self.role, packet) {
+                (Role::Host { seed }, Packet::Hello { version }) if version == PONG_VERSION => {
+                    log::info!("{} joined the Pong game", from);
+                    send(&self.socket, from, &Packet::Welcome { version: PONG_VERSION, seed: *seed });
+                    return self.start(from, 0, *seed).map(Some);
+                }
+                (Role::Host { .. }, Packet::Hello { version }) => {
+                    let reason = format!("Pong v{} is not supported, the host plays v{}", version, PONG_VERSION);
+                    send(&self.socket, from, &Packet::Refused(reason));
+                }
+                (Role::Join { host, .. }, Packet::Welcome { seed, .. }) if from == *host => return self.start(from, 1, seed).map(Some),
+                (Role::Join { host, .. }, Packet::Refused(reason)) if from == *host => return Err(reason),
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+
+    fn start(&self, remote: SocketAddr, side: usize, seed: u64) -> Result<PongMatch, String> {
+        let socket = self.socket.try_clone().map_err(|error| error.to_string())?;
+        Ok(PongMatch::new(socket, remote, side, seed))
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MatchState {
+    Playing,
+    OpponentLeft,
+    TimedOut,
+}
+
+pub struct PongMatch {
+    socket: UdpSocket,
+    remote: SocketAddr,
+    seed: u64,
+    rollback: Rollback,
+    /// How many of our inputs the other side has.
+    acked: u64,
+    /// The other side's frame as of its latest packet.
+    remote_frame: u64,
+    started: Instant,
+    last_ping: Option<Instant>,
+    last_heard: Instant,
+    ping: Option<Duration>,
+    /// Set on every other tick while this side is ahead, which skips it.
+    slowing: bool,
+    state: MatchState,
+}
+
+impl PongMatch {
+    fn new(socket: UdpSocket, remote: SocketAddr, side: usize, seed: u64) -> Self {
+        PongMatch {
+            socket,
+            remote,
+            seed,
+            rollback: Rollback::new(seed, side),
+            acked: 0,
+            remote_frame: 0,
+            started: Instant::now(),
+            last_ping: None,
+            last_heard: Instant::now(),
+            ping: None,
+            slowing: false,
+            state: MatchState::Playing,
+        }
+    }
+
+    pub fn game(&self) -> &PongGame {
+        self.rollback.game()
+    }
+
+    /// Which paddle is ours: 0 is left (the host), 1 is right.
+    pub fn side(&self) -> usize {
+        self.rollback.side()
+    }
+
+    pub fn stats(&self) -> &RollbackStats {
+        &self.rollback.stats
+    }
+
+    /// The round trip time measured last.
+    pub fn ping(&self) -> Option<Duration> {
+        self.ping
+    }
+
+    pub fn state(&self) -> MatchState {
+        self.state
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.rollback.is_finished()
+    }
+
+    /// One frame: reads what the other side sent, plays our `input` and sends it off.
+    pub fn tick(&mut self, input: PaddleInput) {
+        self.receive();
+        if self.state != MatchState::Playing {
+            return;
+        }
+        if self.last_heard.elapsed() > TIMEOUT {
+            log::info!("{} stopped answering", self.remote);
+            self.state = MatchState::TimedOut;
+            return;
+        }
+
+        // A side that runs ahead makes the other one roll back more, so it slows down to let it catch up.
+        let one_way = self.ping.map_or(0, |ping| (ping.as_secs_f64() * 30.0) as u64);
+        let ahead = self.rollback.frame() >= self.remote_frame + one_way + 2;
+        self.slowing = ahead && !self.slowing;
+        if self.slowing {
+            self.rollback.stats.stalls += 1;
+        } else {
+            self.rollback.advance(input);
+        }
+
+        let inputs: Vec<_> = self.rollback.local_inputs(self.acked).iter().take(MAX_INPUTS).copied().collect();
+        let packet = Packet::Inputs {
+            frame: self.rollback.frame(),
+            ack: self.rollback.remote_count(),
+            first: self.acked,
+            inputs,
+            checksum: self.rollback.checksum_due(),
+        };
+        send(&self.socket, self.remote, &packet);
+        if self.last_ping.filter(|sent| sent.elapsed() < PING_INTERVAL).is_none() {
+            send(&self.socket, self.remote, &Packet::Ping(self.started.elapsed().as_micros() as u64));
+            self.last_ping = Some(Instant::now());
+        }
+    }
+
+    fn receive(&mut self) {
+        for (from, packet) in receive(&self.socket) {
+            if from != self.remote {
+                if let Packet::Hello { .. } = packet {
+                    send(&self.socket, from, &Packet::Refused("a game is already on".to_string()));
+                }
+                continue;
+            }
+            self.last_heard = Instant::now();
+            match packet {
+                // The welcome got lost, so the joining side is still asking.
+                Packet::Hello { .. } => send(&self.socket, from, &Packet::Welcome { version: PONG_VERSION, seed: self.seed }),
+                Packet::Inputs { frame, ack, first, inputs, checksum } => {
+                    self.remote_frame = self.remote_frame.max(frame);
+                    self.acked = self.acked.max(ack);
+                    self.rollback.add_remote(first, &inputs);
+                    if let Some((frame, checksum)) = checksum {
+                        self.rollback.add_remote_checksum(frame, checksum);
+                    }
+                }
+                Packet::Ping(time) => send(&self.socket, from, &Packet::Pong(time)),
+                Packet::Pong(time) => {
+                    let sent = Duration::from_micros(time);
+                    self.ping = self.started.elapsed().checked_sub(sent);
+                }
+                Packet::Bye => {
+                    log::info!("{} left the Pong game", from);
+                    self.state = MatchState::OpponentLeft;
+                }
+                Packet::Welcome { .. } | Packet::Refused(_) => {}
+            }
+        }
+    }
+}
+
+impl Drop for PongMatch {
+    fn drop(&mut self) {
+        if self.state == MatchState::Playing {
+            send(&self.socket, self.remote, &Packet::Bye);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    fn connect() -> (PongMatch, PongMatch) {
+        let mut host = Lobby::host("127.0.0.1:0").unwrap();
+        let mut guest = Lobby::join(&host.local_addr().unwrap().to_string()).unwrap();
+        let deadline = Instant::now() + TIMEOUT;
+        let (mut left, mut right) = (None, None);
+        while left.is_none() || right.is_none() {
+            assert!(Instant::now() < deadline, "the lobby never connected");
+            if right.is_none() {
+                right = guest.poll().unwrap();
+            }
+            if left.is_none() {
+                left = host.poll().unwrap();
+            }
+            thread::sleep(Duration::from_millis(2));
+        }
+        (left.unwrap(), right.unwrap())
+    }
+
+    #[test]
+    fn packets_round_trip() {
+        let packets = [
+            Packet::Hello { version: PONG_VERSION },
+            Packet::Welcome { version: PONG_VERSION, seed: u64::MAX },
+            Packet::Refused("a game is already on".to_string()),
+            Packet::Inputs { frame: 90, ack: 80, first: 85, inputs: vec![PaddleInput::Up, PaddleInput::Still, PaddleInput::Down], checksum: Some((64, 12345)) },
+            Packet::Inputs { frame: 0, ack: 2, first: 2, inputs: Vec::new(), checksum: None },
+            Packet::Ping(1_000_000),
+            Packet::Pong(7),
+            Packet::Bye,
+        ];
+        for packet in packets {
+            assert_eq!(Packet::parse(&packet.to_line()), Ok(packet));
+        }
+        assert!(Packet::parse("inputs 1 2 3 uxd").is_err());
+        assert!(Packet::parse("inputs 1 2 3 ud 4").is_err());
+        assert!(Packet::parse("hello").is_err());
+    }
+
+    #[test]
+    fn two_peers_on_localhost_play_the_same_game() {
+        let (mut left, mut right) = connect();
+        assert_eq!((left.side(), right.side()), (0, 1));
+        for tick in 0..600u32 {
+            let input = |side: u32| [PaddleInput::Up, PaddleInput::Down, PaddleInput::Still][((tick / 20 + side) % 3) as usize];
+            left.tick(input(0));
+            right.tick(input(1));
+            thread::sleep(Duration::from_micros(500));
+        }
+        assert_eq!((left.state(), right.state()), (MatchState::Playing, MatchState::Playing));
+        assert!(left.stats().checks > 0 && right.stats().checks > 0, "{:?} {:?}", left.stats(), right.stats());
+        assert_eq!((left.stats().desyncs, right.stats().desyncs), (0, 0));
+        assert!(left.ping().is_some());
+
+        drop(right);
+        let deadline = Instant::now() + TIMEOUT;
+        while left.state() == MatchState::Playing && Instant::now() < deadline {
+            left.tick(PaddleInput::Still);
+        }
+        assert_eq!(left.state(), MatchState::OpponentLeft);
+    }
+
+    #[test]
+    fn other_versions_are_refused() {
+        // A host of some other version, played by a bare socket.
+        let other = UdpSocket::bind("127.0.0.1:0").unwrap();
+        other.set_read_timeout(Some(TIMEOUT)).unwrap();
+        let mut guest = Lobby::join(&other.local_addr().unwrap().to_string()).unwrap();
+        assert!(guest.poll().unwrap().is_none());
+        let mut buffer = [0; 256];
+        let (count, from) = other.recv_from(&mut buffer).unwrap();
+        assert_eq!(String::from_utf8_lossy(&buffer[..count]), format!("hello {}", PONG_VERSION));
+        other.send_to(b"refused Pong v1 is not supported", from).unwrap();
+        let deadline = Instant::now() + TIMEOUT;
+        let reason = loop {
+            match guest.poll() {
+                Err(reason) => break reason,
+                Ok(started) => assert!(started.is_none() && Instant::now() < deadline),
+            }
+            thread::sleep(Duration::from_millis(2));
+        };
+        assert_eq!(reason, "Pong v1 is not supported");
+
+        let mut host = Lobby::host("127.0.0.1:0").unwrap();
+        other.send_to(b"hello 99", host.local_addr().unwrap()).unwrap();
+        other.set_read_timeout(Some(Duration::from_millis(10))).unwrap();
+        let reply = loop {
+            assert!(host.poll().unwrap().is_none() && Instant::now() < deadline);
+            if let Ok((count, _)) = other.recv_from(&mut buffer) {
+                break String::from_utf8_lossy(&buffer[..count]).to_string();
+            }
+        };
+        assert!(reply.starts_with("refused Pong v99"), "{}", reply);
+    }
+}
@@ -0,0 +1,221 @@
+//! What the snake server and its clients say to each other: one line of text per message, in
+//! both directions, so a session can be followed with `nc` as well.
+//!
+//! Client: `join 1 ala`, `input up`, `ping`, `leave`.
+//! Server: `welcome 3 40 30 60`, `refused server is full`,
+//! `state 120 10,12 | 3 ala 30 alive 5,5 4,5 | 4 bob 0 dead`.
+
+use crate::games::GameInput;
+use crate::save;
+
+/// Bumped when the messages change; the server turns away clients that speak another version.
+pub const PROTOCOL_VERSION: u32 = 1;
+pub const DEFAULT_PORT: u16 = 7777;
+pub const MAX_NAME_LEN: usize = 16;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ClientMessage {
+    /// The first message on a connection.
+    Join { version: u32, name: String },
+    Input(GameInput),
+    /// Keeps a quiet connection from timing out.
+    Ping,
+    Leave,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum ServerMessage {
+    Welcome { id: u32, width: i32, height: i32, tick_rate: u32 },
+    /// The join was turned down; the server closes the connection after this.
+    Refused(String),
+    Snapshot(Snapshot),
+}
+
+/// The whole board as of one server tick.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Snapshot {
+    pub tick: u64,
+    pub food: (i32, i32),
+    pub players: Vec<PlayerState>,
+}
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct PlayerState {
+    pub id: u32,
+    pub name: String,
+    pub score: u32,
+    /// Crashed players wait for their respawn with an empty snake.
+    pub alive: bool,
+    /// Head first.
+    pub snake: Vec<(i32, i32)>,
+}
+
+impl Snapshot {
+    pub fn player(&self, id: u32) -> Option<&PlayerState> {
+        self.players.iter().find(|player| player.id == id)
+    }
+}
+
+/// Names go on the wire between spaces, so those are replaced, and long ones are cut short.
+pub fn clean_name(name: &str) -> String {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|character| if character.is_whitespace() || character == '|' { '_' } else { character })
+        .take(MAX_NAME_LEN)
+        .collect();
+    if name.is_empty() {
+        "Player".to_string()
+    } else {
+        name
+    }
+}
+
+fn point((x, y): (i32, i32)) -> String {
+    format!("{},{}", x, y)
+}
+
+fn parse_point(text: &str) -> Result<(i32, i32), String> {
+    let values = save::parse_group(text, 2)?;
+    Ok((values[0], values[1]))
+}
+
+fn number<T: std::str::FromStr>(text: Option<&str>, what: &str) -> Result<T, String> {
+    let text = text.ok_or_else(|| format!("missing {}", what))?;
+    text.parse().map_err(|_| format!("bad {} '{}'", what, text))
+}
+
+impl ClientMessage {
+    pub fn to_line(&self) -> String {
+        match self {
+            ClientMessage::Join { version, name } => format!("join {} {}", version, clean_name(name)),
+            ClientMessage::Input(input) => format!("input {}", input.name()),
+            ClientMessage::Ping => "ping".to_string(),
+            ClientMessage::Leave => "leave".to_string(),
+        }
+    }
+
+    pub fn parse(line: &str) -> Result<ClientMessage, String> {
+        let mut words = line.split_whitespace();
+        match words.next() {
+            Some("join") => {
+                let version = number(words.next(), "version")?;
+                let name = words.next().map_or_else(String::new, clean_name);
+                Ok(ClientMessage::Join { version, name })
+            }
+            Some("input") => Ok(ClientMessage::Input(words.next().ok_or("missing input")?.parse()?)),
+            Some("ping") => Ok(ClientMessage::Ping),
+            Some("leave") => Ok(ClientMessage::Leave),
+            _ => Err(format!("unknown message '{}'", line)),
+        }
+    }
+}
+
+impl ServerMessage {
+    pub fn to_line(&self) -> String {
+        match self {
+            ServerMessage::Welcome { id, width, height, tick_rate } => format!("welcome {} {} {} {}", id, width, height, tick_rate),
+            ServerMessage::Refused(reason) => format!("refused {}", reason.replace('\n', " ")),
+            ServerMessage::Snapshot(snapshot) => {
+                let mut line = format!("state {} {}", snapshot.tick, point(snapshot.food));
+                for player in &snapshot.players {
+                    let state = if player.alive { "alive" } else { "dead" };
+                    line.push_str(&format!(" | {} {} {} {}", player.id, clean_name(&player.name), player.score, state));
+                    for &segment in &player.snake {
+                        line.push(' ');
+                        line.push_str(&point(segment));
+                    }
+                }
+                line
+            }
+        }
+    }
+
+    pub fn parse(line: &str) -> Result<ServerMessage, String> {
+        let (kind, rest) = line.split_once(' ').unwrap_or((line, ""));
+        match kind {
+            "welcome" => {
+                let mut words = rest.split_whitespace();
+                Ok(ServerMessage::Welcome {
+                    id: number(words.next(), "id")?,
+                    width: number(words.next(), "width")?,
+                    height: number(words.next(), "height")?,
+                    tick_rate: number(words.next(), "tick rate")?,
+                })
+            }
+            "refused" => Ok(ServerMessage::Refused(rest.to_string())),
+            "state" => {
+                let mut parts = rest.split(" | ");
+                let mut header = parts.next().unwrap_or_default().split_whitespace();
+                let tick = number(header.next(), "tick")?;
+                let food = parse_point(header.next().ok_or("missing food")?)?;
+                let players = parts.map(parse_player).collect::<Result<_, _>>()?;
+                Ok(ServerMessage::Snapshot(Snapshot { tick, food, players }))
+            }
+            _ => Err(format!("unknown message '{}'", line)),
+        }
+    }
+}
+
+fn parse_player(text: &str) -> Result<PlayerState, String> {
+    let mut words = text.split_whitespace();
+    let id = number(words.next(), "player id")?;
+    let name = words.next().ok_or("missing name")?.to_string();
+    let score = number(words.next(), "score")?;
+    let alive = match words.next() {
+        Some("alive") => true,
+        Some("dead") => false,
+        other => return Err(format!("bad player state '{}'", other.unwrap_or_default())),
+    };
+    let snake = words.map(parse_point).collect::<Result<_, _>>()?;
+    Ok(PlayerState { id, name, score, alive, snake })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip() {
+        let client = [
+            ClientMessage::Join { version: PROTOCOL_VERSION, name: "ala".to_string() },
+            ClientMessage::Input(GameInput::Left),
+            ClientMessage::Ping,
+            ClientMessage::Leave,
+        ];
+        for message in client {
+            assert_eq!(ClientMessage::parse(&message.to_line()), Ok(message));
+        }
+
+        let snapshot = Snapshot {
+            tick: 120,
+            food: (10, 12),
+            players: vec![
+                PlayerState { id: 3, name: "ala".to_string(), score: 30, alive: true, snake: vec![(5, 5), (4, 5)] },
+                PlayerState { id: 4, name: "bob".to_string(), score: 0, alive: false, snake: Vec::new() },
+            ],
+        };
+        let server = [
+            ServerMessage::Welcome { id: 3, width: 40, height: 30, tick_rate: 60 },
+            ServerMessage::Refused("server is full".to_string()),
+            ServerMessage::Snapshot(snapshot),
+        ];
+        for message in server {
+            assert_eq!(ServerMessage::parse(&message.to_line()), Ok(message));
+        }
+        assert_eq!(ServerMessage::parse("state 1 2,2"), Ok(ServerMessage::Snapshot(Snapshot { tick: 1, food: (2, 2), players: Vec::new() })));
+    }
+
+    #[test]
+    fn names_fit_between_spaces_and_bad_lines_are_refused() {
+        assert_eq!(clean_name("  Jan | Kowalski  "), "Jan___Kowalski");
+        assert_eq!(clean_name(""), "Player");
+        assert_eq!(clean_name("a very long name indeed").chars().count(), MAX_NAME_LEN);
+
+        assert!(ClientMessage::parse("input sideways").is_err());
+        assert!(ClientMessage::parse("join x ala").is_err());
+        assert!(ClientMessage::parse("hello").is_err());
+        assert!(ServerMessage::parse("state 1 2,2 | 3 ala 0 sleeping").is_err());
+        assert!(ServerMessage::parse("welcome 1 40").is_err());
+    }
+}
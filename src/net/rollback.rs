@@ -0,0 +1,306 @@
+//! Rollback for two-player Pong. The game runs ahead on a guess of the other player's input (their
+//! last known one) and, when their real input turns out different, goes back to the last state
+//! both sides agree on and plays the frames since then again.
+
+use crate::games::pong::{PaddleInput, PongGame};
+use std::collections::VecDeque;
+
+/// Frames a local input waits before it is played, so it usually reaches the other side in time
+/// and no rollback is needed.
+pub const INPUT_DELAY: u64 = 2;
+/// How far the game may run ahead of the other player's last known input before it waits.
+pub const MAX_ROLLBACK: u64 = 12;
+/// Agreed states are compared across the network every this many frames, see `checksum_due`.
+const CHECK_MASK: u64 = 31;
+/// Own checksums kept for the other side's to be compared against; about half a minute.
+const KEPT_CHECKSUMS: usize = 64;
+
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct RollbackStats {
+    /// Times a wrong guess sent the game back.
+    pub rollbacks: u32,
+    /// Frames played again after those.
+    pub resimulated: u64,
+    /// The furthest back a single rollback went.
+    pub deepest: u64,
+    /// Ticks spent waiting because the other player was too far behind.
+    pub stalls: u32,
+    /// Checksums both sides compared, and how many of those did not match.
+    pub checks: u32,
+    pub desyncs: u32,
+}
+
+pub struct Rollback {
+    /// Which paddle is ours: 0 is left, 1 is right.
+    side: usize,
+    game: PongGame,
+    /// Frames played so far; `game` is the state at the start of this frame.
+    frame: u64,
+    local: Vec<PaddleInput>,
+    /// The other player's inputs, from frame 0 up to the first one that has not arrived.
+    remote: Vec<PaddleInput>,
+    /// The guesses used for the frames played since the last remote input arrived.
+    guesses: VecDeque<PaddleInput>,
+    /// The first frame whose guess turned out wrong, if there is one to go back for.
+    wrong_from: Option<u64>,
+    /// States at the start of each frame that may still be rolled back to, oldest first.
+    saved: VecDeque<(u64, PongGame)>,
+    /// Checksums of agreed states by frame, newest last.
+    checksums: VecDeque<(u64, u64)>,
+    /// The other side's latest checksum, until this side has reached that frame too.
+    remote_checksum: Option<(u64, u64)>,
+    last_checked: Option<u64>,
+    pub stats: RollbackStats,
+}
+
+impl Rollback {
+    pub fn new(seed: u64, side: usize) -> Self {
+        // Both sides start with the same delay, so the first frames have no input from anyone.
+        let delay = vec![PaddleInput::Still; INPUT_DELAY as usize];
+        Rollback {
+            side,
+            game: PongGame::new(seed),
+            frame: 0,
+            local: delay.clone(),
+            remote: delay,
+            guesses: VecDeque::new(),
+            wrong_from: None,
+            saved: VecDeque::new(),
+            checksums: VecDeque::new(),
+            remote_checksum: None,
+            last_checked: None,
+            stats: RollbackStats::default(),
+        }
+    }
+
+    pub fn game(&self) -> &PongGame {
+        &self.game
+    }
+
+    pub fn side(&self) -> usize {
+        self.side
+    }
+
+    pub fn frame(&self) -> u64 {
+        self.frame
+    }
+
+    /// Frames up to here have both players' real inputs and will not change any more.
+    pub fn confirmed_frame(&self) -> u64 {
+        self.frame.min(self.remote.len() as u64)
+    }
+
+    /// The game is over on both sides and cannot be rolled back out of it.
+    pub fn is_finished(&self) -> bool {
+        self.game.is_over() && self.confirmed_frame() == self.frame && self.wrong_from.is_none()
+    }
+
+    /// Our inputs from `from` on, to be sent to the other side until it has them.
+    pub fn local_inputs(&self, from: u64) -> &[PaddleInput] {
+        &self.local[(from as usize).min(self.local.len())..]
+    }
+
+    /// How many of the other side's inputs have arrived, for them to know what to send again.
+    pub fn remote_count(&self) -> u64 {
+        self.remote.len() as u64
+    }
+
+    /// Adds the other player's inputs starting at frame `first`; ones already known are skipped
+    /// and ones after a gap wait for it to be filled by a later packet.
+    pub fn add_remote(&mut self, first: u64, inputs: &[PaddleInput]) {
+        for (frame, &input) in (first..).zip(inputs) {
+            if frame != self.remote.len() as u64 {
+                continue;
+            }
+            if frame < self.frame {
+                let guess = self.guesses.pop_front().unwrap_or_default();
+                if guess != input && self.wrong_from.is_none() {
+                    self.wrong_from = Some(frame);
+                }
+            }
+            self.remote.push(input);
+        }
+    }
+
+    /// Plays one frame with `input` as ours, after fixing up any wrong guesses. Returns false and
+    /// plays nothing when the other player is too far behind for a guess to be safe.
+    pub fn advance(&mut self, input: PaddleInput) -> bool {
+        self.correct();
+        if self.frame - self.confirmed_frame() >= MAX_ROLLBACK {
+            self.stats.stalls += 1;
+            return false;
+        }
+        self.local.push(input);
+        let frame = self.frame;
+        self.play(frame, true);
+        self.forget_agreed();
+        true
+    }
+
+    /// Goes back to the first wrongly guessed frame and plays everything since then again.
+    fn correct(&mut self) {
+        let Some(from) = self.wrong_from.take() else {
+            return;
+        };
+        let index = self.saved.iter().position(|(frame, _)| *frame == from).expect("the state to roll back to is kept");
+        self.game = self.saved[index].1.clone();
+        self.saved.truncate(index);
+        let to = self.frame;
+        self.guesses.clear();
+        for frame in from..to {
+            self.play(frame, false);
+        }
+        self.stats.rollbacks += 1;
+        self.stats.resimulated += to - from;
+        self.stats.deepest = self.stats.deepest.max(to - from);
+    }
+
+    fn play(&mut self, frame: u64, new: bool) {
+        let remote = match self.remote.get(frame as usize) {
+            Some(&input) => input,
+            None => {
+                let guess = self.remote.last().copied().unwrap_or_default();
+                self.guesses.push_back(guess);
+                guess
+            }
+        };
+        let local = self.local[frame as usize];
+        self.saved.push_back((frame, self.game.clone()));
+        let mut inputs = [local; 2];
+        inputs[1 - self.side] = remote;
+        self.game.step(inputs);
+        if new {
+            self.frame += 1;
+        }
+    }
+
+    /// Drops saved states that can no longer be rolled back to, keeping a checksum now and then.
+    fn forget_agreed(&mut self) {
+        let agreed = self.confirmed_frame();
+        while self.saved.len() > 1 && self.saved[1].0 <= agreed {
+            let (frame, state) = self.saved.pop_front().expect("checked above");
+            if frame & CHECK_MASK == 0 {
+                self.checksums.push_back((frame, state.checksum()));
+                if self.checksums.len() > KEPT_CHECKSUMS {
+                    self.checksums.pop_front();
+                }
+                self.compare_checksum();
+            }
+        }
+    }
+
+    /// The latest agreed checksum, to send to the other side.
+    pub fn checksum_due(&self) -> Option<(u64, u64)> {
+        self.checksums.back().copied()
+    }
+
+    /// Takes the other side's checksum of `frame`; ones already compared are ignored.
+    pub fn add_remote_checksum(&mut self, frame: u64, checksum: u64) {
+        if self.last_checked.filter(|&checked| frame <= checked).is_none() {
+            self.remote_checksum = Some((frame, checksum));
+            self.compare_checksum();
+        }
+    }
+
+    fn compare_checksum(&mut self) {
+        let Some((frame, theirs)) = self.remote_checksum else {
+            return;
+        };
+        if let Some(&(_, ours)) = self.checksums.iter().find(|(ours, _)| *ours == frame) {
+            self.stats.checks += 1;
+            if ours != theirs {
+                self.stats.desyncs += 1;
+                log::warn!("Pong went out of sync at frame {}", frame);
+            }
+            self.last_checked = Some(frame);
+            self.remote_checksum = None;
+        } else if self.checksums.front().is_some_and(|&(oldest, _)| oldest > frame) {
+            self.remote_checksum = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn input(side: usize, frame: u64) -> PaddleInput {
+        let pattern = [PaddleInput::Up, PaddleInput::Up, PaddleInput::Still, PaddleInput::Down];
+        pattern[((frame / 7 + side as u64 * 3) & 3) as usize]
+    }
+
+    /// The same game played without any network, every input in time.
+    fn offline(seed: u64, frames: u64) -> PongGame {
+        let mut game = PongGame::new(seed);
+        for frame in 0..frames {
+            let played = |side| if frame < INPUT_DELAY { PaddleInput::Still } else { input(side, frame - INPUT_DELAY) };
+            game.step([played(0), played(1)]);
+        }
+        game
+    }
+
+    #[test]
+    fn late_inputs_are_rolled_back_to_the_offline_game() {
+        let mut left = Rollback::new(9, 0);
+        // The other side's inputs arrive in bursts, a few frames late.
+        for frame in 0..600 {
+            assert!(left.advance(input(0, frame)));
+            if frame & 7 == 7 {
+                let first = left.remote_count();
+                let inputs: Vec<_> = (first..frame + INPUT_DELAY - 4).map(|frame| input(1, frame - INPUT_DELAY)).collect();
+                left.add_remote(first, &inputs);
+            }
+        }
+        let rest: Vec<_> = (left.remote_count()..=left.frame()).map(|frame| input(1, frame - INPUT_DELAY)).collect();
+        left.add_remote(left.remote_count(), &rest);
+        assert!(left.advance(PaddleInput::Still));
+
+        assert_eq!(left.game(), &offline(9, 601));
+        assert!(left.stats.rollbacks > 0);
+        assert!(left.stats.deepest < MAX_ROLLBACK);
+    }
+
+    #[test]
+    fn right_guesses_need_no_rollback_and_silence_stalls() {
+        let mut right = Rollback::new(3, 1);
+        while right.advance(PaddleInput::Down) {}
+        assert_eq!((right.frame(), right.stats.stalls), (MAX_ROLLBACK + INPUT_DELAY, 1));
+
+        // Still is what was guessed, so nothing has to be played again.
+        right.add_remote(INPUT_DELAY, &[PaddleInput::Still; 20]);
+        assert!(right.advance(PaddleInput::Down));
+        assert_eq!(right.stats.rollbacks, 0);
+        assert_eq!(right.confirmed_frame(), right.frame());
+    }
+
+    /// Sends everything `from` has played so far but the last two frames.
+    fn exchange(from: &Rollback, to: &mut Rollback) {
+        let first = to.remote_count();
+        let sent = from.local_inputs(first);
+        to.add_remote(first, &sent[..sent.len().saturating_sub(INPUT_DELAY as usize + 2)]);
+        if let Some((frame, checksum)) = from.checksum_due() {
+            to.add_remote_checksum(frame, checksum);
+        }
+    }
+
+    fn play_pair(left_seed: u64, right_seed: u64) -> (Rollback, Rollback) {
+        let (mut left, mut right) = (Rollback::new(left_seed, 0), Rollback::new(right_seed, 1));
+        for frame in 0..300 {
+            left.advance(input(0, frame));
+            right.advance(input(1, frame));
+            exchange(&left, &mut right);
+            exchange(&right, &mut left);
+        }
+        (left, right)
+    }
+
+    #[test]
+    fn checksums_catch_games_that_drift_apart() {
+        let (left, right) = play_pair(4, 4);
+        assert!(left.stats.checks >= 4, "{:?}", left.stats);
+        assert_eq!((left.stats.desyncs, right.stats.desyncs), (0, 0));
+
+        let (left, _) = play_pair(4, 5);
+        assert!(left.stats.desyncs > 0);
+    }
+}
@@ -0,0 +1,351 @@
+//! The snake server: one thread that takes connections, reads inputs, ticks the arena and sends
+//! every client the new state, all without blocking on any one client.
+
+use crate::games::TICKS_PER_SECOND;
+use crate::net::arena::{Arena, MAX_PLAYERS};
+use crate::net::protocol::{ClientMessage, ServerMessage, PROTOCOL_VERSION};
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// A line longer than this is not one of ours; the client is dropped.
+const MAX_LINE: usize = 1024;
+/// Unsent state piling up past this means the client cannot keep up; it is dropped.
+const MAX_BACKLOG: usize = 256 * 1024;
+
+#[derive(Clone, Debug)]
+pub struct ServerConfig {
+    pub max_players: usize,
+    pub tick_rate: u32,
+    /// Clients that send nothing, not even a ping, for this long are dropped.
+    pub timeout: Duration,
+    pub seed: u64,
+    pub wrap: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            max_players: MAX_PLAYERS,
+            tick_rate: TICKS_PER_SECOND,
+            timeout: Duration::from_secs(10),
+            seed: rand::random(),
+            wrap: false,
+        }
+    }
+}
+
+struct Connection {
+    stream: TcpStream,
+    address: SocketAddr,
+    incoming: Vec<u8>,
+    outgoing: Vec<u8>,
+    player: Option<u32>,
+    last_heard: Instant,
+    /// Set once the connection should go; it is dropped after one last try to send what is left.
+    closing: bool,
+}
+
+impl Connection {
+    fn send(&mut self, message: &ServerMessage) {
+        self.outgoing.extend_from_slice(message.to_line().as_bytes());
+        self.outgoing.push(b'\n');
+    }
+
+    /// Reads whatever has arrived and returns the complete lines; closes on errors and hang-ups.
+    fn receive(&mut self) -> Vec<String> {
+        let mut buffer = [0; 4096];
+        loop {
+            match self.stream.read(&mut buffer) {
+                Ok(0) => {
+                    self.closing = true;
+                    break;
+                }
+                Ok(count) => self.incoming.extend_from_slice(&buffer[..count]),
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => {
+                    log::info!("Dropping {}: {}", self.address, error);
+                    self.closing = true;
+                    break;
+                }
+            }
+        }
+
+        let mut lines = Vec::new();
+        while let Some(end) = self.incoming.iter().position(|&byte| byte == b'\n') {
+            let line: Vec<u8> = self.incoming.drain(..=end).collect();
+            lines.push(String::from_utf8_lossy(&line).trim().to_string());
+        }
+        if self.incoming.len() > MAX_LINE {
+            log::warn!("Dropping {}: line too long", self.address);
+            self.closing = true;
+        }
+        lines
+    }
+
+    fn flush(&mut self) {
+        while !self.outgoing.is_empty() {
+            match self.stream.write(&self.outgoing) {
+                Ok(0) => {
+                    self.closing = true;
+                    return;
+                }
+                Ok(count) => {
+                    self.outgoing.drain(..count);
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => {
+                    log::info!("Dropping {}: {}", self.address, error);
+                    self.closing = true;
+                    return;
+                }
+            }
+        }
+        if self.outgoing.len() > MAX_BACKLOG {
+            log::warn!("Dropping {}: it is not keeping up", self.address);
+            self.closing = true;
+        }
+    }
+}
+
+pub struct Server {
+    listener: TcpListener,
+    config: ServerConfig,
+    arena: Arena,
+    connections: Vec<Connection>,
+}
+
+impl Server {
+    pub fn bind(address: impl ToSocketAddrs, config: ServerConfig) -> io::Result<Server> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let arena = Arena::new(config.seed, config.wrap, config.max_players);
+        Ok(Server { listener, config, arena, connections: Vec::new() })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.arena.players().len()
+    }
+
+    /// Steps at the tick rate until `stop` is set.
+    pub fn run(&mut self, stop: &AtomicBool) {
+        let tick = Duration::from_secs_f64(1.0 / self.config.tick_rate.max(1) as f64);
+        let mut next = Instant::now();
+        while !stop.load(Ordering::Relaxed) {
+            self.step();
+            next += tick;
+            let now = Instant::now();
+            if next > now {
+                thread::sleep(next - now);
+            } else if now - next > tick * 10 {
+                // Too far behind to catch up; carry on from here rather than racing through ticks.
+                next = now;
+            }
+        }
+        for connection in &mut self.connections {
+            connection.send(&ServerMessage::Refused("server is shutting down".to_string()));
+            connection.flush();
+        }
+    }
+
+    /// One tick: takes new connections, reads what the clients sent, moves the arena on and
+    /// sends everyone the new state.
+    pub fn step(&mut self) {
+        self.accept();
+
+        let now = Instant::now();
+        for index in 0..self.connections.len() {
+            let lines = self.connections[index].receive();
+            if !lines.is_empty() {
+                self.connections[index].last_heard = now;
+            }
+            for line in lines.iter().filter(|line| !line.is_empty()) {
+                match ClientMessage::parse(line) {
+                    Ok(message) => self.handle(index, message),
+                    Err(error) => log::debug!("Ignoring a message from {}: {}", self.connections[index].address, error),
+                }
+            }
+            let connection = &mut self.connections[index];
+            if now.duration_since(connection.last_heard) > self.config.timeout && !connection.closing {
+                log::info!("{} timed out", connection.address);
+                connection.closing = true;
+            }
+        }
+
+        self.arena.tick();
+        let snapshot = ServerMessage::Snapshot(self.arena.snapshot());
+        for connection in &mut self.connections {
+            if connection.player.is_some() && !connection.closing {
+                connection.send(&snapshot);
+            }
+            connection.flush();
+        }
+
+        let arena = &mut self.arena;
+        self.connections.retain(|connection| {
+            if connection.closing {
+                if let Some(id) = connection.player {
+                    log::info!("Player {} left", id);
+                    arena.leave(id);
+                }
+            }
+            !connection.closing
+        });
+    }
+
+    fn accept(&mut self) {
+        loop {
+            match self.listener.accept() {
+                Ok((stream, address)) => {
+                    if let Err(error) = stream.set_nonblocking(true).and_then(|()| stream.set_nodelay(true)) {
+                        log::warn!("Could not set up the connection from {}: {}", address, error);
+                        continue;
+                    }
+                    self.connections.push(Connection {
+                        stream,
+                        address,
+                        incoming: Vec::new(),
+                        outgoing: Vec::new(),
+                        player: None,
+                        last_heard: Instant::now(),
+                        closing: false,
+                    });
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => return,
+                Err(error) => {
+                    log::warn!("Could not accept a connection: {}", error);
+                    return;
+                }
+            }
+        }
+    }
+
+    fn handle(&mut self, index: usize, message: ClientMessage) {
+        let connection = &mut self.connections[index];
+        match (message, connection.player) {
+            (ClientMessage::Join { version, .. }, None) if version != PROTOCOL_VERSION => {
+                connection.send(&ServerMessage::Refused(format!("protocol v{} is not supported, the server speaks v{}", version, PROTOCOL_VERSION)));
+                connection.closing = true;
+            }
+            (ClientMessage::Join { name, .. }, None) => match self.arena.join(&name) {
+                Some(id) => {
+                    log::info!("{} joined from {} as player {}", name, connection.address, id);
+                    connection.player = Some(id);
+                    connection.send(&ServerMessage::Welcome {
+                        id,
+                        width: self.arena.width(),
+                        height: self.arena.height(),
+                        tick_rate: self.config.tick_rate,
+                    });
+                }
+                None => {
+                    connection.send(&ServerMessage::Refused("server is full".to_string()));
+                    connection.closing = true;
+                }
+            },
+            (ClientMessage::Input(input), Some(id)) => self.arena.input(id, input),
+            (ClientMessage::Leave, _) => connection.closing = true,
+            // Pings only need to arrive; anything else before a join, or a second join, is ignored.
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::GameInput;
+    use crate::net::client::Client;
+    use crate::net::protocol::Snapshot;
+    use std::sync::Arc;
+    use std::io::BufRead;
+    use std::thread::JoinHandle;
+
+    const WAIT: Duration = Duration::from_secs(5);
+
+    fn start(config: ServerConfig) -> (String, Arc<AtomicBool>, JoinHandle<()>) {
+        let mut server = Server::bind("127.0.0.1:0", config).unwrap();
+        let address = server.local_addr().unwrap().to_string();
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        let handle = thread::spawn(move || server.run(&flag));
+        (address, stop, handle)
+    }
+
+    fn stop(stop: Arc<AtomicBool>, handle: JoinHandle<()>) {
+        stop.store(true, Ordering::Relaxed);
+        handle.join().unwrap();
+    }
+
+    /// Polls until a snapshot passes `check`, pinging so the client is not timed out meanwhile.
+    fn wait_for(client: &mut Client, check: impl Fn(&Snapshot) -> bool) -> Snapshot {
+        let deadline = Instant::now() + WAIT;
+        while Instant::now() < deadline {
+            client.send(&ClientMessage::Ping).unwrap();
+            for message in client.poll().unwrap() {
+                if let ServerMessage::Snapshot(snapshot) = message {
+                    if check(&snapshot) {
+                        return snapshot;
+                    }
+                }
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        panic!("the server never sent the expected state");
+    }
+
+    #[test]
+    fn several_clients_play_on_one_board() {
+        let (address, flag, handle) = start(ServerConfig { seed: 7, ..ServerConfig::default() });
+        let mut clients: Vec<Client> = ["ala", "bob", "cyryl"].iter().map(|name| Client::connect(&address, name, WAIT).unwrap()).collect();
+        let ids: Vec<u32> = clients.iter().map(|client| client.id).collect();
+        assert_eq!((clients[0].width, clients[0].height), (40, 30));
+
+        let everyone = wait_for(&mut clients[0], |snapshot| ids.iter().all(|&id| snapshot.player(id).is_some()));
+        assert_eq!(everyone.player(ids[1]).unwrap().name, "bob");
+
+        // Snakes start out heading right; a turn down shows up in later states.
+        let start_y = everyone.player(ids[2]).unwrap().snake[0].1;
+        clients[2].send(&ClientMessage::Input(GameInput::Down)).unwrap();
+        wait_for(&mut clients[2], |snapshot| snapshot.player(ids[2]).is_some_and(|player| player.snake.first().is_some_and(|head| head.1 > start_y)));
+
+        clients[1].send(&ClientMessage::Leave).unwrap();
+        wait_for(&mut clients[0], |snapshot| snapshot.player(ids[1]).is_none() && snapshot.players.len() == 2);
+        stop(flag, handle);
+    }
+
+    #[test]
+    fn silent_clients_time_out() {
+        let (address, flag, handle) = start(ServerConfig { timeout: Duration::from_millis(300), ..ServerConfig::default() });
+        let mut active = Client::connect(&address, "ala", WAIT).unwrap();
+        let silent = Client::connect(&address, "bob", WAIT).unwrap();
+        wait_for(&mut active, |snapshot| snapshot.player(silent.id).is_some());
+        let id = active.id;
+        wait_for(&mut active, |snapshot| snapshot.player(silent.id).is_none() && snapshot.player(id).is_some());
+        stop(flag, handle);
+    }
+
+    #[test]
+    fn full_servers_and_other_versions_are_refused() {
+        let (address, flag, handle) = start(ServerConfig { max_players: 1, ..ServerConfig::default() });
+        let _first = Client::connect(&address, "ala", WAIT).unwrap();
+        let error = Client::connect(&address, "bob", WAIT).err().unwrap();
+        assert!(error.contains("full"), "{}", error);
+
+        let mut stream = TcpStream::connect(&address).unwrap();
+        stream.write_all(b"join 99 cyryl\n").unwrap();
+        let mut reply = String::new();
+        stream.set_read_timeout(Some(WAIT)).unwrap();
+        io::BufReader::new(stream).read_line(&mut reply).unwrap();
+        assert!(reply.starts_with("refused protocol v99"), "{}", reply);
+        stop(flag, handle);
+    }
+}
@@ -0,0 +1,314 @@
+//! Lets others watch a running game: its state goes out as JSON over a WebSocket on a local
+//! port, and a plain browser visit to the same port gets a page that draws it. Frames are sent
+//! from a thread of its own and dropped when spectators cannot keep up, so the game never waits.
+//! New connections are greeted on threads of their own, so a slow one holds up nobody else.
+
+use crate::export::json_string;
+use std::fmt::Display;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender, TrySendError};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use tungstenite::{Message, WebSocket};
+
+pub const DEFAULT_SPECTATE_PORT: u16 = 7781;
+const VIEWER: &str = include_str!("../../resources/spectator.html");
+/// Frames waiting to go out; newer ones are dropped while these are sent.
+const QUEUED_FRAMES: usize = 2;
+/// A spectator this slow to take a frame is dropped.
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+/// All the time a connection gets to send its request head.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(2);
+const MAX_REQUEST: usize = 8 * 1024;
+
+/// A JSON object written field by field; games fill one in to describe themselves to spectators.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct Frame {
+    fields: Vec<String>,
+}
+
+impl Frame {
+    /// A number or a bool, written as it prints.
+    pub fn set(&mut self, key: &str, value: impl Display) {
+        self.fields.push(format!("{}:{}", json_string(key), value));
+    }
+
+    pub fn set_text(&mut self, key: &str, value: &str) {
+        self.set(key, json_string(value));
+    }
+
+    /// A grid cell as `[x, y]`.
+    pub fn set_point(&mut self, key: &str, (x, y): (i32, i32)) {
+        self.set(key, format!("[{},{}]", x, y));
+    }
+
+    /// Grid cells as `[[x, y], ...]`.
+    pub fn set_points(&mut self, key: &str, points: impl IntoIterator<Item = (i32, i32)>) {
+        let points: Vec<String> = points.into_iter().map(|(x, y)| format!("[{},{}]", x, y)).collect();
+        self.set(key, format!("[{}]", points.join(",")));
+    }
+
+    pub fn set_object(&mut self, key: &str, object: Frame) {
+        self.set(key, object.to_json());
+    }
+
+    pub fn set_objects(&mut self, key: &str, objects: impl IntoIterator<Item = Frame>) {
+        let objects: Vec<String> = objects.into_iter().map(|object| object.to_json()).collect();
+        self.set(key, format!("[{}]", objects.join(",")));
+    }
+
+    pub fn to_json(&self) -> String {
+        format!("{{{}}}", self.fields.join(","))
+    }
+}
+
+/// The game's end of the stream.
+pub struct Spectators {
+    frames: SyncSender<String>,
+    watching: Arc<AtomicUsize>,
+    address: SocketAddr,
+}
+
+impl Spectators {
+    pub fn start(address: impl ToSocketAddrs) -> io::Result<Spectators> {
+        let listener = TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let address = listener.local_addr()?;
+        let (frames, receiver) = mpsc::sync_channel(QUEUED_FRAMES);
+        let watching = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&watching);
+        thread::Builder::new().name("spectators".to_string()).spawn(move || serve(listener, receiver, &counter))?;
+        Ok(Spectators { frames, watching, address })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.address
+    }
+
+    /// Spectators connected at the moment; nothing needs sending while there are none.
+    pub fn watching(&self) -> usize {
+        self.watching.load(Ordering::Relaxed)
+    }
+
+    /// Hands a frame over for sending; it is dropped if the last ones have not gone out yet.
+    pub fn broadcast(&self, frame: &Frame) {
+        match self.frames.try_send(frame.to_json()) {
+            Ok(()) | Err(TrySendError::Full(_)) => {}
+            Err(TrySendError::Disconnected(_)) => log::warn!("The spectator stream has stopped"),
+        }
+    }
+}
+
+/// Takes in new spectators and sends each frame to all of them, until the game drops `Spectators`.
+fn serve(listener: TcpListener, frames: Receiver<String>, watching: &AtomicUsize) {
+    let mut sockets: Vec<WebSocket<TcpStream>> = Vec::new();
+    let (greeted, arrivals) = mpsc::channel();
+    loop {
+        loop {
+            match listener.accept() {
+                Ok((stream, address)) => {
+                    let greeted = greeted.clone();
+                    let greeting = thread::Builder::new().name("spectator greeting".to_string()).spawn(move || {
+                        if let Some(socket) = greet(stream, address) {
+                            let _ = greeted.send(socket);
+                        }
+                    });
+                    if let Err(error) = greeting {
+                        log::warn!("Could not greet {}: {}", address, error);
+                    }
+                }
+                Err(error) if error.kind() == io::ErrorKind::WouldBlock => break,
+                Err(error) => {
+                    log::warn!("Could not take a spectator: {}", error);
+                    break;
+                }
+            }
+        }
+        sockets.extend(arrivals.try_iter());
+        match frames.recv_timeout(Duration::from_millis(50)) {
+            Ok(mut frame) => {
+                while let Ok(newer) = frames.try_recv() {
+                    frame = newer;
+                }
+                sockets.retain_mut(|socket| match socket.send(Message::text(frame.clone())) {
+                    Ok(()) => true,
+                    Err(error) => {
+                        log::info!("A spectator left: {}", error);
+                        false
+                    }
+                });
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => {
+                for socket in &mut sockets {
+                    if socket.close(None).is_ok() {
+                        let _ = socket.flush();
+                    }
+                }
+                return;
+            }
+        }
+        watching.store(sockets.len(), Ordering::Relaxed);
+    }
+}
+
+/// Upgrades a request for `/ws` to a WebSocket and answers anything else with the viewer page.
+fn greet(stream: TcpStream, address: SocketAddr) -> Option<WebSocket<TcpStream>> {
+    let setup = stream
+        .set_nonblocking(false)
+        .and_then(|()| stream.set_read_timeout(Some(REQUEST_TIMEOUT)))
+        .and_then(|()| stream.set_write_timeout(Some(WRITE_TIMEOUT)));
+    if let Err(error) = setup {
+        log::warn!("Could not set up the connection from {}: {}", address, error);
+        return None;
+    }
+    let head = match peek_head(&stream) {
+        Ok(head) => head,
+        Err(error) => {
+            log::info!("Dropping {}: {}", address, error);
+            return None;
+        }
+    };
+    let path = head.split_whitespace().nth(1).unwrap_or("/");
+    if path == "/ws" {
+        return match tungstenite::accept(stream) {
+            Ok(socket) => {
+                log::info!("{} is watching", address);
+                Some(socket)
+            }
+            Err(error) => {
+                log::info!("{} could not become a spectator: {}", address, error);
+                None
+            }
+        };
+    }
+    // Read what was only peeked at, or closing with it unread would cut the answer off.
+    let mut request = vec![0; head.len() + 4];
+    if let Err(error) = (&stream).read_exact(&mut request) {
+        log::info!("Dropping {}: {}", address, error);
+        return None;
+    }
+    let (status, body) = if path == "/" || path.starts_with("/?") { ("200 OK", VIEWER) } else { ("404 Not Found", "not found\n") };
+    let kind = if status.starts_with("200") { "text/html" } else { "text/plain" };
+    let answer = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        kind,
+        body.len(),
+        body
+    );
+    if let Err(error) = (&stream).write_all(answer.as_bytes()) {
+        log::info!("Could not send the viewer to {}: {}", address, error);
+    }
+    None
+}
+
+/// The request line and headers, left in the stream for the WebSocket handshake to read again.
+/// A head still not complete after `REQUEST_TIMEOUT` is given up on.
+fn peek_head(stream: &TcpStream) -> io::Result<String> {
+    let deadline = Instant::now() + REQUEST_TIMEOUT;
+    let mut buffer = vec![0; MAX_REQUEST];
+    loop {
+        let size = stream.peek(&mut buffer)?;
+        let head = String::from_utf8_lossy(&buffer[..size]);
+        if let Some(end) = head.find("\r\n\r\n") {
+            return Ok(head[..end].to_string());
+        }
+        if size == 0 || size == MAX_REQUEST {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "not an HTTP request"));
+        }
+        if Instant::now() >= deadline {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "the request took too long"));
+        }
+        // The rest of the head is still on its way.
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::snake::SnakeGame;
+    use crate::games::Minigame;
+    use crate::net::http;
+
+    #[test]
+    fn frames_are_json_objects() {
+        let mut game = SnakeGame::new(20);
+        game.food = (3, 4);
+        let mut state = Frame::default();
+        game.spectate(&mut state);
+        let mut frame = Frame::default();
+        frame.set_text("player", "a\"b");
+        frame.set("score", 7);
+        frame.set("over", false);
+        frame.set_object("state", state);
+        assert_eq!(
+            frame.to_json(),
+            r#"{"player":"a\"b","score":7,"over":false,"state":{"width":20,"height":10,"snake":[[5,5]],"direction":[1,0],"food":[3,4],"walls":[],"wrap":false}}"#
+        );
+    }
+
+    #[test]
+    fn spectators_get_the_page_and_then_the_frames() {
+        let spectators = Spectators::start("127.0.0.1:0").unwrap();
+        let address = spectators.local_addr();
+
+        let page = http::request("GET", &format!("http://{}/", address), None, Duration::from_secs(5)).unwrap();
+        assert_eq!(page.status, 200);
+        assert!(page.body.contains("<canvas"));
+        let missing = http::request("GET", &format!("http://{}/nothing", address), None, Duration::from_secs(5)).unwrap();
+        assert_eq!(missing.status, 404);
+
+        let (mut socket, _) = tungstenite::connect(format!("ws://{}/ws", address)).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut frame = Frame::default();
+        frame.set("score", 3);
+        // Only frames sent after the spectator was taken in reach it.
+        while spectators.watching() == 0 {
+            assert!(Instant::now() < deadline, "the spectator was never taken in");
+            thread::sleep(Duration::from_millis(10));
+        }
+        spectators.broadcast(&frame);
+        assert_eq!(socket.read().unwrap(), Message::text(r#"{"score":3}"#));
+
+        drop(spectators);
+        assert!(matches!(socket.read(), Ok(Message::Close(_))));
+    }
+
+    #[test]
+    fn a_stalled_request_holds_up_nobody() {
+        let spectators = Spectators::start("127.0.0.1:0").unwrap();
+        let address = spectators.local_addr();
+        let mut stalled = TcpStream::connect(address).unwrap();
+        stalled.write_all(b"GET /ws HT").unwrap();
+        let silent = TcpStream::connect(address).unwrap();
+
+        let started = Instant::now();
+        let (mut socket, _) = tungstenite::connect(format!("ws://{}/ws", address)).unwrap();
+        while spectators.watching() == 0 {
+            assert!(started.elapsed() < REQUEST_TIMEOUT, "the spectator waited for the stalled requests");
+            thread::sleep(Duration::from_millis(10));
+        }
+        let mut frame = Frame::default();
+        frame.set("score", 1);
+        spectators.broadcast(&frame);
+        assert_eq!(socket.read().unwrap(), Message::text(r#"{"score":1}"#));
+
+        // The stalled one is dropped once its time is up, however it trickles in.
+        stalled.set_read_timeout(Some(REQUEST_TIMEOUT * 3)).unwrap();
+        for _ in 0..5 {
+            let _ = stalled.write_all(b"T");
+            thread::sleep(REQUEST_TIMEOUT / 4);
+        }
+        let dropped = match stalled.read(&mut [0; 16]) {
+            Ok(size) => size == 0,
+            Err(error) => error.kind() == io::ErrorKind::ConnectionReset,
+        };
+        assert!(dropped, "the stalled request was never given up on");
+        drop(silent);
+    }
+}
@@ -0,0 +1,373 @@
+//! Keeps the player's runs in step with a shared leaderboard server. All the network work is done
+//! on a thread of its own, so a slow or missing server never holds up the game; runs that could
+//! not be sent wait in a file in the data dir and are sent again, backing off, until they get there.
+
+use crate::net::http;
+use crate::net::leaderboard::{Submission, MAX_LIMIT};
+use crate::scores::ScoreEntry;
+use crate::storage;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const QUEUE_FILE: &str = "sync-queue.txt";
+const TIMEOUT: Duration = Duration::from_secs(3);
+const FIRST_RETRY: Duration = Duration::from_secs(5);
+const LAST_RETRY: Duration = Duration::from_secs(300);
+/// Runs sent in one request; a long queue goes in several.
+const BATCH: usize = 100;
+
+enum Job {
+    Submit(ScoreEntry),
+    Fetch { game: String, mode: String },
+}
+
+enum Update {
+    Board { game: String, mode: String, entries: Vec<ScoreEntry> },
+    Status { online: bool, pending: usize },
+}
+
+/// The game's end of the sync; everything it does returns right away.
+pub struct ScoreSync {
+    url: String,
+    jobs: Sender<Job>,
+    updates: Receiver<Update>,
+    /// The server's boards as last fetched.
+    boards: HashMap<(String, String), Vec<ScoreEntry>>,
+    online: Option<bool>,
+    pending: usize,
+}
+
+impl ScoreSync {
+    /// Starts the worker for the server at `url`, e.g. `http://scores.local:7780`.
+    pub fn start(url: &str, queue_path: PathBuf) -> Result<ScoreSync, String> {
+        http::split_url(url)?;
+        let (jobs, job_receiver) = mpsc::channel();
+        let (update_sender, updates) = mpsc::channel();
+        let worker = Worker::new(url.trim().trim_end_matches('/').to_string(), queue_path, update_sender);
+        let pending = worker.queue.len();
+        thread::Builder::new()
+            .name("score-sync".to_string())
+            .spawn(move || worker.run(job_receiver))
+            .map_err(|error| error.to_string())?;
+        Ok(ScoreSync { url: url.to_string(), jobs, updates, boards: HashMap::new(), online: None, pending })
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn submit(&mut self, entry: ScoreEntry) {
+        self.pending += 1;
+        self.send(Job::Submit(entry));
+    }
+
+    /// Asks for the server's top runs of a board; they show up in `board` after a `poll`.
+    pub fn fetch(&self, game: &str, mode: &str) {
+        self.send(Job::Fetch { game: game.to_string(), mode: mode.to_string() });
+    }
+
+    fn send(&self, job: Job) {
+        if self.jobs.send(job).is_err() {
+            log::warn!("The score sync has stopped");
+        }
+    }
+
+    /// Takes in whatever the worker has done since the last call.
+    pub fn poll(&mut self) {
+        while let Ok(update) = self.updates.try_recv() {
+            match update {
+                Update::Board { game, mode, entries } => {
+                    self.boards.insert((game, mode), entries);
+                }
+                Update::Status { online, pending } => {
+                    self.online = Some(online);
+                    self.pending = pending;
+                }
+            }
+        }
+    }
+
+    pub fn board(&self, game: &str, mode: &str) -> Option<&[ScoreEntry]> {
+        self.boards.get(&(game.to_string(), mode.to_string())).map(Vec::as_slice)
+    }
+
+    /// Whether the server answered last time; `None` before the first try.
+    pub fn is_online(&self) -> Option<bool> {
+        self.online
+    }
+
+    /// Runs not yet on the server.
+    pub fn pending(&self) -> usize {
+        self.pending
+    }
+}
+
+struct Worker {
+    url: String,
+    queue_path: PathBuf,
+    queue: Vec<Submission>,
+    fetches: Vec<(String, String)>,
+    updates: Sender<Update>,
+}
+
+impl Worker {
+    fn new(url: String, queue_path: PathBuf, updates: Sender<Update>) -> Self {
+        let queue = load_queue(&queue_path);
+        Worker { url, queue_path, queue, fetches: Vec::new(), updates }
+    }
+
+    /// Works until the game drops its `ScoreSync`.
+    fn run(mut self, jobs: Receiver<Job>) {
+        let mut retry_at = Instant::now();
+        let mut backoff = FIRST_RETRY;
+        loop {
+            let job = if self.queue.is_empty() && self.fetches.is_empty() {
+                jobs.recv().map_err(|_| RecvTimeoutError::Disconnected)
+            } else {
+                jobs.recv_timeout(retry_at.saturating_duration_since(Instant::now()))
+            };
+            match job {
+                Ok(job) => {
+                    self.take(job);
+                    while let Ok(job) = jobs.try_recv() {
+                        self.take(job);
+                    }
+                    // Something new to do is worth a try even while backing off.
+                    retry_at = Instant::now();
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+            if Instant::now() < retry_at {
+                continue;
+            }
+
+            let online = self.flush() && self.fetch();
+            // Nobody is listening any more once the game has gone.
+            let _ = self.updates.send(Update::Status { online, pending: self.queue.len() });
+            if online {
+                backoff = FIRST_RETRY;
+            } else {
+                retry_at = Instant::now() + backoff;
+                backoff = (backoff * 2).min(LAST_RETRY);
+            }
+        }
+    }
+
+    fn take(&mut self, job: Job) {
+        match job {
+            Job::Submit(entry) => {
+                self.queue.push(Submission::new(entry));
+                self.save_queue();
+            }
+            Job::Fetch { game, mode } => {
+                if !self.fetches.iter().any(|(g, m)| *g == game && *m == mode) {
+                    self.fetches.push((game, mode));
+                }
+            }
+        }
+    }
+
+    /// Sends the queued runs; false if the server could not be reached.
+    fn flush(&mut self) -> bool {
+        while !self.queue.is_empty() {
+            let count = self.queue.len().min(BATCH);
+            let body: String = self.queue[..count].iter().map(|run| run.to_line() + "\n").collect();
+            match http::request("POST", &format!("{}/api/runs", self.url), Some(&body), TIMEOUT) {
+                Ok(response) if response.status == 200 => log::info!("Sent {} runs to {}: {}", count, self.url, response.body.trim()),
+                // The server refuses a whole batch over one bad run, so the rest go again one by one.
+                Ok(response) if (400..500).contains(&response.status) && count > 1 => {
+                    log::warn!("{} refused {} runs, sending them one at a time: {} {}", self.url, count, response.status, response.body.trim());
+                    if !self.flush_one_by_one(count) {
+                        return false;
+                    }
+                    continue;
+                }
+                // Sending it again would only be refused again.
+                Ok(response) if (400..500).contains(&response.status) => {
+                    log::error!("{} refused a run: {} {}", self.url, response.status, response.body.trim())
+                }
+                Ok(response) => {
+                    log::warn!("{} could not take the runs: {} {}", self.url, response.status, response.body.trim());
+                    return false;
+                }
+                Err(error) => {
+                    log::warn!("Could not send runs: {}", error);
+                    return false;
+                }
+            }
+            self.queue.drain(..count);
+            self.save_queue();
+        }
+        true
+    }
+
+    /// Sends the first `count` queued runs separately, dropping only those refused on their own.
+    fn flush_one_by_one(&mut self, count: usize) -> bool {
+        for _ in 0..count {
+            let Some(run) = self.queue.first() else {
+                break;
+            };
+            let line = run.to_line();
+            match http::request("POST", &format!("{}/api/runs", self.url), Some(&(line.clone() + "\n")), TIMEOUT) {
+                Ok(response) if response.status == 200 => {}
+                Ok(response) if (400..500).contains(&response.status) => {
+                    log::error!("{} refused the run '{}': {} {}", self.url, line, response.status, response.body.trim())
+                }
+                Ok(response) => {
+                    log::warn!("{} could not take the runs: {} {}", self.url, response.status, response.body.trim());
+                    return false;
+                }
+                Err(error) => {
+                    log::warn!("Could not send runs: {}", error);
+                    return false;
+                }
+            }
+            self.queue.remove(0);
+            self.save_queue();
+        }
+        true
+    }
+
+    fn fetch(&mut self) -> bool {
+        while let Some((game, mode)) = self.fetches.first().cloned() {
+            let url = format!("{}/api/top?game={}&mode={}&limit={}", self.url, http::encode(&game), http::encode(&mode), MAX_LIMIT);
+            match http::request("GET", &url, None, TIMEOUT) {
+                Ok(response) if response.status == 200 => {
+                    let entries = response.body.lines().filter_map(ScoreEntry::parse).collect();
+                    let _ = self.updates.send(Update::Board { game, mode, entries });
+                }
+                Ok(response) => log::warn!("{} has no board {} {}: {} {}", self.url, game, mode, response.status, response.body.trim()),
+                Err(error) => {
+                    log::warn!("Could not fetch the leaderboard: {}", error);
+                    return false;
+                }
+            }
+            self.fetches.remove(0);
+        }
+        true
+    }
+
+    fn save_queue(&self) {
+        let content: String = self.queue.iter().map(|run| run.to_line() + "\n").collect();
+        if let Err(error) = storage::write_atomic(&self.queue_path, &content) {
+            log::error!("Could not save {}: {}", self.queue_path.display(), error);
+        }
+    }
+}
+
+fn load_queue(path: &Path) -> Vec<Submission> {
+    let Ok(content) = fs::read_to_string(path) else {
+        return Vec::new();
+    };
+    content
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .filter_map(|line| match Submission::parse(line) {
+            Ok(run) => Some(run),
+            Err(error) => {
+                log::warn!("Dropping a queued run from {}: {}", path.display(), error);
+                None
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::net::leaderboard::{Database, LeaderboardServer};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+    use std::thread::JoinHandle;
+
+    fn entry(name: &str, score: u32) -> ScoreEntry {
+        ScoreEntry { game: "snake".to_string(), mode: "classic".to_string(), name: name.to_string(), score, timestamp: 1_700_000_000 + score as u64, duration: 30.0 }
+    }
+
+    fn start_server(address: &str) -> (String, Arc<AtomicBool>, JoinHandle<()>) {
+        let mut server = LeaderboardServer::bind(address, Database::in_memory().unwrap()).unwrap();
+        let url = format!("http://{}", server.local_addr().unwrap());
+        let stop = Arc::new(AtomicBool::new(false));
+        let flag = Arc::clone(&stop);
+        (url, stop, thread::spawn(move || server.run(&flag)))
+    }
+
+    fn wait_for(sync: &mut ScoreSync, done: impl Fn(&ScoreSync) -> bool) {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        while !done(sync) {
+            assert!(Instant::now() < deadline, "the sync never got there");
+            thread::sleep(Duration::from_millis(20));
+            sync.poll();
+        }
+    }
+
+    #[test]
+    fn runs_reach_the_server_and_its_board_comes_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let (url, stop, server) = start_server("127.0.0.1:0");
+        let mut sync = ScoreSync::start(&url, dir.path().join(QUEUE_FILE)).unwrap();
+
+        sync.submit(entry("ala", 40));
+        sync.submit(entry("bob", 90));
+        sync.fetch("snake", "classic");
+        wait_for(&mut sync, |sync| sync.board("snake", "classic").is_some() && sync.is_online().is_some());
+        assert_eq!(sync.board("snake", "classic").unwrap(), &[entry("bob", 90), entry("ala", 40)]);
+        assert_eq!((sync.is_online(), sync.pending()), (Some(true), 0));
+
+        stop.store(true, Ordering::Relaxed);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn runs_made_offline_wait_in_the_queue_until_the_server_is_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let queue = dir.path().join(QUEUE_FILE);
+        // A port nobody listens on any more.
+        let address = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+
+        let mut sync = ScoreSync::start(&format!("http://{}", address), queue.clone()).unwrap();
+        sync.submit(entry("ala", 40));
+        wait_for(&mut sync, |sync| sync.is_online() == Some(false));
+        assert_eq!(sync.pending(), 1);
+        drop(sync);
+        assert_eq!(load_queue(&queue).len(), 1);
+
+        // The next start finds the queue and sends it first.
+        let (url, stop, server) = start_server(&address.to_string());
+        let mut sync = ScoreSync::start(&url, queue.clone()).unwrap();
+        assert_eq!(sync.pending(), 1);
+        sync.fetch("snake", "classic");
+        wait_for(&mut sync, |sync| sync.board("snake", "classic").is_some() && sync.is_online().is_some());
+        assert_eq!(sync.board("snake", "classic").unwrap(), &[entry("ala", 40)]);
+        assert_eq!(sync.pending(), 0);
+        assert!(load_queue(&queue).is_empty());
+
+        stop.store(true, Ordering::Relaxed);
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn one_refused_run_does_not_take_the_rest_of_the_batch_with_it() {
+        let dir = tempfile::tempdir().unwrap();
+        let (url, stop, server) = start_server("127.0.0.1:0");
+        let (updates, _) = mpsc::channel();
+        let mut worker = Worker::new(url.clone(), dir.path().join(QUEUE_FILE), updates);
+        let refused = ScoreEntry { game: "Not a game!".to_string(), ..entry("eve", 99) };
+        worker.queue = vec![Submission::new(entry("ala", 40)), Submission::new(refused), Submission::new(entry("bob", 90))];
+
+        assert!(worker.flush());
+        assert!(worker.queue.is_empty());
+        let response = http::request("GET", &format!("{}/api/top?game=snake&mode=classic", url), None, TIMEOUT).unwrap();
+        let board: Vec<ScoreEntry> = response.body.lines().filter_map(ScoreEntry::parse).collect();
+        assert_eq!(board, [entry("bob", 90), entry("ala", 40)]);
+
+        stop.store(true, Ordering::Relaxed);
+        server.join().unwrap();
+    }
+}
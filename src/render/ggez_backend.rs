@@ -0,0 +1,77 @@
+use crate::render::{RectStyle, Renderer};
+use crate::scenes::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::assets::Assets;
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Font, Mesh, PxScale, Rect, Text, TextFragment};
+use ggez::mint::Point2;
+use ggez::{Context, GameResult};
+
+/// Draws into the ggez window, with sprites from the loaded assets.
+pub struct GgezRenderer<'a> {
+    ctx: &'a mut Context,
+    assets: &'a mut Assets,
+}
+
+impl<'a> GgezRenderer<'a> {
+    pub fn new(ctx: &'a mut Context, assets: &'a mut Assets) -> Self {
+        GgezRenderer { ctx, assets }
+    }
+
+    /// Shifts everything drawn afterwards `x` pixels to the right, for sliding transitions.
+    pub fn set_offset(&mut self, x: f32) -> GameResult {
+        graphics::set_screen_coordinates(self.ctx, Rect::new(-x, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT))
+    }
+
+    pub fn present(self) -> GameResult {
+        graphics::present(self.ctx)
+    }
+}
+
+fn text(content: &str, font: Font, size: f32, color: Color) -> Text {
+    Text::new(
+        TextFragment::new(content)
+            .font(font)
+            .scale(PxScale::from(size))
+            .color(color),
+    )
+}
+
+impl Renderer for GgezRenderer<'_> {
+    fn clear(&mut self, color: Color) {
+        graphics::clear(self.ctx, color);
+    }
+
+    fn rect(&mut self, rect: Rect, color: Color, style: RectStyle) -> GameResult {
+        let mode = match style {
+            RectStyle::Filled => DrawMode::fill(),
+            RectStyle::Outline(width) => DrawMode::stroke(width),
+        };
+        let mesh = Mesh::new_rectangle(self.ctx, mode, rect, color)?;
+        graphics::draw(self.ctx, &mesh, DrawParam::default())
+    }
+
+    fn text(&mut self, content: &str, x: f32, y: f32, size: f32, color: Color) -> GameResult {
+        graphics::draw(self.ctx, &text(content, self.assets.font(), size, color), (Point2 { x, y },))
+    }
+
+    fn text_width(&mut self, content: &str, size: f32) -> f32 {
+        text(content, self.assets.font(), size, Color::WHITE).width(self.ctx)
+    }
+
+    fn sprite(&mut self, name: &str, frame: usize, dest: Rect, rotation: f32) -> GameResult<bool> {
+        let Some((image, source)) = self.assets.frame(self.ctx, name, frame) else {
+            return Ok(false);
+        };
+        let scale = [
+            dest.w / (source.w * image.width() as f32),
+            dest.h / (source.h * image.height() as f32),
+        ];
+        let param = DrawParam::default()
+            .src(source)
+            .dest([dest.x + dest.w / 2.0, dest.y + dest.h / 2.0])
+            .offset([0.5, 0.5])
+            .rotation(rotation)
+            .scale(scale);
+        graphics::draw(self.ctx, image, param)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,72 @@
+//! Everything on screen is drawn through `Renderer`, so games and scenes do not need a GPU to be
+//! drawn: the ggez backend puts it in the window, the recording backend keeps a list for tests.
+
+pub mod ggez_backend;
+pub mod recording;
+pub mod terminal;
+
+use ggez::graphics::{Color, Rect};
+use ggez::GameResult;
+
+pub use ggez_backend::GgezRenderer;
+pub use recording::{DrawCommand, RecordingRenderer};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RectStyle {
+    Filled,
+    /// Only the border, this many pixels wide.
+    Outline(f32),
+}
+
+pub trait Renderer {
+    fn clear(&mut self, color: Color);
+
+    fn rect(&mut self, rect: Rect, color: Color, style: RectStyle) -> GameResult;
+
+    /// Draws `content` with its top-left corner at (x, y), `size` pixels tall.
+    fn text(&mut self, content: &str, x: f32, y: f32, size: f32, color: Color) -> GameResult;
+
+    fn text_width(&mut self, content: &str, size: f32) -> f32;
+
+    /// Draws a frame of the named sprite stretched over `dest`, turned `rotation` radians clockwise
+    /// around its centre. Returns false without drawing anything if the sprite is not available,
+    /// so the caller can draw plain shapes instead.
+    fn sprite(&mut self, name: &str, frame: usize, dest: Rect, rotation: f32) -> GameResult<bool>;
+}
+
+/// Draws through another renderer, moved by (`x`, `y`), e.g. to shake the screen.
+pub struct Shifted<'a> {
+    pub inner: &'a mut dyn Renderer,
+    pub x: f32,
+    pub y: f32,
+}
+
+impl Shifted<'_> {
+    fn moved(&self, rect: Rect) -> Rect {
+        Rect::new(rect.x + self.x, rect.y + self.y, rect.w, rect.h)
+    }
+}
+
+impl Renderer for Shifted<'_> {
+    fn clear(&mut self, color: Color) {
+        self.inner.clear(color);
+    }
+
+    fn rect(&mut self, rect: Rect, color: Color, style: RectStyle) -> GameResult {
+        let rect = self.moved(rect);
+        self.inner.rect(rect, color, style)
+    }
+
+    fn text(&mut self, content: &str, x: f32, y: f32, size: f32, color: Color) -> GameResult {
+        self.inner.text(content, x + self.x, y + self.y, size, color)
+    }
+
+    fn text_width(&mut self, content: &str, size: f32) -> f32 {
+        self.inner.text_width(content, size)
+    }
+
+    fn sprite(&mut self, name: &str, frame: usize, dest: Rect, rotation: f32) -> GameResult<bool> {
+        let dest = self.moved(dest);
+        self.inner.sprite(name, frame, dest, rotation)
+    }
+}
@@ -0,0 +1,98 @@
+use crate::render::{RectStyle, Renderer};
+use ggez::graphics::{Color, Rect};
+use ggez::GameResult;
+use std::collections::HashSet;
+
+#[derive(Clone, PartialEq, Debug)]
+pub enum DrawCommand {
+    Clear(Color),
+    Rect { rect: Rect, color: Color, style: RectStyle },
+    Text { content: String, x: f32, y: f32, size: f32, color: Color },
+    Sprite { name: String, frame: usize, dest: Rect, rotation: f32 },
+}
+
+/// Keeps every draw call as a display list instead of drawing anything.
+#[derive(Default)]
+pub struct RecordingRenderer {
+    pub commands: Vec<DrawCommand>,
+    /// Sprites it pretends to have; any other sprite is reported missing.
+    pub sprites: HashSet<String>,
+}
+
+impl RecordingRenderer {
+    pub fn new() -> Self {
+        RecordingRenderer::default()
+    }
+
+    pub fn with_sprites(names: &[&str]) -> Self {
+        RecordingRenderer {
+            commands: Vec::new(),
+            sprites: names.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    /// Names of the sprites drawn, in order.
+    pub fn sprite_names(&self) -> Vec<&str> {
+        self.commands
+            .iter()
+            .filter_map(|command| match command {
+                DrawCommand::Sprite { name, .. } => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn texts(&self) -> Vec<&str> {
+        self.commands
+            .iter()
+            .filter_map(|command| match command {
+                DrawCommand::Text { content, .. } => Some(content.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn has_text(&self, wanted: &str) -> bool {
+        self.texts().iter().any(|content| content.contains(wanted))
+    }
+
+    /// Rectangles drawn in exactly this colour.
+    pub fn rects(&self, wanted: Color) -> Vec<Rect> {
+        self.commands
+            .iter()
+            .filter_map(|command| match command {
+                DrawCommand::Rect { rect, color, .. } if *color == wanted => Some(*rect),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+impl Renderer for RecordingRenderer {
+    fn clear(&mut self, color: Color) {
+        self.commands.push(DrawCommand::Clear(color));
+    }
+
+    fn rect(&mut self, rect: Rect, color: Color, style: RectStyle) -> GameResult {
+        self.commands.push(DrawCommand::Rect { rect, color, style });
+        Ok(())
+    }
+
+    fn text(&mut self, content: &str, x: f32, y: f32, size: f32, color: Color) -> GameResult {
+        self.commands.push(DrawCommand::Text { content: content.to_string(), x, y, size, color });
+        Ok(())
+    }
+
+    /// A rough estimate; the default font is about half as wide as it is tall.
+    fn text_width(&mut self, content: &str, size: f32) -> f32 {
+        content.chars().count() as f32 * size * 0.5
+    }
+
+    fn sprite(&mut self, name: &str, frame: usize, dest: Rect, rotation: f32) -> GameResult<bool> {
+        if !self.sprites.contains(name) {
+            return Ok(false);
+        }
+        self.commands.push(DrawCommand::Sprite { name: name.to_string(), frame, dest, rotation });
+        Ok(true)
+    }
+}
@@ -0,0 +1,212 @@
+use crate::render::{RectStyle, Renderer};
+use crate::scenes::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crossterm::cursor::MoveTo;
+use crossterm::style::{self, Print, SetBackgroundColor, SetForegroundColor};
+use crossterm::QueueableCommand;
+use ggez::graphics::{Color, Rect};
+use ggez::GameResult;
+use std::io::{self, Write};
+
+/// Each terminal cell shows two stacked pixels through the upper half block, so a 10x10 pixel
+/// keeps the 800x600 screen at 80x30 cells and the snake's 20px grid at two cells per square.
+pub const PIXEL_SIZE: f32 = 10.0;
+pub const COLUMNS: u16 = (SCREEN_WIDTH / PIXEL_SIZE) as u16;
+pub const ROWS: u16 = (SCREEN_HEIGHT / PIXEL_SIZE / 2.0) as u16;
+
+type Rgb = [u8; 3];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Cell {
+    pub top: Rgb,
+    pub bottom: Rgb,
+    pub glyph: Option<(char, Rgb)>,
+}
+
+const BLANK: Cell = Cell { top: [0, 0, 0], bottom: [0, 0, 0], glyph: None };
+
+/// Rasterises draw calls into terminal cells and writes only the cells that changed since the
+/// previous frame, which keeps it usable over SSH.
+pub struct TerminalRenderer {
+    cells: Vec<Cell>,
+    shown: Vec<Option<Cell>>,
+}
+
+impl Default for TerminalRenderer {
+    fn default() -> Self {
+        TerminalRenderer::new()
+    }
+}
+
+impl TerminalRenderer {
+    pub fn new() -> Self {
+        let count = COLUMNS as usize * ROWS as usize;
+        TerminalRenderer { cells: vec![BLANK; count], shown: vec![None; count] }
+    }
+
+    pub fn cell(&self, column: u16, row: u16) -> Cell {
+        self.cells[row as usize * COLUMNS as usize + column as usize]
+    }
+
+    /// Forgets what is on the terminal, so the next `flush` redraws every cell.
+    pub fn invalidate(&mut self) {
+        self.shown.iter_mut().for_each(|cell| *cell = None);
+    }
+
+    pub fn flush(&mut self, out: &mut impl Write) -> io::Result<()> {
+        for row in 0..ROWS {
+            for column in 0..COLUMNS {
+                let index = row as usize * COLUMNS as usize + column as usize;
+                let cell = self.cells[index];
+                if self.shown[index] == Some(cell) {
+                    continue;
+                }
+                self.shown[index] = Some(cell);
+
+                let (symbol, foreground, background) = match cell.glyph {
+                    Some((symbol, color)) => (symbol, color, mix(cell.top, cell.bottom, 0.5)),
+                    None if cell.top == cell.bottom => (' ', cell.top, cell.bottom),
+                    None => ('▀', cell.top, cell.bottom),
+                };
+                out.queue(MoveTo(column, row))?
+                    .queue(SetForegroundColor(terminal_color(foreground)))?
+                    .queue(SetBackgroundColor(terminal_color(background)))?
+                    .queue(Print(symbol))?;
+            }
+        }
+        out.queue(style::ResetColor)?;
+        out.flush()
+    }
+
+    /// Calls `paint` with every pixel whose centre lies inside `rect`, and whether it is on the rect's edge.
+    fn for_each_pixel(rect: Rect, mut paint: impl FnMut(u16, u16, bool)) {
+        let inside = |x: i32, y: i32| {
+            let (cx, cy) = ((x as f32 + 0.5) * PIXEL_SIZE, (y as f32 + 0.5) * PIXEL_SIZE);
+            cx >= rect.x && cx < rect.x + rect.w && cy >= rect.y && cy < rect.y + rect.h
+        };
+        let first_x = ((rect.x / PIXEL_SIZE).floor() as i32).max(0);
+        let last_x = (((rect.x + rect.w) / PIXEL_SIZE).ceil() as i32).min(COLUMNS as i32);
+        let first_y = ((rect.y / PIXEL_SIZE).floor() as i32).max(0);
+        let last_y = (((rect.y + rect.h) / PIXEL_SIZE).ceil() as i32).min(ROWS as i32 * 2);
+        for y in first_y..last_y {
+            for x in first_x..last_x {
+                if inside(x, y) {
+                    let edge = !(inside(x - 1, y) && inside(x + 1, y) && inside(x, y - 1) && inside(x, y + 1));
+                    paint(x as u16, y as u16, edge);
+                }
+            }
+        }
+    }
+}
+
+fn rgb(color: Color) -> Rgb {
+    let (r, g, b) = color.to_rgb();
+    [r, g, b]
+}
+
+fn mix(from: Rgb, to: Rgb, amount: f32) -> Rgb {
+    let channel = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * amount).round() as u8;
+    [channel(from[0], to[0]), channel(from[1], to[1]), channel(from[2], to[2])]
+}
+
+fn terminal_color(color: Rgb) -> style::Color {
+    style::Color::Rgb { r: color[0], g: color[1], b: color[2] }
+}
+
+impl Renderer for TerminalRenderer {
+    fn clear(&mut self, color: Color) {
+        let color = rgb(color);
+        self.cells.iter_mut().for_each(|cell| *cell = Cell { top: color, bottom: color, glyph: None });
+    }
+
+    fn rect(&mut self, rect: Rect, color: Color, style: RectStyle) -> GameResult {
+        let (paint, alpha) = (rgb(color), color.a.clamp(0.0, 1.0));
+        let cells = &mut self.cells;
+        TerminalRenderer::for_each_pixel(rect, |x, y, edge| {
+            if matches!(style, RectStyle::Outline(_)) && !edge {
+                return;
+            }
+            let cell = &mut cells[(y / 2) as usize * COLUMNS as usize + x as usize];
+            let half = if y % 2 == 0 { &mut cell.top } else { &mut cell.bottom };
+            *half = mix(*half, paint, alpha);
+            match &mut cell.glyph {
+                // Anything opaque drawn over text hides it; translucent overlays only dim it.
+                Some(_) if alpha >= 1.0 => cell.glyph = None,
+                Some((_, glyph_color)) => *glyph_color = mix(*glyph_color, paint, alpha / 2.0),
+                None => {}
+            }
+        });
+        Ok(())
+    }
+
+    fn text(&mut self, content: &str, x: f32, y: f32, _size: f32, color: Color) -> GameResult {
+        let row = (y / (PIXEL_SIZE * 2.0)).round() as i32;
+        if !(0..ROWS as i32).contains(&row) {
+            return Ok(());
+        }
+        let first_column = (x / PIXEL_SIZE).round() as i32;
+        for (offset, symbol) in content.chars().enumerate() {
+            let column = first_column + offset as i32;
+            if !(0..COLUMNS as i32).contains(&column) {
+                continue;
+            }
+            let cell = &mut self.cells[row as usize * COLUMNS as usize + column as usize];
+            let background = mix(cell.top, cell.bottom, 0.5);
+            cell.glyph = Some((symbol, mix(background, rgb(color), color.a.clamp(0.0, 1.0))));
+        }
+        Ok(())
+    }
+
+    /// Text is always one character per cell, whatever size it asks for.
+    fn text_width(&mut self, content: &str, _size: f32) -> f32 {
+        content.chars().count() as f32 * PIXEL_SIZE
+    }
+
+    /// The terminal cannot show images, so games always fall back to shapes.
+    fn sprite(&mut self, _name: &str, _frame: usize, _dest: Rect, _rotation: f32) -> GameResult<bool> {
+        Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GREEN: Rgb = [0, 255, 0];
+    const BLACK: Rgb = [0, 0, 0];
+
+    #[test]
+    fn a_snake_square_covers_two_cells() {
+        let mut renderer = TerminalRenderer::new();
+        renderer.clear(Color::BLACK);
+        renderer.rect(Rect::new(100.0, 100.0, 20.0, 20.0), Color::GREEN, RectStyle::Filled).unwrap();
+
+        for column in [10, 11] {
+            assert_eq!(renderer.cell(column, 5), Cell { top: GREEN, bottom: GREEN, glyph: None });
+        }
+        assert_eq!(renderer.cell(9, 5).top, BLACK);
+        assert_eq!(renderer.cell(10, 4).bottom, BLACK);
+    }
+
+    #[test]
+    fn text_lands_on_the_nearest_cell_and_opaque_rects_hide_it() {
+        let mut renderer = TerminalRenderer::new();
+        renderer.text("GAME OVER", 280.0, 100.0, 50.0, Color::WHITE).unwrap();
+        assert_eq!(renderer.cell(28, 5).glyph, Some(('G', [255, 255, 255])));
+        assert_eq!(renderer.cell(36, 5).glyph, Some(('R', [255, 255, 255])));
+
+        renderer.rect(Rect::new(280.0, 100.0, 10.0, 20.0), Color::RED, RectStyle::Filled).unwrap();
+        assert_eq!(renderer.cell(28, 5).glyph, None);
+        assert!(renderer.cell(29, 5).glyph.is_some());
+    }
+
+    #[test]
+    fn unchanged_cells_are_not_written_again() {
+        let mut renderer = TerminalRenderer::new();
+        let mut first = Vec::new();
+        renderer.flush(&mut first).unwrap();
+        let mut second = Vec::new();
+        renderer.rect(Rect::new(0.0, 0.0, 10.0, 10.0), Color::GREEN, RectStyle::Filled).unwrap();
+        renderer.flush(&mut second).unwrap();
+        assert!(second.len() * 100 < first.len());
+    }
+}
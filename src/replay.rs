@@ -0,0 +1,85 @@
+use crate::games::{GameInput, RunConfig};
+use crate::storage;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+const HEADER: &str = "# gierki-replay v1";
+
+/// A run reduced to its config and the inputs applied at each tick; playing them back on the same
+/// seed reproduces the run exactly.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Replay {
+    pub config: RunConfig,
+    pub inputs: Vec<(u32, GameInput)>,
+    pub ticks: u32,
+}
+
+impl Replay {
+    pub fn new(config: RunConfig) -> Self {
+        Replay { config, inputs: Vec::new(), ticks: 0 }
+    }
+
+    pub fn record(&mut self, tick: u32, input: GameInput) {
+        self.inputs.push((tick, input));
+    }
+
+    pub fn file_name(game: &str, mode: &str) -> String {
+        format!("last-{}-{}.replay", game, mode)
+    }
+
+    pub fn load(path: &Path) -> Result<Replay, String> {
+        let content = fs::read_to_string(path).map_err(|error| format!("{}: {}", path.display(), error))?;
+        Replay::parse(&content).map_err(|error| format!("{}: {}", path.display(), error))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        storage::write_atomic(path, &self.to_text())
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "{}\ngame {}\nmode {}\ndifficulty {}\nseed {}\nticks {}\n",
+            HEADER, self.config.game, self.config.mode, self.config.difficulty, self.config.seed, self.ticks
+        );
+        for (tick, input) in &self.inputs {
+            text.push_str(&format!("input {} {}\n", tick, input.name()));
+        }
+        text
+    }
+
+    pub fn parse(text: &str) -> Result<Replay, String> {
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err("not a replay file".to_string());
+        }
+
+        let mut replay = Replay::new(RunConfig::new("", ""));
+        for (number, line) in lines.enumerate() {
+            let fail = |message: &str| format!("line {}: {}", number + 2, message);
+            let (key, value) = line.split_once(' ').ok_or_else(|| fail("expected a key and a value"))?;
+            match key {
+                "game" => replay.config.game = value.to_string(),
+                "mode" => replay.config.mode = value.to_string(),
+                "difficulty" => replay.config.difficulty = value.parse().map_err(|error: String| fail(&error))?,
+                "seed" => replay.config.seed = value.parse().map_err(|_| fail("bad seed"))?,
+                "ticks" => replay.ticks = value.parse().map_err(|_| fail("bad tick count"))?,
+                "input" => {
+                    let (tick, input) = value.split_once(' ').ok_or_else(|| fail("expected a tick and an input"))?;
+                    let tick = tick.parse().map_err(|_| fail("bad tick"))?;
+                    let input = input.parse().map_err(|error: String| fail(&error))?;
+                    replay.record(tick, input);
+                }
+                _ => return Err(fail(&format!("unknown key '{}'", key))),
+            }
+        }
+
+        if replay.config.game.is_empty() {
+            return Err("replay does not say which game it is for".to_string());
+        }
+        Ok(replay)
+    }
+}
@@ -0,0 +1,47 @@
+use crate::render::{RectStyle, Renderer};
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared};
+use ggez::event::KeyCode;
+use ggez::graphics::{Color, Rect};
+use ggez::{Context, GameResult};
+
+/// Yes/no dialog drawn over whatever is below it. Y or Enter runs `on_yes`, N or ESC just closes it.
+pub struct ConfirmScene {
+    message: String,
+    on_yes: Option<SceneCommand>,
+}
+
+impl ConfirmScene {
+    pub fn new(message: &str, on_yes: SceneCommand) -> Self {
+        ConfirmScene {
+            message: message.to_string(),
+            on_yes: Some(on_yes),
+        }
+    }
+}
+
+impl Scene for ConfirmScene {
+    fn update(&mut self, _ctx: &mut Context, _shared: &mut Shared) -> GameResult<SceneCommand> {
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let theme = &shared.theme;
+        fill_screen(renderer, Color { a: theme.overlay.a * 0.6, ..theme.overlay })?;
+        renderer.rect(Rect::new(200.0, 220.0, 400.0, 140.0), theme.background, RectStyle::Filled)?;
+        renderer.rect(Rect::new(200.0, 220.0, 400.0, 140.0), theme.text, RectStyle::Outline(2.0))?;
+        renderer.text(&self.message, 230.0, 245.0, 30.0, theme.text)?;
+        renderer.text(shared.strings.get("confirm.help"), 230.0, 310.0, 20.0, theme.text)
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, _shared: &mut Shared, keycode: KeyCode) -> SceneCommand {
+        match keycode {
+            KeyCode::Y | KeyCode::Return => self.on_yes.take().unwrap_or(SceneCommand::Pop),
+            KeyCode::N => SceneCommand::Pop,
+            _ => SceneCommand::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,403 @@
+use crate::games::course::{self, Course, CoursePipe, COURSES_DIR, MAX_PIPES, PIPE_WIDTH};
+use crate::games::flappy_bird::FlappyBirdGame;
+use crate::games::{GameInput, Minigame, TICKS_PER_SECOND};
+use crate::i18n::Strings;
+use crate::render::{RectStyle, Renderer};
+use crate::scenes::confirm::ConfirmScene;
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared, Transition};
+use crate::scores;
+use ggez::event::{KeyCode, MouseButton};
+use ggez::graphics::Rect;
+use ggez::input::mouse;
+use ggez::{timer, Context, GameResult};
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// The course at half size: 760 by 300 pixels for a 1520 by 600 stretch of it.
+const PREVIEW: Rect = Rect { x: 20.0, y: 60.0, w: 760.0, h: 300.0 };
+const SCALE: f32 = 0.5;
+const ROW_Y: f32 = 380.0;
+const ROW_SPACING: f32 = 32.0;
+const STEP_BUTTON: f32 = 28.0;
+const MINUS_X: f32 = 330.0;
+const PLUS_X: f32 = 370.0;
+const PANEL_X: f32 = 600.0;
+const BUTTON_WIDTH: f32 = 180.0;
+const BUTTON_HEIGHT: f32 = 34.0;
+const BUTTON_SPACING: f32 = 40.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Field {
+    Spacing,
+    GapTop,
+    GapSize,
+    Range,
+    Speed,
+}
+
+const FIELDS: [Field; 5] = [Field::Spacing, Field::GapTop, Field::GapSize, Field::Range, Field::Speed];
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Action {
+    Add,
+    Remove,
+    TestPlay,
+    Save,
+}
+
+const ACTIONS: [Action; 4] = [Action::Add, Action::Remove, Action::TestPlay, Action::Save];
+
+impl Field {
+    /// How much one click of - or + changes it.
+    fn step(self) -> f32 {
+        match self {
+            Field::Spacing => 20.0,
+            Field::GapTop | Field::GapSize | Field::Range => 10.0,
+            Field::Speed => 0.5,
+        }
+    }
+
+    fn value(self, mut pipe: CoursePipe) -> f32 {
+        *self.value_mut(&mut pipe)
+    }
+
+    fn value_mut(self, pipe: &mut CoursePipe) -> &mut f32 {
+        match self {
+            Field::Spacing => &mut pipe.spacing,
+            Field::GapTop => &mut pipe.gap_top,
+            Field::GapSize => &mut pipe.gap_size,
+            Field::Range => &mut pipe.range,
+            Field::Speed => &mut pipe.speed,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            Field::Spacing => "course_editor.spacing",
+            Field::GapTop => "course_editor.gap_top",
+            Field::GapSize => "course_editor.gap_size",
+            Field::Range => "course_editor.range",
+            Field::Speed => "course_editor.speed",
+        }
+    }
+}
+
+/// Lays out a Flappy Bird course a pipe at a time: where it stands, where its gap is and how it
+/// slides. Every change is checked by `CoursePipe::check`, so the course is always playable.
+pub struct CourseEditorScene {
+    course: Course,
+    /// The player's file the course came from; built-in and new courses are saved as new files.
+    path: Option<PathBuf>,
+    selected: usize,
+    changed: bool,
+    /// Set after a save, so the course list can pick it up.
+    saved: Rc<Cell<bool>>,
+    /// A test run of the course, played in place of the editor until ESC.
+    test: Option<FlappyBirdGame>,
+}
+
+impl CourseEditorScene {
+    pub fn new(course: Course, path: Option<PathBuf>, saved: Rc<Cell<bool>>) -> Self {
+        CourseEditorScene { course, path, selected: 0, changed: false, saved, test: None }
+    }
+
+    fn row_y(index: usize) -> f32 {
+        ROW_Y + index as f32 * ROW_SPACING
+    }
+
+    fn step_rect(index: usize, x: f32) -> Rect {
+        Rect::new(x, CourseEditorScene::row_y(index), STEP_BUTTON, STEP_BUTTON)
+    }
+
+    fn button_rect(index: usize) -> Rect {
+        Rect::new(PANEL_X, ROW_Y + index as f32 * BUTTON_SPACING, BUTTON_WIDTH, BUTTON_HEIGHT)
+    }
+
+    /// Where a point of the course is drawn in the preview, which keeps the selected pipe in the middle.
+    fn preview_x(&self, x: f32) -> f32 {
+        let centre = self.course.pipe_x(self.selected) + PIPE_WIDTH / 2.0;
+        PREVIEW.x + PREVIEW.w / 2.0 + (x - centre) * SCALE
+    }
+
+    /// The pipe closest to a point in the preview.
+    fn pipe_near(&self, x: f32) -> usize {
+        let distance = |index: usize| (self.preview_x(self.course.pipe_x(index) + PIPE_WIDTH / 2.0) - x).abs();
+        (0..self.course.pipes.len()).min_by(|&a, &b| distance(a).total_cmp(&distance(b))).unwrap_or(0)
+    }
+
+    /// Changes the selected pipe, unless that would break it.
+    fn edit(&mut self, change: impl FnOnce(&mut CoursePipe)) {
+        let pipe = &mut self.course.pipes[self.selected];
+        let mut edited = *pipe;
+        change(&mut edited);
+        if edited != *pipe && edited.check().is_ok() {
+            *pipe = edited;
+            self.changed = true;
+        }
+    }
+
+    fn adjust(&mut self, field: Field, steps: f32) {
+        self.edit(|pipe| *field.value_mut(pipe) += field.step() * steps);
+    }
+
+    /// Centres the selected pipe's gap on a point in the preview, as far as it fits.
+    fn move_gap(&mut self, y: f32) {
+        self.edit(|pipe| {
+            let lowest = course::FIELD_HEIGHT - pipe.gap_size - pipe.range;
+            pipe.gap_top = ((y - PREVIEW.y) / SCALE - pipe.gap_size / 2.0).round().clamp(0.0, lowest);
+        });
+    }
+
+    /// The first click picks a pipe, the next ones move its gap.
+    fn click_preview(&mut self, x: f32, y: f32) {
+        match self.pipe_near(x) {
+            index if index == self.selected => self.move_gap(y),
+            index => self.select(index),
+        }
+    }
+
+    fn select(&mut self, index: usize) {
+        self.selected = index.min(self.course.pipes.len() - 1);
+    }
+
+    fn run(&mut self, action: Action, shared: &mut Shared) {
+        match action {
+            Action::Add if self.course.pipes.len() < MAX_PIPES => {
+                self.course.pipes.insert(self.selected + 1, self.course.pipes[self.selected]);
+                self.selected += 1;
+                self.changed = true;
+            }
+            Action::Add => {}
+            Action::Remove if self.course.pipes.len() > 1 => {
+                self.course.pipes.remove(self.selected);
+                self.select(self.selected);
+                self.changed = true;
+            }
+            Action::Remove => {}
+            Action::TestPlay => self.test = Some(FlappyBirdGame::with_course(self.course.clone())),
+            Action::Save => self.save(shared),
+        }
+    }
+
+    fn save(&mut self, shared: &mut Shared) {
+        let path = self.path.clone().unwrap_or_else(|| course::free_path(&shared.storage.config_path(COURSES_DIR)));
+        match self.course.save(&path) {
+            Ok(()) => {
+                let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+                shared.toasts.push(shared.strings.format("course_editor.saved", &[("course", &course::title(&name))]));
+                self.path = Some(path);
+                self.changed = false;
+                self.saved.set(true);
+            }
+            Err(error) => {
+                log::error!("Could not save the course to {}: {}", path.display(), error);
+                shared.toasts.push(shared.strings.format("error.course", &[("error", &error)]));
+            }
+        }
+    }
+
+    fn label(action: Action, strings: &Strings) -> &str {
+        strings.get(match action {
+            Action::Add => "course_editor.add",
+            Action::Remove => "course_editor.remove",
+            Action::TestPlay => "course_editor.test",
+            Action::Save => "course_editor.save",
+        })
+    }
+
+    fn draw_preview(&self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let theme = &shared.theme;
+        renderer.rect(PREVIEW, theme.sky, RectStyle::Filled)?;
+        let visible = |x: f32, w: f32| x >= PREVIEW.x && x + w <= PREVIEW.right();
+        for (index, pipe) in self.course.pipes.iter().enumerate() {
+            let x = self.preview_x(self.course.pipe_x(index));
+            let width = PIPE_WIDTH * SCALE;
+            if !visible(x, width) {
+                continue;
+            }
+            let color = if index == self.selected { theme.button_selected } else { theme.pipe };
+            let bottom = pipe.gap_top + pipe.gap_size;
+            renderer.rect(Rect::new(x, PREVIEW.y, width, pipe.gap_top * SCALE), color, RectStyle::Filled)?;
+            renderer.rect(Rect::new(x, PREVIEW.y + bottom * SCALE, width, PREVIEW.h - bottom * SCALE), color, RectStyle::Filled)?;
+            if pipe.is_moving() {
+                // Where the gap slides down to.
+                let lowest = Rect::new(x, PREVIEW.y + (pipe.gap_top + pipe.range) * SCALE, width, pipe.gap_size * SCALE);
+                renderer.rect(lowest, theme.text, RectStyle::Outline(1.0))?;
+            }
+        }
+        let finish = self.preview_x(self.course.finish_x());
+        if visible(finish, 4.0) {
+            renderer.rect(Rect::new(finish, PREVIEW.y, 4.0, PREVIEW.h), theme.game_text, RectStyle::Filled)?;
+        }
+        renderer.rect(PREVIEW, theme.text, RectStyle::Outline(1.0))
+    }
+
+    fn draw_test(game: &FlappyBirdGame, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let (theme, strings) = (&shared.theme, &shared.strings);
+        fill_screen(renderer, theme.sky)?;
+        game.draw(renderer, theme)?;
+        let help = if let Some(ticks) = game.finish_ticks {
+            strings.format("course_editor.test_finished", &[("time", &scores::format_ticks(ticks))])
+        } else if game.is_over() {
+            strings.format("course_editor.test_over", &[("score", &game.score.to_string())])
+        } else {
+            strings.get("course_editor.test_help").to_string()
+        };
+        renderer.text(&help, 10.0, 575.0, 18.0, theme.game_text)
+    }
+}
+
+impl Scene for CourseEditorScene {
+    fn update(&mut self, ctx: &mut Context, _shared: &mut Shared) -> GameResult<SceneCommand> {
+        if let Some(game) = &mut self.test {
+            while timer::check_update_time(ctx, TICKS_PER_SECOND) {
+                game.update();
+            }
+        }
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        if let Some(game) = &self.test {
+            return CourseEditorScene::draw_test(game, renderer, shared);
+        }
+        let (theme, strings) = (&shared.theme, &shared.strings);
+        fill_screen(renderer, theme.background)?;
+        renderer.text(strings.get("course_editor.title"), PREVIEW.x, 15.0, 36.0, theme.text)?;
+        let file = match &self.path {
+            Some(path) => {
+                let name = path.file_stem().map(|stem| stem.to_string_lossy().into_owned()).unwrap_or_default();
+                format!("{}{}", course::title(&name), if self.changed { " *" } else { "" })
+            }
+            None => strings.get("course_editor.unsaved").to_string(),
+        };
+        renderer.text(&file, PANEL_X, 25.0, 18.0, theme.text)?;
+        self.draw_preview(renderer, shared)?;
+
+        let pipe = &self.course.pipes[self.selected];
+        let heading = strings.format("course_editor.pipe", &[("number", &(self.selected + 1)), ("count", &self.course.pipes.len())]);
+        renderer.text(&heading, PREVIEW.x, ROW_Y - 2.0, 18.0, theme.text)?;
+        for (index, field) in FIELDS.iter().enumerate().map(|(index, field)| (index + 1, *field)) {
+            renderer.text(&format!("{}: {}", strings.get(field.label()), field.value(*pipe)), PREVIEW.x, CourseEditorScene::row_y(index) + 4.0, 18.0, theme.text)?;
+            for (x, sign) in [(MINUS_X, "-"), (PLUS_X, "+")] {
+                let rect = CourseEditorScene::step_rect(index, x);
+                renderer.rect(rect, theme.button, RectStyle::Filled)?;
+                renderer.text(sign, rect.x + 9.0, rect.y + 4.0, 20.0, theme.text)?;
+            }
+        }
+
+        for (index, &action) in ACTIONS.iter().enumerate() {
+            let rect = CourseEditorScene::button_rect(index);
+            renderer.rect(rect, theme.button, RectStyle::Filled)?;
+            renderer.text(CourseEditorScene::label(action, strings), rect.x + 10.0, rect.y + 8.0, 18.0, theme.text)?;
+        }
+        renderer.text(strings.get("course_editor.help"), PREVIEW.x, 575.0, 16.0, theme.text)
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, shared: &mut Shared, keycode: KeyCode) -> SceneCommand {
+        if let Some(game) = &mut self.test {
+            match (keycode, GameInput::from_keycode(keycode)) {
+                (KeyCode::R, _) if game.is_over() => self.run(Action::TestPlay, shared),
+                (_, Some(input)) => game.input(input),
+                _ => {}
+            }
+            return SceneCommand::None;
+        }
+        match keycode {
+            KeyCode::Left => self.select(self.selected.saturating_sub(1)),
+            KeyCode::Right => self.select(self.selected + 1),
+            KeyCode::Up => self.adjust(Field::GapTop, -1.0),
+            KeyCode::Down => self.adjust(Field::GapTop, 1.0),
+            KeyCode::Insert | KeyCode::A => self.run(Action::Add, shared),
+            KeyCode::Delete => self.run(Action::Remove, shared),
+            KeyCode::T => self.run(Action::TestPlay, shared),
+            _ => {}
+        }
+        SceneCommand::None
+    }
+
+    fn mouse_down(&mut self, _ctx: &mut Context, shared: &mut Shared, button: MouseButton, x: f32, y: f32) -> SceneCommand {
+        if self.test.is_some() || button != MouseButton::Left {
+            return SceneCommand::None;
+        }
+        if PREVIEW.contains([x, y]) {
+            self.click_preview(x, y);
+        }
+        for (index, field) in FIELDS.iter().enumerate().map(|(index, field)| (index + 1, *field)) {
+            if CourseEditorScene::step_rect(index, MINUS_X).contains([x, y]) {
+                self.adjust(field, -1.0);
+            } else if CourseEditorScene::step_rect(index, PLUS_X).contains([x, y]) {
+                self.adjust(field, 1.0);
+            }
+        }
+        if let Some(index) = (0..ACTIONS.len()).find(|&index| CourseEditorScene::button_rect(index).contains([x, y])) {
+            self.run(ACTIONS[index], shared);
+        }
+        SceneCommand::None
+    }
+
+    /// Dragging over the selected pipe moves its gap along.
+    fn mouse_motion(&mut self, ctx: &mut Context, _shared: &mut Shared, x: f32, y: f32) {
+        if self.test.is_none() && mouse::button_pressed(ctx, MouseButton::Left) && PREVIEW.contains([x, y]) && self.pipe_near(x) == self.selected {
+            self.move_gap(y);
+        }
+    }
+
+    /// ESC leaves a test run first, and asks before dropping unsaved changes.
+    fn escape(&mut self, _ctx: &mut Context, shared: &mut Shared) -> SceneCommand {
+        if self.test.take().is_some() {
+            return SceneCommand::None;
+        }
+        if self.changed {
+            return SceneCommand::Push(Box::new(ConfirmScene::new(shared.strings.get("course_editor.leave"), SceneCommand::PopN(2))));
+        }
+        SceneCommand::Pop
+    }
+
+    fn transition(&self) -> Transition {
+        Transition::Slide(0.25)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn editor() -> CourseEditorScene {
+        CourseEditorScene::new(Course::new(), None, Rc::new(Cell::new(false)))
+    }
+
+    #[test]
+    fn edits_that_would_break_the_pipe_are_refused() {
+        let mut editor = editor();
+        editor.adjust(Field::GapSize, 1.0);
+        assert_eq!(editor.course.pipes[0].gap_size, 210.0);
+        assert!(editor.changed);
+
+        // 200 from the top and 210 high: the gap can slide down 190 more and no further.
+        for _ in 0..30 {
+            editor.adjust(Field::Range, 1.0);
+        }
+        assert_eq!(editor.course.pipes[0].range, 190.0);
+        editor.adjust(Field::GapTop, 1.0);
+        assert_eq!(editor.course.pipes[0].gap_top, 200.0);
+        for _ in 0..30 {
+            editor.adjust(Field::Spacing, -1.0);
+        }
+        assert_eq!(editor.course.pipes[0].spacing, 120.0);
+    }
+
+    #[test]
+    fn clicks_pick_the_nearest_pipe_then_move_its_gap() {
+        let mut editor = editor();
+        let second = editor.preview_x(editor.course.pipe_x(1));
+        editor.click_preview(second + 2.0, PREVIEW.y + 10.0);
+        // The picked pipe moves to the middle of the preview.
+        editor.click_preview(PREVIEW.x + PREVIEW.w / 2.0, PREVIEW.y + 100.0);
+        assert_eq!(editor.selected, 1);
+        // A gap centred 200 pixels down, so its top is 100 above that.
+        assert_eq!(editor.course.pipes[1].gap_top, 100.0);
+        assert_eq!(editor.course.pipes[0].gap_top, 200.0);
+    }
+}
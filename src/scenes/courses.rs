@@ -0,0 +1,144 @@
+use crate::games::course::{self, Course, CourseInfo};
+use crate::games::RunConfig;
+use crate::render::{RectStyle, Renderer};
+use crate::scenes::course_editor::CourseEditorScene;
+use crate::scenes::play::PlayScene;
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared, Transition};
+use crate::scores;
+use ggez::event::{KeyCode, MouseButton};
+use ggez::graphics::Rect;
+use ggez::{Context, GameResult};
+use std::cell::Cell;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const GAME: &str = "flappy_bird";
+const FIRST_ROW_Y: f32 = 110.0;
+const ROW_HEIGHT: f32 = 36.0;
+const VISIBLE_ROWS: usize = 12;
+
+/// The Courses mode's list: the built-in courses and the player's, each with its best time.
+/// A course is played from here, or opened in the editor.
+pub struct CoursesScene {
+    courses: Vec<CourseInfo>,
+    selected: usize,
+    /// Set by the editor after a save, so the list is read again.
+    saved: Rc<Cell<bool>>,
+}
+
+impl CoursesScene {
+    pub fn new() -> Self {
+        CoursesScene { courses: Vec::new(), selected: 0, saved: Rc::new(Cell::new(false)) }
+    }
+
+    fn reload(&mut self, shared: &mut Shared) {
+        let (courses, notices) = course::all();
+        for notice in &notices {
+            shared.toasts.push(shared.strings.notice(notice));
+        }
+        self.courses = courses;
+        self.selected = self.selected.min(self.courses.len().saturating_sub(1));
+    }
+
+    /// The rows that fit on the screen, scrolled so the selected one is among them.
+    fn first_visible(&self) -> usize {
+        (self.selected + 1).saturating_sub(VISIBLE_ROWS)
+    }
+
+    fn shown(&self) -> Range<usize> {
+        self.first_visible()..(self.first_visible() + VISIBLE_ROWS).min(self.courses.len())
+    }
+
+    fn row_rect(&self, index: usize) -> Rect {
+        let row = index.saturating_sub(self.first_visible());
+        Rect::new(60.0, FIRST_ROW_Y + row as f32 * ROW_HEIGHT, 680.0, ROW_HEIGHT - 4.0)
+    }
+
+    fn play(&self) -> SceneCommand {
+        match self.courses.get(self.selected) {
+            Some(info) => SceneCommand::Push(Box::new(PlayScene::new(RunConfig::new(GAME, &course::mode(&info.id))))),
+            None => SceneCommand::None,
+        }
+    }
+
+    fn edit(&self, course: Course, path: Option<PathBuf>) -> SceneCommand {
+        SceneCommand::Push(Box::new(CourseEditorScene::new(course, path, Rc::clone(&self.saved))))
+    }
+}
+
+impl Default for CoursesScene {
+    fn default() -> Self {
+        CoursesScene::new()
+    }
+}
+
+impl Scene for CoursesScene {
+    fn update(&mut self, _ctx: &mut Context, shared: &mut Shared) -> GameResult<SceneCommand> {
+        if self.saved.replace(false) {
+            self.reload(shared);
+        }
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let (theme, strings) = (&shared.theme, &shared.strings);
+        fill_screen(renderer, theme.background)?;
+        renderer.text(strings.get("courses.title"), 60.0, 40.0, 40.0, theme.text)?;
+
+        let shown = self.shown();
+        for (index, info) in self.courses.iter().enumerate().take(shown.end).skip(shown.start) {
+            let rect = self.row_rect(index);
+            let color = if index == self.selected { theme.button_selected } else { theme.button };
+            renderer.rect(rect, color, RectStyle::Filled)?;
+            let mut title = course::title(&info.id);
+            if info.path.is_none() {
+                title = strings.format("courses.built_in", &[("course", &title)]);
+            }
+            renderer.text(&title, rect.x + 15.0, rect.y + 6.0, 20.0, theme.text)?;
+            let best = match shared.scores.board(GAME, &course::mode(&info.id)).first() {
+                Some(entry) => strings.format("courses.best", &[("time", &scores::format_ticks(entry.score)), ("name", &entry.name)]),
+                None => strings.get("courses.unplayed").to_string(),
+            };
+            let pipes = strings.format("courses.pipes", &[("count", &info.course.pipes.len())]);
+            renderer.text(&pipes, rect.x + 300.0, rect.y + 8.0, 16.0, theme.text)?;
+            renderer.text(&best, rect.x + 420.0, rect.y + 8.0, 16.0, theme.text)?;
+        }
+        renderer.text(strings.get("courses.help"), 60.0, 560.0, 16.0, theme.text)
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, _shared: &mut Shared, keycode: KeyCode) -> SceneCommand {
+        let count = self.courses.len().max(1);
+        match keycode {
+            KeyCode::Up => self.selected = (self.selected + count - 1) % count,
+            KeyCode::Down => self.selected = (self.selected + 1) % count,
+            KeyCode::Return | KeyCode::NumpadEnter => return self.play(),
+            KeyCode::E => {
+                if let Some(info) = self.courses.get(self.selected) {
+                    return self.edit(info.course.clone(), info.path.clone());
+                }
+            }
+            KeyCode::N => return self.edit(Course::new(), None),
+            _ => {}
+        }
+        SceneCommand::None
+    }
+
+    fn mouse_down(&mut self, _ctx: &mut Context, _shared: &mut Shared, button: MouseButton, x: f32, y: f32) -> SceneCommand {
+        match self.shown().find(|&index| self.row_rect(index).contains([x, y])) {
+            Some(index) if button == MouseButton::Left => {
+                self.selected = index;
+                self.play()
+            }
+            _ => SceneCommand::None,
+        }
+    }
+
+    fn on_enter(&mut self, shared: &mut Shared) {
+        self.reload(shared);
+    }
+
+    fn transition(&self) -> Transition {
+        Transition::Slide(0.25)
+    }
+}
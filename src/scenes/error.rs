@@ -0,0 +1,73 @@
+use crate::render::Renderer;
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared};
+use ggez::event::KeyCode;
+use ggez::{Context, GameResult};
+
+/// Longest line of the message before it is wrapped.
+const LINE_LENGTH: usize = 60;
+
+/// Shown in place of a game that had to stop, such as a broken script. Any of ESC, Enter or
+/// Space goes back.
+pub struct ErrorScene {
+    title: String,
+    lines: Vec<String>,
+}
+
+impl ErrorScene {
+    pub fn new(title: &str, message: &str) -> Self {
+        ErrorScene { title: title.to_string(), lines: wrap(message, LINE_LENGTH) }
+    }
+}
+
+/// Breaks `text` into lines of at most `width` characters, at spaces where it can.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.lines() {
+        let mut line = String::new();
+        for word in paragraph.split_whitespace() {
+            if !line.is_empty() && line.chars().count() + 1 + word.chars().count() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() {
+                line.push(' ');
+            }
+            line.push_str(word);
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+impl Scene for ErrorScene {
+    fn update(&mut self, _ctx: &mut Context, _shared: &mut Shared) -> GameResult<SceneCommand> {
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let theme = &shared.theme;
+        fill_screen(renderer, theme.background)?;
+        renderer.text(&self.title, 60.0, 80.0, 40.0, theme.warning)?;
+        for (row, line) in self.lines.iter().take(16).enumerate() {
+            renderer.text(line, 60.0, 150.0 + row as f32 * 24.0, 20.0, theme.text)?;
+        }
+        renderer.text(shared.strings.get("error.back"), 60.0, 560.0, 20.0, theme.text)
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, _shared: &mut Shared, keycode: KeyCode) -> SceneCommand {
+        match keycode {
+            KeyCode::Return | KeyCode::NumpadEnter | KeyCode::Space => SceneCommand::Pop,
+            _ => SceneCommand::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_messages_wrap_at_spaces() {
+        let lines = wrap("catch.rhai: tick(): Variable not found: missing (line 3, position 12)\nsecond", 30);
+        assert_eq!(lines, ["catch.rhai: tick(): Variable", "not found: missing (line 3,", "position 12)", "second"]);
+    }
+}
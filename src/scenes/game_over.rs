@@ -0,0 +1,228 @@
+use crate::date;
+use crate::games::session::{RunSummary, Session};
+use crate::i18n::Strings;
+use crate::render::{RectStyle, Renderer};
+use crate::scenes::fill_screen;
+use crate::scores;
+use crate::theme::Theme;
+use ggez::graphics::{Color, Rect};
+use ggez::GameResult;
+
+const PANEL: Rect = Rect { x: 180.0, y: 110.0, w: 440.0, h: 380.0 };
+const BUTTON_Y: f32 = 390.0;
+const BUTTON_WIDTH: f32 = 130.0;
+const BUTTON_HEIGHT: f32 = 44.0;
+const BUTTON_GAP: f32 = 10.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PanelAction {
+    Restart,
+    Menu,
+    WatchReplay,
+}
+
+/// The summary and buttons shown over a finished game. It only draws and tracks the selection;
+/// each frontend maps its own keys and mouse onto it.
+pub struct GameOverPanel {
+    pub summary: RunSummary,
+    actions: Vec<PanelAction>,
+    selected: usize,
+    /// Shown instead of the leaderboard line, e.g. for unranked runs.
+    note: Option<String>,
+    /// Days in a row with the daily challenge, for daily runs.
+    streak: Option<usize>,
+    /// Raced against the clock: the finish time stands in for the score.
+    timed: bool,
+}
+
+impl GameOverPanel {
+    pub fn new(summary: RunSummary, session: &Session, strings: &Strings) -> Self {
+        let mut actions = vec![PanelAction::Restart, PanelAction::Menu];
+        if !session.is_playback() {
+            actions.push(PanelAction::WatchReplay);
+        }
+        let timed = scores::is_timed(&session.config.mode);
+        let note = if session.is_playback() {
+            Some(strings.get("play.replay_over").to_string())
+        } else if session.practice {
+            Some(strings.get("play.practice").to_string())
+        } else if timed && summary.finish_ticks.is_none() {
+            Some(strings.get("play.not_finished").to_string())
+        } else if summary.entry.is_none() {
+            let difficulty = strings.get(&format!("difficulty.{}", session.config.difficulty)).to_string();
+            Some(strings.format("play.not_ranked", &[("difficulty", &difficulty)]))
+        } else {
+            None
+        };
+        GameOverPanel { summary, actions, selected: 0, note, streak: None, timed }
+    }
+
+    pub fn with_streak(mut self, days: usize) -> Self {
+        self.streak = Some(days);
+        self
+    }
+
+    pub fn has(&self, action: PanelAction) -> bool {
+        self.actions.contains(&action)
+    }
+
+    pub fn select_next(&mut self, step: isize) {
+        let count = self.actions.len() as isize;
+        self.selected = (self.selected as isize + step).rem_euclid(count) as usize;
+    }
+
+    pub fn selected(&self) -> PanelAction {
+        self.actions[self.selected]
+    }
+
+    pub fn action_at(&self, x: f32, y: f32) -> Option<PanelAction> {
+        (0..self.actions.len()).find(|&index| self.button_rect(index).contains([x, y])).map(|index| self.actions[index])
+    }
+
+    fn button_rect(&self, index: usize) -> Rect {
+        let count = self.actions.len() as f32;
+        let width = count * BUTTON_WIDTH + (count - 1.0) * BUTTON_GAP;
+        let x = PANEL.x + (PANEL.w - width) / 2.0 + index as f32 * (BUTTON_WIDTH + BUTTON_GAP);
+        Rect::new(x, BUTTON_Y, BUTTON_WIDTH, BUTTON_HEIGHT)
+    }
+
+    pub fn draw(&self, renderer: &mut dyn Renderer, theme: &Theme, strings: &Strings) -> GameResult {
+        let summary = &self.summary;
+        fill_screen(renderer, Color { a: theme.overlay.a * 0.6, ..theme.overlay })?;
+        renderer.rect(PANEL, theme.background, RectStyle::Filled)?;
+        renderer.rect(PANEL, theme.text, RectStyle::Outline(2.0))?;
+
+        let center = |renderer: &mut dyn Renderer, text: &str, y: f32, size: f32, color: Color| {
+            let width = renderer.text_width(text, size);
+            renderer.text(text, PANEL.x + (PANEL.w - width) / 2.0, y, size, color)
+        };
+
+        if summary.is_new_record() {
+            renderer.rect(Rect::new(PANEL.x, PANEL.y, PANEL.w, 40.0), theme.highlight, RectStyle::Filled)?;
+            center(renderer, strings.get("play.record"), PANEL.y + 8.0, 26.0, theme.text)?;
+        }
+        center(renderer, strings.get("play.over"), PANEL.y + 55.0, 44.0, theme.text)?;
+        let (headline, best) = match summary.finish_ticks.filter(|_| self.timed) {
+            Some(ticks) => {
+                let best = summary.previous_best.map_or(ticks, |best| best.min(ticks));
+                (strings.format("play.finish_time", &[("time", &scores::format_ticks(ticks))]), scores::format_ticks(best))
+            }
+            None if self.timed => {
+                (strings.format("play.score", &[("score", &summary.score)]), summary.previous_best.map_or("-".to_string(), scores::format_ticks))
+            }
+            None => {
+                let best = summary.previous_best.map_or(summary.score, |best| best.max(summary.score));
+                (strings.format("play.score", &[("score", &summary.score)]), best.to_string())
+            }
+        };
+        center(renderer, &headline, PANEL.y + 115.0, 30.0, theme.text)?;
+
+        let mut lines = vec![
+            strings.format("play.best", &[("best", &best)]),
+            match (&self.note, summary.rank) {
+                (Some(note), _) => note.clone(),
+                (None, Some(rank)) => strings.format("play.rank", &[("rank", &(rank + 1))]),
+                (None, None) => strings.get("play.no_rank").to_string(),
+            },
+            strings.format("play.time", &[("time", &date::format_duration(summary.seconds))]),
+        ];
+        if let Some(days) = self.streak {
+            lines.push(strings.format("play.streak", &[("days", &days)]));
+        }
+        for (row, line) in lines.iter().enumerate() {
+            center(renderer, line, PANEL.y + 160.0 + row as f32 * 30.0, 20.0, theme.text)?;
+        }
+
+        for (index, action) in self.actions.iter().enumerate() {
+            let rect = self.button_rect(index);
+            let color = if index == self.selected { theme.button_selected } else { theme.button };
+            renderer.rect(rect, color, RectStyle::Filled)?;
+            let label = match action {
+                PanelAction::Restart => strings.get("play.restart"),
+                PanelAction::Menu => strings.get("play.menu"),
+                PanelAction::WatchReplay => strings.get("play.watch"),
+            };
+            let width = renderer.text_width(label, 20.0);
+            renderer.text(label, rect.x + (rect.w - width) / 2.0, rect.y + 12.0, 20.0, theme.text)?;
+        }
+        center(renderer, strings.get("play.help"), PANEL.bottom() - 28.0, 14.0, theme.text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::games::RunConfig;
+    use crate::render::RecordingRenderer;
+    use crate::replay::Replay;
+    use crate::scores::ScoreEntry;
+
+    fn summary(entry: bool, previous_best: Option<u32>) -> RunSummary {
+        let session = Session::new(RunConfig::new("snake", "classic"));
+        let mut entry = entry.then(|| session.score_entry().unwrap());
+        if let Some(entry) = &mut entry {
+            entry.score = 40;
+        }
+        RunSummary { score: 40, seconds: 75.0, entry, rank: Some(2), previous_best, finish_ticks: None }
+    }
+
+    fn drawn(panel: &GameOverPanel) -> RecordingRenderer {
+        let mut renderer = RecordingRenderer::new();
+        panel.draw(&mut renderer, &Theme::light(), &Strings::default()).unwrap();
+        renderer
+    }
+
+    #[test]
+    fn a_new_record_gets_the_banner_and_its_place() {
+        let session = Session::new(RunConfig::new("snake", "classic"));
+        let panel = GameOverPanel::new(summary(true, Some(30)), &session, &Strings::default());
+        let renderer = drawn(&panel);
+        assert!(renderer.has_text("NEW RECORD!"));
+        assert!(renderer.has_text("Personal best: 40"));
+        assert!(renderer.has_text("Place on the leaderboard: 3"));
+        assert!(renderer.has_text("Time alive: 1:15"));
+
+        let beaten = GameOverPanel::new(summary(true, Some(90)), &session, &Strings::default());
+        assert!(!drawn(&beaten).has_text("NEW RECORD!"));
+        assert!(drawn(&beaten).has_text("Personal best: 90"));
+    }
+
+    #[test]
+    fn replays_cannot_be_rewatched_from_their_own_panel() {
+        let session = Session::replay(Replay::new(RunConfig::new("snake", "classic")));
+        let mut panel = GameOverPanel::new(summary(false, None), &session, &Strings::default());
+        assert!(!panel.has(PanelAction::WatchReplay));
+        assert!(drawn(&panel).has_text("End of the replay"));
+        panel.select_next(-1);
+        assert_eq!(panel.selected(), PanelAction::Menu);
+        let restart = panel.button_rect(0);
+        assert_eq!(panel.action_at(restart.x + 1.0, restart.y + 1.0), Some(PanelAction::Restart));
+    }
+
+    #[test]
+    fn daily_practice_runs_say_so_and_show_the_streak() {
+        let mut session = Session::new(RunConfig::new("snake", "daily"));
+        session.practice = true;
+        let panel = GameOverPanel::new(summary(false, Some(40)), &session, &Strings::default()).with_streak(4);
+        let renderer = drawn(&panel);
+        assert!(renderer.has_text("Practice: today's scored run is done"));
+        assert!(renderer.has_text("Daily streak: 4 days"));
+    }
+
+    #[test]
+    fn course_runs_show_the_finish_time_and_the_fastest_is_the_best() {
+        let session = Session::new(RunConfig::new("flappy_bird", "course"));
+        let entry = ScoreEntry { mode: session.config.mode.clone(), score: 741, ..summary(true, None).entry.unwrap() };
+        let finished = RunSummary { score: 3, finish_ticks: Some(741), entry: Some(entry), previous_best: Some(900), ..summary(false, None) };
+        let renderer = drawn(&GameOverPanel::new(finished.clone(), &session, &Strings::default()));
+        assert!(renderer.has_text("NEW RECORD!"));
+        assert!(renderer.has_text("Finish time: 12.35 s"));
+        assert!(renderer.has_text("Personal best: 12.35 s"));
+
+        let crashed = RunSummary { finish_ticks: None, entry: None, ..finished };
+        let renderer = drawn(&GameOverPanel::new(crashed, &session, &Strings::default()));
+        assert!(!renderer.has_text("NEW RECORD!"));
+        assert!(renderer.has_text("Did not reach the finish"));
+        assert!(renderer.has_text("Personal best: 15.00 s"));
+    }
+}
@@ -0,0 +1,261 @@
+use crate::date;
+use crate::render::{RectStyle, Renderer};
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared, Transition, SCREEN_WIDTH};
+use crate::scores::{self, BoardTab, ScoreEntry};
+use ggez::event::{KeyCode, MouseButton};
+use ggez::graphics::Rect;
+use ggez::{Context, GameResult};
+
+const TAB_Y: f32 = 95.0;
+const TAB_HEIGHT: f32 = 36.0;
+const TAB_WIDTH: f32 = 150.0;
+const TABS_X: f32 = 20.0;
+const HEADER_Y: f32 = 145.0;
+const FIRST_ROW_Y: f32 = 180.0;
+const ROW_HEIGHT: f32 = 30.0;
+const VISIBLE_ROWS: usize = 12;
+const SOURCE_Y: f32 = 45.0;
+const SOURCE_WIDTH: f32 = 90.0;
+const SOURCES_X: f32 = 600.0;
+const COLUMNS: [(&str, f32); 5] = [
+    ("leaderboard.rank", 40.0),
+    ("leaderboard.name", 100.0),
+    ("leaderboard.score", 380.0),
+    ("leaderboard.date", 500.0),
+    ("leaderboard.time", 660.0),
+];
+
+pub struct LeaderboardScene {
+    tabs: Vec<BoardTab>,
+    selected: usize,
+    scroll: usize,
+    /// Showing the shared leaderboard's runs instead of this computer's.
+    global: bool,
+}
+
+impl LeaderboardScene {
+    pub fn new() -> Self {
+        LeaderboardScene {
+            tabs: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            global: false,
+        }
+    }
+
+    fn entries<'a>(&self, shared: &'a Shared) -> Vec<&'a ScoreEntry> {
+        let Some(tab) = self.tabs.get(self.selected) else {
+            return Vec::new();
+        };
+        if self.global {
+            let board = shared.sync.as_ref().and_then(|sync| sync.board(&tab.game, &tab.mode));
+            board.map_or_else(Vec::new, |entries| entries.iter().collect())
+        } else {
+            shared.scores.board(&tab.game, &tab.mode)
+        }
+    }
+
+    fn select(&mut self, shared: &Shared, index: usize) {
+        if index < self.tabs.len() {
+            self.selected = index;
+            self.scroll = 0;
+            self.fetch(shared);
+        }
+    }
+
+    fn show_global(&mut self, shared: &Shared, global: bool) {
+        if self.global != global {
+            self.global = global;
+            self.scroll = 0;
+            self.fetch(shared);
+        }
+    }
+
+    /// Asks the server for the selected board again; the answer turns up in a later frame.
+    fn fetch(&self, shared: &Shared) {
+        if let (true, Some(tab), Some(sync)) = (self.global, self.tabs.get(self.selected), &shared.sync) {
+            sync.fetch(&tab.game, &tab.mode);
+        }
+    }
+
+    fn source_rect(global: bool) -> Rect {
+        let x = if global { SOURCES_X + SOURCE_WIDTH } else { SOURCES_X };
+        Rect::new(x, SOURCE_Y, SOURCE_WIDTH - 4.0, TAB_HEIGHT)
+    }
+
+    /// What to say when the global board has nothing to show.
+    fn global_notice(&self, shared: &Shared) -> &'static str {
+        let Some(sync) = &shared.sync else {
+            return "leaderboard.no_server";
+        };
+        let tab = &self.tabs[self.selected.min(self.tabs.len().saturating_sub(1))];
+        match (sync.board(&tab.game, &tab.mode), sync.is_online()) {
+            (Some(_), _) => "leaderboard.empty",
+            (None, Some(false)) => "leaderboard.offline",
+            (None, _) => "leaderboard.loading",
+        }
+    }
+
+    fn scroll_by(&mut self, shared: &Shared, rows: i32) {
+        let max_scroll = self.entries(shared).len().saturating_sub(VISIBLE_ROWS);
+        let scroll = self.scroll as i32 + rows;
+        self.scroll = scroll.clamp(0, max_scroll as i32) as usize;
+    }
+
+    fn first_visible_tab(&self) -> usize {
+        let per_page = ((SCREEN_WIDTH - 2.0 * TABS_X) / TAB_WIDTH) as usize;
+        (self.selected + 1).saturating_sub(per_page)
+    }
+
+    fn tab_rect(&self, index: usize) -> Rect {
+        let position = index as f32 - self.first_visible_tab() as f32;
+        Rect::new(TABS_X + position * TAB_WIDTH, TAB_Y, TAB_WIDTH - 6.0, TAB_HEIGHT)
+    }
+}
+
+impl Default for LeaderboardScene {
+    fn default() -> Self {
+        LeaderboardScene::new()
+    }
+}
+
+fn is_same_run(a: &ScoreEntry, b: &ScoreEntry) -> bool {
+    a.game == b.game && a.mode == b.mode && a.name == b.name && a.score == b.score && a.timestamp == b.timestamp
+}
+
+impl Scene for LeaderboardScene {
+    fn update(&mut self, _ctx: &mut Context, shared: &mut Shared) -> GameResult<SceneCommand> {
+        if let Some(sync) = &mut shared.sync {
+            sync.poll();
+        }
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let (theme, strings) = (&shared.theme, &shared.strings);
+        fill_screen(renderer, theme.background)?;
+        renderer.text(strings.get("leaderboard.title"), 250.0, 30.0, 50.0, theme.text)?;
+
+        for (global, label) in [(false, "leaderboard.local"), (true, "leaderboard.global")] {
+            let rect = LeaderboardScene::source_rect(global);
+            let color = if global == self.global { theme.button_selected } else { theme.tab };
+            renderer.rect(rect, color, RectStyle::Filled)?;
+            let width = renderer.text_width(strings.get(label), 18.0);
+            renderer.text(strings.get(label), rect.x + (rect.w - width) / 2.0, rect.y + 9.0, 18.0, theme.text)?;
+        }
+
+        for index in self.first_visible_tab()..self.tabs.len() {
+            let rect = self.tab_rect(index);
+            if rect.right() > SCREEN_WIDTH {
+                break;
+            }
+            let color = if index == self.selected { theme.button_selected } else { theme.tab };
+            renderer.rect(rect, color, RectStyle::Filled)?;
+            let tab = &self.tabs[index];
+            renderer.text(&strings.run_title("leaderboard.board", &tab.game, &tab.mode), rect.x + 8.0, rect.y + 9.0, 18.0, theme.text)?;
+        }
+
+        let mode = self.tabs.get(self.selected).map_or("", |tab| tab.mode.as_str());
+        for (column, x) in COLUMNS {
+            let column = if column == "leaderboard.score" && scores::is_timed(mode) { "leaderboard.finish" } else { column };
+            renderer.text(strings.get(column), x, HEADER_Y, 22.0, theme.text)?;
+        }
+
+        let entries = self.entries(shared);
+        if entries.is_empty() {
+            let notice = strings.get(if self.global && !self.tabs.is_empty() { self.global_notice(shared) } else { "leaderboard.empty" });
+            let width = renderer.text_width(notice, 25.0);
+            renderer.text(notice, (SCREEN_WIDTH - width) / 2.0, FIRST_ROW_Y + 40.0, 25.0, theme.text)?;
+        }
+        if let (true, Some(sync)) = (self.global, &shared.sync) {
+            if sync.is_online() == Some(false) && !entries.is_empty() {
+                renderer.text(strings.get("leaderboard.offline"), 30.0, 545.0, 16.0, theme.warning)?;
+            } else if sync.pending() > 0 {
+                renderer.text(&strings.format("leaderboard.pending", &[("count", &sync.pending())]), 30.0, 545.0, 16.0, theme.text)?;
+            }
+        }
+
+        for (row, (rank, entry)) in entries.iter().enumerate().skip(self.scroll).take(VISIBLE_ROWS).enumerate() {
+            let y = FIRST_ROW_Y + row as f32 * ROW_HEIGHT;
+            let highlighted = shared.last_score.as_ref().is_some_and(|last| is_same_run(last, entry));
+            if highlighted {
+                renderer.rect(Rect::new(30.0, y - 3.0, 740.0, ROW_HEIGHT), theme.highlight, RectStyle::Filled)?;
+            }
+
+            let cells = [
+                (rank + 1).to_string(),
+                entry.name.clone(),
+                scores::format_score(mode, entry.score),
+                date::format_date(entry.timestamp),
+                date::format_duration(entry.duration),
+            ];
+            for (cell, (_, x)) in cells.iter().zip(COLUMNS) {
+                renderer.text(cell, x, y, 22.0, theme.text)?;
+            }
+        }
+
+        if entries.len() > VISIBLE_ROWS {
+            let last = (self.scroll + VISIBLE_ROWS).min(entries.len());
+            let position = strings.format(
+                "leaderboard.position",
+                &[("first", &(self.scroll + 1)), ("last", &last), ("total", &entries.len())],
+            );
+            renderer.text(&position, 660.0, 545.0, 16.0, theme.text)?;
+        }
+
+        renderer.text(strings.get("leaderboard.help"), 30.0, 570.0, 16.0, theme.text)
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, shared: &mut Shared, keycode: KeyCode) -> SceneCommand {
+        match keycode {
+            KeyCode::Left => self.select(shared, self.selected.saturating_sub(1)),
+            KeyCode::Right | KeyCode::Tab => self.select(shared, (self.selected + 1) % self.tabs.len().max(1)),
+            KeyCode::G => self.show_global(shared, !self.global),
+            KeyCode::Up => self.scroll_by(shared, -1),
+            KeyCode::Down => self.scroll_by(shared, 1),
+            KeyCode::PageUp => self.scroll_by(shared, -(VISIBLE_ROWS as i32)),
+            KeyCode::PageDown => self.scroll_by(shared, VISIBLE_ROWS as i32),
+            KeyCode::Home => self.scroll = 0,
+            KeyCode::End => self.scroll_by(shared, i32::MAX / 2),
+            _ => {}
+        }
+        SceneCommand::None
+    }
+
+    fn mouse_down(&mut self, _ctx: &mut Context, shared: &mut Shared, button: MouseButton, x: f32, y: f32) -> SceneCommand {
+        if button == MouseButton::Left {
+            if let Some(index) = (self.first_visible_tab()..self.tabs.len()).find(|&index| self.tab_rect(index).contains([x, y])) {
+                self.select(shared, index);
+            }
+            if let Some(global) = [false, true].into_iter().find(|&global| LeaderboardScene::source_rect(global).contains([x, y])) {
+                self.show_global(shared, global);
+            }
+        }
+        SceneCommand::None
+    }
+
+    fn mouse_wheel(&mut self, _ctx: &mut Context, shared: &mut Shared, y: f32) {
+        if y > 0.0 {
+            self.scroll_by(shared, -1);
+        } else if y < 0.0 {
+            self.scroll_by(shared, 1);
+        }
+    }
+
+    fn on_enter(&mut self, shared: &mut Shared) {
+        self.tabs = shared.scores.tabs();
+
+        if let Some(last) = shared.last_score.clone() {
+            if let Some(index) = self.tabs.iter().position(|tab| tab.game == last.game && tab.mode == last.mode) {
+                self.select(shared, index);
+                if let Some(rank) = self.entries(shared).iter().position(|entry| is_same_run(entry, &last)) {
+                    self.scroll_by(shared, rank as i32 - VISIBLE_ROWS as i32 / 2);
+                }
+            }
+        }
+    }
+
+    fn transition(&self) -> Transition {
+        Transition::Slide(0.25)
+    }
+}
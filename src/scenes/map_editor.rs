@@ -0,0 +1,384 @@
+use crate::games::snake::SnakeGame;
+use crate::games::snake_map::{self, Cell, Problem, SnakeMap, MAPS_DIR, MAP_HEIGHT, MAP_WIDTH};
+use crate::games::{GameInput, Minigame, RunConfig, TICKS_PER_SECOND};
+use crate::i18n::Strings;
+use crate::render::{RectStyle, Renderer};
+use crate::scenes::confirm::ConfirmScene;
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared, Transition};
+use ggez::event::{KeyCode, MouseButton};
+use ggez::graphics::Rect;
+use ggez::input::mouse;
+use ggez::{timer, Context, GameResult};
+use std::path::{Path, PathBuf};
+
+const GRID_X: f32 = 20.0;
+const GRID_Y: f32 = 70.0;
+const CELL_SIZE: f32 = 14.0;
+const PANEL_X: f32 = 600.0;
+const BUTTON_WIDTH: f32 = 180.0;
+const BUTTON_HEIGHT: f32 = 34.0;
+const BUTTON_SPACING: f32 = 40.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Tool {
+    Wall,
+    Erase,
+    Start,
+    Food,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Action {
+    Tool(Tool),
+    Turn,
+    TestPlay,
+    Save,
+    Load,
+    New,
+}
+
+const ACTIONS: [Action; 9] = [
+    Action::Tool(Tool::Wall),
+    Action::Tool(Tool::Erase),
+    Action::Tool(Tool::Start),
+    Action::Tool(Tool::Food),
+    Action::Turn,
+    Action::TestPlay,
+    Action::Save,
+    Action::Load,
+    Action::New,
+];
+
+/// Paints Snake maps with the mouse: walls, the start and its direction, and food zones. A map
+/// has to pass the checks in `SnakeMap::problems` before it can be test-played or saved.
+pub struct MapEditorScene {
+    map: SnakeMap,
+    tool: Tool,
+    /// The file the map was loaded from or last saved to.
+    path: Option<PathBuf>,
+    /// The map Load went to last, so the next Load moves on past it even if it was broken.
+    browsed: Option<PathBuf>,
+    changed: bool,
+    /// Load or New clicked once with unsaved changes; a second click drops them.
+    dropping: Option<Action>,
+    /// A test run of the map, played in place of the editor until ESC.
+    test: Option<SnakeGame>,
+}
+
+impl MapEditorScene {
+    pub fn new() -> Self {
+        MapEditorScene { map: SnakeMap::new(), tool: Tool::Wall, path: None, browsed: None, changed: false, dropping: None, test: None }
+    }
+
+    fn button_rect(index: usize) -> Rect {
+        Rect::new(PANEL_X, GRID_Y + index as f32 * BUTTON_SPACING, BUTTON_WIDTH, BUTTON_HEIGHT)
+    }
+
+    fn cell_rect((x, y): (i32, i32)) -> Rect {
+        Rect::new(GRID_X + x as f32 * CELL_SIZE, GRID_Y + y as f32 * CELL_SIZE, CELL_SIZE, CELL_SIZE)
+    }
+
+    /// The map cell under a point on the screen.
+    fn cell_at(x: f32, y: f32) -> Option<(i32, i32)> {
+        let cell = (((x - GRID_X) / CELL_SIZE).floor() as i32, ((y - GRID_Y) / CELL_SIZE).floor() as i32);
+        SnakeMap::new().contains(cell).then_some(cell)
+    }
+
+    fn label(action: Action, strings: &Strings) -> &str {
+        strings.get(match action {
+            Action::Tool(Tool::Wall) => "editor.wall",
+            Action::Tool(Tool::Erase) => "editor.erase",
+            Action::Tool(Tool::Start) => "editor.start",
+            Action::Tool(Tool::Food) => "editor.food",
+            Action::Turn => "editor.turn",
+            Action::TestPlay => "editor.test",
+            Action::Save => "editor.save",
+            Action::Load => "editor.load",
+            Action::New => "editor.new",
+        })
+    }
+
+    fn paint(&mut self, cell: (i32, i32), tool: Tool) {
+        let before = (self.map.cell(cell), self.map.start);
+        match tool {
+            Tool::Wall => self.map.set(cell, Cell::Wall),
+            Tool::Erase => self.map.set(cell, Cell::Open),
+            Tool::Food => self.map.set(cell, Cell::Food),
+            Tool::Start => self.map.start = cell,
+        }
+        self.changed |= before != (self.map.cell(cell), self.map.start);
+    }
+
+    fn turn(&mut self, direction: (i32, i32)) {
+        self.changed |= self.map.direction != direction;
+        self.map.direction = direction;
+    }
+
+    fn problem_text(problem: &Problem, strings: &Strings) -> String {
+        match problem {
+            Problem::StartInWall => strings.get("editor.start_in_wall").to_string(),
+            Problem::Unreachable { count } => strings.format("editor.unreachable", &[("count", &count.to_string())]),
+        }
+    }
+
+    /// False, with a toast saying why, if the map is not ready to be played.
+    fn check(&self, shared: &mut Shared) -> bool {
+        match self.map.problems().first() {
+            Some(problem) => {
+                let problem = MapEditorScene::problem_text(problem, &shared.strings);
+                shared.toasts.push(shared.strings.format("editor.invalid", &[("problem", &problem)]));
+                false
+            }
+            None => true,
+        }
+    }
+
+    fn start_test(&mut self, shared: &mut Shared) {
+        if self.check(shared) {
+            let mut game = SnakeGame::with_config(MAP_WIDTH, &RunConfig::new("snake", "classic"));
+            game.use_map(&self.map);
+            self.test = Some(game);
+        }
+    }
+
+    fn save(&mut self, shared: &mut Shared) {
+        if !self.check(shared) {
+            return;
+        }
+        let path = self.path.clone().unwrap_or_else(|| snake_map::free_path(&shared.storage.config_path(MAPS_DIR)));
+        match self.map.save(&path) {
+            Ok(()) => {
+                shared.toasts.push(shared.strings.format("editor.saved", &[("file", &file_name(&path))]));
+                self.path = Some(path);
+                self.changed = false;
+            }
+            Err(error) => {
+                log::error!("Could not save the map to {}: {}", path.display(), error);
+                shared.toasts.push(shared.strings.format("error.map", &[("error", &error)]));
+            }
+        }
+    }
+
+    /// Opens the map after the current one in the maps folder, going round to the first.
+    fn load_next(&mut self, shared: &mut Shared) {
+        let dir = shared.storage.config_path(MAPS_DIR);
+        let paths = snake_map::list(&dir);
+        let next = match self.browsed.as_ref().and_then(|current| paths.iter().position(|path| path == current)) {
+            Some(index) => paths.get(index + 1).or(paths.first()),
+            None => paths.first(),
+        };
+        let Some(path) = next.cloned() else {
+            shared.toasts.push(shared.strings.format("editor.no_maps", &[("dir", &dir.display().to_string())]));
+            return;
+        };
+        match SnakeMap::load(&path) {
+            Ok(map) => {
+                shared.toasts.push(shared.strings.format("editor.loaded", &[("file", &file_name(&path))]));
+                *self = MapEditorScene { map, tool: self.tool, path: Some(path.clone()), browsed: Some(path), ..MapEditorScene::new() };
+            }
+            Err(error) => {
+                log::error!("Could not load the map {}: {}", path.display(), error);
+                shared.toasts.push(shared.strings.format("error.load_map", &[("file", &file_name(&path)), ("error", &error)]));
+                self.browsed = Some(path);
+            }
+        }
+    }
+
+    fn run(&mut self, action: Action, shared: &mut Shared) {
+        let drops_changes = matches!(action, Action::Load | Action::New) && self.changed;
+        if drops_changes && self.dropping != Some(action) {
+            shared.toasts.push(shared.strings.get("editor.unsaved_changes").to_string());
+            self.dropping = Some(action);
+            return;
+        }
+        self.dropping = None;
+        match action {
+            Action::Tool(tool) => self.tool = tool,
+            Action::Turn => {
+                let (x, y) = self.map.direction;
+                self.turn((-y, x));
+            }
+            Action::TestPlay => self.start_test(shared),
+            Action::Save => self.save(shared),
+            Action::Load => self.load_next(shared),
+            Action::New => *self = MapEditorScene { tool: self.tool, browsed: self.browsed.take(), ..MapEditorScene::new() },
+        }
+    }
+
+    fn draw_test(game: &SnakeGame, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let (theme, strings) = (&shared.theme, &shared.strings);
+        fill_screen(renderer, theme.sky)?;
+        game.draw(renderer, theme)?;
+        let help = if game.is_over() {
+            strings.format("editor.test_over", &[("score", &game.score.to_string())])
+        } else {
+            strings.get("editor.test_help").to_string()
+        };
+        renderer.text(&help, 10.0, 575.0, 18.0, theme.game_text)
+    }
+}
+
+impl Default for MapEditorScene {
+    fn default() -> Self {
+        MapEditorScene::new()
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+impl Scene for MapEditorScene {
+    fn update(&mut self, ctx: &mut Context, _shared: &mut Shared) -> GameResult<SceneCommand> {
+        if let Some(game) = &mut self.test {
+            while timer::check_update_time(ctx, TICKS_PER_SECOND) {
+                if game.is_over() {
+                    game.animate_death();
+                } else {
+                    game.update();
+                }
+            }
+        }
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        if let Some(game) = &self.test {
+            return MapEditorScene::draw_test(game, renderer, shared);
+        }
+        let (theme, strings) = (&shared.theme, &shared.strings);
+        fill_screen(renderer, theme.background)?;
+        renderer.text(strings.get("editor.title"), GRID_X, 15.0, 36.0, theme.text)?;
+        let file = match &self.path {
+            Some(path) if self.changed => format!("{} *", file_name(path)),
+            Some(path) => file_name(path),
+            None => strings.get("editor.unsaved").to_string(),
+        };
+        renderer.text(&file, PANEL_X, 25.0, 18.0, theme.text)?;
+
+        let board = Rect::new(GRID_X, GRID_Y, MAP_WIDTH as f32 * CELL_SIZE, MAP_HEIGHT as f32 * CELL_SIZE);
+        renderer.rect(board, theme.sky, RectStyle::Filled)?;
+        for y in 0..MAP_HEIGHT {
+            for x in 0..MAP_WIDTH {
+                let color = match self.map.cell((x, y)) {
+                    Cell::Open => continue,
+                    Cell::Wall => theme.wall,
+                    Cell::Food => theme.food,
+                };
+                renderer.rect(MapEditorScene::cell_rect((x, y)), color, RectStyle::Filled)?;
+            }
+        }
+        let start = MapEditorScene::cell_rect(self.map.start);
+        renderer.rect(start, theme.snake, RectStyle::Filled)?;
+        // A notch on the side the snake sets off towards.
+        let (dx, dy) = self.map.direction;
+        let notch = Rect::new(start.x + start.w * (dx + 1) as f32 / 3.0, start.y + start.h * (dy + 1) as f32 / 3.0, start.w / 3.0, start.h / 3.0);
+        renderer.rect(notch, theme.text, RectStyle::Filled)?;
+        renderer.rect(board, theme.text, RectStyle::Outline(1.0))?;
+
+        for (index, &action) in ACTIONS.iter().enumerate() {
+            let rect = MapEditorScene::button_rect(index);
+            let color = if action == Action::Tool(self.tool) { theme.button_selected } else { theme.button };
+            renderer.rect(rect, color, RectStyle::Filled)?;
+            renderer.text(MapEditorScene::label(action, strings), rect.x + 10.0, rect.y + 8.0, 18.0, theme.text)?;
+        }
+
+        let (status, color) = match self.map.problems().first() {
+            Some(problem) => (MapEditorScene::problem_text(problem, strings), theme.warning),
+            None => (strings.get("editor.ok").to_string(), theme.text),
+        };
+        renderer.text(&status, GRID_X, 510.0, 20.0, color)?;
+        renderer.text(strings.get("editor.help"), GRID_X, 560.0, 16.0, theme.text)
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, shared: &mut Shared, keycode: KeyCode) -> SceneCommand {
+        if let Some(game) = &mut self.test {
+            match (keycode, GameInput::from_keycode(keycode)) {
+                (KeyCode::R, _) if game.is_over() => self.start_test(shared),
+                (_, Some(input)) => game.change_direction(input),
+                _ => {}
+            }
+            return SceneCommand::None;
+        }
+        match keycode {
+            KeyCode::Up => self.turn((0, -1)),
+            KeyCode::Down => self.turn((0, 1)),
+            KeyCode::Left => self.turn((-1, 0)),
+            KeyCode::Right => self.turn((1, 0)),
+            KeyCode::T => self.start_test(shared),
+            _ => {}
+        }
+        SceneCommand::None
+    }
+
+    fn mouse_down(&mut self, _ctx: &mut Context, shared: &mut Shared, button: MouseButton, x: f32, y: f32) -> SceneCommand {
+        if self.test.is_some() {
+            return SceneCommand::None;
+        }
+        if let Some(cell) = MapEditorScene::cell_at(x, y) {
+            match button {
+                MouseButton::Left => self.paint(cell, self.tool),
+                MouseButton::Right => self.paint(cell, Tool::Erase),
+                _ => {}
+            }
+        } else if let Some(index) = (0..ACTIONS.len()).find(|&index| MapEditorScene::button_rect(index).contains([x, y])) {
+            if button == MouseButton::Left {
+                self.run(ACTIONS[index], shared);
+            }
+        }
+        SceneCommand::None
+    }
+
+    /// Dragging with a button held paints every cell it passes over.
+    fn mouse_motion(&mut self, ctx: &mut Context, _shared: &mut Shared, x: f32, y: f32) {
+        let Some(cell) = MapEditorScene::cell_at(x, y).filter(|_| self.test.is_none()) else {
+            return;
+        };
+        if mouse::button_pressed(ctx, MouseButton::Left) {
+            self.paint(cell, self.tool);
+        } else if mouse::button_pressed(ctx, MouseButton::Right) {
+            self.paint(cell, Tool::Erase);
+        }
+    }
+
+    /// ESC leaves a test run first, and asks before dropping unsaved changes.
+    fn escape(&mut self, _ctx: &mut Context, shared: &mut Shared) -> SceneCommand {
+        if self.test.take().is_some() {
+            return SceneCommand::None;
+        }
+        if self.changed {
+            return SceneCommand::Push(Box::new(ConfirmScene::new(shared.strings.get("editor.leave"), SceneCommand::PopN(2))));
+        }
+        SceneCommand::Pop
+    }
+
+    fn transition(&self) -> Transition {
+        Transition::Slide(0.25)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clicks_land_on_the_cell_under_them() {
+        assert_eq!(MapEditorScene::cell_at(GRID_X + 1.0, GRID_Y + 1.0), Some((0, 0)));
+        assert_eq!(MapEditorScene::cell_at(GRID_X + 3.5 * CELL_SIZE, GRID_Y + 2.5 * CELL_SIZE), Some((3, 2)));
+        assert_eq!(MapEditorScene::cell_at(GRID_X - 1.0, GRID_Y), None);
+        assert_eq!(MapEditorScene::cell_at(GRID_X + MAP_WIDTH as f32 * CELL_SIZE, GRID_Y), None);
+        assert_eq!(MapEditorScene::cell_at(PANEL_X + 1.0, GRID_Y + 1.0), None);
+    }
+
+    #[test]
+    fn painting_marks_the_map_as_changed_only_when_it_does_change() {
+        let mut editor = MapEditorScene::new();
+        editor.paint((3, 3), Tool::Erase);
+        assert!(!editor.changed);
+        editor.paint((3, 3), Tool::Wall);
+        editor.paint((4, 3), Tool::Food);
+        editor.paint((8, 8), Tool::Start);
+        assert!(editor.changed);
+        assert_eq!((editor.map.cell((3, 3)), editor.map.cell((4, 3)), editor.map.start), (Cell::Wall, Cell::Food, (8, 8)));
+    }
+}
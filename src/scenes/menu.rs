@@ -0,0 +1,225 @@
+use crate::games::{self, course, RunConfig};
+use crate::i18n::Strings;
+use crate::render::{RectStyle, Renderer};
+use crate::save::SaveGame;
+use crate::scenes::courses::CoursesScene;
+use crate::scenes::leaderboard::LeaderboardScene;
+use crate::scenes::map_editor::MapEditorScene;
+use crate::scenes::play::PlayScene;
+use crate::scenes::settings::SettingsScene;
+use crate::scenes::stats::StatsScene;
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared};
+use crate::storage;
+use ggez::event::{KeyCode, MouseButton};
+use ggez::graphics::Rect;
+use ggez::{Context, GameResult};
+use std::fs;
+
+const FIRST_BUTTON_Y: f32 = 170.0;
+const BUTTON_SPACING: f32 = 44.0;
+const BUTTON_HEIGHT: f32 = 38.0;
+/// Buttons close up when there are too many to fit above this, e.g. with script games added.
+const LAST_BUTTON_BOTTOM: f32 = 566.0;
+const LABEL_SIZE: f32 = 26.0;
+/// The games' shortcuts, in menu order; games past the ninth have none.
+const NUMBER_KEYS: [KeyCode; 9] = [
+    KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5,
+    KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+];
+
+enum Action {
+    Continue,
+    Play { game: &'static str, mode: &'static str },
+    Leaderboard,
+    Stats,
+    Settings,
+    Editor,
+}
+
+impl Action {
+    /// The letter shortcuts of everything but the games, which keep the number keys to themselves.
+    fn letter(&self) -> Option<(KeyCode, char)> {
+        match self {
+            Action::Continue => Some((KeyCode::C, 'C')),
+            Action::Play { .. } => None,
+            Action::Leaderboard => Some((KeyCode::L, 'L')),
+            Action::Stats => Some((KeyCode::T, 'T')),
+            Action::Settings => Some((KeyCode::S, 'S')),
+            Action::Editor => Some((KeyCode::E, 'E')),
+        }
+    }
+}
+
+struct Button {
+    action: Action,
+    rect: Rect,
+    /// The key that activates the button and the name it is shown under, if it has one.
+    shortcut: Option<(KeyCode, char)>,
+}
+
+pub struct MenuScene {
+    buttons: Vec<Button>,
+    selected: usize,
+}
+
+impl MenuScene {
+    /// Continue, one button per registered game and mode, then the leaderboard, stats, settings and map editor.
+    pub fn new() -> Self {
+        let mut actions = vec![Action::Continue];
+        for game in games::registry() {
+            for mode in game.modes {
+                actions.push(Action::Play { game: game.id, mode: mode.id });
+            }
+        }
+        actions.push(Action::Leaderboard);
+        actions.push(Action::Stats);
+        actions.push(Action::Settings);
+        actions.push(Action::Editor);
+
+        let spacing = BUTTON_SPACING.min((LAST_BUTTON_BOTTOM - FIRST_BUTTON_Y) / actions.len() as f32);
+        let height = BUTTON_HEIGHT * spacing / BUTTON_SPACING;
+        let mut numbers = NUMBER_KEYS.iter().zip('1'..='9');
+        let buttons = actions
+            .into_iter()
+            .enumerate()
+            .map(|(index, action)| Button {
+                shortcut: match action {
+                    Action::Play { .. } => numbers.next().map(|(&key, name)| (key, name)),
+                    _ => action.letter(),
+                },
+                action,
+                rect: Rect::new(200.0, FIRST_BUTTON_Y + index as f32 * spacing, 400.0, height),
+            })
+            .collect();
+        MenuScene { buttons, selected: 0 }
+    }
+
+    /// Labels are looked up when drawing, so they follow a language change right away.
+    fn label(action: &Action, strings: &Strings) -> String {
+        match action {
+            Action::Continue => strings.get("menu.continue").to_string(),
+            Action::Play { game, mode } => strings.run_title("menu.run", game, mode),
+            Action::Leaderboard => strings.get("menu.leaderboard").to_string(),
+            Action::Stats => strings.get("menu.stats").to_string(),
+            Action::Settings => strings.get("menu.settings").to_string(),
+            Action::Editor => strings.get("menu.editor").to_string(),
+        }
+    }
+
+    /// Loads the save slot and deletes it, so a saved run can only be picked up once.
+    fn resume(shared: &mut Shared) -> SceneCommand {
+        let path = shared.storage.data_path(storage::SAVE_FILE);
+        if !path.exists() {
+            shared.toasts.push(shared.strings.get("menu.no_save").to_string());
+            return SceneCommand::None;
+        }
+        match SaveGame::load(&path).and_then(PlayScene::resume) {
+            Ok(scene) => {
+                if let Err(error) = fs::remove_file(&path) {
+                    log::warn!("Could not remove {}: {}", path.display(), error);
+                }
+                SceneCommand::Push(Box::new(scene))
+            }
+            Err(error) => {
+                log::error!("Could not resume the saved game: {}", error);
+                shared.toasts.push(shared.strings.format("error.resume", &[("error", &error)]));
+                SceneCommand::None
+            }
+        }
+    }
+
+    fn activate(&self, index: usize, shared: &mut Shared) -> SceneCommand {
+        match self.buttons.get(index).map(|button| &button.action) {
+            Some(Action::Continue) => MenuScene::resume(shared),
+            Some(Action::Play { mode, .. }) if *mode == course::MODE => SceneCommand::Push(Box::new(CoursesScene::new())),
+            Some(Action::Play { game, mode }) => SceneCommand::Push(Box::new(PlayScene::new(RunConfig::new(game, mode)))),
+            Some(Action::Leaderboard) => SceneCommand::Push(Box::new(LeaderboardScene::new())),
+            Some(Action::Stats) => SceneCommand::Push(Box::new(StatsScene::new())),
+            Some(Action::Settings) => SceneCommand::Push(Box::new(SettingsScene::new())),
+            Some(Action::Editor) => SceneCommand::Push(Box::new(MapEditorScene::new())),
+            None => SceneCommand::None,
+        }
+    }
+}
+
+impl Default for MenuScene {
+    fn default() -> Self {
+        MenuScene::new()
+    }
+}
+
+impl Scene for MenuScene {
+    fn update(&mut self, _ctx: &mut Context, _shared: &mut Shared) -> GameResult<SceneCommand> {
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let (theme, strings) = (&shared.theme, &shared.strings);
+        fill_screen(renderer, theme.background)?;
+        renderer.text(strings.get("menu.title"), 280.0, 100.0, 50.0, theme.text)?;
+        renderer.text(strings.get("menu.hint"), 210.0, 150.0, 15.0, theme.text)?;
+
+        let has_save = shared.storage.data_path(storage::SAVE_FILE).exists();
+        for (index, button) in self.buttons.iter().enumerate() {
+            let color = match &button.action {
+                _ if index == self.selected => theme.button_selected,
+                Action::Continue if !has_save => theme.tab,
+                _ => theme.button,
+            };
+            renderer.rect(button.rect, color, RectStyle::Filled)?;
+            let key = button.shortcut.map_or('-', |(_, name)| name);
+            let label = format!("{}. {}", key, MenuScene::label(&button.action, strings));
+            let size = LABEL_SIZE * button.rect.h / BUTTON_HEIGHT;
+            renderer.text(&label, button.rect.x + 20.0, button.rect.y + (button.rect.h - size) / 2.0, size, theme.text)?;
+        }
+
+        let bottom = self.buttons.last().map_or(FIRST_BUTTON_Y, |button| button.rect.bottom());
+        renderer.text(strings.get("menu.quit"), 330.0, bottom + 16.0, 20.0, theme.warning)
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, shared: &mut Shared, keycode: KeyCode) -> SceneCommand {
+        if let Some(index) = self.buttons.iter().position(|button| button.shortcut.is_some_and(|(key, _)| key == keycode)) {
+            return self.activate(index, shared);
+        }
+        match keycode {
+            KeyCode::Up => self.selected = (self.selected + self.buttons.len() - 1) % self.buttons.len(),
+            KeyCode::Down => self.selected = (self.selected + 1) % self.buttons.len(),
+            KeyCode::Return | KeyCode::NumpadEnter => return self.activate(self.selected, shared),
+            KeyCode::Q => return SceneCommand::Quit,
+            _ => {}
+        }
+        SceneCommand::None
+    }
+
+    fn mouse_down(&mut self, _ctx: &mut Context, shared: &mut Shared, button: MouseButton, x: f32, y: f32) -> SceneCommand {
+        if button != MouseButton::Left {
+            return SceneCommand::None;
+        }
+        match self.buttons.iter().position(|button| button.rect.contains([x, y])) {
+            Some(index) => self.activate(index, shared),
+            None => SceneCommand::None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn games_keep_the_number_keys_and_everything_else_has_a_letter() {
+        let menu = MenuScene::new();
+        let games = menu.buttons.iter().filter(|button| matches!(button.action, Action::Play { .. }));
+        for (button, number) in games.zip('1'..='9') {
+            assert_eq!(button.shortcut.map(|(_, name)| name), Some(number));
+        }
+        for button in menu.buttons.iter().filter(|button| !matches!(button.action, Action::Play { .. })) {
+            assert!(button.shortcut.is_some());
+        }
+        let mut keys: Vec<char> = menu.buttons.iter().filter_map(|button| button.shortcut.map(|(_, name)| name)).collect();
+        let count = keys.len();
+        keys.sort_unstable();
+        keys.dedup();
+        assert_eq!(keys.len(), count, "no two buttons share a key");
+    }
+}
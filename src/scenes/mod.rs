@@ -0,0 +1,553 @@
+pub mod confirm;
+pub mod course_editor;
+pub mod courses;
+pub mod error;
+pub mod game_over;
+pub mod leaderboard;
+pub mod map_editor;
+pub mod menu;
+pub mod online;
+pub mod pause;
+pub mod play;
+pub mod pong;
+pub mod settings;
+pub mod stats;
+pub mod toast;
+
+use ggez::event::{KeyCode, MouseButton};
+use ggez::graphics::{Color, Rect};
+use ggez::{Context, GameResult};
+use crate::assets::Assets;
+use crate::games::TICKS_PER_SECOND;
+use crate::i18n::{Notice, Strings};
+use crate::net::spectate::Spectators;
+use crate::net::sync::{self, ScoreSync};
+use crate::render::{GgezRenderer, RectStyle, Renderer};
+use crate::scores::{ScoreBoard, ScoreEntry};
+use crate::scenes::toast::Toasts;
+use crate::settings::{Settings, SETTINGS_FILE};
+use crate::stats::{History, RunRecord};
+use crate::storage::{self, Storage};
+use crate::theme::{self, Theme};
+
+pub const SCREEN_WIDTH: f32 = 800.0;
+pub const SCREEN_HEIGHT: f32 = 600.0;
+
+/// State every scene can read and change, owned by `GameState` and handed down with each event.
+pub struct Shared {
+    pub scores: ScoreBoard,
+    /// The run saved most recently, so the leaderboard can highlight it.
+    pub last_score: Option<ScoreEntry>,
+    pub toasts: Toasts,
+    /// Every finished run, for the stats screen.
+    pub history: History,
+    pub storage: Storage,
+    pub settings: Settings,
+    /// Built-in themes followed by the player's own from the config dir.
+    pub themes: Vec<Theme>,
+    /// The theme named in the settings, or Light if it no longer exists.
+    pub theme: Theme,
+    /// Texts in the language named in the settings.
+    pub strings: Strings,
+    /// Sends runs to the shared leaderboard, when the settings name one.
+    pub sync: Option<ScoreSync>,
+    /// Streams running games to spectators, when started with `--spectate`.
+    pub spectators: Option<Spectators>,
+}
+
+impl Shared {
+    pub fn new(storage: Storage) -> Self {
+        let (settings, mut notices) = Settings::load(storage.config_path(SETTINGS_FILE));
+        let strings = Strings::load(&settings.language);
+        let (themes, theme_notices) = Theme::load_all(&storage.config_path(theme::THEMES_DIR));
+        notices.extend(theme_notices);
+
+        let (mut scores, score_notices) = ScoreBoard::load(storage.data_path(storage::SCORES_FILE));
+        notices.extend(score_notices);
+        let (history, history_notices) = History::load(storage.data_path(storage::HISTORY_FILE));
+        notices.extend(history_notices);
+        if let Some(legacy) = storage::legacy_scores_file() {
+            match scores.migrate(&legacy) {
+                Ok(count) => log::info!("Moved {} scores from {} into the data dir", count, legacy.display()),
+                Err(error) => {
+                    log::error!("Could not migrate {}: {}", legacy.display(), error);
+                    notices.push(Notice::new("error.migrate").with("error", error));
+                }
+            }
+        }
+
+        let mut toasts = Toasts::new();
+        for notice in &notices {
+            toasts.push(strings.notice(notice));
+        }
+        let sync = match settings.leaderboard_server.as_str() {
+            "" => None,
+            url => match ScoreSync::start(url, storage.data_path(sync::QUEUE_FILE)) {
+                Ok(sync) => Some(sync),
+                Err(error) => {
+                    log::error!("Could not start the leaderboard sync: {}", error);
+                    toasts.push(strings.format("error.sync", &[("error", &error)]));
+                    None
+                }
+            },
+        };
+        let theme = find_theme(&themes, &settings.theme);
+        Shared {
+            scores,
+            last_score: None,
+            toasts,
+            history,
+            storage,
+            settings,
+            themes,
+            theme,
+            strings,
+            sync,
+            spectators: None,
+        }
+    }
+
+    pub fn select_theme(&mut self, name: &str) {
+        self.settings.theme = name.to_string();
+        self.theme = find_theme(&self.themes, name);
+    }
+
+    pub fn select_language(&mut self, code: &str) {
+        self.strings = Strings::load(code);
+        self.settings.language = self.strings.language().to_string();
+    }
+
+    pub fn save_settings(&mut self) {
+        if let Err(error) = self.settings.save() {
+            log::error!("Could not save settings: {}", error);
+            self.toasts.push(self.strings.format("error.settings", &[("error", &error)]));
+        }
+    }
+
+    pub fn record_run(&mut self, run: RunRecord) {
+        if let Err(error) = self.history.add(run) {
+            log::error!("Could not save the run history: {}", error);
+            self.toasts.push(self.strings.format("error.history", &[("error", &error)]));
+        }
+    }
+
+    pub fn save_scores(&mut self) {
+        if let Err(error) = self.scores.save() {
+            log::error!("Could not save scores: {}", error);
+            self.toasts.push(self.strings.format("error.scores", &[("error", &error)]));
+        }
+    }
+}
+
+/// The theme called `name`, falling back to the first one (Light) when it is gone.
+pub fn find_theme(themes: &[Theme], name: &str) -> Theme {
+    match themes.iter().find(|theme| theme.name == name) {
+        Some(theme) => theme.clone(),
+        None => {
+            log::warn!("No theme called '{}', using {}", name, themes[0].name);
+            themes[0].clone()
+        }
+    }
+}
+
+/// What a scene asks the stack to do after handling an event.
+pub enum SceneCommand {
+    None,
+    Push(Box<dyn Scene>),
+    Pop,
+    PopN(usize),
+    Replace(Box<dyn Scene>),
+    Quit,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Transition {
+    None,
+    Fade(f32),
+    Slide(f32),
+}
+
+impl Transition {
+    fn duration(self) -> f32 {
+        match self {
+            Transition::None => 0.0,
+            Transition::Fade(duration) | Transition::Slide(duration) => duration,
+        }
+    }
+}
+
+pub trait Scene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<SceneCommand>;
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult;
+
+    fn key_down(&mut self, _ctx: &mut Context, _shared: &mut Shared, _keycode: KeyCode) -> SceneCommand {
+        SceneCommand::None
+    }
+
+    fn mouse_down(&mut self, _ctx: &mut Context, _shared: &mut Shared, _button: MouseButton, _x: f32, _y: f32) -> SceneCommand {
+        SceneCommand::None
+    }
+
+    fn mouse_wheel(&mut self, _ctx: &mut Context, _shared: &mut Shared, _y: f32) {}
+
+    /// The pointer moved; scenes that paint with a held button check it in `ggez::input::mouse`.
+    fn mouse_motion(&mut self, _ctx: &mut Context, _shared: &mut Shared, _x: f32, _y: f32) {}
+
+    /// Called by the stack for ESC. Every scene pops itself unless it has a good reason not to.
+    fn escape(&mut self, _ctx: &mut Context, _shared: &mut Shared) -> SceneCommand {
+        SceneCommand::Pop
+    }
+
+    fn on_enter(&mut self, _shared: &mut Shared) {}
+
+    fn on_exit(&mut self, _shared: &mut Shared) {}
+
+    /// Overlays are drawn on top of the scene below them instead of replacing it.
+    fn is_overlay(&self) -> bool {
+        false
+    }
+
+    /// Animation played when the scene is pushed onto or popped off the stack.
+    fn transition(&self) -> Transition {
+        Transition::None
+    }
+}
+
+struct ActiveTransition {
+    kind: Transition,
+    elapsed: f32,
+    leaving: Option<Box<dyn Scene>>,
+}
+
+impl ActiveTransition {
+    fn progress(&self) -> f32 {
+        let duration = self.kind.duration();
+        if duration <= 0.0 {
+            1.0
+        } else {
+            (self.elapsed / duration).min(1.0)
+        }
+    }
+}
+
+/// The open scenes and the transition between them, kept apart from ggez's context so the
+/// bookkeeping can be tested on its own.
+struct Stack {
+    scenes: Vec<Box<dyn Scene>>,
+    transition: Option<ActiveTransition>,
+}
+
+impl Stack {
+    fn push(&mut self, shared: &mut Shared, mut scene: Box<dyn Scene>) {
+        scene.on_enter(shared);
+        self.start_transition(scene.transition(), None);
+        self.scenes.push(scene);
+    }
+
+    fn pop(&mut self, shared: &mut Shared) {
+        if let Some(mut scene) = self.scenes.pop() {
+            scene.on_exit(shared);
+            let kind = scene.transition();
+            self.start_transition(kind, Some(scene));
+        }
+    }
+
+    fn replace(&mut self, shared: &mut Shared, scene: Box<dyn Scene>) {
+        if let Some(mut old) = self.scenes.pop() {
+            old.on_exit(shared);
+        }
+        self.push(shared, scene);
+    }
+
+    fn clear(&mut self, shared: &mut Shared) {
+        while let Some(mut scene) = self.scenes.pop() {
+            scene.on_exit(shared);
+        }
+    }
+
+    fn start_transition(&mut self, kind: Transition, leaving: Option<Box<dyn Scene>>) {
+        self.transition = if kind == Transition::None {
+            None
+        } else {
+            Some(ActiveTransition { kind, elapsed: 0.0, leaving })
+        };
+    }
+
+    fn run(&mut self, shared: &mut Shared, command: SceneCommand) {
+        match command {
+            SceneCommand::None => {}
+            SceneCommand::Push(scene) => self.push(shared, scene),
+            SceneCommand::Pop => self.pop(shared),
+            SceneCommand::PopN(count) => {
+                for _ in 0..count {
+                    self.pop(shared);
+                }
+            }
+            SceneCommand::Replace(scene) => self.replace(shared, scene),
+            SceneCommand::Quit => self.clear(shared),
+        }
+        if self.scenes.is_empty() {
+            self.transition = None;
+        }
+    }
+
+    /// Moves the transition on by `seconds`, ending it once it has played out.
+    fn advance(&mut self, seconds: f32) {
+        if let Some(transition) = &mut self.transition {
+            transition.elapsed += seconds;
+            if transition.progress() >= 1.0 {
+                self.transition = None;
+            }
+        }
+    }
+
+    fn is_animating(&self) -> bool {
+        self.transition.is_some()
+    }
+}
+
+pub struct SceneStack {
+    stack: Stack,
+    shared: Shared,
+    assets: Assets,
+}
+
+impl SceneStack {
+    pub fn new(ctx: &mut Context, mut shared: Shared, mut root: Box<dyn Scene>) -> Self {
+        root.on_enter(&mut shared);
+        SceneStack {
+            stack: Stack { scenes: vec![root], transition: None },
+            shared,
+            assets: Assets::load(ctx),
+        }
+    }
+
+    pub fn push(&mut self, scene: Box<dyn Scene>) {
+        self.stack.push(&mut self.shared, scene);
+    }
+
+    /// Pops every scene so each one gets its exit hook; `apply` closes the window once the stack is empty.
+    pub fn clear(&mut self) {
+        self.stack.clear(&mut self.shared);
+    }
+
+    pub fn apply(&mut self, ctx: &mut Context, command: SceneCommand) {
+        self.stack.run(&mut self.shared, command);
+        if self.stack.scenes.is_empty() {
+            ggez::event::quit(ctx);
+        }
+    }
+
+    pub fn update(&mut self, ctx: &mut Context) -> GameResult {
+        let seconds = ggez::timer::delta(ctx).as_secs_f32();
+        self.shared.toasts.update(seconds);
+        self.stack.advance(seconds);
+
+        let command = match self.stack.scenes.last_mut() {
+            Some(scene) => scene.update(ctx, &mut self.shared)?,
+            None => SceneCommand::None,
+        };
+        // Games use up the fixed-step time they need; whatever is left is dropped here, so a game
+        // does not fast-forward through the time spent in the menu or behind the pause overlay.
+        while ggez::timer::check_update_time(ctx, TICKS_PER_SECOND) {}
+        self.apply(ctx, command);
+        Ok(())
+    }
+
+    pub fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        let mut renderer = GgezRenderer::new(ctx, &mut self.assets);
+        renderer.clear(Color::BLACK);
+
+        let first_visible = self
+            .stack
+            .scenes
+            .iter()
+            .rposition(|scene| !scene.is_overlay())
+            .unwrap_or(0);
+        let top = self.stack.scenes.len().saturating_sub(1);
+
+        let (kind, progress) = match &self.stack.transition {
+            Some(transition) => (transition.kind, transition.progress()),
+            None => (Transition::None, 1.0),
+        };
+        let entering = self.stack.transition.as_ref().is_some_and(|t| t.leaving.is_none());
+
+        for index in first_visible..self.stack.scenes.len() {
+            let slide = entering && index == top && matches!(kind, Transition::Slide(_));
+            if slide {
+                renderer.set_offset((1.0 - progress) * SCREEN_WIDTH)?;
+            }
+            self.stack.scenes[index].draw(&mut renderer, &self.shared)?;
+            if slide {
+                renderer.set_offset(0.0)?;
+            }
+        }
+
+        if let Some(transition) = &mut self.stack.transition {
+            if let (Some(leaving), Transition::Slide(_)) = (&mut transition.leaving, kind) {
+                renderer.set_offset(progress * SCREEN_WIDTH)?;
+                leaving.draw(&mut renderer, &self.shared)?;
+                renderer.set_offset(0.0)?;
+            }
+        }
+
+        if let Transition::Fade(_) = kind {
+            let alpha = 1.0 - progress;
+            fill_screen(&mut renderer, Color::new(0.0, 0.0, 0.0, alpha))?;
+        }
+
+        self.shared.toasts.draw(&mut renderer, &self.shared.theme)?;
+        renderer.present()
+    }
+
+    pub fn key_down(&mut self, ctx: &mut Context, keycode: KeyCode) {
+        if self.stack.is_animating() {
+            return;
+        }
+        let command = match self.stack.scenes.last_mut() {
+            Some(scene) if keycode == KeyCode::Escape => scene.escape(ctx, &mut self.shared),
+            Some(scene) => scene.key_down(ctx, &mut self.shared, keycode),
+            None => SceneCommand::None,
+        };
+        self.apply(ctx, command);
+    }
+
+    pub fn mouse_down(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if self.stack.is_animating() {
+            return;
+        }
+        let command = match self.stack.scenes.last_mut() {
+            Some(scene) => scene.mouse_down(ctx, &mut self.shared, button, x, y),
+            None => SceneCommand::None,
+        };
+        self.apply(ctx, command);
+    }
+
+    pub fn mouse_wheel(&mut self, ctx: &mut Context, y: f32) {
+        if let Some(scene) = self.stack.scenes.last_mut() {
+            scene.mouse_wheel(ctx, &mut self.shared, y);
+        }
+    }
+
+    pub fn mouse_motion(&mut self, ctx: &mut Context, x: f32, y: f32) {
+        if self.stack.is_animating() {
+            return;
+        }
+        if let Some(scene) = self.stack.scenes.last_mut() {
+            scene.mouse_motion(ctx, &mut self.shared, x, y);
+        }
+    }
+}
+
+/// Scenes paint their background with this instead of `Renderer::clear`, so slides and overlays keep working.
+pub fn fill_screen(renderer: &mut dyn Renderer, color: Color) -> GameResult {
+    renderer.rect(Rect::new(0.0, 0.0, SCREEN_WIDTH, SCREEN_HEIGHT), color, RectStyle::Filled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::RecordingRenderer;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    type Log = Rc<RefCell<Vec<String>>>;
+
+    /// Writes its hooks, and its name whenever it is drawn, into a shared log.
+    struct Stub {
+        name: &'static str,
+        transition: Transition,
+        log: Log,
+    }
+
+    impl Scene for Stub {
+        fn update(&mut self, _ctx: &mut Context, _shared: &mut Shared) -> GameResult<SceneCommand> {
+            Ok(SceneCommand::None)
+        }
+
+        fn draw(&mut self, _renderer: &mut dyn Renderer, _shared: &Shared) -> GameResult {
+            self.log.borrow_mut().push(self.name.to_string());
+            Ok(())
+        }
+
+        fn on_enter(&mut self, _shared: &mut Shared) {
+            self.log.borrow_mut().push(format!("enter {}", self.name));
+        }
+
+        fn on_exit(&mut self, _shared: &mut Shared) {
+            self.log.borrow_mut().push(format!("exit {}", self.name));
+        }
+
+        fn transition(&self) -> Transition {
+            self.transition
+        }
+    }
+
+    fn stub(name: &'static str, log: &Log) -> Box<dyn Scene> {
+        Box::new(Stub { name, transition: Transition::None, log: Rc::clone(log) })
+    }
+
+    /// What the log gained since the last call.
+    fn taken(log: &Log) -> Vec<String> {
+        log.borrow_mut().drain(..).collect()
+    }
+
+    /// The scenes on the stack, bottom first.
+    fn names(stack: &mut Stack, shared: &Shared, log: &Log) -> Vec<String> {
+        taken(log);
+        for scene in &mut stack.scenes {
+            scene.draw(&mut RecordingRenderer::new(), shared).unwrap();
+        }
+        taken(log)
+    }
+
+    #[test]
+    fn scenes_enter_and_exit_in_stack_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut shared = Shared::new(Storage::in_dir(dir.path().to_path_buf()));
+        let log = Log::default();
+        let mut stack = Stack { scenes: Vec::new(), transition: None };
+
+        for name in ["menu", "play", "pause"] {
+            stack.run(&mut shared, SceneCommand::Push(stub(name, &log)));
+        }
+        assert_eq!(taken(&log), ["enter menu", "enter play", "enter pause"]);
+
+        stack.run(&mut shared, SceneCommand::Replace(stub("game over", &log)));
+        assert_eq!(taken(&log), ["exit pause", "enter game over"]);
+        assert_eq!(names(&mut stack, &shared, &log), ["menu", "play", "game over"]);
+
+        stack.run(&mut shared, SceneCommand::PopN(2));
+        assert_eq!(taken(&log), ["exit game over", "exit play"]);
+        assert_eq!(names(&mut stack, &shared, &log), ["menu"]);
+
+        stack.run(&mut shared, SceneCommand::Push(stub("stats", &log)));
+        stack.run(&mut shared, SceneCommand::Quit);
+        assert_eq!(taken(&log), ["enter stats", "exit stats", "exit menu"]);
+        assert!(stack.scenes.is_empty());
+    }
+
+    #[test]
+    fn transitions_play_out_and_end_with_the_stack() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut shared = Shared::new(Storage::in_dir(dir.path().to_path_buf()));
+        let log = Log::default();
+        let mut stack = Stack { scenes: vec![stub("menu", &log)], transition: None };
+
+        let sliding = Box::new(Stub { name: "leaderboard", transition: Transition::Slide(0.25), log: Rc::clone(&log) });
+        stack.run(&mut shared, SceneCommand::Push(sliding));
+        assert!(stack.is_animating());
+        stack.advance(0.2);
+        assert!(stack.is_animating());
+        stack.advance(0.1);
+        assert!(!stack.is_animating());
+
+        // Popping a sliding scene keeps it around to slide out; popping the last one does not.
+        stack.run(&mut shared, SceneCommand::Pop);
+        assert!(stack.transition.as_ref().is_some_and(|transition| transition.leaving.is_some()));
+        stack.run(&mut shared, SceneCommand::Push(Box::new(Stub { name: "fade", transition: Transition::Fade(1.0), log: Rc::clone(&log) })));
+        stack.run(&mut shared, SceneCommand::PopN(5));
+        assert!(stack.scenes.is_empty() && !stack.is_animating());
+    }
+}
@@ -0,0 +1,96 @@
+use crate::games::GameInput;
+use crate::net::client::Client;
+use crate::net::protocol::{ClientMessage, ServerMessage, Snapshot};
+use crate::render::{RectStyle, Renderer};
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared, SCREEN_WIDTH};
+use ggez::event::KeyCode;
+use ggez::graphics::Rect;
+use ggez::{Context, GameResult};
+
+/// A game of Snake on a server: it sends the arrows there and draws whatever state came back last.
+pub struct OnlineScene {
+    client: Client,
+    snapshot: Option<Snapshot>,
+    /// Why the connection ended, once it has.
+    lost: Option<String>,
+}
+
+impl OnlineScene {
+    pub fn new(client: Client) -> Self {
+        OnlineScene { client, snapshot: None, lost: None }
+    }
+
+    /// The board fills the width of the screen, whatever size the server picked.
+    fn cell(&self, (x, y): (i32, i32)) -> Rect {
+        let size = SCREEN_WIDTH / self.client.width.max(1) as f32;
+        Rect::new(x as f32 * size, y as f32 * size, size, size)
+    }
+}
+
+impl Scene for OnlineScene {
+    fn update(&mut self, _ctx: &mut Context, _shared: &mut Shared) -> GameResult<SceneCommand> {
+        if self.lost.is_some() {
+            return Ok(SceneCommand::None);
+        }
+        let result = self.client.keep_alive().and_then(|()| self.client.poll());
+        match result {
+            Ok(messages) => {
+                for message in messages {
+                    match message {
+                        ServerMessage::Snapshot(snapshot) => self.snapshot = Some(snapshot),
+                        ServerMessage::Refused(reason) => self.lost = Some(reason),
+                        ServerMessage::Welcome { .. } => {}
+                    }
+                }
+            }
+            Err(error) => {
+                log::warn!("Lost the connection to the server: {}", error);
+                self.lost = Some(error.to_string());
+            }
+        }
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let theme = &shared.theme;
+        fill_screen(renderer, theme.sky)?;
+        if let Some(snapshot) = &self.snapshot {
+            renderer.rect(self.cell(snapshot.food), theme.food, RectStyle::Filled)?;
+            for player in &snapshot.players {
+                let color = if player.id == self.client.id { theme.snake } else { theme.pipe };
+                for &segment in &player.snake {
+                    renderer.rect(self.cell(segment), color, RectStyle::Filled)?;
+                }
+            }
+
+            let mut players: Vec<_> = snapshot.players.iter().collect();
+            players.sort_by(|a, b| b.score.cmp(&a.score).then(a.id.cmp(&b.id)));
+            for (row, player) in players.iter().enumerate() {
+                let key = if player.id == self.client.id { "online.you" } else { "online.player" };
+                let text = shared.strings.format(key, &[("name", &player.name), ("score", &player.score)]);
+                let color = if player.alive { theme.game_text } else { theme.warning };
+                renderer.text(&text, 10.0, 10.0 + row as f32 * 22.0, 20.0, color)?;
+            }
+            if snapshot.player(self.client.id).is_some_and(|player| !player.alive) {
+                renderer.text(shared.strings.get("online.respawn"), 280.0, 280.0, 26.0, theme.warning)?;
+            }
+        }
+        renderer.text(shared.strings.get("online.help"), 10.0, 575.0, 18.0, theme.game_text)?;
+
+        if let Some(error) = &self.lost {
+            fill_screen(renderer, theme.overlay)?;
+            renderer.text(&shared.strings.format("online.lost", &[("error", error)]), 40.0, 280.0, 22.0, theme.overlay_text)?;
+        }
+        Ok(())
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, _shared: &mut Shared, keycode: KeyCode) -> SceneCommand {
+        let input = GameInput::from_keycode(keycode).filter(|input| *input != GameInput::Action);
+        if let (Some(input), None) = (input, &self.lost) {
+            if let Err(error) = self.client.send(&ClientMessage::Input(input)) {
+                self.lost = Some(error.to_string());
+            }
+        }
+        SceneCommand::None
+    }
+}
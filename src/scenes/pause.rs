@@ -0,0 +1,66 @@
+use crate::scenes::confirm::ConfirmScene;
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared};
+use crate::render::Renderer;
+use ggez::event::KeyCode;
+use ggez::{Context, GameResult};
+use std::cell::Cell;
+use std::rc::Rc;
+
+pub struct PauseScene {
+    /// Shared with the game below, which saves the run instead of ending it once this is set.
+    /// `None` when the run cannot be saved, e.g. during a replay.
+    save_and_quit: Option<Rc<Cell<bool>>>,
+}
+
+impl PauseScene {
+    pub fn new() -> Self {
+        PauseScene { save_and_quit: None }
+    }
+
+    pub fn with_save(save_and_quit: Rc<Cell<bool>>) -> Self {
+        PauseScene { save_and_quit: Some(save_and_quit) }
+    }
+}
+
+impl Default for PauseScene {
+    fn default() -> Self {
+        PauseScene::new()
+    }
+}
+
+impl Scene for PauseScene {
+    fn update(&mut self, _ctx: &mut Context, _shared: &mut Shared) -> GameResult<SceneCommand> {
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let theme = &shared.theme;
+        fill_screen(renderer, theme.overlay)?;
+        renderer.text(shared.strings.get("pause.title"), 320.0, 200.0, 50.0, theme.overlay_text)?;
+        renderer.text(shared.strings.get("pause.help"), 190.0, 280.0, 25.0, theme.overlay_text)?;
+        if self.save_and_quit.is_some() {
+            renderer.text(shared.strings.get("pause.save"), 190.0, 320.0, 25.0, theme.overlay_text)?;
+        }
+        Ok(())
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, shared: &mut Shared, keycode: KeyCode) -> SceneCommand {
+        match keycode {
+            KeyCode::P => SceneCommand::Pop,
+            // Pops the dialog, this overlay and the game underneath it.
+            KeyCode::Q => SceneCommand::Push(Box::new(ConfirmScene::new(shared.strings.get("pause.leave"), SceneCommand::PopN(3)))),
+            KeyCode::S => match &self.save_and_quit {
+                Some(save_and_quit) => {
+                    save_and_quit.set(true);
+                    SceneCommand::PopN(2)
+                }
+                None => SceneCommand::None,
+            },
+            _ => SceneCommand::None,
+        }
+    }
+
+    fn is_overlay(&self) -> bool {
+        true
+    }
+}
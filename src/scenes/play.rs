@@ -0,0 +1,264 @@
+use crate::daily;
+use crate::date;
+use crate::effects::Effects;
+use crate::games::ghost::{self, Ghost};
+use crate::games::session::{RunSummary, Session};
+use crate::games::{GameInput, RunConfig, TICKS_PER_SECOND};
+use crate::render::{Renderer, Shifted};
+use crate::replay::Replay;
+use crate::save::SaveGame;
+use crate::scenes::error::ErrorScene;
+use crate::scenes::game_over::{GameOverPanel, PanelAction};
+use crate::scenes::pause::PauseScene;
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared, Transition};
+use crate::scores;
+use crate::storage;
+use ggez::event::{KeyCode, MouseButton};
+use ggez::{timer, Context, GameResult};
+use std::cell::Cell;
+use std::rc::Rc;
+
+pub struct PlayScene {
+    session: Session,
+    pending: Vec<GameInput>,
+    keep_seed: bool,
+    effects: Effects,
+    /// Set once the replay and score are saved, so a run is never saved twice.
+    finished: bool,
+    panel: Option<GameOverPanel>,
+    /// Set by the pause overlay's Save & Quit.
+    save_and_quit: Rc<Cell<bool>>,
+    /// An earlier run raced alongside this one, picked in the settings.
+    ghost: Option<Ghost>,
+}
+
+impl PlayScene {
+    pub fn new(config: RunConfig) -> Self {
+        PlayScene::with_session(Session::new(config))
+    }
+
+    pub fn replay(replay: Replay) -> Self {
+        PlayScene::with_session(Session::replay(replay))
+    }
+
+    pub fn resume(save: SaveGame) -> Result<Self, String> {
+        Ok(PlayScene::with_session(Session::resume(save)?))
+    }
+
+    fn with_session(session: Session) -> Self {
+        PlayScene {
+            session,
+            pending: Vec::new(),
+            keep_seed: false,
+            effects: Effects::new(),
+            finished: false,
+            panel: None,
+            save_and_quit: Rc::new(Cell::new(false)),
+            ghost: None,
+        }
+    }
+
+    /// Restarts keep the seed instead of rolling a new one, e.g. when it was chosen on the command line.
+    pub fn keep_seed(mut self) -> Self {
+        self.keep_seed = true;
+        self
+    }
+
+    fn restart(&self) -> PlayScene {
+        let mut session = self.session.restarted();
+        if !self.keep_seed && !session.is_playback() {
+            let mut config = session.config.clone();
+            config.reseed();
+            session = Session::new(config);
+        }
+        PlayScene { keep_seed: self.keep_seed, ..PlayScene::with_session(session) }
+    }
+
+    fn save_replay(&self, shared: &mut Shared) {
+        let config = &self.session.config;
+        let path = shared.storage.replay_path(&Replay::file_name(&config.game, &config.mode));
+        if let Err(error) = self.session.recording().save(&path) {
+            log::error!("Could not save replay to {}: {}", path.display(), error);
+            shared.toasts.push(shared.strings.format("error.replay", &[("error", &error)]));
+        }
+    }
+
+    /// Saves the replay and the score the first time it is called for a run; later calls do nothing.
+    fn finish(&mut self, shared: &mut Shared) -> Option<RunSummary> {
+        if self.finished || self.session.tick == 0 {
+            return None;
+        }
+        self.finished = true;
+        if !self.session.is_playback() {
+            self.save_replay(shared);
+            shared.record_run(self.session.run_record());
+        }
+        let summary = self.session.record(&mut shared.scores);
+        if let Some(entry) = &summary.entry {
+            shared.save_scores();
+            if let Some(sync) = &mut shared.sync {
+                sync.submit(entry.clone());
+            }
+            shared.last_score = Some(entry.clone());
+        }
+        if let Err(error) = ghost::keep(&shared.storage, &self.session, &summary) {
+            log::error!("Could not keep the run as a ghost: {}", error);
+            shared.toasts.push(shared.strings.format("error.replay", &[("error", &error)]));
+        }
+        Some(summary)
+    }
+
+    /// Writes the run to the save slot instead of ending it; true if it was saved.
+    fn save(&mut self, shared: &mut Shared) -> bool {
+        let Some(save) = self.session.save() else {
+            return false;
+        };
+        let path = shared.storage.data_path(storage::SAVE_FILE);
+        match save.save(&path) {
+            Ok(()) => {
+                self.finished = true;
+                shared.toasts.push(shared.strings.get("play.saved").to_string());
+                true
+            }
+            Err(error) => {
+                log::error!("Could not save the game to {}: {}", path.display(), error);
+                shared.toasts.push(shared.strings.format("error.save", &[("error", &error)]));
+                false
+            }
+        }
+    }
+
+    fn run(&self, action: PanelAction) -> SceneCommand {
+        match action {
+            PanelAction::Restart => SceneCommand::Replace(Box::new(self.restart())),
+            PanelAction::Menu => SceneCommand::Pop,
+            PanelAction::WatchReplay => SceneCommand::Replace(Box::new(PlayScene::replay(self.session.recording()))),
+        }
+    }
+}
+
+impl Scene for PlayScene {
+    fn update(&mut self, ctx: &mut Context, shared: &mut Shared) -> GameResult<SceneCommand> {
+        while timer::check_update_time(ctx, TICKS_PER_SECOND) {
+            if let Some(ghost) = self.ghost.as_mut().filter(|_| !self.session.game.is_over()) {
+                ghost.step();
+            }
+            self.session.step(self.pending.drain(..));
+            self.effects.follow(&mut self.session, &shared.theme, &shared.settings);
+        }
+        if let Some(error) = self.session.game.error() {
+            return Ok(SceneCommand::Replace(Box::new(ErrorScene::new(shared.strings.get("error.script"), &error))));
+        }
+        if let Some(spectators) = shared.spectators.as_ref().filter(|spectators| spectators.watching() > 0) {
+            spectators.broadcast(&self.session.spectate());
+        }
+        if self.session.is_finished() && self.panel.is_none() {
+            if let Some(summary) = self.finish(shared) {
+                let mut panel = GameOverPanel::new(summary, &self.session, &shared.strings);
+                let config = &self.session.config;
+                if daily::is_daily(&config.mode) && !self.session.is_playback() {
+                    panel = panel.with_streak(daily::streak(&shared.history, &config.game, &scores::player_name(), date::now_timestamp()));
+                }
+                self.panel = Some(panel);
+            }
+        }
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        fill_screen(renderer, shared.theme.sky)?;
+        let (x, y) = self.effects.offset();
+        let mut shifted = Shifted { inner: renderer, x, y };
+        if let Some(ghost) = &self.ghost {
+            ghost.draw(&mut shifted, &shared.theme)?;
+        }
+        self.session.game.draw(&mut shifted, &shared.theme)?;
+        self.effects.draw(&mut shifted)?;
+        match (&self.panel, &self.ghost) {
+            (Some(panel), _) => panel.draw(renderer, &shared.theme, &shared.strings),
+            (None, Some(ghost)) => ghost.draw_lead(renderer, &shared.theme, &shared.strings, self.session.game.score()),
+            (None, None) => Ok(()),
+        }
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, _shared: &mut Shared, keycode: KeyCode) -> SceneCommand {
+        if let Some(panel) = &mut self.panel {
+            let action = match keycode {
+                KeyCode::R | KeyCode::Space => Some(PanelAction::Restart),
+                KeyCode::M => Some(PanelAction::Menu),
+                KeyCode::W if panel.has(PanelAction::WatchReplay) => Some(PanelAction::WatchReplay),
+                KeyCode::Return | KeyCode::NumpadEnter => Some(panel.selected()),
+                KeyCode::Left | KeyCode::Up => {
+                    panel.select_next(-1);
+                    None
+                }
+                KeyCode::Right | KeyCode::Down | KeyCode::Tab => {
+                    panel.select_next(1);
+                    None
+                }
+                _ => None,
+            };
+            return action.map_or(SceneCommand::None, |action| self.run(action));
+        }
+        // R skips the death animation too, for an instant restart.
+        if self.session.game.is_over() {
+            return match keycode {
+                KeyCode::R => SceneCommand::Replace(Box::new(self.restart())),
+                _ => SceneCommand::None,
+            };
+        }
+        if keycode == KeyCode::P {
+            let pause = match self.session.save() {
+                Some(_) => PauseScene::with_save(Rc::clone(&self.save_and_quit)),
+                None => PauseScene::new(),
+            };
+            return SceneCommand::Push(Box::new(pause));
+        }
+        if let Some(input) = GameInput::from_keycode(keycode) {
+            self.pending.push(input);
+        }
+        SceneCommand::None
+    }
+
+    fn mouse_down(&mut self, _ctx: &mut Context, _shared: &mut Shared, button: MouseButton, x: f32, y: f32) -> SceneCommand {
+        match self.panel.as_ref().and_then(|panel| panel.action_at(x, y)) {
+            Some(action) if button == MouseButton::Left => self.run(action),
+            _ => SceneCommand::None,
+        }
+    }
+
+    /// Brings in the ghost, moving a new run onto its seed so both meet the same course. Resumed
+    /// runs keep their seed and only get a ghost that was recorded on it. Daily challenges
+    /// already played today become practice.
+    fn on_enter(&mut self, shared: &mut Shared) {
+        if self.session.is_playback() {
+            return;
+        }
+        let mut config = self.session.config.clone();
+        let fixed_seed = self.keep_seed || self.session.tick > 0;
+        self.ghost = Ghost::load(&shared.storage, shared.settings.ghost, &mut config, fixed_seed);
+        if config.seed != self.session.config.seed {
+            self.session = Session::new(config);
+        }
+        if let Some(ghost) = &mut self.ghost {
+            ghost.skip_to(self.session.tick);
+        }
+        self.session.practice = daily::is_practice(&shared.history, &self.session.config, &scores::player_name());
+    }
+
+    /// Runs left before the panel showed up are saved here, or put in the save slot after Save & Quit.
+    fn on_exit(&mut self, shared: &mut Shared) {
+        if self.save_and_quit.get() && self.save(shared) {
+            return;
+        }
+        let unranked = !self.session.config.is_ranked() && !self.session.is_playback();
+        if self.finish(shared).is_some() && unranked {
+            let difficulty = shared.strings.get(&format!("difficulty.{}", self.session.config.difficulty)).to_string();
+            shared.toasts.push(shared.strings.format("play.unranked", &[("difficulty", &difficulty)]));
+        }
+    }
+
+    fn transition(&self) -> Transition {
+        Transition::Fade(0.3)
+    }
+}
@@ -0,0 +1,126 @@
+use crate::games::pong::PaddleInput;
+use crate::games::TICKS_PER_SECOND;
+use crate::net::pong::{Lobby, MatchState, PongMatch};
+use crate::render::Renderer;
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared};
+use ggez::event::KeyCode;
+use ggez::input::keyboard;
+use ggez::{timer, Context, GameResult};
+
+enum Stage {
+    Lobby(Lobby),
+    Playing(Box<PongMatch>),
+    /// The lobby failed, e.g. the host turned us away.
+    Failed(String),
+}
+
+/// Online Pong: waits in the lobby for the other player, then plays with the paddle held down
+/// by the arrows (or W and S).
+pub struct PongScene {
+    stage: Stage,
+}
+
+impl PongScene {
+    pub fn new(lobby: Lobby) -> Self {
+        PongScene { stage: Stage::Lobby(lobby) }
+    }
+
+    fn draw_lobby(lobby: &Lobby, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let theme = &shared.theme;
+        let text = match lobby.host_addr() {
+            Some(host) => shared.strings.format("pong.joining", &[("address", &host)]),
+            None => {
+                let port = lobby.local_addr().map_or_else(|_| "?".to_string(), |address| address.port().to_string());
+                shared.strings.format("pong.hosting", &[("port", &port)])
+            }
+        };
+        renderer.text(&text, 60.0, 260.0, 26.0, theme.text)?;
+        renderer.text(shared.strings.get("pong.lobby_help"), 60.0, 310.0, 20.0, theme.text)
+    }
+
+    fn draw_match(game: &PongMatch, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let (theme, strings) = (&shared.theme, &shared.strings);
+        game.game().draw(renderer, theme)?;
+
+        let stats = game.stats();
+        let ping = game.ping().map_or_else(|| "-".to_string(), |ping| ping.as_millis().to_string());
+        let line = strings.format(
+            "pong.stats",
+            &[("ping", &ping), ("rollbacks", &stats.rollbacks), ("frames", &stats.resimulated), ("deepest", &stats.deepest), ("stalls", &stats.stalls)],
+        );
+        renderer.text(&line, 10.0, 575.0, 16.0, theme.game_text)?;
+        if stats.desyncs > 0 {
+            renderer.text(&strings.format("pong.desync", &[("count", &stats.desyncs)]), 10.0, 550.0, 16.0, theme.warning)?;
+        }
+        let side = strings.get(if game.side() == 0 { "pong.left" } else { "pong.right" });
+        renderer.text(side, 10.0, 10.0, 18.0, theme.game_text)?;
+
+        let message = match game.state() {
+            MatchState::OpponentLeft => Some(strings.get("pong.left_game").to_string()),
+            MatchState::TimedOut => Some(strings.get("pong.timed_out").to_string()),
+            MatchState::Playing if game.is_finished() => {
+                let won = game.game().winner() == Some(game.side());
+                Some(strings.get(if won { "pong.won" } else { "pong.lost" }).to_string())
+            }
+            MatchState::Playing => None,
+        };
+        if let Some(message) = message {
+            fill_screen(renderer, theme.overlay)?;
+            let width = renderer.text_width(&message, 40.0);
+            renderer.text(&message, 400.0 - width / 2.0, 250.0, 40.0, theme.overlay_text)?;
+            let help = strings.get("pong.over_help");
+            let width = renderer.text_width(help, 22.0);
+            renderer.text(help, 400.0 - width / 2.0, 320.0, 22.0, theme.overlay_text)?;
+        }
+        Ok(())
+    }
+}
+
+impl Scene for PongScene {
+    fn update(&mut self, ctx: &mut Context, _shared: &mut Shared) -> GameResult<SceneCommand> {
+        // Counted in the lobby too, or the time spent waiting would be played all at once.
+        let mut ticks = 0;
+        while timer::check_update_time(ctx, TICKS_PER_SECOND) {
+            ticks += 1;
+        }
+        let started = match &mut self.stage {
+            Stage::Lobby(lobby) => lobby.poll(),
+            Stage::Playing(game) => {
+                let up = keyboard::is_key_pressed(ctx, KeyCode::Up) || keyboard::is_key_pressed(ctx, KeyCode::W);
+                let down = keyboard::is_key_pressed(ctx, KeyCode::Down) || keyboard::is_key_pressed(ctx, KeyCode::S);
+                for _ in 0..ticks {
+                    game.tick(PaddleInput::from_keys(up, down));
+                }
+                Ok(None)
+            }
+            Stage::Failed(_) => Ok(None),
+        };
+        match started {
+            Ok(Some(game)) => self.stage = Stage::Playing(Box::new(game)),
+            Ok(None) => {}
+            Err(reason) => {
+                log::warn!("Could not start the Pong game: {}", reason);
+                self.stage = Stage::Failed(reason);
+            }
+        }
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        match &self.stage {
+            Stage::Lobby(lobby) => {
+                fill_screen(renderer, shared.theme.background)?;
+                PongScene::draw_lobby(lobby, renderer, shared)
+            }
+            Stage::Playing(game) => {
+                fill_screen(renderer, shared.theme.sky)?;
+                PongScene::draw_match(game, renderer, shared)
+            }
+            Stage::Failed(reason) => {
+                fill_screen(renderer, shared.theme.background)?;
+                renderer.text(&shared.strings.format("pong.failed", &[("error", reason)]), 60.0, 260.0, 22.0, shared.theme.warning)?;
+                renderer.text(shared.strings.get("pong.lobby_help"), 60.0, 310.0, 20.0, shared.theme.text)
+            }
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::i18n::Strings;
+use crate::render::{RectStyle, Renderer};
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared, Transition};
+use crate::settings::GhostSource;
+use crate::theme::Theme;
+use ggez::event::{KeyCode, MouseButton};
+use ggez::graphics::Rect;
+use ggez::{Context, GameResult};
+
+const FIRST_ROW_Y: f32 = 120.0;
+const ROW_SPACING: f32 = 48.0;
+const ROW_X: f32 = 150.0;
+const ROW_WIDTH: f32 = 500.0;
+const ROW_HEIGHT: f32 = 40.0;
+const PREVIEW_Y: f32 = 415.0;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Row {
+    Theme,
+    Language,
+    Particles,
+    ScreenShake,
+    DeathAnimation,
+    Ghost,
+}
+
+const ROWS: [Row; 6] = [Row::Theme, Row::Language, Row::Particles, Row::ScreenShake, Row::DeathAnimation, Row::Ghost];
+
+/// Player preferences; Left/Right or a click on the arrows changes the selected row, and the
+/// settings are saved when the screen closes.
+pub struct SettingsScene {
+    selected: usize,
+    changed: bool,
+}
+
+impl SettingsScene {
+    pub fn new() -> Self {
+        SettingsScene { selected: 0, changed: false }
+    }
+
+    fn row_rect(index: usize) -> Rect {
+        Rect::new(ROW_X, FIRST_ROW_Y + index as f32 * ROW_SPACING, ROW_WIDTH, ROW_HEIGHT)
+    }
+
+    fn change(&mut self, shared: &mut Shared, row: Row, step: isize) {
+        match row {
+            Row::Theme => {
+                let count = shared.themes.len() as isize;
+                let current = shared.themes.iter().position(|theme| theme.name == shared.theme.name).unwrap_or(0) as isize;
+                let next = (current + step).rem_euclid(count) as usize;
+                let name = shared.themes[next].name.clone();
+                shared.select_theme(&name);
+            }
+            Row::Language => {
+                let languages = Strings::languages();
+                let count = languages.len() as isize;
+                let current = languages.iter().position(|&code| code == shared.strings.language()).unwrap_or(0) as isize;
+                shared.select_language(languages[(current + step).rem_euclid(count) as usize]);
+            }
+            Row::Particles => shared.settings.particles = !shared.settings.particles,
+            Row::ScreenShake => shared.settings.screen_shake = !shared.settings.screen_shake,
+            Row::DeathAnimation => shared.settings.death_animation = !shared.settings.death_animation,
+            Row::Ghost => {
+                let count = GhostSource::ALL.len() as isize;
+                let current = GhostSource::ALL.iter().position(|&source| source == shared.settings.ghost).unwrap_or(0) as isize;
+                shared.settings.ghost = GhostSource::ALL[(current + step).rem_euclid(count) as usize];
+            }
+        }
+        self.changed = true;
+    }
+
+    fn label(row: Row, shared: &Shared) -> (&str, String) {
+        let strings = &shared.strings;
+        match row {
+            Row::Theme => (strings.get("settings.theme"), shared.theme.name.clone()),
+            Row::Language => (strings.get("settings.language"), strings.get("language").to_string()),
+            Row::Particles => (strings.get("settings.particles"), switch(strings, shared.settings.particles)),
+            Row::ScreenShake => (strings.get("settings.screen_shake"), switch(strings, shared.settings.screen_shake)),
+            Row::DeathAnimation => (strings.get("settings.death_animation"), switch(strings, shared.settings.death_animation)),
+            Row::Ghost => (strings.get("settings.ghost"), strings.get(&format!("settings.ghost_{}", shared.settings.ghost.name())).to_string()),
+        }
+    }
+}
+
+impl Default for SettingsScene {
+    fn default() -> Self {
+        SettingsScene::new()
+    }
+}
+
+fn switch(strings: &Strings, on: bool) -> String {
+    strings.get(if on { "settings.on" } else { "settings.off" }).to_string()
+}
+
+/// A strip of the theme's game colours, so a theme can be judged without starting a game.
+fn draw_preview(renderer: &mut dyn Renderer, theme: &Theme, strings: &Strings) -> GameResult {
+    let area = Rect::new(ROW_X, PREVIEW_Y, ROW_WIDTH, 120.0);
+    renderer.rect(area, theme.sky, RectStyle::Filled)?;
+    renderer.rect(area, theme.text, RectStyle::Outline(2.0))?;
+    for segment in 0..4 {
+        renderer.rect(Rect::new(ROW_X + 40.0 + segment as f32 * 20.0, PREVIEW_Y + 50.0, 20.0, 20.0), theme.snake, RectStyle::Filled)?;
+    }
+    renderer.rect(Rect::new(ROW_X + 160.0, PREVIEW_Y + 50.0, 20.0, 20.0), theme.food, RectStyle::Filled)?;
+    renderer.rect(Rect::new(ROW_X + 300.0, PREVIEW_Y, 50.0, 40.0), theme.pipe, RectStyle::Filled)?;
+    renderer.rect(Rect::new(ROW_X + 300.0, PREVIEW_Y + 90.0, 50.0, 30.0), theme.pipe, RectStyle::Filled)?;
+    renderer.rect(Rect::new(ROW_X + 250.0, PREVIEW_Y + 55.0, 20.0, 20.0), theme.bird, RectStyle::Outline(1.0))?;
+    renderer.text(&strings.format("play.score", &[("score", &7)]), ROW_X + 380.0, PREVIEW_Y + 50.0, 20.0, theme.game_text)
+}
+
+impl Scene for SettingsScene {
+    fn update(&mut self, _ctx: &mut Context, _shared: &mut Shared) -> GameResult<SceneCommand> {
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let theme = &shared.theme;
+        fill_screen(renderer, theme.background)?;
+        renderer.text(shared.strings.get("settings.title"), 300.0, 60.0, 50.0, theme.text)?;
+
+        for (index, &row) in ROWS.iter().enumerate() {
+            let rect = SettingsScene::row_rect(index);
+            let color = if index == self.selected { theme.button_selected } else { theme.button };
+            renderer.rect(rect, color, RectStyle::Filled)?;
+            let (name, value) = SettingsScene::label(row, shared);
+            renderer.text(name, rect.x + 20.0, rect.y + 8.0, 25.0, theme.text)?;
+            let value = format!("<  {}  >", value);
+            let width = renderer.text_width(&value, 25.0);
+            renderer.text(&value, rect.right() - width - 20.0, rect.y + 8.0, 25.0, theme.text)?;
+        }
+
+        draw_preview(renderer, theme, &shared.strings)?;
+        renderer.text(shared.strings.get("settings.help"), 160.0, 560.0, 16.0, theme.text)
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, shared: &mut Shared, keycode: KeyCode) -> SceneCommand {
+        match keycode {
+            KeyCode::Up => self.selected = (self.selected + ROWS.len() - 1) % ROWS.len(),
+            KeyCode::Down => self.selected = (self.selected + 1) % ROWS.len(),
+            KeyCode::Left => self.change(shared, ROWS[self.selected], -1),
+            KeyCode::Right | KeyCode::Return => self.change(shared, ROWS[self.selected], 1),
+            _ => {}
+        }
+        SceneCommand::None
+    }
+
+    fn mouse_down(&mut self, _ctx: &mut Context, shared: &mut Shared, button: MouseButton, x: f32, y: f32) -> SceneCommand {
+        if button != MouseButton::Left {
+            return SceneCommand::None;
+        }
+        if let Some(index) = (0..ROWS.len()).find(|&index| SettingsScene::row_rect(index).contains([x, y])) {
+            self.selected = index;
+            // The left half of the row steps back, the right half forward.
+            let step = if x < SettingsScene::row_rect(index).center().x { -1 } else { 1 };
+            self.change(shared, ROWS[index], step);
+        }
+        SceneCommand::None
+    }
+
+    fn on_exit(&mut self, shared: &mut Shared) {
+        if self.changed {
+            shared.save_settings();
+        }
+    }
+
+    fn transition(&self) -> Transition {
+        Transition::Slide(0.25)
+    }
+}
@@ -0,0 +1,190 @@
+use crate::date;
+use crate::export::{self, ExportFormat};
+use crate::games;
+use crate::i18n::Strings;
+use crate::render::{RectStyle, Renderer};
+use crate::scenes::{fill_screen, Scene, SceneCommand, Shared, Transition};
+use crate::stats::{GameStats, RECENT_RUNS};
+use crate::theme::Theme;
+use ggez::event::{KeyCode, MouseButton};
+use ggez::graphics::Rect;
+use ggez::{Context, GameResult};
+
+const TAB_Y: f32 = 95.0;
+const TAB_HEIGHT: f32 = 36.0;
+const TAB_WIDTH: f32 = 150.0;
+const TABS_X: f32 = 20.0;
+const FIRST_LINE_Y: f32 = 165.0;
+const LINE_SPACING: f32 = 36.0;
+const CHART: Rect = Rect { x: 420.0, y: 190.0, w: 350.0, h: 280.0 };
+const BAR_GAP: f32 = 3.0;
+const EXPORT_BUTTONS: [(ExportFormat, Rect); 2] = [
+    (ExportFormat::Csv, Rect { x: 500.0, y: 500.0, w: 130.0, h: 40.0 }),
+    (ExportFormat::Json, Rect { x: 640.0, y: 500.0, w: 130.0, h: 40.0 }),
+];
+
+/// Lifetime numbers for each game, with the latest scores as a bar chart, and the export of
+/// the whole history.
+pub struct StatsScene {
+    selected: usize,
+}
+
+impl StatsScene {
+    pub fn new() -> Self {
+        StatsScene { selected: 0 }
+    }
+
+    fn tab_rect(index: usize) -> Rect {
+        Rect::new(TABS_X + index as f32 * TAB_WIDTH, TAB_Y, TAB_WIDTH - 6.0, TAB_HEIGHT)
+    }
+
+    /// Exports every run, not only the selected game's, into the exports dir.
+    fn export(shared: &mut Shared, format: ExportFormat) {
+        let path = shared.storage.export_path(&format.file_name(date::now_timestamp()));
+        match export::export(shared.history.runs(), format, &path) {
+            Ok(()) => shared.toasts.push(shared.strings.format("stats.exported", &[("path", &path.display())])),
+            Err(error) => {
+                log::error!("Could not export to {}: {}", path.display(), error);
+                shared.toasts.push(shared.strings.format("error.export", &[("error", &error)]));
+            }
+        }
+    }
+
+    /// The left column; lines for counters a game does not have are left out.
+    fn lines(stats: &GameStats, strings: &Strings) -> Vec<String> {
+        let mut lines = vec![
+            strings.format("stats.played", &[("count", &stats.played)]),
+            strings.format("stats.play_time", &[("time", &date::format_duration(stats.play_time))]),
+            strings.format("stats.average", &[("score", &format!("{:.1}", stats.average))]),
+            strings.format("stats.median", &[("score", &stats.median)]),
+            strings.format("stats.best", &[("score", &stats.best)]),
+            strings.format("stats.streak", &[("days", &stats.best_streak)]),
+        ];
+        if let Some(length) = stats.longest_snake {
+            lines.push(strings.format("stats.longest_snake", &[("length", &length)]));
+        }
+        if let Some(pipes) = stats.pipes_passed {
+            lines.push(strings.format("stats.pipes", &[("pipes", &pipes)]));
+        }
+        if let Some(finishes) = stats.finishes {
+            lines.push(strings.format("stats.finishes", &[("count", &finishes)]));
+        }
+        lines
+    }
+}
+
+impl Default for StatsScene {
+    fn default() -> Self {
+        StatsScene::new()
+    }
+}
+
+/// One bar per score, scaled to the highest one, with the average drawn across them.
+fn draw_chart(renderer: &mut dyn Renderer, scores: &[u32], average: f32, theme: &Theme) -> GameResult {
+    renderer.rect(CHART, theme.tab, RectStyle::Filled)?;
+    let highest = scores.iter().copied().max().unwrap_or(0).max(1) as f32;
+    let width = CHART.w / RECENT_RUNS as f32;
+    for (index, &score) in scores.iter().enumerate() {
+        let height = score as f32 / highest * CHART.h;
+        let color = if index + 1 == scores.len() { theme.highlight } else { theme.button_selected };
+        let bar = Rect::new(CHART.x + index as f32 * width + BAR_GAP / 2.0, CHART.bottom() - height, width - BAR_GAP, height);
+        renderer.rect(bar, color, RectStyle::Filled)?;
+    }
+    let y = CHART.bottom() - average / highest * CHART.h;
+    renderer.rect(Rect::new(CHART.x, y - 1.0, CHART.w, 2.0), theme.warning, RectStyle::Filled)?;
+    renderer.text(&highest.to_string(), CHART.x + 4.0, CHART.y + 4.0, 16.0, theme.text)
+}
+
+impl Scene for StatsScene {
+    fn update(&mut self, _ctx: &mut Context, _shared: &mut Shared) -> GameResult<SceneCommand> {
+        Ok(SceneCommand::None)
+    }
+
+    fn draw(&mut self, renderer: &mut dyn Renderer, shared: &Shared) -> GameResult {
+        let (theme, strings) = (&shared.theme, &shared.strings);
+        fill_screen(renderer, theme.background)?;
+        renderer.text(strings.get("stats.title"), 270.0, 30.0, 50.0, theme.text)?;
+
+        for (index, game) in games::registry().iter().enumerate() {
+            let rect = StatsScene::tab_rect(index);
+            let color = if index == self.selected { theme.button_selected } else { theme.tab };
+            renderer.rect(rect, color, RectStyle::Filled)?;
+            renderer.text(&strings.game(game.id), rect.x + 8.0, rect.y + 9.0, 18.0, theme.text)?;
+        }
+
+        let stats = shared.history.stats(games::registry()[self.selected].id);
+        if stats.played == 0 {
+            renderer.text(strings.get("stats.empty"), 280.0, 280.0, 25.0, theme.text)?;
+        } else {
+            for (row, line) in StatsScene::lines(&stats, strings).iter().enumerate() {
+                renderer.text(line, 40.0, FIRST_LINE_Y + row as f32 * LINE_SPACING, 22.0, theme.text)?;
+            }
+            let title = strings.format("stats.recent", &[("count", &stats.recent.len())]);
+            renderer.text(&title, CHART.x, FIRST_LINE_Y, 18.0, theme.text)?;
+            draw_chart(renderer, &stats.recent, stats.average, theme)?;
+        }
+
+        for (format, rect) in EXPORT_BUTTONS {
+            renderer.rect(rect, theme.button, RectStyle::Filled)?;
+            renderer.text(&strings.format("stats.export", &[("format", &format)]), rect.x + 12.0, rect.y + 10.0, 20.0, theme.text)?;
+        }
+        renderer.text(strings.get("stats.help"), 30.0, 570.0, 16.0, theme.text)
+    }
+
+    fn key_down(&mut self, _ctx: &mut Context, shared: &mut Shared, keycode: KeyCode) -> SceneCommand {
+        let count = games::registry().len();
+        match keycode {
+            KeyCode::C => StatsScene::export(shared, ExportFormat::Csv),
+            KeyCode::J => StatsScene::export(shared, ExportFormat::Json),
+            KeyCode::Left => self.selected = (self.selected + count - 1) % count,
+            KeyCode::Right | KeyCode::Tab => self.selected = (self.selected + 1) % count,
+            _ => {}
+        }
+        SceneCommand::None
+    }
+
+    fn mouse_down(&mut self, _ctx: &mut Context, shared: &mut Shared, button: MouseButton, x: f32, y: f32) -> SceneCommand {
+        if button == MouseButton::Left {
+            if let Some((format, _)) = EXPORT_BUTTONS.iter().find(|(_, rect)| rect.contains([x, y])) {
+                StatsScene::export(shared, *format);
+            } else if let Some(index) = (0..games::registry().len()).find(|&index| StatsScene::tab_rect(index).contains([x, y])) {
+                self.selected = index;
+            }
+        }
+        SceneCommand::None
+    }
+
+    fn transition(&self) -> Transition {
+        Transition::Slide(0.25)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::recording::RecordingRenderer;
+
+    #[test]
+    fn only_the_games_own_counters_are_listed() {
+        let strings = Strings::load("en");
+        let snake = GameStats { played: 2, longest_snake: Some(14), ..GameStats::default() };
+        let lines = StatsScene::lines(&snake, &strings);
+        assert!(lines.contains(&"Longest snake: 14".to_string()));
+        assert!(!lines.iter().any(|line| line.starts_with("Pipes passed")));
+
+        let flappy = GameStats { played: 2, pipes_passed: Some(31), ..GameStats::default() };
+        assert!(StatsScene::lines(&flappy, &strings).contains(&"Pipes passed: 31".to_string()));
+    }
+
+    #[test]
+    fn the_highest_score_fills_the_chart() {
+        let theme = Theme::light();
+        let mut renderer = RecordingRenderer::new();
+        draw_chart(&mut renderer, &[10, 40, 20], 70.0 / 3.0, &theme).unwrap();
+
+        let bars = renderer.rects(theme.button_selected);
+        assert_eq!(bars.len(), 2);
+        assert_eq!(bars[1].h, CHART.h);
+        assert_eq!(renderer.rects(theme.highlight)[0].h, CHART.h / 2.0);
+    }
+}
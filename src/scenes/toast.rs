@@ -0,0 +1,62 @@
+use crate::render::{RectStyle, Renderer};
+use crate::scenes::{SCREEN_HEIGHT, SCREEN_WIDTH};
+use crate::theme::Theme;
+use ggez::graphics::{Color, Rect};
+use ggez::GameResult;
+use std::collections::VecDeque;
+
+const TOAST_SECONDS: f32 = 4.0;
+const MAX_TOASTS: usize = 4;
+
+struct Toast {
+    message: String,
+    remaining: f32,
+}
+
+/// Short notices shown in the bottom corner on top of every scene, e.g. when saving fails.
+pub struct Toasts {
+    items: VecDeque<Toast>,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Toasts { items: VecDeque::new() }
+    }
+
+    pub fn push(&mut self, message: impl Into<String>) {
+        if self.items.len() == MAX_TOASTS {
+            self.items.pop_front();
+        }
+        self.items.push_back(Toast { message: message.into(), remaining: TOAST_SECONDS });
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        for toast in &mut self.items {
+            toast.remaining -= delta;
+        }
+        self.items.retain(|toast| toast.remaining > 0.0);
+    }
+
+    pub fn draw(&self, renderer: &mut dyn Renderer, theme: &Theme) -> GameResult {
+        let mut y = SCREEN_HEIGHT - 50.0;
+        for toast in self.items.iter().rev() {
+            let alpha = toast.remaining.min(1.0);
+            let width = renderer.text_width(&toast.message, 18.0) + 20.0;
+            let x = SCREEN_WIDTH - width - 10.0;
+            renderer.rect(Rect::new(x, y, width, 34.0), fade(theme.toast, alpha), RectStyle::Filled)?;
+            renderer.text(&toast.message, x + 10.0, y + 8.0, 18.0, fade(theme.toast_text, alpha))?;
+            y -= 40.0;
+        }
+        Ok(())
+    }
+}
+
+fn fade(color: Color, alpha: f32) -> Color {
+    Color { a: color.a * alpha, ..color }
+}
+
+impl Default for Toasts {
+    fn default() -> Self {
+        Toasts::new()
+    }
+}
@@ -0,0 +1,560 @@
+use crate::daily;
+use crate::date;
+use crate::games::{self, course, TICKS_PER_SECOND};
+use crate::i18n::Notice;
+use crate::storage::{self, BACKUP_COUNT};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub const MAX_ENTRIES_PER_BOARD: usize = 100;
+
+const HEADER: &str = "# gierki-scores v1";
+const CHECKSUM_PREFIX: &str = "# checksum ";
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct ScoreEntry {
+    pub game: String,
+    pub mode: String,
+    pub name: String,
+    pub score: u32,
+    pub timestamp: u64,
+    pub duration: f32,
+}
+
+impl ScoreEntry {
+    /// `snake | classic | ala | 120 | 1714521600 | 42.0`, as in the score file.
+    pub fn to_line(&self) -> String {
+        format_line(self)
+    }
+
+    /// Reads a line written by `to_line`; the older formats the score file still accepts are not.
+    pub fn parse(line: &str) -> Option<ScoreEntry> {
+        if line.split(" | ").count() != 6 {
+            return None;
+        }
+        parse_line(line)
+    }
+}
+
+/// A board as the leaderboard screen and `--list-scores` present it.
+pub struct BoardTab {
+    pub game: String,
+    pub mode: String,
+    pub title: String,
+}
+
+/// All saved runs, grouped by (game, mode) when read back.
+pub struct ScoreBoard {
+    path: PathBuf,
+    entries: Vec<ScoreEntry>,
+}
+
+#[derive(PartialEq, Debug)]
+enum Integrity {
+    /// Header and checksum are present and match.
+    Valid,
+    /// Written by a version without checksums; nothing to verify against.
+    Legacy,
+    /// Truncated, edited or otherwise damaged.
+    Corrupt,
+}
+
+struct ParsedFile {
+    entries: Vec<ScoreEntry>,
+    malformed: usize,
+    integrity: Integrity,
+}
+
+impl ScoreBoard {
+    /// Loads the board, falling back to the newest intact backup if the main file is damaged.
+    /// Returns the problems worth telling the player about; they are logged as well.
+    pub fn load(path: PathBuf) -> (Self, Vec<Notice>) {
+        let mut notices = Vec::new();
+
+        let primary = match fs::read_to_string(&path) {
+            Ok(content) => Some(parse_file(&content)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => None,
+            Err(error) => {
+                log::error!("Could not read {}: {}", path.display(), error);
+                None
+            }
+        };
+
+        let parsed = match primary {
+            Some(parsed) if parsed.integrity != Integrity::Corrupt => Some(parsed),
+            primary => {
+                let recovered = (1..=BACKUP_COUNT).find_map(|number| {
+                    let backup = storage::backup_path(&path, number);
+                    let parsed = parse_file(&fs::read_to_string(backup).ok()?);
+                    (parsed.integrity != Integrity::Corrupt).then_some((number, parsed))
+                });
+                match (recovered, primary) {
+                    (Some((number, parsed)), _) => {
+                        log::warn!("{} is damaged, restored scores from backup {}", path.display(), number);
+                        notices.push(Notice::new("notice.scores_restored"));
+                        Some(parsed)
+                    }
+                    (None, Some(parsed)) => {
+                        log::warn!("{} is damaged and no backup is usable, keeping what could be read", path.display());
+                        notices.push(Notice::new("notice.scores_damaged").with("count", parsed.entries.len()));
+                        Some(parsed)
+                    }
+                    (None, None) => None,
+                }
+            }
+        };
+
+        let entries = match parsed {
+            Some(parsed) => {
+                if parsed.malformed > 0 {
+                    log::warn!("Skipped {} malformed lines in {}", parsed.malformed, path.display());
+                    notices.push(Notice::new("notice.score_lines").with("count", parsed.malformed));
+                }
+                parsed.entries
+            }
+            None => Vec::new(),
+        };
+
+        let mut board = ScoreBoard { path, entries };
+        board.trim();
+        (board, notices)
+    }
+
+    /// Merges an old `game_scores.txt` into this board and renames it so it is only picked up once.
+    pub fn migrate(&mut self, legacy: &Path) -> io::Result<usize> {
+        let parsed = parse_file(&fs::read_to_string(legacy)?);
+        let count = parsed.entries.len();
+        self.entries.extend(parsed.entries);
+        self.trim();
+        self.save()?;
+        fs::rename(legacy, legacy.with_extension("txt.migrated"))?;
+        Ok(count)
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        storage::write_with_backups(&self.path, &format_file(&self.entries), |previous| {
+            parse_file(previous).integrity != Integrity::Corrupt
+        })
+    }
+
+    /// Adds a run and returns its 0-based rank, or `None` if it did not make the board.
+    pub fn add(&mut self, entry: ScoreEntry) -> Option<usize> {
+        self.entries.push(entry.clone());
+        self.trim();
+        self.board(&entry.game, &entry.mode)
+            .iter()
+            .position(|other| **other == entry)
+    }
+
+    /// Entries of a single board, best first: the highest score, or the fastest time on timed boards.
+    pub fn board(&self, game: &str, mode: &str) -> Vec<&ScoreEntry> {
+        let mut board: Vec<&ScoreEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.game == game && entry.mode == mode)
+            .collect();
+        let timed = is_timed(mode);
+        board.sort_by(|a, b| {
+            let by_score = if timed { a.score.cmp(&b.score) } else { b.score.cmp(&a.score) };
+            by_score.then(a.timestamp.cmp(&b.timestamp))
+        });
+        board
+    }
+
+    /// The best score `name` has on a board, if they are on it at all.
+    pub fn personal_best(&self, game: &str, mode: &str, name: &str) -> Option<u32> {
+        self.board(game, mode).into_iter().find(|entry| entry.name == name).map(|entry| entry.score)
+    }
+
+    /// Every (game, mode) pair that has at least one entry.
+    pub fn boards(&self) -> Vec<(String, String)> {
+        let mut boards: Vec<(String, String)> = Vec::new();
+        for entry in &self.entries {
+            let key = (entry.game.clone(), entry.mode.clone());
+            if !boards.contains(&key) {
+                boards.push(key);
+            }
+        }
+        boards
+    }
+
+    /// One tab per registered game and mode, followed by any board found in the score file
+    /// that no longer has a registered game. The daily challenge shows today's board only, and
+    /// courses show up once they have scores.
+    pub fn tabs(&self) -> Vec<BoardTab> {
+        let today = daily::mode(date::now_timestamp());
+        let mut tabs = Vec::new();
+        for game in games::registry() {
+            for mode in game.modes.iter().filter(|mode| mode.id != course::MODE) {
+                let title = if game.modes.len() == 1 {
+                    game.title.to_string()
+                } else {
+                    format!("{} - {}", game.title, mode.title)
+                };
+                let board = if mode.id == daily::MODE { today.clone() } else { mode.id.to_string() };
+                tabs.push(BoardTab { game: game.id.to_string(), mode: board, title });
+            }
+        }
+        for (game, mode) in self.boards() {
+            if !daily::is_daily(&mode) && !tabs.iter().any(|tab| tab.game == game && tab.mode == mode) {
+                let title = match (games::find_game(&game), course::id(&mode)) {
+                    (Some(info), Some(id)) => format!("{} - {}", info.title, course::title(id)),
+                    _ => format!("{} - {}", game, mode),
+                };
+                tabs.push(BoardTab { game, mode, title });
+            }
+        }
+        tabs
+    }
+
+    fn trim(&mut self) {
+        let mut kept: Vec<ScoreEntry> = Vec::new();
+        for (game, mode) in self.boards() {
+            kept.extend(
+                self.board(&game, &mode)
+                    .into_iter()
+                    .take(MAX_ENTRIES_PER_BOARD)
+                    .cloned(),
+            );
+        }
+        self.entries = kept;
+    }
+}
+
+/// Course boards keep the finish time in ticks as the score, and the fastest run is the best.
+pub fn is_timed(mode: &str) -> bool {
+    course::id(mode).is_some()
+}
+
+/// A time in ticks as the boards show it, like `12.35 s`.
+pub fn format_ticks(ticks: u32) -> String {
+    format!("{:.2} s", ticks as f32 / TICKS_PER_SECOND as f32)
+}
+
+/// A board entry's score as shown: points, or the finish time on timed boards.
+pub fn format_score(mode: &str, score: u32) -> String {
+    if is_timed(mode) {
+        format_ticks(score)
+    } else {
+        score.to_string()
+    }
+}
+
+pub fn player_name() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| "Player".to_string())
+}
+
+/// FNV-1a, only used to notice damage, not to stop anyone from editing the file.
+fn checksum(body: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in body.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0000_0100_0000_01b3);
+    }
+    hash
+}
+
+fn format_file(entries: &[ScoreEntry]) -> String {
+    let mut body = format!("{}\n", HEADER);
+    for entry in entries {
+        body.push_str(&format_line(entry));
+        body.push('\n');
+    }
+    let sum = checksum(&body);
+    body.push_str(&format!("{}{:016x}\n", CHECKSUM_PREFIX, sum));
+    body
+}
+
+fn parse_file(content: &str) -> ParsedFile {
+    let mut entries = Vec::new();
+    let mut malformed = 0;
+    for line in content.lines() {
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        match parse_line(line) {
+            Some(entry) => entries.push(entry),
+            None => malformed += 1,
+        }
+    }
+
+    // Crashes tend to leave empty or cut-off files, so only a non-empty file with no header at all counts as legacy.
+    let integrity = if !content.is_empty() && !content.starts_with('#') {
+        Integrity::Legacy
+    } else if !content.starts_with(HEADER) {
+        Integrity::Corrupt
+    } else {
+        let trimmed = content.trim_end_matches(['\n', '\r']);
+        let last_line_start = trimmed.rfind('\n').map_or(0, |index| index + 1);
+        let expected = trimmed[last_line_start..]
+            .strip_prefix(CHECKSUM_PREFIX)
+            .and_then(|hex| u64::from_str_radix(hex, 16).ok());
+        match expected {
+            Some(expected) if expected == checksum(&content[..last_line_start]) => Integrity::Valid,
+            _ => Integrity::Corrupt,
+        }
+    };
+
+    ParsedFile { entries, malformed, integrity }
+}
+
+fn format_line(entry: &ScoreEntry) -> String {
+    format!(
+        "{} | {} | {} | {} | {} | {:.1}",
+        entry.game,
+        entry.mode,
+        entry.name.replace('|', "/").replace(['\n', '\r'], " "),
+        entry.score,
+        entry.timestamp,
+        entry.duration
+    )
+}
+
+fn parse_line(line: &str) -> Option<ScoreEntry> {
+    let parts: Vec<&str> = line.split(" | ").collect();
+    match parts.as_slice() {
+        [game, mode, name, score, timestamp, duration] => Some(ScoreEntry {
+            game: game.to_string(),
+            mode: mode.to_string(),
+            name: name.to_string(),
+            score: score.parse().ok()?,
+            timestamp: timestamp.parse().ok()?,
+            duration: duration.parse().ok().filter(|duration: &f32| duration.is_finite())?,
+        }),
+        // Files written before boards had modes: "Flappy Bird | Score: 12".
+        [title, score] => {
+            let score = score.strip_prefix("Score: ")?.parse().ok()?;
+            let game = games::registry()
+                .iter()
+                .find(|game| game.title == *title)
+                .map_or(title.to_string(), |game| game.id.to_string());
+            Some(ScoreEntry {
+                game,
+                mode: games::CLASSIC.id.to_string(),
+                name: "?".to_string(),
+                score,
+                timestamp: 0,
+                duration: 0.0,
+            })
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn entry(game: &str, score: u32, timestamp: u64) -> ScoreEntry {
+        ScoreEntry {
+            game: game.to_string(),
+            mode: "classic".to_string(),
+            name: "tester".to_string(),
+            score,
+            timestamp,
+            duration: 12.5,
+        }
+    }
+
+    fn board_in(dir: &tempfile::TempDir) -> ScoreBoard {
+        ScoreBoard::load(dir.path().join("scores.txt")).0
+    }
+
+    fn arb_entry() -> impl Strategy<Value = ScoreEntry> {
+        (
+            "[a-z_]{1,12}",
+            "[a-z_]{1,12}",
+            "[^|\n\r]{0,20}",
+            any::<u32>(),
+            any::<u64>(),
+            0u32..1_000_000,
+        )
+            .prop_map(|(game, mode, name, score, timestamp, tenths)| ScoreEntry {
+                game,
+                mode,
+                name,
+                score,
+                timestamp,
+                duration: tenths as f32 / 10.0,
+            })
+    }
+
+    #[test]
+    fn reads_legacy_lines() {
+        let parsed = parse_file("Flappy Bird | Score: 12\nSnake | Score: 40\n");
+        assert_eq!(parsed.integrity, Integrity::Legacy);
+        assert_eq!(parsed.malformed, 0);
+        assert_eq!(parsed.entries[0].game, "flappy_bird");
+        assert_eq!(parsed.entries[1].score, 40);
+    }
+
+    #[test]
+    fn counts_malformed_lines_instead_of_hiding_them() {
+        let parsed = parse_file("snake | classic | a | 10 | 0 | 1.0\nsnake | classic | b | ten | 0 | 1.0\ngarbage\n");
+        assert_eq!(parsed.entries.len(), 1);
+        assert_eq!(parsed.malformed, 2);
+    }
+
+    #[test]
+    fn save_keeps_previous_versions_as_backups() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut board = board_in(&dir);
+        for score in 0..5 {
+            board.add(entry("snake", score, score as u64));
+            board.save().unwrap();
+        }
+
+        let path = dir.path().join("scores.txt");
+        for number in 1..=BACKUP_COUNT {
+            assert!(storage::backup_path(&path, number).exists());
+        }
+        assert!(!storage::backup_path(&path, BACKUP_COUNT + 1).exists());
+        assert_eq!(board_in(&dir).board("snake", "classic").len(), 5);
+    }
+
+    #[test]
+    fn damaged_file_is_restored_from_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scores.txt");
+        let mut board = board_in(&dir);
+        board.add(entry("snake", 10, 1));
+        board.save().unwrap();
+        board.add(entry("snake", 20, 2));
+        board.save().unwrap();
+
+        let content = fs::read_to_string(&path).unwrap();
+        fs::write(&path, &content[..content.len() - 30]).unwrap();
+
+        let (restored, notices) = ScoreBoard::load(path);
+        assert_eq!(notices, [Notice::new("notice.scores_restored")]);
+        let scores: Vec<u32> = restored.board("snake", "classic").iter().map(|entry| entry.score).collect();
+        assert_eq!(scores, vec![10]);
+    }
+
+    #[test]
+    fn saving_over_a_damaged_file_keeps_the_good_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scores.txt");
+        let mut board = board_in(&dir);
+        board.add(entry("snake", 10, 1));
+        board.save().unwrap();
+        board.add(entry("snake", 20, 2));
+        board.save().unwrap();
+        let good = fs::read_to_string(storage::backup_path(&path, 1)).unwrap();
+
+        for score in [30, 40] {
+            fs::write(&path, "# gierki-scores v1\ncut off |").unwrap();
+            board.add(entry("snake", score, score as u64));
+            board.save().unwrap();
+        }
+        assert_eq!(fs::read_to_string(storage::backup_path(&path, 1)).unwrap(), good);
+        assert!(!storage::backup_path(&path, 2).exists());
+        assert_eq!(board_in(&dir).board("snake", "classic").len(), 4);
+    }
+
+    #[test]
+    fn leftover_temp_file_is_ignored() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut board = board_in(&dir);
+        board.add(entry("snake", 10, 1));
+        board.save().unwrap();
+        fs::write(dir.path().join("scores.txt.tmp"), "half a line |").unwrap();
+
+        let (loaded, notices) = ScoreBoard::load(dir.path().join("scores.txt"));
+        assert!(notices.is_empty());
+        assert_eq!(loaded.board("snake", "classic").len(), 1);
+    }
+
+    #[test]
+    fn legacy_file_is_merged_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let legacy = dir.path().join("game_scores.txt");
+        fs::write(&legacy, "Snake | Score: 40\nFlappy Bird | Score: 3\n").unwrap();
+        let mut board = board_in(&dir);
+        board.add(entry("snake", 10, 1));
+
+        assert_eq!(board.migrate(&legacy).unwrap(), 2);
+        assert!(!legacy.exists());
+        let (reloaded, notices) = ScoreBoard::load(dir.path().join("scores.txt"));
+        assert!(notices.is_empty());
+        assert_eq!(reloaded.board("snake", "classic").len(), 2);
+        assert_eq!(reloaded.board("flappy_bird", "classic").len(), 1);
+    }
+
+    #[test]
+    fn unwritable_location_returns_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let board = ScoreBoard::load(dir.path().join("missing").join("scores.txt")).0;
+        assert!(board.save().is_err());
+    }
+
+    #[test]
+    fn course_boards_put_the_fastest_time_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut board = board_in(&dir);
+        for (ticks, timestamp) in [(900, 1), (741, 2), (1200, 3)] {
+            board.add(ScoreEntry { mode: course::mode("loop"), ..entry("flappy_bird", ticks, timestamp) });
+        }
+        let times: Vec<u32> = board.board("flappy_bird", "course-loop").iter().map(|entry| entry.score).collect();
+        assert_eq!(times, [741, 900, 1200]);
+        assert_eq!(board.personal_best("flappy_bird", "course-loop", "tester"), Some(741));
+        assert_eq!(format_score("course-loop", 741), "12.35 s");
+        assert_eq!(format_score("classic", 741), "741");
+    }
+
+    proptest! {
+        #[test]
+        fn lines_round_trip(entry in arb_entry()) {
+            prop_assert_eq!(parse_line(&format_line(&entry)), Some(entry));
+        }
+
+        #[test]
+        fn files_round_trip(entries in prop::collection::vec(arb_entry(), 0..20)) {
+            let parsed = parse_file(&format_file(&entries));
+            prop_assert_eq!(parsed.integrity, Integrity::Valid);
+            prop_assert_eq!(parsed.malformed, 0);
+            prop_assert_eq!(parsed.entries, entries);
+        }
+
+        #[test]
+        fn truncated_files_are_never_trusted(entries in prop::collection::vec(arb_entry(), 1..10), cut in any::<prop::sample::Index>()) {
+            let content = format_file(&entries);
+            let end = cut.index(content.len());
+            // Dropping only the final newline leaves the data intact.
+            if content.is_char_boundary(end) && end < content.len() - 1 {
+                prop_assert_eq!(parse_file(&content[..end]).integrity, Integrity::Corrupt);
+            }
+        }
+
+        #[test]
+        fn flipped_bits_are_detected(entries in prop::collection::vec(arb_entry(), 1..10), at in any::<prop::sample::Index>(), bit in 0u8..7) {
+            let mut bytes = format_file(&entries).into_bytes();
+            let index = at.index(bytes.len());
+            bytes[index] ^= 1 << bit;
+            // Flipping the case of a checksum digit changes nothing that matters, so a file may
+            // still pass as long as it reads back the same.
+            if let Ok(content) = String::from_utf8(bytes) {
+                let parsed = parse_file(&content);
+                if parsed.integrity == Integrity::Valid {
+                    prop_assert_eq!(parsed.entries, entries);
+                }
+            }
+        }
+
+        // Fuzzing: whatever ends up on disk, parsing must not panic.
+        #[test]
+        fn parser_never_panics(content in any::<String>()) {
+            let parsed = parse_file(&content);
+            prop_assert!(parsed.entries.len() + parsed.malformed <= content.lines().count());
+        }
+
+        #[test]
+        fn parser_never_panics_on_near_valid_lines(line in "[a-z ]{0,8}( \\| [a-zA-Z0-9. -]{0,8}){0,7}") {
+            let _ = parse_line(&line);
+        }
+    }
+}
@@ -0,0 +1,170 @@
+use crate::i18n::{Notice, DEFAULT_LANGUAGE};
+use crate::storage;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+pub const SETTINGS_FILE: &str = "settings.txt";
+
+/// Which earlier run is raced as a ghost, in games that can show one.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum GhostSource {
+    Off,
+    PersonalBest,
+    TopRun,
+}
+
+impl GhostSource {
+    pub const ALL: [GhostSource; 3] = [GhostSource::Off, GhostSource::PersonalBest, GhostSource::TopRun];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            GhostSource::Off => "off",
+            GhostSource::PersonalBest => "best",
+            GhostSource::TopRun => "top",
+        }
+    }
+}
+
+impl FromStr for GhostSource {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        GhostSource::ALL
+            .into_iter()
+            .find(|source| source.name() == name)
+            .ok_or_else(|| format!("unknown ghost '{}' (expected off, best or top)", name))
+    }
+}
+
+/// Player preferences, kept as `key = value` lines in the config dir.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Settings {
+    path: PathBuf,
+    /// Name of the selected theme.
+    pub theme: String,
+    /// Code of the selected language, e.g. `pl`.
+    pub language: String,
+    /// Bursts when eating, crashing and passing pipes.
+    pub particles: bool,
+    pub screen_shake: bool,
+    /// Blinking snake and falling bird before the game-over text; off shows it right away.
+    pub death_animation: bool,
+    pub ghost: GhostSource,
+    /// Address of a shared leaderboard, e.g. `http://scores.local:7780`; empty keeps scores local.
+    pub leaderboard_server: String,
+}
+
+impl Settings {
+    /// Loads the settings, keeping the defaults for anything missing or unreadable.
+    /// Returns the problems worth telling the player about; they are logged as well.
+    pub fn load(path: PathBuf) -> (Self, Vec<Notice>) {
+        let mut settings = Settings {
+            path,
+            theme: "Light".to_string(),
+            language: DEFAULT_LANGUAGE.to_string(),
+            particles: true,
+            screen_shake: true,
+            death_animation: true,
+            ghost: GhostSource::Off,
+            leaderboard_server: String::new(),
+        };
+        let mut notices = Vec::new();
+        let content = match fs::read_to_string(&settings.path) {
+            Ok(content) => content,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => return (settings, notices),
+            Err(error) => {
+                log::error!("Could not read {}: {}", settings.path.display(), error);
+                notices.push(Notice::new("notice.settings").with("error", error));
+                return (settings, notices);
+            }
+        };
+
+        for line in content.lines().map(str::trim).filter(|line| !line.is_empty() && !line.starts_with('#')) {
+            match line.split_once('=').map(|(key, value)| (key.trim(), value.trim())) {
+                Some(("theme", value)) => settings.theme = value.to_string(),
+                Some(("language", value)) => settings.language = value.to_string(),
+                Some((key @ ("particles" | "screen_shake" | "death_animation"), value)) => match parse_switch(value) {
+                    Some(on) => *settings.switch_mut(key) = on,
+                    None => log::warn!("Ignoring '{}' for {} in {}, expected on or off", value, key, settings.path.display()),
+                },
+                Some(("ghost", value)) => match value.parse() {
+                    Ok(source) => settings.ghost = source,
+                    Err(error) => log::warn!("Ignoring the ghost setting in {}: {}", settings.path.display(), error),
+                },
+                Some(("leaderboard_server", value)) => settings.leaderboard_server = value.to_string(),
+                // Settings from newer versions are dropped rather than treated as damage.
+                Some((key, _)) => log::warn!("Ignoring unknown setting '{}' in {}", key, settings.path.display()),
+                None => log::warn!("Ignoring malformed line '{}' in {}", line, settings.path.display()),
+            }
+        }
+        (settings, notices)
+    }
+
+    fn switch_mut(&mut self, key: &str) -> &mut bool {
+        match key {
+            "particles" => &mut self.particles,
+            "screen_shake" => &mut self.screen_shake,
+            _ => &mut self.death_animation,
+        }
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let switch = |on: bool| if on { "on" } else { "off" };
+        let content = format!(
+            "theme = {}\nlanguage = {}\nparticles = {}\nscreen_shake = {}\ndeath_animation = {}\nghost = {}\nleaderboard_server = {}\n",
+            self.theme,
+            self.language,
+            switch(self.particles),
+            switch(self.screen_shake),
+            switch(self.death_animation),
+            self.ghost.name(),
+            self.leaderboard_server
+        );
+        storage::write_atomic(&self.path, &content)
+    }
+}
+
+fn parse_switch(value: &str) -> Option<bool> {
+    match value {
+        "on" => Some(true),
+        "off" => Some(false),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_survive_a_save_and_load() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SETTINGS_FILE);
+        let (mut settings, notices) = Settings::load(path.clone());
+        assert!(notices.is_empty());
+        assert_eq!(settings.theme, "Light");
+
+        settings.theme = "High Contrast".to_string();
+        settings.language = "pl".to_string();
+        settings.screen_shake = false;
+        settings.ghost = GhostSource::TopRun;
+        settings.leaderboard_server = "http://scores.local:7780".to_string();
+        settings.save().unwrap();
+        assert_eq!(Settings::load(path).0, settings);
+    }
+
+    #[test]
+    fn unknown_and_malformed_lines_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(SETTINGS_FILE);
+        fs::write(&path, "volume = 3\nnonsense\ntheme = Dark\nparticles = maybe\nscreen_shake = off\nghost = always\n").unwrap();
+        let (settings, notices) = Settings::load(path);
+        assert_eq!(settings.theme, "Dark");
+        assert!(settings.particles);
+        assert!(!settings.screen_shake);
+        assert_eq!(settings.ghost, GhostSource::Off);
+        assert!(notices.is_empty());
+    }
+}
@@ -0,0 +1,160 @@
+use crate::games::rng::GameRng;
+use crate::games::{self, GameInput, Minigame, RunConfig};
+use crate::replay::Replay;
+use rand::Rng;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+const ALL_INPUTS: [GameInput; 5] = [GameInput::Up, GameInput::Down, GameInput::Left, GameInput::Right, GameInput::Action];
+
+/// How the soak runner plays.
+#[derive(Clone, Debug)]
+pub enum InputScript {
+    /// Each tick presses a random input with the given probability.
+    Random { chance: f64 },
+    /// Presses the inputs in order, one every `every` ticks, starting over at the end.
+    Cycle { inputs: Vec<GameInput>, every: u32 },
+}
+
+impl InputScript {
+    fn next(&self, tick: u32, rng: &mut GameRng) -> Option<GameInput> {
+        match self {
+            InputScript::Random { chance } => {
+                rng.gen_bool(*chance).then(|| ALL_INPUTS[rng.gen_range(0..ALL_INPUTS.len())])
+            }
+            InputScript::Cycle { inputs, every } => {
+                let every = (*every).max(1);
+                match tick % every {
+                    0 if !inputs.is_empty() => Some(inputs[(tick / every) as usize % inputs.len()]),
+                    _ => None,
+                }
+            }
+        }
+    }
+}
+
+pub struct SoakReport {
+    pub config: RunConfig,
+    pub ticks: u64,
+    pub runs: u32,
+    pub best_score: u32,
+    pub elapsed: Duration,
+}
+
+impl SoakReport {
+    pub fn ticks_per_second(&self) -> f64 {
+        self.ticks as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+/// A broken invariant, with the replay of the run that broke it so it can be watched with `--replay`.
+pub struct Violation {
+    pub replay: Replay,
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} (seed {}), tick {}: {}",
+            self.replay.config.game, self.replay.config.mode, self.replay.config.seed, self.replay.ticks, self.message
+        )
+    }
+}
+
+/// Plays `total_ticks` ticks of the configured game without a window, checking the game's
+/// invariants after every tick. A run that ends is restarted with the next seed.
+pub fn soak(config: &RunConfig, total_ticks: u64, script: &InputScript) -> Result<SoakReport, Violation> {
+    let info = games::find_game(&config.game).ok_or_else(|| Violation {
+        replay: Replay::new(config.clone()),
+        message: format!("no game registered as '{}'", config.game),
+    })?;
+
+    let mut input_rng = GameRng::new(config.seed ^ 0x5eed_5eed_5eed_5eed);
+    let mut run_config = config.clone();
+    let mut game: Box<dyn Minigame> = info.create(&run_config);
+    let mut replay = Replay::new(run_config.clone());
+    let mut runs = 1;
+    let mut best_score = 0;
+
+    let started = Instant::now();
+    for _ in 0..total_ticks {
+        if game.is_over() {
+            best_score = best_score.max(game.score());
+            run_config.seed = run_config.seed.wrapping_add(1);
+            game = info.create(&run_config);
+            replay = Replay::new(run_config.clone());
+            runs += 1;
+        }
+
+        if let Some(input) = script.next(replay.ticks, &mut input_rng) {
+            replay.record(replay.ticks, input);
+            game.input(input);
+        }
+        game.tick();
+        replay.ticks += 1;
+
+        if let Err(message) = game.check_invariants() {
+            return Err(Violation { replay, message });
+        }
+    }
+    best_score = best_score.max(game.score());
+
+    Ok(SoakReport {
+        config: config.clone(),
+        ticks: total_ticks,
+        runs,
+        best_score,
+        elapsed: started.elapsed(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TICKS: u64 = 200_000;
+
+    fn configs() -> Vec<RunConfig> {
+        let mut configs = Vec::new();
+        for game in games::registry() {
+            for mode in game.modes {
+                let mut config = RunConfig::new(game.id, mode.id);
+                config.seed = 42;
+                configs.push(config);
+            }
+        }
+        configs
+    }
+
+    fn assert_soaks(script: InputScript) {
+        for config in configs() {
+            if let Err(violation) = soak(&config, TICKS, &script) {
+                panic!("{}", violation);
+            }
+        }
+    }
+
+    #[test]
+    fn random_inputs_keep_every_invariant() {
+        assert_soaks(InputScript::Random { chance: 0.05 });
+    }
+
+    #[test]
+    fn cycled_inputs_keep_every_invariant() {
+        assert_soaks(InputScript::Cycle {
+            inputs: vec![GameInput::Up, GameInput::Action, GameInput::Left, GameInput::Down, GameInput::Right],
+            every: 7,
+        });
+    }
+
+    #[test]
+    fn same_seed_plays_the_same_runs() {
+        let config = &configs()[0];
+        let script = InputScript::Random { chance: 0.05 };
+        let first = soak(config, 10_000, &script).ok().unwrap();
+        let second = soak(config, 10_000, &script).ok().unwrap();
+        assert_eq!((first.runs, first.best_score), (second.runs, second.best_score));
+    }
+}
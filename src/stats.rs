@@ -0,0 +1,354 @@
+//! Every finished run, kept for the lifetime statistics. The leaderboard only keeps the best
+//! ranked runs; this keeps all of them, at any difficulty.
+
+use crate::daily;
+use crate::games::Difficulty;
+use crate::i18n::Notice;
+use crate::scores;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// How many of the latest runs the stats screen charts.
+pub const RECENT_RUNS: usize = 20;
+
+const HEADER: &str = "# gierki-history v2";
+const SECONDS_PER_DAY: u64 = 86_400;
+
+#[derive(Clone, PartialEq, Debug)]
+pub struct RunRecord {
+    pub game: String,
+    pub mode: String,
+    pub difficulty: Difficulty,
+    pub player: String,
+    pub score: u32,
+    /// With the config, enough to replay the run's random numbers.
+    pub seed: u64,
+    pub timestamp: u64,
+    pub duration: f32,
+    /// Game-specific numbers from `Minigame::counters`, e.g. the snake's final length.
+    pub counters: Vec<(String, u32)>,
+}
+
+impl RunRecord {
+    pub fn counter(&self, name: &str) -> Option<u32> {
+        self.counters.iter().find(|(counter, _)| counter == name).map(|&(_, value)| value)
+    }
+}
+
+/// Lifetime numbers for one game, over all of its modes and difficulties.
+#[derive(Clone, PartialEq, Debug, Default)]
+pub struct GameStats {
+    pub played: usize,
+    pub play_time: f32,
+    pub average: f32,
+    pub median: f32,
+    pub best: u32,
+    /// Most days in a row with at least one run.
+    pub best_streak: usize,
+    /// Only for games that report a `length` counter.
+    pub longest_snake: Option<u32>,
+    /// Only for games that report a `pipes` counter.
+    pub pipes_passed: Option<u32>,
+    /// Runs that reached the finish line, for games with timed boards; those runs are raced
+    /// against the clock, so their scores are left out of the ones above.
+    pub finishes: Option<usize>,
+    /// Scores of the latest runs, oldest first.
+    pub recent: Vec<u32>,
+}
+
+/// The run history file. New runs are appended, so a crash can at worst cut off the last line.
+pub struct History {
+    path: PathBuf,
+    runs: Vec<RunRecord>,
+}
+
+impl History {
+    /// A missing file is an empty history; damaged lines are skipped and reported.
+    pub fn load(path: PathBuf) -> (Self, Vec<Notice>) {
+        let mut notices = Vec::new();
+        let content = match fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(error) => {
+                if error.kind() != io::ErrorKind::NotFound {
+                    log::error!("Could not read {}: {}", path.display(), error);
+                }
+                String::new()
+            }
+        };
+
+        let mut runs = Vec::new();
+        let mut malformed = 0;
+        for line in content.lines() {
+            if line.starts_with('#') || line.trim().is_empty() {
+                continue;
+            }
+            match parse_line(line) {
+                Some(run) => runs.push(run),
+                None => malformed += 1,
+            }
+        }
+        if malformed > 0 {
+            log::warn!("Skipped {} malformed lines in {}", malformed, path.display());
+            notices.push(Notice::new("notice.history_lines").with("count", malformed));
+        }
+        (History { path, runs }, notices)
+    }
+
+    /// Appends a run to the file, writing the header first if the file is new.
+    pub fn add(&mut self, run: RunRecord) -> io::Result<()> {
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        let mut text = String::new();
+        if file.metadata()?.len() == 0 {
+            text.push_str(HEADER);
+            text.push('\n');
+        }
+        text.push_str(&format_line(&run));
+        text.push('\n');
+        file.write_all(text.as_bytes())?;
+        self.runs.push(run);
+        Ok(())
+    }
+
+    /// Every run, oldest first.
+    pub fn runs(&self) -> &[RunRecord] {
+        &self.runs
+    }
+
+    /// Whether `player` has finished a run of this game and mode, e.g. today's daily challenge.
+    pub fn has_played(&self, game: &str, mode: &str, player: &str) -> bool {
+        self.runs.iter().any(|run| run.game == game && run.mode == mode && run.player == player)
+    }
+
+    /// Days in a row with a daily challenge of `game` played by `player`, counting back from
+    /// the day of `now`. A streak not yet kept up today still counts until the day is over.
+    pub fn daily_streak(&self, game: &str, player: &str, now: u64) -> usize {
+        let mut days: Vec<i64> = self
+            .runs
+            .iter()
+            .filter(|run| run.game == game && run.player == player && daily::is_daily(&run.mode))
+            .map(|run| (run.timestamp / SECONDS_PER_DAY) as i64)
+            .collect();
+        days.sort_unstable();
+        days.dedup();
+
+        let today = (now / SECONDS_PER_DAY) as i64;
+        let mut day = if days.last() == Some(&today) { today } else { today - 1 };
+        let mut streak = 0;
+        while days.binary_search(&day).is_ok() {
+            streak += 1;
+            day -= 1;
+        }
+        streak
+    }
+
+    pub fn stats(&self, game: &str) -> GameStats {
+        let runs: Vec<&RunRecord> = self.runs.iter().filter(|run| run.game == game).collect();
+        if runs.is_empty() {
+            return GameStats::default();
+        }
+
+        let (timed, scored): (Vec<&RunRecord>, Vec<&RunRecord>) = runs.iter().partition(|run| scores::is_timed(&run.mode));
+        let mut points: Vec<u32> = scored.iter().map(|run| run.score).collect();
+        let recent = points[points.len().saturating_sub(RECENT_RUNS)..].to_vec();
+        points.sort_unstable();
+        let middle = points.len() / 2;
+        let median = match points.len() {
+            0 => 0.0,
+            count if count & 1 == 0 => (points[middle - 1] as f32 + points[middle] as f32) / 2.0,
+            _ => points[middle] as f32,
+        };
+
+        let lengths = runs.iter().filter_map(|run| run.counter("length"));
+        let pipes: Vec<u32> = runs.iter().filter_map(|run| run.counter("pipes")).collect();
+        GameStats {
+            played: runs.len(),
+            play_time: runs.iter().map(|run| run.duration).sum(),
+            average: points.iter().map(|&score| score as f32).sum::<f32>() / points.len().max(1) as f32,
+            median,
+            best: points.last().copied().unwrap_or(0),
+            best_streak: best_streak(runs.iter().map(|run| run.timestamp)),
+            longest_snake: lengths.max(),
+            pipes_passed: (!pipes.is_empty()).then(|| pipes.iter().sum()),
+            finishes: (!timed.is_empty()).then(|| timed.iter().filter(|run| run.counter("finish_ticks").is_some()).count()),
+            recent,
+        }
+    }
+}
+
+/// Longest run of consecutive UTC days in `timestamps`, which may come in any order.
+fn best_streak(timestamps: impl Iterator<Item = u64>) -> usize {
+    let mut days: Vec<u64> = timestamps.filter(|&timestamp| timestamp > 0).map(|timestamp| timestamp / SECONDS_PER_DAY).collect();
+    days.sort_unstable();
+    days.dedup();
+    let (mut best, mut current) = (0, 0);
+    for (index, day) in days.iter().enumerate() {
+        current = if index > 0 && days[index - 1] + 1 == *day { current + 1 } else { 1 };
+        best = best.max(current);
+    }
+    best
+}
+
+fn format_line(run: &RunRecord) -> String {
+    let counters: Vec<String> = run.counters.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+    format!(
+        "{} | {} | {} | {} | {} | {} | {} | {:.1} | {}",
+        run.game,
+        run.mode,
+        run.difficulty,
+        run.player.replace('|', "/").replace(['\n', '\r'], " "),
+        run.score,
+        run.seed,
+        run.timestamp,
+        run.duration,
+        counters.join(" ")
+    )
+}
+
+fn parse_line(line: &str) -> Option<RunRecord> {
+    let parts: Vec<&str> = line.split(" | ").collect();
+    let (game, mode, difficulty, player, score, seed, timestamp, duration, counters) = match parts.as_slice() {
+        [game, mode, difficulty, player, score, seed, timestamp, duration, counters] => {
+            (game, mode, difficulty, player.to_string(), score, seed.parse().ok()?, timestamp, duration, counters)
+        }
+        // The first version did not keep the player or the seed.
+        [game, mode, difficulty, score, timestamp, duration, counters] => {
+            (game, mode, difficulty, "?".to_string(), score, 0, timestamp, duration, counters)
+        }
+        _ => return None,
+    };
+    let counters = counters
+        .split_whitespace()
+        .map(|counter| {
+            let (name, value) = counter.split_once('=')?;
+            Some((name.to_string(), value.parse().ok()?))
+        })
+        .collect::<Option<Vec<_>>>()?;
+    Some(RunRecord {
+        game: game.to_string(),
+        mode: mode.to_string(),
+        difficulty: difficulty.parse().ok()?,
+        player,
+        score: score.parse().ok()?,
+        seed,
+        timestamp: timestamp.parse().ok()?,
+        duration: duration.parse().ok().filter(|duration: &f32| duration.is_finite())?,
+        counters,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(game: &str, score: u32, day: u64) -> RunRecord {
+        RunRecord {
+            game: game.to_string(),
+            mode: "classic".to_string(),
+            difficulty: Difficulty::Normal,
+            player: "tester".to_string(),
+            score,
+            seed: day * 7,
+            timestamp: day * SECONDS_PER_DAY + 3600,
+            duration: 30.0,
+            counters: vec![("length".to_string(), 3 + score / 10)],
+        }
+    }
+
+    #[test]
+    fn runs_are_appended_and_read_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.txt");
+        let mut history = History::load(path.clone()).0;
+        history.add(run("snake", 40, 1)).unwrap();
+        let mut hard = run("flappy_bird", 3, 2);
+        hard.difficulty = Difficulty::Hard;
+        hard.counters = vec![("pipes".to_string(), 3)];
+        history.add(hard.clone()).unwrap();
+
+        let (loaded, notices) = History::load(path.clone());
+        assert!(notices.is_empty());
+        assert_eq!(loaded.runs(), &[run("snake", 40, 1), hard]);
+        assert!(fs::read_to_string(path).unwrap().starts_with(HEADER));
+    }
+
+    #[test]
+    fn damaged_lines_are_skipped_and_reported() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("history.txt");
+        fs::write(&path, format!("{}\n{}\nsnake | classic | normal | 1\n", HEADER, format_line(&run("snake", 10, 1)))).unwrap();
+
+        let (history, notices) = History::load(path);
+        assert_eq!(history.runs().len(), 1);
+        assert_eq!(notices.len(), 1);
+    }
+
+    #[test]
+    fn first_version_lines_are_still_read() {
+        let run = parse_line("snake | wrap | hard | 30 | 86400 | 12.5 | length=6").unwrap();
+        assert_eq!((run.player.as_str(), run.seed, run.score), ("?", 0, 30));
+        assert_eq!(run.counter("length"), Some(6));
+    }
+
+    #[test]
+    fn daily_streaks_count_back_from_today() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = History::load(dir.path().join("history.txt")).0;
+        for day in [1, 3, 4, 5] {
+            let mut daily = run("snake", 10, day);
+            daily.mode = daily::mode(daily.timestamp);
+            history.add(daily).unwrap();
+        }
+        history.add(run("snake", 10, 2)).unwrap();
+
+        let day = |day: u64| day * SECONDS_PER_DAY + 7200;
+        assert_eq!(history.daily_streak("snake", "tester", day(5)), 3);
+        assert_eq!(history.daily_streak("snake", "tester", day(6)), 3);
+        assert_eq!(history.daily_streak("snake", "tester", day(7)), 0);
+        assert_eq!(history.daily_streak("snake", "someone else", day(5)), 0);
+        assert!(history.has_played("snake", &daily::mode(day(4)), "tester"));
+        assert!(!history.has_played("snake", &daily::mode(day(2)), "tester"));
+    }
+
+    #[test]
+    fn stats_sum_up_one_game() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = History::load(dir.path().join("history.txt")).0;
+        for (score, day) in [(10, 1), (50, 2), (20, 3), (0, 5), (30, 6), (90, 6)] {
+            history.add(run("snake", score, day)).unwrap();
+        }
+        history.add(run("flappy_bird", 7, 10)).unwrap();
+
+        let stats = history.stats("snake");
+        assert_eq!(stats.played, 6);
+        assert_eq!(stats.play_time, 180.0);
+        assert_eq!(stats.average, 200.0 / 6.0);
+        assert_eq!(stats.median, 25.0);
+        assert_eq!(stats.best, 90);
+        assert_eq!(stats.best_streak, 3);
+        assert_eq!(stats.longest_snake, Some(12));
+        assert_eq!(stats.pipes_passed, None);
+        assert_eq!(stats.recent, vec![10, 50, 20, 0, 30, 90]);
+        assert_eq!(stats.finishes, None);
+        assert_eq!(history.stats("pong"), GameStats::default());
+    }
+
+    #[test]
+    fn course_runs_stay_out_of_the_scores() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut history = History::load(dir.path().join("history.txt")).0;
+        history.add(run("flappy_bird", 4, 1)).unwrap();
+        for (pipes, finish) in [(12, Some(741)), (3, None)] {
+            let mut course = run("flappy_bird", pipes, 2);
+            course.mode = "course-loop".to_string();
+            course.counters.extend(finish.map(|ticks| ("finish_ticks".to_string(), ticks)));
+            history.add(course).unwrap();
+        }
+
+        let stats = history.stats("flappy_bird");
+        assert_eq!((stats.played, stats.best, stats.median), (3, 4, 4.0));
+        assert_eq!(stats.recent, vec![4]);
+        assert_eq!(stats.finishes, Some(1));
+        assert_eq!(history.runs()[1].counter("finish_ticks"), Some(741));
+    }
+}
@@ -0,0 +1,137 @@
+use directories::ProjectDirs;
+use ggez::{filesystem, Context};
+use std::ffi::OsString;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Points every persistent file somewhere else, e.g. next to the executable for portable installs or into a temp dir for tests.
+pub const DATA_DIR_ENV: &str = "GIERKI_DATA_DIR";
+
+/// Also passed to ggez, which derives its user directories from them.
+pub const GAME_ID: &str = "GierekPare";
+pub const AUTHOR: &str = "Olek";
+
+pub const SCORES_FILE: &str = "scores.txt";
+pub const HISTORY_FILE: &str = "history.txt";
+/// The single save slot for a run left with Save & Quit.
+pub const SAVE_FILE: &str = "save.txt";
+pub const REPLAYS_DIR: &str = "replays";
+pub const EXPORTS_DIR: &str = "exports";
+pub const LOG_FILE: &str = "gierki.log";
+pub const BACKUP_COUNT: usize = 3;
+const LEGACY_SCORES_FILE: &str = "game_scores.txt";
+
+/// Where the game keeps everything it writes: scores, settings, replays and stats.
+pub struct Storage {
+    data_dir: PathBuf,
+    config_dir: PathBuf,
+}
+
+impl Storage {
+    /// Uses ggez's per-user directories unless a directory is given on the command line or in the env var.
+    pub fn from_context(ctx: &Context, data_dir: Option<PathBuf>) -> Self {
+        match Storage::override_dir(data_dir) {
+            Some(dir) => Storage::in_dir(dir),
+            None => Storage::new(
+                filesystem::user_data_dir(ctx).to_path_buf(),
+                filesystem::user_config_dir(ctx).to_path_buf(),
+            ),
+        }
+    }
+
+    /// The same directories as `from_context`, for tools that never open a window.
+    pub fn headless(data_dir: Option<PathBuf>) -> Self {
+        if let Some(dir) = Storage::override_dir(data_dir) {
+            return Storage::in_dir(dir);
+        }
+        match ProjectDirs::from("", AUTHOR, GAME_ID) {
+            Some(dirs) => Storage::new(dirs.data_local_dir().to_path_buf(), dirs.config_dir().to_path_buf()),
+            None => Storage::in_dir(PathBuf::from(".")),
+        }
+    }
+
+    fn override_dir(data_dir: Option<PathBuf>) -> Option<PathBuf> {
+        data_dir.or_else(|| std::env::var_os(DATA_DIR_ENV).map(PathBuf::from))
+    }
+
+    /// Keeps data and config together in a single directory.
+    pub fn in_dir(dir: PathBuf) -> Self {
+        Storage::new(dir.clone(), dir)
+    }
+
+    fn new(data_dir: PathBuf, config_dir: PathBuf) -> Self {
+        for dir in [&data_dir, &config_dir] {
+            if let Err(error) = fs::create_dir_all(dir) {
+                log::error!("Could not create {}: {}", dir.display(), error);
+            }
+        }
+        Storage { data_dir, config_dir }
+    }
+
+    pub fn data_path(&self, name: &str) -> PathBuf {
+        self.data_dir.join(name)
+    }
+
+    pub fn replay_path(&self, name: &str) -> PathBuf {
+        self.data_dir.join(REPLAYS_DIR).join(name)
+    }
+
+    pub fn export_path(&self, name: &str) -> PathBuf {
+        self.data_dir.join(EXPORTS_DIR).join(name)
+    }
+
+    pub fn config_path(&self, name: &str) -> PathBuf {
+        self.config_dir.join(name)
+    }
+}
+
+/// A `game_scores.txt` left in the working directory by versions that did not use the data dir.
+pub fn legacy_scores_file() -> Option<PathBuf> {
+    let legacy = Path::new(LEGACY_SCORES_FILE);
+    legacy.is_file().then(|| legacy.to_path_buf())
+}
+
+/// `scores.txt` -> `scores.txt.bak1`, the newest backup being number 1.
+pub fn backup_path(path: &Path, number: usize) -> PathBuf {
+    let mut name = OsString::from(path.as_os_str());
+    name.push(format!(".bak{}", number));
+    PathBuf::from(name)
+}
+
+/// Replaces `path` without ever leaving a half-written file behind: the new contents go to a
+/// temp file that is synced and then renamed over the old one.
+pub fn write_atomic(path: &Path, contents: &str) -> io::Result<()> {
+    let mut temp_name = OsString::from(path.as_os_str());
+    temp_name.push(".tmp");
+    let temp = PathBuf::from(temp_name);
+
+    {
+        let mut file = File::create(&temp)?;
+        file.write_all(contents.as_bytes())?;
+        file.sync_all()?;
+    }
+
+    fs::rename(&temp, path)?;
+    if let Some(dir) = path.parent().and_then(|dir| File::open(dir).ok()) {
+        // Makes the rename itself durable; not every platform can sync a directory, so errors are ignored.
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// Like `write_atomic`, but keeps the previous version as `.bak1`, pushing older backups down
+/// until `BACKUP_COUNT` is reached. A previous version `is_intact` turns down is just replaced,
+/// so a damaged file never pushes a good backup out.
+pub fn write_with_backups(path: &Path, contents: &str, is_intact: impl Fn(&str) -> bool) -> io::Result<()> {
+    if fs::read_to_string(path).is_ok_and(|previous| is_intact(&previous)) {
+        for number in (1..BACKUP_COUNT).rev() {
+            let older = backup_path(path, number);
+            if older.exists() {
+                fs::rename(&older, backup_path(path, number + 1))?;
+            }
+        }
+        fs::copy(path, backup_path(path, 1))?;
+    }
+    write_atomic(path, contents)
+}
@@ -0,0 +1,222 @@
+use crate::i18n::Notice;
+use ggez::graphics::Color;
+use std::fs;
+use std::path::Path;
+
+pub const THEMES_DIR: &str = "themes";
+const THEME_EXTENSION: &str = "theme";
+
+const LIGHT: &str = include_str!("../resources/themes/light.theme");
+const BUILT_IN: [&str; 3] = [
+    include_str!("../resources/themes/dark.theme"),
+    include_str!("../resources/themes/high_contrast.theme"),
+    include_str!("../resources/themes/colorblind.theme"),
+];
+
+/// Every colour the screens and games use, so the whole look can be switched in one place.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Theme {
+    pub name: String,
+    /// Sprite colours ignore the palette, so themes that need their exact colours turn them off.
+    pub sprites: bool,
+
+    pub background: Color,
+    pub text: Color,
+    pub button: Color,
+    pub button_selected: Color,
+    pub tab: Color,
+    pub highlight: Color,
+    pub warning: Color,
+    pub overlay: Color,
+    pub overlay_text: Color,
+    pub toast: Color,
+    pub toast_text: Color,
+
+    pub sky: Color,
+    pub game_text: Color,
+    pub snake: Color,
+    pub food: Color,
+    pub pipe: Color,
+    pub bird: Color,
+    pub wall: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme::light()
+    }
+}
+
+impl Theme {
+    pub fn light() -> Self {
+        let black = Color::BLACK;
+        let base = Theme {
+            name: String::new(),
+            sprites: true,
+            background: black,
+            text: black,
+            button: black,
+            button_selected: black,
+            tab: black,
+            highlight: black,
+            warning: black,
+            overlay: black,
+            overlay_text: black,
+            toast: black,
+            toast_text: black,
+            sky: black,
+            game_text: black,
+            snake: black,
+            food: black,
+            pipe: black,
+            bird: black,
+            wall: black,
+        };
+        Theme::parse(LIGHT, &base).expect("the built-in light theme is valid")
+    }
+
+    /// The built-in themes followed by any `.theme` file in `dir`. A file with the name of an
+    /// earlier theme replaces it. Broken files are skipped and reported in the returned notices.
+    pub fn load_all(dir: &Path) -> (Vec<Theme>, Vec<Notice>) {
+        let light = Theme::light();
+        let mut themes = vec![light.clone()];
+        themes.extend(BUILT_IN.iter().map(|text| Theme::parse(text, &light).expect("built-in themes are valid")));
+
+        let mut notices = Vec::new();
+        let mut paths: Vec<_> = match fs::read_dir(dir) {
+            Ok(entries) => entries.filter_map(|entry| entry.ok().map(|entry| entry.path())).collect(),
+            Err(_) => Vec::new(),
+        };
+        paths.sort();
+        for path in paths.iter().filter(|path| path.extension().is_some_and(|ext| ext == THEME_EXTENSION)) {
+            let parsed = fs::read_to_string(path)
+                .map_err(|error| error.to_string())
+                .and_then(|text| Theme::parse(&text, &light));
+            match parsed {
+                Ok(theme) => match themes.iter_mut().find(|known| known.name == theme.name) {
+                    Some(known) => *known = theme,
+                    None => themes.push(theme),
+                },
+                Err(error) => {
+                    log::error!("Skipping theme {}: {}", path.display(), error);
+                    notices.push(Notice::new("notice.broken_theme").with("path", path.display()).with("error", error));
+                }
+            }
+        }
+        (themes, notices)
+    }
+
+    /// `key = value` lines on top of `base`; colours are `#rrggbb` or `#rrggbbaa`.
+    pub fn parse(text: &str, base: &Theme) -> Result<Theme, String> {
+        let mut theme = base.clone();
+        theme.name = String::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let fail = |message: String| format!("line {}: {}", number + 1, message);
+            let (key, value) = line.split_once('=').ok_or_else(|| fail("expected key = value".to_string()))?;
+            let (key, value) = (key.trim(), value.trim());
+            match key {
+                "name" => theme.name = value.to_string(),
+                "sprites" => {
+                    theme.sprites = match value {
+                        "on" => true,
+                        "off" => false,
+                        _ => return Err(fail(format!("sprites must be on or off, not '{}'", value))),
+                    }
+                }
+                _ => {
+                    let slot = theme.color_mut(key).ok_or_else(|| fail(format!("unknown key '{}'", key)))?;
+                    *slot = parse_color(value).ok_or_else(|| fail(format!("'{}' is not a #rrggbb colour", value)))?;
+                }
+            }
+        }
+        if theme.name.is_empty() {
+            return Err("the theme has no name".to_string());
+        }
+        Ok(theme)
+    }
+
+    pub(crate) fn color_mut(&mut self, key: &str) -> Option<&mut Color> {
+        Some(match key {
+            "background" => &mut self.background,
+            "text" => &mut self.text,
+            "button" => &mut self.button,
+            "button_selected" => &mut self.button_selected,
+            "tab" => &mut self.tab,
+            "highlight" => &mut self.highlight,
+            "warning" => &mut self.warning,
+            "overlay" => &mut self.overlay,
+            "overlay_text" => &mut self.overlay_text,
+            "toast" => &mut self.toast,
+            "toast_text" => &mut self.toast_text,
+            "sky" => &mut self.sky,
+            "game_text" => &mut self.game_text,
+            "snake" => &mut self.snake,
+            "food" => &mut self.food,
+            "pipe" => &mut self.pipe,
+            "bird" => &mut self.bird,
+            "wall" => &mut self.wall,
+            _ => return None,
+        })
+    }
+}
+
+pub(crate) fn parse_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Some(Color::from_rgba(channel(0)?, channel(2)?, channel(4)?, alpha))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn light_keeps_the_original_colours() {
+        let light = Theme::light();
+        assert_eq!(light.background, Color::from_rgb(255, 253, 208));
+        assert_eq!(light.sky, Color::from_rgb(135, 206, 250));
+        assert_eq!(light.snake, Color::GREEN);
+        assert!(light.sprites);
+    }
+
+    #[test]
+    fn built_in_themes_all_load() {
+        let (themes, notices) = Theme::load_all(Path::new("/nonexistent"));
+        let names: Vec<&str> = themes.iter().map(|theme| theme.name.as_str()).collect();
+        assert_eq!(names, vec!["Light", "Dark", "High Contrast", "Deuteranopia/Protanopia"]);
+        assert!(notices.is_empty());
+    }
+
+    #[test]
+    fn user_themes_override_and_extend() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("mine.theme"), "name = Mine\nsnake = #123456\n").unwrap();
+        fs::write(dir.path().join("dark.theme"), "name = Dark\nsky = #000000\n").unwrap();
+        fs::write(dir.path().join("broken.theme"), "name = Broken\nsnake = green\n").unwrap();
+        fs::write(dir.path().join("notes.txt"), "not a theme").unwrap();
+
+        let (themes, notices) = Theme::load_all(dir.path());
+        assert_eq!(themes.len(), 5);
+        let mine = themes.iter().find(|theme| theme.name == "Mine").unwrap();
+        assert_eq!(mine.snake, Color::from_rgb(0x12, 0x34, 0x56));
+        assert_eq!(mine.sky, Theme::light().sky);
+        assert_eq!(themes[1].sky, Color::BLACK);
+        assert_eq!(notices.len(), 1);
+    }
+
+    #[test]
+    fn colours_need_a_hash_and_hex_digits() {
+        assert_eq!(parse_color("#ff000080"), Some(Color::from_rgba(255, 0, 0, 128)));
+        assert_eq!(parse_color("ff0000"), None);
+        assert_eq!(parse_color("#ff00"), None);
+        assert_eq!(parse_color("#gg0000"), None);
+    }
+}